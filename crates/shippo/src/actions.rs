@@ -0,0 +1,49 @@
+//! GitHub Actions integration: job summaries and workflow-command annotations.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use anyhow::Result;
+use shippo_core::Manifest;
+
+pub fn enabled() -> bool {
+    std::env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true")
+}
+
+/// Appends a markdown summary of the run to `GITHUB_STEP_SUMMARY`.
+pub fn write_summary(title: &str, manifest: &Manifest, changelog: Option<&str>) -> Result<()> {
+    let Some(path) = std::env::var_os("GITHUB_STEP_SUMMARY") else {
+        return Ok(());
+    };
+    let mut md = format!("## {title}\n\n");
+    md.push_str(&format!("Version: `{}`\n\n", manifest.project.version));
+    md.push_str(&manifest.artifacts_markdown());
+    if let Some(changelog) = changelog.filter(|c| !c.trim().is_empty()) {
+        md.push_str("\n### Changelog\n\n");
+        md.push_str(changelog.trim_end());
+        md.push('\n');
+    }
+    md.push('\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(md.as_bytes())?;
+    Ok(())
+}
+
+pub fn error(message: &str) {
+    if enabled() {
+        println!("::error title=shippo::{}", escape(message));
+    }
+}
+
+pub fn warning(message: &str) {
+    if enabled() {
+        println!("::warning title=shippo::{}", escape(message));
+    }
+}
+
+fn escape(message: &str) -> String {
+    message
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
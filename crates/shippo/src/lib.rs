@@ -0,0 +1,23 @@
+//! Library entry point for embedding Shippo without shelling out to the CLI.
+
+mod metrics;
+mod pipeline;
+mod summary;
+#[cfg(feature = "wasm-plugins")]
+mod wasm;
+
+pub use metrics::{ArtifactMetric, MetricsRecorder, RunMetrics, StageMetric, TargetMetric};
+pub use pipeline::{
+    BuildResult, PackageResult, Pipeline, PipelineOptions, PruneResult, PublishOptions,
+    PublishResult, PublishedRelease, ScanResult, StageStatus, TestResult,
+};
+pub use shippo_builders::{BuildContext, Builder, BuilderRegistry, BuiltTarget};
+pub use shippo_core::events::{Event, EventBus, Stage, Subscriber};
+pub use shippo_core::exec::{
+    CommandSpec, Executor, MockExecutor, RecordingExecutor, SystemExecutor,
+};
+pub use shippo_core::{Manifest, Plan, ShippoConfig};
+pub use shippo_pack::{BuiltOutput, DistLock};
+pub use summary::{
+    PackageSummary, RunSummary, StageSummary, Status, SummaryRecorder, TargetSummary, SUMMARY_FILE,
+};
@@ -0,0 +1,136 @@
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use shippo::Event;
+use shippo_core::redact::redact;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines on stdout
+    Text,
+    /// One JSON object per line on stderr, with stage/package/target spans
+    Json,
+}
+
+/// Writer factory that masks secrets in every formatted log event before it
+/// reaches the terminal.
+#[derive(Clone, Copy, Default)]
+pub struct RedactingMakeWriter {
+    stderr: bool,
+}
+
+impl<'a> MakeWriter<'a> for RedactingMakeWriter {
+    type Writer = RedactingWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            buf: Vec::new(),
+            stderr: self.stderr,
+        }
+    }
+}
+
+/// Buffers a single event and writes the redacted text on drop.
+pub struct RedactingWriter {
+    buf: Vec<u8>,
+    stderr: bool,
+}
+
+impl Write for RedactingWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let text = redact(&String::from_utf8_lossy(&self.buf));
+        self.buf.clear();
+        if self.stderr {
+            let mut out = io::stderr().lock();
+            out.write_all(text.as_bytes())?;
+            out.flush()
+        } else {
+            let mut out = io::stdout().lock();
+            out.write_all(text.as_bytes())?;
+            out.flush()
+        }
+    }
+}
+
+impl Drop for RedactingWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+pub fn init_logging(verbose: bool, color: bool, format: LogFormat) {
+    let filter = if verbose {
+        "shippo=debug"
+    } else {
+        "shippo=info"
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(filter))
+        .with_target(false);
+    let _ = match format {
+        LogFormat::Text => builder
+            .with_ansi(color)
+            .with_writer(RedactingMakeWriter { stderr: false })
+            .try_init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(RedactingMakeWriter { stderr: true })
+            .try_init(),
+    };
+}
+
+/// Pipeline subscriber that turns progress events into structured log events,
+/// so JSON logs carry the same information as the human output.
+pub fn log_events(event: &Event) {
+    match event {
+        Event::StageStarted { stage } => tracing::info!(stage = stage.as_str(), "stage started"),
+        Event::StageFinished { stage } => {
+            tracing::info!(stage = stage.as_str(), "stage finished")
+        }
+        Event::StageFailed { stage, error } => {
+            tracing::error!(stage = stage.as_str(), error = %error, "stage failed")
+        }
+        Event::PackageBuildStarted { package, targets } => {
+            tracing::info!(package = %package, targets = %targets.join(","), "building package")
+        }
+        Event::PackageBuilt {
+            package,
+            target,
+            artifacts,
+            cached,
+        } => tracing::info!(
+            package = %package,
+            target = %target,
+            artifacts,
+            cached,
+            "built target"
+        ),
+        Event::ArtifactPackaged {
+            package,
+            target,
+            filename,
+            bytes,
+        } => tracing::info!(
+            package = %package,
+            target = %target,
+            filename = %filename,
+            bytes,
+            "packaged artifact"
+        ),
+        Event::AssetUploaded { filename, bytes } => {
+            tracing::info!(filename = %filename, bytes, "uploaded asset")
+        }
+    }
+}
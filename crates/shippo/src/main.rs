@@ -1,15 +1,44 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use anyhow::{anyhow, Result};
 use clap::{ArgAction, Parser, Subcommand};
+use shippo::{
+    MetricsRecorder, Pipeline, PipelineOptions, PublishOptions, RunSummary, Stage, SummaryRecorder,
+    SUMMARY_FILE,
+};
+use shippo_core::cache::ArtifactCache;
+use shippo_core::exec::SystemExecutor;
+use shippo_core::import::{import_cargo_dist, import_goreleaser, ImportReport};
+use shippo_core::lock::{Lockfile, LOCK_FILE};
+use shippo_core::metadata;
+use shippo_core::plugin;
+use shippo_core::provenance::Provenance;
+use shippo_core::scaffold::ci_workflow;
 use shippo_core::{
-    build_plan, detect_projects, load_config, BuildConfig, PackageEntry, Plan, ShippoConfig,
+    detect_projects, human_bytes, load_config, plan_warnings, sha256_file, BuildConfig,
+    ConfigError, Manifest, PackageEntry, Plan, ProjectType, ShippoConfig, SignersConfig,
+    ToolingInfo, VerifyKeysConfig, MANIFEST_VERSION,
+};
+use shippo_git::current_commit;
+use shippo_pack::{
+    compare_config, compare_rebuild, inspect_artifact, mark_partial, merge_partials,
+    merge_signatures, sign_as, upgrade_manifest, verify_manifest, verify_manifest_data,
+    ArtifactInspection, Check, CheckStatus, DistLock, VerifyOptions, MANIFEST_FILE,
+    PARTIAL_MANIFEST_FILE,
 };
-use shippo_git::{current_commit, repo_url};
-use shippo_pack::{package_outputs, verify_manifest, BuiltOutput};
-use shippo_publish::{publish_github, ReleaseInput};
-use tracing_subscriber::EnvFilter;
+use shippo_publish::changelog_body;
+use shippo_publish::crates_io::{CratesApi, OwnersChange};
+use shippo_publish::diff::{AssetChange, RemoteDiff};
+use shippo_publish::http::{ReqwestClient, Retry};
+
+mod actions;
+mod logging;
+mod output;
+
+use logging::{init_logging, log_events, LogFormat};
+use output::{ColorChoice, Output, OutputStyle};
 
 #[derive(Parser)]
 #[command(
@@ -39,6 +68,15 @@ pub struct Cli {
     #[arg(long, value_name = "TAG")]
     tag: Option<String>,
 
+    /// Only operate on packages with commits touching their path since REV
+    #[arg(long, value_name = "REV")]
+    changed_since: Option<String>,
+
+    /// Read git history from this repository (e.g. a bare mirror) when the
+    /// workspace is a source export without `.git`
+    #[arg(long, value_name = "PATH")]
+    git_dir: Option<PathBuf>,
+
     /// Force draft release
     #[arg(long, action = ArgAction::SetTrue)]
     draft: bool,
@@ -47,14 +85,54 @@ pub struct Cli {
     #[arg(long, action = ArgAction::SetTrue)]
     no_draft: bool,
 
-    /// Override prerelease flag
-    #[arg(long)]
+    /// Force a prerelease (by default versions like 1.2.0-rc.1 are)
+    #[arg(long, action = ArgAction::SetTrue)]
     prerelease: bool,
 
+    /// Force a full release, even for a prerelease version
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "prerelease")]
+    no_prerelease: bool,
+
     /// Output directory
     #[arg(long, default_value = "dist")]
     output: PathBuf,
 
+    /// Run build commands without network, secrets or write access outside
+    /// build directories (Linux; see `[sandbox]`)
+    #[arg(long)]
+    sandbox: bool,
+
+    /// Build everything even if `[cache] enabled` is set
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Build up to N packages at once (0 = one per CPU); their command
+    /// output is printed line by line, tagged with the package
+    #[arg(long, short = 'j', value_name = "N", default_value_t = 1)]
+    jobs: usize,
+
+    /// Non-interactive CI mode: plain output, credentials checked up front,
+    /// warnings handled per `[ci].warnings`
+    #[arg(long)]
+    ci: bool,
+
+    /// When to use colored output (respects NO_COLOR/CLICOLOR in auto)
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Human output style
+    #[arg(long, value_enum, default_value_t = OutputStyle::Compact)]
+    style: OutputStyle,
+
+    /// Write run metrics (durations, sizes, cache hits) to this file:
+    /// JSON for `.json`, Prometheus text format otherwise
+    #[arg(long, value_name = "FILE")]
+    metrics: Option<PathBuf>,
+
+    /// Log format; `json` writes structured logs to stderr for log ingestion
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -62,74 +140,424 @@ pub struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Detect projects and generate a default config
-    Init,
-    /// Show execution plan
+    Init {
+        /// Import settings from a goreleaser config
+        #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = ".goreleaser.yaml", conflicts_with = "from_cargo_dist")]
+        from_goreleaser: Option<PathBuf>,
+        /// Import settings from cargo-dist metadata in a Cargo.toml
+        #[arg(long, value_name = "FILE", num_args = 0..=1, default_missing_value = "Cargo.toml")]
+        from_cargo_dist: Option<PathBuf>,
+        /// Also write a release workflow for a CI provider (github, gitlab)
+        #[arg(long = "ci", value_name = "PROVIDER", value_parser = ["github", "gitlab"])]
+        ci_provider: Option<String>,
+    },
+    /// Show execution plan and pin it in shippo.lock
     Plan {
         #[arg(long)]
         json: bool,
     },
     /// Build all packages
-    Build,
+    Build {
+        /// Only build these targets (repeatable), e.g. one per CI runner
+        #[arg(long = "target", value_name = "TARGET")]
+        targets: Vec<String>,
+        /// Package into a partial dist for a later `shippo merge`
+        #[arg(long)]
+        emit_partial: bool,
+    },
     /// Package artifacts into dist/
-    Package,
+    Package {
+        /// Package binaries built elsewhere instead of building: a directory
+        /// with the files and an artifacts.toml listing each one's package
+        /// and target
+        #[arg(long, value_name = "DIR")]
+        from_artifacts: Option<PathBuf>,
+    },
     /// Build, package and publish release
-    Release,
+    Release {
+        /// Run `[verify.tests]` commands before packaging and record the
+        /// results in the manifest
+        #[arg(long)]
+        with_tests: bool,
+        /// Build and package, then report which assets the provider's
+        /// release would get created, replaced or left alone, without
+        /// changing anything there
+        #[arg(long)]
+        dry_run_remote: bool,
+        /// Publish during a `[[policy.freeze]]` window; the reason is
+        /// recorded in the manifest
+        #[arg(long, value_name = "REASON")]
+        override_freeze: Option<String>,
+    },
     /// Verify manifest and signatures
-    Verify,
+    Verify {
+        /// Fail on signatures that cannot be verified: missing gpg/cosign,
+        /// rejected signatures, or checksum placeholders (the default)
+        #[arg(long, overrides_with = "no_strict")]
+        strict: bool,
+        /// Only warn about signatures that cannot be verified
+        #[arg(long)]
+        no_strict: bool,
+        /// Also check the files inside every archive against the manifest
+        #[arg(long)]
+        deep: bool,
+        /// Rebuild and repackage in a scratch directory and compare artifact
+        /// digests with the manifest
+        #[arg(long, conflicts_with = "manifest_url")]
+        rebuild: bool,
+        /// Verify a published release: fetch its manifest.json from this URL
+        /// and check the files downloaded into --dir
+        #[arg(long, value_name = "URL")]
+        manifest_url: Option<String>,
+        /// Directory holding the files to verify (defaults to --output)
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+        /// Fail when the config or plan recorded in the manifest differs from
+        /// the working tree's config
+        #[arg(long)]
+        compare_config: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Merge partial dists from matrix builds into one dist
+    Merge {
+        /// Partial dist directories
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// Merged output directory
+        #[arg(long, default_value = "dist")]
+        out: PathBuf,
+        /// The inputs are copies of the dist in --out that signers signed
+        /// with `shippo sign`; add their signatures to it
+        #[arg(long)]
+        signatures: bool,
+    },
+    /// Add your signature to every archive and SBOM in --output as one of
+    /// the [verify.signers]
+    Sign {
+        /// Your name in [verify.signers]
+        #[arg(long)]
+        signer: String,
+        /// Your minisign secret key
+        #[arg(long, value_name = "FILE")]
+        key: PathBuf,
+    },
+    /// List plugins from [plugins] and shippo-plugin-* on PATH
+    Plugins,
+    /// Manage the local artifact cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+    /// Delete older versions' build outputs and cache entries, keeping the
+    /// current version and the newest others
+    Prune {
+        /// How many versions to keep (defaults to package.keep_last)
+        #[arg(long)]
+        keep: Option<usize>,
+    },
+    /// Check one dist file against the manifest next to it and show its
+    /// contents, signatures and embedded provenance; for other files, print
+    /// the provenance embedded with `[build] provenance`
+    Inspect {
+        /// A dist archive or SBOM, a binary, or a shippo-provenance.json
+        file: PathBuf,
+        /// Fail on signatures that cannot be verified (the default)
+        #[arg(long, overrides_with = "no_strict")]
+        strict: bool,
+        /// Only warn about signatures that cannot be verified
+        #[arg(long)]
+        no_strict: bool,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Inspect or migrate dist manifests
+    Manifest {
+        #[command(subcommand)]
+        command: ManifestCommand,
+    },
+    /// Manage the project's crates on crates.io ([release.crates])
+    Crates {
+        #[command(subcommand)]
+        command: CratesCommand,
+    },
 }
 
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Show the number of cached builds and their size
+    Stats,
+    /// Delete every cached build
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum ManifestCommand {
+    /// Rewrite the manifest in --output in the current schema version
+    Upgrade,
+}
+
+#[derive(Subcommand)]
+enum CratesCommand {
+    /// Crate owners
+    Owners {
+        #[command(subcommand)]
+        command: OwnersCommand,
+    },
+    /// Yank a version of every crate, e.g. after a bad release
+    Yank {
+        /// The version, with or without a leading `v`
+        version: String,
+        /// Unyank it instead
+        #[arg(long)]
+        undo: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum OwnersCommand {
+    /// Invite the owners in release.crates.owners that a crate lacks, and
+    /// with remove_unlisted remove the others
+    Sync {
+        /// Only show what would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Shared by every pipeline a command creates, written out when it exits.
+static METRICS: OnceLock<MetricsRecorder> = OnceLock::new();
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
-    init_logging(cli.verbose);
+    let out = Output::new(cli.color, cli.style, cli.ci);
+    init_logging(cli.verbose, out.color(), cli.log_format);
+    let result = run(&cli, &out);
+    if let Err(err) = &result {
+        actions::error(&format!("{err:#}"));
+    }
+    if let (Some(path), Some(recorder)) = (&cli.metrics, METRICS.get()) {
+        if let Err(err) = recorder.finish(result.is_ok()).write(path) {
+            out.warn(format!(
+                "failed to write metrics to {}: {err}",
+                path.display()
+            ));
+        }
+    }
+    result
+}
+
+fn run(cli: &Cli, out: &Output) -> Result<()> {
     match cli.command {
-        Commands::Init => cmd_init(&cli),
-        Commands::Plan { json } => cmd_plan(&cli, json),
-        Commands::Build => cmd_build(&cli, false),
-        Commands::Package => cmd_build(&cli, true),
-        Commands::Release => cmd_release(&cli),
-        Commands::Verify => cmd_verify(&cli),
+        Commands::Init {
+            ref from_goreleaser,
+            ref from_cargo_dist,
+            ref ci_provider,
+        } => {
+            let config_only_ci = ci_provider.is_some() && cli.config.exists();
+            match (from_goreleaser, from_cargo_dist) {
+                _ if config_only_ci => {}
+                (Some(src), _) => cmd_import(cli, out, src, import_goreleaser)?,
+                (_, Some(src)) => cmd_import(cli, out, src, import_cargo_dist)?,
+                _ => cmd_init(cli, out)?,
+            }
+            match ci_provider {
+                Some(provider) => cmd_init_ci(cli, out, provider),
+                None => Ok(()),
+            }
+        }
+        Commands::Plan { json } => cmd_plan(cli, out, json),
+        Commands::Build {
+            ref targets,
+            emit_partial,
+        } => cmd_build(cli, out, emit_partial, targets, emit_partial, None),
+        Commands::Package { ref from_artifacts } => {
+            cmd_build(cli, out, true, &[], false, from_artifacts.as_deref())
+        }
+        Commands::Release {
+            with_tests,
+            dry_run_remote,
+            ref override_freeze,
+        } => cmd_release(
+            cli,
+            out,
+            with_tests,
+            dry_run_remote,
+            override_freeze.as_deref(),
+        ),
+        Commands::Verify {
+            no_strict,
+            deep,
+            rebuild,
+            ref manifest_url,
+            ref dir,
+            compare_config,
+            json,
+            ..
+        } => {
+            let options = VerifyOptions {
+                strict: !no_strict,
+                deep,
+                keys: verify_keys(cli)?,
+                signers: verify_signers(cli)?,
+                downloaded: manifest_url.is_some(),
+            };
+            let dist = dir.clone().unwrap_or_else(|| cli.output.clone());
+            let extra = ExtraChecks {
+                rebuild,
+                compare_config,
+            };
+            cmd_verify(
+                cli,
+                out,
+                &dist,
+                manifest_url.as_deref(),
+                &options,
+                extra,
+                json,
+            )
+        }
+        Commands::Merge {
+            ref inputs,
+            out: ref merged,
+            signatures: false,
+        } => cmd_merge(out, inputs, merged),
+        Commands::Merge {
+            ref inputs,
+            out: ref merged,
+            signatures: true,
+        } => cmd_merge_signatures(cli, out, inputs, merged),
+        Commands::Sign {
+            ref signer,
+            ref key,
+        } => cmd_sign(cli, out, signer, key),
+        Commands::Plugins => cmd_plugins(cli, out),
+        Commands::Cache { ref command } => cmd_cache(cli, out, command),
+        Commands::Prune { keep } => cmd_prune(cli, out, keep),
+        Commands::Inspect {
+            ref file,
+            no_strict,
+            json,
+            ..
+        } => cmd_inspect(cli, out, file, !no_strict, json),
+        Commands::Manifest {
+            command: ManifestCommand::Upgrade,
+        } => cmd_manifest_upgrade(cli, out),
+        Commands::Crates { ref command } => cmd_crates(cli, out, command),
     }
 }
 
-fn init_logging(verbose: bool) {
-    let filter = if verbose {
-        "shippo=debug"
-    } else {
-        "shippo=info"
+fn pipeline(cli: &Cli, out: &Output, targets: &[String]) -> Result<Pipeline> {
+    let options = PipelineOptions {
+        only: cli.only.clone(),
+        tag: cli.tag.clone(),
+        targets: targets.to_vec(),
+        workspace_root: PathBuf::from("."),
+        dist: cli.output.clone(),
+        verbose: cli.verbose,
+        sign: true,
+        sandbox: cli.sandbox,
+        cache: !cli.no_cache,
+        changed_since: cli.changed_since.clone(),
+        git_dir: cli.git_dir.clone(),
+        jobs: cli.jobs,
     };
-    let _ = tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::new(filter))
-        .with_target(false)
-        .try_init();
+    let mut pipeline = Pipeline::from_config_file(&cli.config, options)?.subscribe(out.progress());
+    if cli.metrics.is_some() {
+        pipeline = pipeline.subscribe(METRICS.get_or_init(MetricsRecorder::new).clone());
+    }
+    Ok(match cli.log_format {
+        LogFormat::Json => pipeline.subscribe(log_events),
+        LogFormat::Text => pipeline,
+    })
 }
 
-fn load_plan(cli: &Cli) -> Result<Plan> {
-    let cfg = load_config(&cli.config).map_err(|e| anyhow!("{e}"))?;
-    build_plan(&cfg, cli.only.as_deref(), cli.tag.clone())
-        .map_err(|e| anyhow!("failed to build plan: {e}"))
+fn load_plan(cli: &Cli, out: &Output, pipeline: &Pipeline) -> Result<Plan> {
+    let plan = pipeline.plan()?;
+    check_policy(cli, out, pipeline.config(), &plan)?;
+    Ok(plan)
+}
+
+/// `shippo.lock` lives next to the config file.
+fn lock_path(cli: &Cli) -> PathBuf {
+    cli.config.with_file_name(LOCK_FILE)
+}
+
+fn current_lock(cli: &Cli, plan: &Plan) -> Result<Lockfile> {
+    let config_sha256 = sha256_file(&cli.config)
+        .map_err(|e| anyhow!("failed to hash {}: {e}", cli.config.display()))?;
+    let types: Vec<_> = plan
+        .packages
+        .iter()
+        .map(|p| p.project_type.clone())
+        .collect();
+    let tooling = ToolingInfo::probe_for(&SystemExecutor, &types);
+    Ok(Lockfile::new(plan, config_sha256, tooling))
+}
+
+/// Refuses to continue when the plan drifted from an existing `shippo.lock`.
+fn check_lock(cli: &Cli, plan: &Plan) -> Result<()> {
+    let path = lock_path(cli);
+    let Some(locked) = Lockfile::read(&path)? else {
+        return Ok(());
+    };
+    locked.check(&current_lock(cli, plan)?, &path)?;
+    Ok(())
 }
 
-fn cmd_init(cli: &Cli) -> Result<()> {
+fn check_policy(cli: &Cli, out: &Output, cfg: &ShippoConfig, plan: &Plan) -> Result<()> {
+    let ci_cfg = cfg.ci.clone().unwrap_or_default();
+    let warnings = plan_warnings(plan);
+    for warning in &warnings {
+        out.warn(warning);
+        actions::warning(warning);
+    }
+    if !cli.ci {
+        return Ok(());
+    }
+    if ci_cfg.warnings == "deny" && !warnings.is_empty() {
+        return Err(anyhow!(
+            "{} warning(s) treated as errors in --ci mode (set [ci].warnings = \"warn\" to allow)",
+            warnings.len()
+        ));
+    }
+    let missing: Vec<_> = ci_cfg
+        .require_env
+        .iter()
+        .filter(|name| std::env::var(name.as_str()).is_err())
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "missing required environment: {}",
+            missing.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+fn github_token() -> Result<String> {
+    std::env::var("GITHUB_TOKEN")
+        .or_else(|_| std::env::var("GH_TOKEN"))
+        .map_err(|_| anyhow!("GITHUB_TOKEN or GH_TOKEN must be set to publish"))
+}
+
+fn cmd_init(cli: &Cli, out: &Output) -> Result<()> {
     let path = &cli.config;
     if path.exists() {
         return Err(anyhow!("{} already exists", path.display()));
     }
     let projects = detect_projects(std::path::Path::new("."));
     let mut cfg = ShippoConfig {
-        project: None,
-        packages: vec![],
-        node: None,
-        python: None,
-        version: None,
         build: Some(BuildConfig {
             targets: vec!["native".into()],
             env: Default::default(),
+            builder: None,
+            provenance: false,
+            scratch_dir: None,
         }),
-        package: None,
-        sbom: None,
-        sign: None,
-        release: None,
-        changelog: None,
+        ..Default::default()
     };
     if projects.len() == 1 {
         cfg.project = Some(shippo_core::ProjectConfig {
@@ -149,128 +577,1048 @@ fn cmd_init(cli: &Cli) -> Result<()> {
                 sign: None,
                 node: None,
                 python: None,
+                go: None,
+                docker: None,
             });
         }
     }
     let toml = toml::to_string_pretty(&cfg)?;
     fs::write(path, toml)?;
-    println!("wrote {}", path.display());
+    out.success(format!("wrote {}", path.display()));
     Ok(())
 }
 
-fn cmd_plan(cli: &Cli, json: bool) -> Result<()> {
-    let plan = load_plan(cli)?;
+fn cmd_import(
+    cli: &Cli,
+    out: &Output,
+    source: &Path,
+    import: fn(&str, &str) -> Result<ImportReport, ConfigError>,
+) -> Result<()> {
+    let path = &cli.config;
+    if path.exists() {
+        return Err(anyhow!("{} already exists", path.display()));
+    }
+    let content = fs::read_to_string(source)
+        .map_err(|e| anyhow!("failed to read {}: {e}", source.display()))?;
+    let fallback_name = std::env::current_dir()?
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "app".into());
+    let report = import(&content, &fallback_name)?;
+    for note in &report.notes {
+        out.warn(note);
+    }
+    fs::write(path, toml::to_string_pretty(&report.config)?)?;
+    out.success(format!(
+        "wrote {} from {}",
+        path.display(),
+        source.display()
+    ));
+    Ok(())
+}
+
+fn cmd_init_ci(cli: &Cli, out: &Output, provider: &str) -> Result<()> {
+    let plan = load_plan(cli, out, &pipeline(cli, out, &[])?)?;
+    let (path, workflow) = ci_workflow(provider, &plan)?;
+    let path = Path::new(path);
+    if path.exists() {
+        return Err(anyhow!("{} already exists", path.display()));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, workflow)?;
+    out.success(format!("wrote {}", path.display()));
+    Ok(())
+}
+
+fn cmd_plan(cli: &Cli, out: &Output, json: bool) -> Result<()> {
+    let plan = load_plan(cli, out, &pipeline(cli, out, &[])?)?;
+    let lock = lock_path(cli);
+    current_lock(cli, &plan)?.write(&lock)?;
     if json {
         println!("{}", serde_json::to_string_pretty(&plan)?);
     } else {
-        println!("Plan for version {}", plan.version);
+        out.success(format!("wrote {}", lock.display()));
+        out.heading(format!("Plan for version {}", plan.version));
+        out.detail(format!("fingerprint: {}", plan.fingerprint()));
         for pkg in &plan.packages {
-            println!(
-                "- {} ({:?}) targets: {}",
+            out.item(format!(
+                "{} ({:?}) targets: {}",
                 pkg.name,
                 pkg.project_type,
                 pkg.targets.join(", ")
-            );
+            ));
+            out.detail(format!("path: {}", pkg.path));
+            out.detail(format!("formats: {}", pkg.package.formats.join(", ")));
+            out.detail(format!(
+                "sbom: {}",
+                if pkg.sbom.enabled {
+                    pkg.sbom.output_formats().join(", ")
+                } else {
+                    "disabled".to_string()
+                }
+            ));
+            out.detail(format!(
+                "sign: {}",
+                if pkg.sign.enabled {
+                    pkg.sign.method.as_str()
+                } else {
+                    "disabled"
+                }
+            ));
         }
     }
     Ok(())
 }
 
-fn cmd_build(cli: &Cli, package_after: bool) -> Result<()> {
-    let plan = load_plan(cli)?;
-    let mut outputs = Vec::new();
-    for pkg in &plan.packages {
-        let built = shippo_builders::build_package(
-            pkg,
-            std::path::Path::new("."),
-            &plan.version,
-            cli.verbose,
-        )?;
-        for target in built {
-            outputs.push(BuiltOutput {
-                package: pkg.name.clone(),
-                target: target.target,
-                artifacts: target.artifacts,
-            });
-        }
-    }
+fn cmd_build(
+    cli: &Cli,
+    out: &Output,
+    package_after: bool,
+    targets: &[String],
+    partial: bool,
+    from_artifacts: Option<&Path>,
+) -> Result<()> {
+    let pipeline = pipeline(cli, out, targets)?;
+    let plan = load_plan(cli, out, &pipeline)?;
+    check_lock(cli, &plan)?;
+    let _dist_lock = DistLock::acquire(&cli.output)?;
+    let built = match from_artifacts {
+        Some(dir) => pipeline.from_artifacts(&plan, dir)?,
+        None => pipeline.build(&plan)?,
+    };
     if package_after {
-        let dist = cli.output.clone();
-        let manifest = package_outputs(&plan, &outputs, &dist, repo_url(), current_commit(), true)?;
-        println!(
+        let scans = pipeline.scan(&plan, &built)?;
+        let mut packaged = pipeline.package(&plan, &built)?;
+        pipeline.record_scans(&mut packaged, &scans)?;
+        if partial {
+            mark_partial(&packaged.dist)?;
+        } else if actions::enabled() {
+            actions::write_summary("Shippo package", &packaged.manifest, None)?;
+        }
+        out.success(format!(
             "packaged {} packages into {}",
-            manifest.packages.len(),
-            dist.display()
-        );
+            packaged.manifest.packages.len(),
+            packaged.dist.display()
+        ));
+    } else {
+        out.success(format!(
+            "built {} target(s) for {} package(s)",
+            built.outputs.len(),
+            plan.packages.len()
+        ));
     }
     Ok(())
 }
 
-fn cmd_release(cli: &Cli) -> Result<()> {
-    let plan = load_plan(cli)?;
-    let mut outputs = Vec::new();
-    for pkg in &plan.packages {
-        let built = shippo_builders::build_package(
-            pkg,
-            std::path::Path::new("."),
-            &plan.version,
-            cli.verbose,
-        )?;
-        for target in built {
-            outputs.push(BuiltOutput {
-                package: pkg.name.clone(),
-                target: target.target,
-                artifacts: target.artifacts,
-            });
+fn cmd_release(
+    cli: &Cli,
+    out: &Output,
+    with_tests: bool,
+    dry_run_remote: bool,
+    override_freeze: Option<&str>,
+) -> Result<()> {
+    let summary = SummaryRecorder::new();
+    let pipeline = pipeline(cli, out, &[])?.subscribe(summary.clone());
+    let summary_path = cli.output.join(SUMMARY_FILE);
+    // a summary left by an earlier failed run would be uploaded with the release
+    if summary_path.exists() {
+        fs::remove_file(&summary_path)?;
+    }
+    let result = release(
+        cli,
+        out,
+        &pipeline,
+        with_tests,
+        dry_run_remote,
+        override_freeze,
+    );
+    if let Err(err) = &result {
+        let mut stages = vec![Stage::Plan, Stage::Build];
+        if with_tests {
+            stages.push(Stage::Test);
+        }
+        stages.extend([Stage::Scan, Stage::Package, Stage::Publish]);
+        let summary = summary.finish(&stages, Some(format!("{err:#}")), &cli.output);
+        print_run_summary(out, &summary);
+        match summary.write(&summary_path) {
+            Ok(()) => out.item(format!("summary written to {}", summary_path.display())),
+            Err(err) => out.warn(format!("failed to write {}: {err}", summary_path.display())),
+        }
+    }
+    result
+}
+
+fn release(
+    cli: &Cli,
+    out: &Output,
+    pipeline: &Pipeline,
+    with_tests: bool,
+    dry_run_remote: bool,
+    override_freeze: Option<&str>,
+) -> Result<()> {
+    let publishing = !cli.dry_run && !dry_run_remote;
+    // a frozen release stops before planning or building anything
+    let freeze_override = match publishing {
+        true => pipeline.check_freeze(override_freeze)?,
+        false => None,
+    };
+    let plan = load_plan(cli, out, pipeline)?;
+    check_lock(cli, &plan)?;
+    let _dist_lock = DistLock::acquire(&cli.output)?;
+    // resolve credentials before building so CI runs fail fast
+    let token = if !publishing {
+        None
+    } else if pipeline.release_provider()? == "s3" {
+        // only for what still talks to GitHub, such as approval issues
+        Some(github_token().unwrap_or_default())
+    } else {
+        Some(github_token()?)
+    };
+    // checked before building, and not again when publishing
+    let tag_signer = match token {
+        Some(_) => pipeline.verify_tag(&plan)?,
+        None => None,
+    };
+    if let Some(signer) = &tag_signer {
+        out.item(format!("release tag {} signed by {signer}", plan.version));
+    }
+    let built = pipeline.build(&plan)?;
+    let tests = if with_tests {
+        Some(pipeline.test(&plan)?)
+    } else {
+        None
+    };
+    let scans = pipeline.scan(&plan, &built)?;
+    let mut packaged = pipeline.package(&plan, &built)?;
+    pipeline.record_scans(&mut packaged, &scans)?;
+    for (package, scan) in &scans.runs {
+        out.item(format!("scanned {package}: {} file(s) clean", scan.files));
+    }
+    if let Some(tests) = &tests {
+        pipeline.record_tests(&mut packaged, tests)?;
+        for (package, run) in &tests.runs {
+            if run.passed {
+                out.item(format!("tests passed: {package} ({})", run.command));
+            } else {
+                out.warn(format!("tests failed: {package} ({})", run.command));
+            }
         }
     }
-    let dist = cli.output.clone();
-    let manifest = package_outputs(&plan, &outputs, &dist, repo_url(), current_commit(), true)?;
-    if cli.dry_run {
-        println!("dry-run release complete; skipping publish");
+    if dry_run_remote {
+        // read-only; public repositories can be read without a token
+        let diffs = pipeline.remote_diff(&plan, &packaged, github_token().ok().as_deref())?;
+        for diff in &diffs {
+            print_remote_diff(out, diff);
+        }
+        out.success("dry-run release complete; nothing was changed on the provider");
         return Ok(());
     }
-    let cfg = load_config(&cli.config).map_err(|e| anyhow!("{e}"))?;
-    let release_cfg = cfg
-        .release
-        .ok_or_else(|| anyhow!("release config missing"))?;
-    let gh = release_cfg
-        .github
-        .ok_or_else(|| anyhow!("release.github missing"))?;
-    let token = std::env::var("GITHUB_TOKEN").or_else(|_| std::env::var("GH_TOKEN"))?;
+    if let Some(freeze_override) = &freeze_override {
+        pipeline.record_freeze_override(&mut packaged, freeze_override)?;
+        out.warn(format!(
+            "publishing during the {} freeze: {}",
+            freeze_override.window, freeze_override.reason
+        ));
+    }
+    let Some(token) = token else {
+        if actions::enabled() {
+            actions::write_summary("Shippo release (dry run)", &packaged.manifest, None)?;
+        }
+        out.success("dry-run release complete; skipping publish");
+        return Ok(());
+    };
+    let symbols = pipeline.upload_symbols(&plan, &built)?;
+    if symbols > 0 {
+        out.item(format!("uploaded {symbols} debug file(s)"));
+    }
     let draft = if cli.no_draft {
-        false
+        Some(false)
     } else if cli.draft {
-        true
+        Some(true)
+    } else {
+        None
+    };
+    let prerelease = if cli.no_prerelease {
+        Some(false)
+    } else if cli.prerelease {
+        Some(true)
     } else {
-        release_cfg.draft
+        None
     };
-    let input = ReleaseInput {
-        owner: &gh.owner,
-        repo: &gh.repo,
-        tag: &plan.version,
-        name: &plan.version,
+    let opts = PublishOptions {
+        token,
         draft,
-        prerelease: cli.prerelease || release_cfg.prerelease,
-        changelog_mode: &cfg
-            .changelog
-            .map(|c| c.mode)
-            .unwrap_or_else(|| "auto".into()),
-        dist: &dist,
-        manifest: &manifest,
+        prerelease,
+        tag_signer,
     };
-    publish_github(&token, &input)?;
-    println!(
-        "published release {} to {}/{}",
-        plan.version, gh.owner, gh.repo
-    );
+    let published = pipeline.publish(&plan, &packaged, &opts)?;
+    // the release is out, so reporting it only warns when it fails
+    if actions::enabled() {
+        let mut notes = Vec::new();
+        for release in &published.releases {
+            let body = match changelog_body(
+                pipeline.options().repo(),
+                &published.changelog,
+                &release.tag,
+                release.tag_pattern.as_deref(),
+                &release.packages,
+            ) {
+                Ok(body) => body,
+                Err(err) => {
+                    out.warn(format!("no release notes in the job summary: {err}"));
+                    continue;
+                }
+            };
+            notes.push(match published.releases.len() {
+                1 => body,
+                _ => format!("## {}\n\n{body}", release.tag),
+            });
+        }
+        let notes = notes.join("\n\n");
+        if let Err(err) = actions::write_summary(
+            &format!("Released {}", plan.version),
+            &packaged.manifest,
+            Some(&notes),
+        ) {
+            out.warn(format!("failed to write the job summary: {err}"));
+        }
+    }
+    for (i, release) in published.releases.iter().enumerate() {
+        let title = if release.name == release.tag {
+            String::new()
+        } else {
+            format!(" ({:?})", release.name)
+        };
+        let target = match published.s3.get(i) {
+            Some(url) => url.clone(),
+            None => format!("{}/{}", published.owner, published.repo),
+        };
+        out.success(format!(
+            "published release {}{title} to {target}",
+            release.tag
+        ));
+    }
+    for image in &published.images {
+        out.item(format!("pushed image {image}"));
+    }
+    if let Some(url) = &published.homebrew {
+        out.item(format!("updated the Homebrew formula: {url}"));
+    }
+    if let Some(url) = &published.scoop {
+        out.item(format!("updated the Scoop manifest: {url}"));
+    }
+    for spec in &published.npm {
+        out.item(format!("published {spec} to npm"));
+    }
+    if !published.pypi.is_empty() {
+        out.item(format!("uploaded to PyPI: {}", published.pypi.join(", ")));
+    }
     Ok(())
 }
 
-fn cmd_verify(cli: &Cli) -> Result<()> {
-    let dist = cli.output.clone();
+fn print_run_summary(out: &Output, summary: &RunSummary) {
+    out.heading("Release failed");
+    let rows: Vec<Vec<String>> = summary
+        .stages
+        .iter()
+        .map(|s| {
+            vec![
+                s.stage.as_str().to_string(),
+                s.status.as_str().to_string(),
+                s.error.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    out.table(&["stage", "status", "error"], &rows);
+    let rows: Vec<Vec<String>> = summary
+        .packages
+        .iter()
+        .flat_map(|p| {
+            p.targets.iter().map(|t| {
+                vec![
+                    p.package.clone(),
+                    t.target.clone(),
+                    t.status.as_str().to_string(),
+                ]
+            })
+        })
+        .collect();
+    if !rows.is_empty() {
+        out.table(&["package", "target", "built"], &rows);
+    }
+    if let Some(cmd) = &summary.failed_command {
+        let status = match (&cmd.error, cmd.exit_code) {
+            (Some(err), _) => err.clone(),
+            (None, Some(code)) => format!("exit code {code}"),
+            (None, None) => "killed by a signal".to_string(),
+        };
+        out.item(format!(
+            "failing command: {} {} ({status})",
+            cmd.program,
+            cmd.args.join(" ")
+        ));
+        if let Some(cwd) = &cmd.cwd {
+            out.item(format!("in {}", cwd.display()));
+        }
+    }
+    if let Some(log) = &summary.command_log {
+        out.item(format!("command log: {}", log.display()));
+    }
+}
+
+fn print_remote_diff(out: &Output, diff: &RemoteDiff) {
+    out.heading(format!("Release {} on the provider", diff.tag));
+    match &diff.release {
+        Some(release) => {
+            let kind = match (release.draft, release.prerelease) {
+                (true, _) => "draft release",
+                (false, true) => "prerelease",
+                (false, false) => "release",
+            };
+            out.item(format!(
+                "{kind} exists{}",
+                release
+                    .url
+                    .as_ref()
+                    .map(|u| format!(": {u}"))
+                    .unwrap_or_default()
+            ));
+            out.warn(format!(
+                "`shippo release` creates a new release, so publishing {} would be rejected while this one exists",
+                diff.tag
+            ));
+        }
+        None if diff.tag_exists => out.item("tag exists; the release would be created for it"),
+        None => out.item("tag and release would be created"),
+    }
+    let rows: Vec<Vec<String>> = diff
+        .assets
+        .iter()
+        .map(|a| {
+            vec![
+                a.change.label().to_string(),
+                a.filename.clone(),
+                human_bytes(a.bytes),
+            ]
+        })
+        .collect();
+    out.table(&["change", "asset", "size"], &rows);
+    out.detail(format!(
+        "{} to create, {} to replace, {} unchanged, {} only on the release",
+        diff.count(AssetChange::Create),
+        diff.count(AssetChange::Replace),
+        diff.count(AssetChange::Keep),
+        diff.count(AssetChange::RemoteOnly)
+    ));
+}
+
+fn cmd_verify(
+    cli: &Cli,
+    out: &Output,
+    dist: &Path,
+    manifest_url: Option<&str>,
+    options: &VerifyOptions,
+    extra: ExtraChecks,
+    json: bool,
+) -> Result<()> {
     let manifest_path = dist.join("manifest.json");
-    verify_manifest(&manifest_path, &dist)?;
-    println!("manifest verified");
+    let (data, mut report) = match manifest_url {
+        Some(url) => {
+            let (name, data) = fetch_manifest(url)?;
+            let report = verify_manifest_data(&data, &name, dist, options, &SystemExecutor)?;
+            let present = report
+                .checks
+                .iter()
+                .any(|c| c.check == Check::Checksum && c.status != CheckStatus::Warn);
+            if !present {
+                return Err(anyhow!(
+                    "none of the release's files are in {}; download them first",
+                    dist.display()
+                ));
+            }
+            (data, report)
+        }
+        None => (
+            fs::read_to_string(&manifest_path)?,
+            verify_manifest(&manifest_path, dist, options, &SystemExecutor)?,
+        ),
+    };
+    let original = Manifest::from_json(&data)?;
+    if extra.compare_config {
+        let options = PipelineOptions {
+            only: cli.only.clone(),
+            tag: Some(original.project.version.clone()),
+            dist: dist.to_path_buf(),
+            ..Default::default()
+        };
+        let current = Pipeline::from_config_file(&cli.config, options)?.plan()?;
+        let name = cli.config.file_name().unwrap_or_default().to_string_lossy();
+        report
+            .checks
+            .extend(compare_config(&original, &current, &name).checks);
+    }
+    let mut reproducible = Vec::new();
+    if extra.rebuild {
+        let rebuilt = rebuild_dist(cli, out, &original)?;
+        let compared = compare_rebuild(&original, &rebuilt);
+        for pkg in &original.packages {
+            let same = pkg.targets.iter().flat_map(|t| &t.artifacts).all(|a| {
+                compared
+                    .checks
+                    .iter()
+                    .any(|c| c.artifact == a.filename && c.status == CheckStatus::Pass)
+            });
+            reproducible.push((pkg.name.clone(), same));
+        }
+        report.checks.extend(compared.checks);
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        let rows: Vec<Vec<String>> = report
+            .checks
+            .iter()
+            .map(|c| {
+                vec![
+                    c.status.as_str().to_string(),
+                    c.check.as_str().to_string(),
+                    c.artifact.clone(),
+                    c.details.clone(),
+                ]
+            })
+            .collect();
+        out.table(&["STATUS", "CHECK", "ARTIFACT", "DETAILS"], &rows);
+        for (package, same) in &reproducible {
+            let verdict = if *same {
+                "reproducible"
+            } else {
+                "NOT reproducible"
+            };
+            out.item(format!("{package}: {verdict}"));
+        }
+    }
+    let failed = report.failures().count();
+    if failed > 0 {
+        return Err(anyhow!("{failed} of {} checks failed", report.checks.len()));
+    }
+    if !json {
+        out.success(format!("{} checks passed", report.checks.len()));
+    }
+    Ok(())
+}
+
+/// Checks `shippo verify` runs on top of the manifest checks.
+#[derive(Debug, Clone, Copy)]
+struct ExtraChecks {
+    rebuild: bool,
+    compare_config: bool,
+}
+
+/// Downloads a release's manifest, returning the file name `SHA256SUMS`
+/// lists it under and its contents.
+fn fetch_manifest(url: &str) -> Result<(String, String)> {
+    let response = reqwest::blocking::Client::new()
+        .get(url)
+        .header("User-Agent", "shippo")
+        .send()
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| anyhow!("failed to fetch {url}: {e}"))?;
+    let name = response
+        .url()
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("manifest.json")
+        .to_string();
+    let data = response
+        .text()
+        .map_err(|e| anyhow!("failed to read {url}: {e}"))?;
+    Ok((name, data))
+}
+
+/// `[verify.keys]` with paths made absolute; verifying works without a
+/// config, e.g. on a machine that only downloaded the release.
+fn verify_keys(cli: &Cli) -> Result<VerifyKeysConfig> {
+    if !cli.config.exists() {
+        return Ok(VerifyKeysConfig::default());
+    }
+    let keys = load_config(&cli.config)?
+        .verify
+        .and_then(|v| v.keys)
+        .unwrap_or_default();
+    let config = fs::canonicalize(&cli.config)?;
+    Ok(keys.resolve(config.parent().unwrap_or(Path::new("/"))))
+}
+
+/// `[verify.signers]`, with key paths resolved; an error when its threshold
+/// can't be met.
+fn verify_signers(cli: &Cli) -> Result<Option<SignersConfig>> {
+    if !cli.config.exists() {
+        return Ok(None);
+    }
+    let Some(signers) = load_config(&cli.config)?.verify.and_then(|v| v.signers) else {
+        return Ok(None);
+    };
+    signers
+        .check()
+        .map_err(|e| anyhow!("[verify.signers]: {e}"))?;
+    let config = fs::canonicalize(&cli.config)?;
+    Ok(Some(
+        signers.resolve(config.parent().unwrap_or(Path::new("/"))),
+    ))
+}
+
+/// Builds and packages the release `original` describes into a scratch
+/// dist, unsigned and without the artifact cache.
+fn rebuild_dist(cli: &Cli, out: &Output, original: &Manifest) -> Result<Manifest> {
+    let head = current_commit(cli.git_dir.as_deref().unwrap_or(Path::new(".")));
+    if original.project.commit.is_some() && original.project.commit != head {
+        out.warn(format!(
+            "the manifest was built from commit {}, but HEAD is {}; digests will likely differ",
+            original.project.commit.as_deref().unwrap_or_default(),
+            head.as_deref().unwrap_or("unknown")
+        ));
+    }
+    let scratch = tempfile::tempdir()?;
+    let options = PipelineOptions {
+        only: cli.only.clone(),
+        tag: Some(original.project.version.clone()),
+        targets: Vec::new(),
+        workspace_root: PathBuf::from("."),
+        dist: scratch.path().to_path_buf(),
+        verbose: cli.verbose,
+        sign: false,
+        sandbox: cli.sandbox,
+        cache: false,
+        changed_since: None,
+        git_dir: cli.git_dir.clone(),
+        jobs: cli.jobs,
+    };
+    let pipeline = Pipeline::from_config_file(&cli.config, options)?.subscribe(out.progress());
+    let mut plan = pipeline.plan()?;
+    // only rebuild what this dist contains, e.g. one runner's share
+    for pkg in &mut plan.packages {
+        let Some(shipped) = original.packages.iter().find(|p| p.name == pkg.name) else {
+            pkg.targets.clear();
+            continue;
+        };
+        pkg.targets
+            .retain(|t| shipped.targets.iter().any(|s| &s.target == t));
+    }
+    plan.packages.retain(|p| !p.targets.is_empty());
+    let built = pipeline.build(&plan)?;
+    Ok(pipeline.package(&plan, &built)?.manifest)
+}
+
+fn cmd_cache(cli: &Cli, out: &Output, command: &CacheCommand) -> Result<()> {
+    // the config is optional here; it only moves the cache root
+    let configured = if cli.config.exists() {
+        load_config(&cli.config)?.cache.and_then(|c| c.dir)
+    } else {
+        None
+    };
+    let root = configured
+        .map(PathBuf::from)
+        .or_else(ArtifactCache::default_root)
+        .ok_or_else(|| anyhow!("cannot locate the cache directory; set SHIPPO_CACHE_DIR"))?;
+    let cache = ArtifactCache::new(root);
+    match command {
+        CacheCommand::Stats => {
+            let stats = cache.stats()?;
+            out.heading(format!("Artifact cache at {}", cache.root().display()));
+            out.item(format!("{} build(s)", stats.entries));
+            out.item(format!("{} bytes", stats.bytes));
+        }
+        CacheCommand::Clear => {
+            let removed = cache.clear()?;
+            out.success(format!(
+                "removed {} build(s), {} bytes from {}",
+                removed.entries,
+                removed.bytes,
+                cache.root().display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn cmd_prune(cli: &Cli, out: &Output, keep: Option<usize>) -> Result<()> {
+    let pipeline = pipeline(cli, out, &[])?;
+    let keep = keep
+        .or_else(|| pipeline.config().package.as_ref()?.keep_last)
+        .ok_or_else(|| anyhow!("pass --keep or set package.keep_last"))?;
+    let plan = load_plan(cli, out, &pipeline)?;
+    let _dist_lock = DistLock::acquire(&cli.output)?;
+    let pruned = pipeline.prune(&plan, keep)?;
+    for dir in &pruned.removed {
+        out.item(format!(
+            "removed {} ({})",
+            dir.path.display(),
+            human_bytes(dir.bytes)
+        ));
+    }
+    let bytes = pruned.removed.iter().map(|d| d.bytes).sum::<u64>() + pruned.cache.bytes;
+    out.success(format!(
+        "pruned {} version(s) and {} cached build(s), {} freed",
+        pruned.removed.len(),
+        pruned.cache.entries,
+        human_bytes(bytes)
+    ));
+    Ok(())
+}
+
+fn cmd_manifest_upgrade(cli: &Cli, out: &Output) -> Result<()> {
+    let _dist_lock = DistLock::acquire(&cli.output)?;
+    let found = upgrade_manifest(&cli.output)?;
+    if found == MANIFEST_VERSION {
+        out.success(format!("manifest is already version {MANIFEST_VERSION}"));
+    } else {
+        out.success(format!(
+            "upgraded manifest from version {found} to {MANIFEST_VERSION}"
+        ));
+    }
+    Ok(())
+}
+
+fn cmd_crates(cli: &Cli, out: &Output, command: &CratesCommand) -> Result<()> {
+    let pipeline = pipeline(cli, out, &[])?;
+    let config = pipeline
+        .config()
+        .release
+        .as_ref()
+        .and_then(|r| r.crates.clone())
+        .ok_or_else(|| anyhow!("shippo crates needs a [release.crates] section"))?;
+    let crates = if config.crates.is_empty() {
+        let plan = pipeline.plan()?;
+        let root = &pipeline.options().workspace_root;
+        plan.packages
+            .iter()
+            .filter(|p| p.project_type == ProjectType::Rust)
+            .map(|p| {
+                metadata::crate_name(&root.join(&p.path)).ok_or_else(|| {
+                    anyhow!("package {}: no crate name in {}/Cargo.toml", p.name, p.path)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        config.crates.clone()
+    };
+    if crates.is_empty() {
+        return Err(anyhow!(
+            "no crates to manage; list them in release.crates.crates or add type = \"rust\" packages"
+        ));
+    }
+    let token = std::env::var(&config.token_env)
+        .map_err(|_| anyhow!("{} must be set for shippo crates", config.token_env))?;
+    let client = ReqwestClient::new();
+    let api = CratesApi {
+        client: &client,
+        retry: Retry::default(),
+        api: &config.api,
+        token: &token,
+    };
+    match command {
+        CratesCommand::Owners {
+            command: OwnersCommand::Sync { dry_run },
+        } => {
+            if config.owners.is_empty() {
+                return Err(anyhow!("release.crates.owners is empty; nothing to sync"));
+            }
+            let verb = |done: &'static str, planned: &'static str| match dry_run {
+                true => planned,
+                false => done,
+            };
+            let mut failed = 0;
+            for krate in &crates {
+                let change = match api.sync_owners(
+                    krate,
+                    &config.owners,
+                    config.remove_unlisted,
+                    *dry_run,
+                ) {
+                    Ok(change) => change,
+                    Err(err) => {
+                        out.warn(err);
+                        failed += 1;
+                        continue;
+                    }
+                };
+                for login in &change.added {
+                    out.item(format!(
+                        "{krate}: {} {login}",
+                        verb("invited", "would invite")
+                    ));
+                }
+                for login in &change.removed {
+                    out.item(format!(
+                        "{krate}: {} {login}",
+                        verb("removed", "would remove")
+                    ));
+                }
+                if change == OwnersChange::default() {
+                    out.item(format!("{krate}: owners up to date"));
+                }
+            }
+            if failed > 0 {
+                return Err(anyhow!(
+                    "owners of {failed} of {} crate(s) failed to sync",
+                    crates.len()
+                ));
+            }
+            out.success(format!(
+                "{} owners of {} crate(s); invited users become owners once they accept",
+                verb("synced", "checked"),
+                crates.len()
+            ));
+        }
+        CratesCommand::Yank { version, undo } => {
+            let version = version.trim_start_matches('v');
+            let mut failed = 0;
+            for krate in &crates {
+                match api.yank(krate, version, *undo) {
+                    Ok(()) => out.item(format!("{krate} {version}")),
+                    Err(err) => {
+                        out.warn(err);
+                        failed += 1;
+                    }
+                }
+            }
+            if failed > 0 {
+                return Err(anyhow!(
+                    "{failed} of {} crate(s) failed to {}",
+                    crates.len(),
+                    if *undo { "unyank" } else { "yank" }
+                ));
+            }
+            out.success(format!(
+                "{} {version} of {} crate(s)",
+                if *undo { "unyanked" } else { "yanked" },
+                crates.len()
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn print_artifacts(out: &Output, manifest: &shippo_core::Manifest) {
+    for pkg in &manifest.packages {
+        for target in &pkg.targets {
+            for art in &target.artifacts {
+                out.detail(format!("{} ({} bytes)", art.filename, art.bytes));
+            }
+        }
+    }
+}
+
+fn cmd_merge(out: &Output, inputs: &[PathBuf], merged: &Path) -> Result<()> {
+    let _dist_lock = DistLock::acquire(merged)?;
+    let manifest = merge_partials(inputs, merged)?;
+    print_artifacts(out, &manifest);
+    out.success(format!(
+        "merged {} partial dist(s) into {}",
+        inputs.len(),
+        merged.display()
+    ));
+    Ok(())
+}
+
+fn cmd_sign(cli: &Cli, out: &Output, signer: &str, key: &Path) -> Result<()> {
+    let signers = verify_signers(cli)?
+        .ok_or_else(|| anyhow!("shippo sign needs a [verify.signers] section"))?;
+    if !signers.minisign.contains_key(signer) {
+        return Err(anyhow!(
+            "{signer} is not in [verify.signers]; signers are {}",
+            signers
+                .minisign
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    let _dist_lock = DistLock::acquire(&cli.output)?;
+    let signed = sign_as(&cli.output, signer, key, &SystemExecutor)?;
+    for file in &signed {
+        out.detail(file);
+    }
+    out.success(format!(
+        "signed {} file(s) in {} as {signer}",
+        signed.len(),
+        cli.output.display()
+    ));
+    Ok(())
+}
+
+fn cmd_merge_signatures(cli: &Cli, out: &Output, inputs: &[PathBuf], dist: &Path) -> Result<()> {
+    let _dist_lock = DistLock::acquire(dist)?;
+    let added = merge_signatures(dist, inputs)?;
+    for file in &added {
+        out.detail(file);
+    }
+    out.success(format!(
+        "added {} signature(s) from {} signed dist(s) to {}",
+        added.len(),
+        inputs.len(),
+        dist.display()
+    ));
+    // the threshold only needs to be met once every signer is merged
+    if let Some(signers) = verify_signers(cli)? {
+        let threshold = signers.threshold;
+        let options = VerifyOptions {
+            keys: verify_keys(cli)?,
+            signers: Some(signers),
+            ..Default::default()
+        };
+        let manifest = [MANIFEST_FILE, PARTIAL_MANIFEST_FILE]
+            .iter()
+            .map(|m| dist.join(m))
+            .find(|m| m.exists())
+            .unwrap_or_else(|| dist.join(MANIFEST_FILE));
+        let report = verify_manifest(&manifest, dist, &options, &SystemExecutor)?;
+        let short = report
+            .checks
+            .iter()
+            .filter(|c| c.check == Check::Threshold && c.status != CheckStatus::Pass)
+            .count();
+        if short > 0 {
+            out.warn(format!(
+                "{short} file(s) still lack the {threshold} signer signatures `shippo verify` requires"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn cmd_inspect(cli: &Cli, out: &Output, file: &Path, strict: bool, json: bool) -> Result<()> {
+    let dist = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let manifest = [MANIFEST_FILE, PARTIAL_MANIFEST_FILE]
+        .iter()
+        .map(|m| dist.join(m))
+        .find(|m| m.exists());
+    if let Some(manifest) = manifest {
+        let manifest = Manifest::from_json(&fs::read_to_string(manifest)?)?;
+        let options = VerifyOptions {
+            strict,
+            keys: verify_keys(cli)?,
+            signers: verify_signers(cli)?,
+            ..Default::default()
+        };
+        if let Some(found) = inspect_artifact(dist, &manifest, &name, &options, &SystemExecutor)? {
+            return print_inspection(out, &name, &found, json);
+        }
+    }
+    let data = fs::read(file).map_err(|e| anyhow!("cannot read {}: {e}", file.display()))?;
+    let provenance = Provenance::find(&data).ok_or_else(|| {
+        anyhow!(
+            "{} has no shippo provenance; was it built with [build] provenance = true?",
+            file.display()
+        )
+    })?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&provenance)?);
+        return Ok(());
+    }
+    out.heading(format!("{} {}", provenance.package, provenance.version));
+    out.item(format!("target: {}", provenance.target));
+    out.item(format!("builder: {}", provenance.builder));
+    out.item(format!(
+        "commit: {}",
+        provenance.commit.as_deref().unwrap_or("unknown")
+    ));
+    if let Some(sha) = &provenance.config_sha256 {
+        out.item(format!("config sha256: {sha}"));
+    }
+    Ok(())
+}
+
+fn print_inspection(
+    out: &Output,
+    name: &str,
+    found: &ArtifactInspection,
+    json: bool,
+) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(found)?);
+    } else {
+        out.heading(format!("{name} ({} {})", found.package, found.target));
+        out.item(format!(
+            "{} bytes, sha256 {}",
+            found.artifact.bytes, found.artifact.sha256
+        ));
+        let rows: Vec<Vec<String>> = found
+            .report
+            .checks
+            .iter()
+            .map(|c| {
+                vec![
+                    c.status.as_str().to_string(),
+                    c.check.as_str().to_string(),
+                    c.artifact.clone(),
+                    c.details.clone(),
+                ]
+            })
+            .collect();
+        out.table(&["STATUS", "CHECK", "ARTIFACT", "DETAILS"], &rows);
+        if !found.signed {
+            out.warn("the manifest records no signature for it");
+        }
+        for (path, bytes) in &found.contents {
+            out.item(format!("{path} ({bytes} bytes)"));
+            if let Some(p) = found.provenance.get(path) {
+                out.detail(format!(
+                    "built by {} from commit {} as {} {}",
+                    p.builder,
+                    p.commit.as_deref().unwrap_or("unknown"),
+                    p.package,
+                    p.version
+                ));
+            }
+        }
+        if let Some(p) = found.provenance.get(name) {
+            out.item(format!(
+                "provenance: {} {} built by {} from commit {}",
+                p.package,
+                p.version,
+                p.builder,
+                p.commit.as_deref().unwrap_or("unknown")
+            ));
+        }
+    }
+    let failed = found.report.failures().count();
+    if failed > 0 {
+        return Err(anyhow!(
+            "{failed} of {} checks failed",
+            found.report.checks.len()
+        ));
+    }
+    Ok(())
+}
+
+fn cmd_plugins(cli: &Cli, out: &Output) -> Result<()> {
+    let configured = if cli.config.exists() {
+        load_config(&cli.config)?.plugins
+    } else {
+        Default::default()
+    };
+    let plugins = plugin::discover(&configured);
+    for found in &plugins {
+        match found.describe() {
+            Ok(desc) => out.item(format!(
+                "{} {} [{}]",
+                found.name,
+                desc.version,
+                desc.capabilities.join(", ")
+            )),
+            Err(err) => out.warn(format!("{}: {err}", found.name)),
+        }
+        out.detail(found.path.display());
+    }
+    out.success(format!("{} plugin(s) found", plugins.len()));
     Ok(())
 }
@@ -0,0 +1,350 @@
+//! Per-run metrics written to a local file, for Prometheus' node-exporter
+//! textfile collector or any JSON consumer. Nothing is sent anywhere.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use serde::Serialize;
+use shippo_core::events::{Event, Stage, Subscriber};
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunMetrics {
+    pub success: bool,
+    pub duration_seconds: f64,
+    pub stages: Vec<StageMetric>,
+    pub targets: Vec<TargetMetric>,
+    pub artifacts: Vec<ArtifactMetric>,
+    pub cache_hits: usize,
+    pub uploaded_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageMetric {
+    pub stage: Stage,
+    pub duration_seconds: f64,
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetMetric {
+    pub package: String,
+    pub target: String,
+    pub duration_seconds: f64,
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactMetric {
+    pub package: String,
+    pub target: String,
+    pub filename: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug)]
+struct State {
+    started: Instant,
+    /// When each running stage started.
+    stage_started: Vec<(Stage, Instant)>,
+    /// When each package's current target began: its previous target
+    /// finishing or the package starting. Packages build concurrently with
+    /// `--jobs`.
+    target_started: BTreeMap<String, Instant>,
+    metrics: RunMetrics,
+}
+
+/// [`Subscriber`] that times stages and builds from pipeline events.
+/// Clones share the same state.
+#[derive(Debug, Clone)]
+pub struct MetricsRecorder {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for MetricsRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricsRecorder {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                started: Instant::now(),
+                stage_started: Vec::new(),
+                target_started: BTreeMap::new(),
+                metrics: RunMetrics::default(),
+            })),
+        }
+    }
+
+    /// Metrics so far; `success` is whatever the caller reports.
+    pub fn finish(&self, success: bool) -> RunMetrics {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        RunMetrics {
+            success,
+            duration_seconds: state.started.elapsed().as_secs_f64(),
+            ..state.metrics.clone()
+        }
+    }
+}
+
+impl Subscriber for MetricsRecorder {
+    fn on_event(&self, event: &Event) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let since = |start: Option<Instant>| start.map_or(0.0, |s| (now - s).as_secs_f64());
+        match event {
+            Event::StageStarted { stage } => state.stage_started.push((*stage, now)),
+            Event::StageFinished { stage } | Event::StageFailed { stage, .. } => {
+                let started = state.stage_started.iter().rposition(|(s, _)| s == stage);
+                let started = started.map(|i| state.stage_started.remove(i).1);
+                let duration_seconds = since(started);
+                state.metrics.stages.push(StageMetric {
+                    stage: *stage,
+                    duration_seconds,
+                    success: matches!(event, Event::StageFinished { .. }),
+                });
+            }
+            Event::PackageBuildStarted { package, .. } => {
+                state.target_started.insert(package.clone(), now);
+            }
+            Event::PackageBuilt {
+                package,
+                target,
+                cached,
+                ..
+            } => {
+                let duration_seconds = since(state.target_started.insert(package.clone(), now));
+                state.metrics.cache_hits += usize::from(*cached);
+                state.metrics.targets.push(TargetMetric {
+                    package: package.clone(),
+                    target: target.clone(),
+                    duration_seconds,
+                    cached: *cached,
+                });
+            }
+            Event::ArtifactPackaged {
+                package,
+                target,
+                filename,
+                bytes,
+            } => state.metrics.artifacts.push(ArtifactMetric {
+                package: package.clone(),
+                target: target.clone(),
+                filename: filename.clone(),
+                bytes: *bytes,
+            }),
+            Event::AssetUploaded { bytes, .. } => state.metrics.uploaded_bytes += bytes,
+        }
+    }
+}
+
+impl RunMetrics {
+    /// Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            for (labels, value) in samples {
+                let _ = writeln!(out, "{name}{labels} {value}");
+            }
+        };
+        family(
+            "shippo_run_success",
+            "gauge",
+            "Whether the last run succeeded.",
+            vec![(String::new(), u8::from(self.success).to_string())],
+        );
+        family(
+            "shippo_run_duration_seconds",
+            "gauge",
+            "Wall time of the last run.",
+            vec![(String::new(), self.duration_seconds.to_string())],
+        );
+        family(
+            "shippo_stage_duration_seconds",
+            "gauge",
+            "Wall time per pipeline stage.",
+            self.stages
+                .iter()
+                .map(|s| {
+                    (
+                        labels(&[("stage", s.stage.as_str())]),
+                        s.duration_seconds.to_string(),
+                    )
+                })
+                .collect(),
+        );
+        family(
+            "shippo_target_build_duration_seconds",
+            "gauge",
+            "Wall time per built target.",
+            self.targets
+                .iter()
+                .map(|t| {
+                    (
+                        labels(&[
+                            ("package", &t.package),
+                            ("target", &t.target),
+                            ("cached", if t.cached { "true" } else { "false" }),
+                        ]),
+                        t.duration_seconds.to_string(),
+                    )
+                })
+                .collect(),
+        );
+        family(
+            "shippo_targets_built",
+            "gauge",
+            "Targets built in the last run, including cache hits.",
+            vec![(String::new(), self.targets.len().to_string())],
+        );
+        family(
+            "shippo_cache_hits",
+            "gauge",
+            "Targets restored from the artifact cache in the last run.",
+            vec![(String::new(), self.cache_hits.to_string())],
+        );
+        family(
+            "shippo_artifact_bytes",
+            "gauge",
+            "Size of each packaged artifact.",
+            self.artifacts
+                .iter()
+                .map(|a| {
+                    (
+                        labels(&[
+                            ("package", &a.package),
+                            ("target", &a.target),
+                            ("filename", &a.filename),
+                        ]),
+                        a.bytes.to_string(),
+                    )
+                })
+                .collect(),
+        );
+        family(
+            "shippo_uploaded_bytes",
+            "gauge",
+            "Bytes uploaded as release assets in the last run.",
+            vec![(String::new(), self.uploaded_bytes.to_string())],
+        );
+        out
+    }
+
+    /// Writes JSON for a `.json` path and Prometheus text otherwise. The file
+    /// is replaced atomically so collectors never read a partial file.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let body = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::to_string_pretty(self).map_err(io::Error::other)?
+        } else {
+            self.to_prometheus()
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, body)?;
+        fs::rename(&tmp, path)
+    }
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let inner: Vec<String> = pairs
+        .iter()
+        .map(|(k, v)| {
+            let v = v
+                .replace('\\', r"\\")
+                .replace('"', "\\\"")
+                .replace('\n', r"\n");
+            format!("{k}=\"{v}\"")
+        })
+        .collect();
+    format!("{{{}}}", inner.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder_prometheus_output() {
+        let recorder = MetricsRecorder::new();
+        for event in [
+            Event::StageStarted {
+                stage: Stage::Build,
+            },
+            Event::PackageBuildStarted {
+                package: "app".into(),
+                targets: vec!["linux-amd64".into()],
+            },
+            Event::PackageBuilt {
+                package: "app".into(),
+                target: "linux-amd64".into(),
+                artifacts: 1,
+                cached: true,
+            },
+            Event::StageFinished {
+                stage: Stage::Build,
+            },
+            Event::ArtifactPackaged {
+                package: "app".into(),
+                target: "linux-amd64".into(),
+                filename: "app.tar.gz".into(),
+                bytes: 42,
+            },
+        ] {
+            recorder.on_event(&event);
+        }
+        let metrics = recorder.finish(true);
+        assert_eq!(metrics.cache_hits, 1);
+        let text = metrics.to_prometheus();
+        assert!(text.contains("shippo_run_success 1\n"));
+        assert!(text.contains("shippo_stage_duration_seconds{stage=\"build\"} "));
+        assert!(text.contains(
+            "shippo_artifact_bytes{package=\"app\",target=\"linux-amd64\",filename=\"app.tar.gz\"} 42\n"
+        ));
+        assert!(text.contains("shippo_cache_hits 1\n"));
+    }
+
+    #[test]
+    fn test_concurrent_packages_timed_from_their_own_start() {
+        let recorder = MetricsRecorder::new();
+        let started = |package: &str| Event::PackageBuildStarted {
+            package: package.into(),
+            targets: vec!["native".into()],
+        };
+        let built = |package: &str| Event::PackageBuilt {
+            package: package.into(),
+            target: "native".into(),
+            artifacts: 1,
+            cached: false,
+        };
+        recorder.on_event(&Event::StageStarted {
+            stage: Stage::Build,
+        });
+        recorder.on_event(&started("slow"));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        recorder.on_event(&started("fast"));
+        recorder.on_event(&built("fast"));
+        recorder.on_event(&built("slow"));
+        recorder.on_event(&Event::StageFinished {
+            stage: Stage::Build,
+        });
+        let metrics = recorder.finish(true);
+        let duration = |package: &str| {
+            let target = metrics.targets.iter().find(|t| t.package == package);
+            target.unwrap().duration_seconds
+        };
+        assert!(duration("slow") >= 0.05);
+        assert!(duration("fast") < duration("slow"));
+        assert!(metrics.stages[0].duration_seconds >= 0.05);
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt::Display;
+use std::io::IsTerminal;
+
+use clap::ValueEnum;
+use shippo::Event;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputStyle {
+    /// One summary line per command
+    Compact,
+    /// Per-package and per-artifact details
+    Verbose,
+}
+
+/// Human-facing terminal output shared by all subcommands.
+#[derive(Debug, Clone, Copy)]
+pub struct Output {
+    color: bool,
+    style: OutputStyle,
+}
+
+impl Output {
+    pub fn new(choice: ColorChoice, style: OutputStyle, ci: bool) -> Self {
+        Self {
+            color: color_enabled(choice, ci),
+            style,
+        }
+    }
+
+    pub fn color(&self) -> bool {
+        self.color
+    }
+
+    pub fn verbose(&self) -> bool {
+        self.style == OutputStyle::Verbose
+    }
+
+    pub fn heading(&self, msg: impl Display) {
+        println!("{}", self.paint("1", msg));
+    }
+
+    pub fn success(&self, msg: impl Display) {
+        println!("{} {}", self.paint("32", "ok"), msg);
+    }
+
+    pub fn warn(&self, msg: impl Display) {
+        eprintln!("{} {}", self.paint("33", "warning:"), msg);
+    }
+
+    pub fn item(&self, msg: impl Display) {
+        println!("- {msg}");
+    }
+
+    /// Printed only with `--style verbose`.
+    pub fn detail(&self, msg: impl Display) {
+        if self.verbose() {
+            println!("    {}", self.paint("2", msg));
+        }
+    }
+
+    /// Prints rows as left-aligned columns under `header`.
+    pub fn table(&self, header: &[&str], rows: &[Vec<String>]) {
+        let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+        for row in rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let line = |cells: Vec<String>| {
+            let padded: Vec<String> = cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect();
+            padded.join("  ").trim_end().to_string()
+        };
+        println!(
+            "{}",
+            self.paint("1", line(header.iter().map(|h| h.to_string()).collect()))
+        );
+        for row in rows {
+            println!("{}", line(row.clone()));
+        }
+    }
+
+    /// Renders pipeline events as progress lines.
+    pub fn progress(self) -> impl Fn(&Event) + Send + Sync {
+        move |event: &Event| match event {
+            Event::PackageBuildStarted { package, targets } => {
+                self.item(format!("building {package} ({})", targets.join(", ")))
+            }
+            Event::PackageBuilt {
+                package,
+                target,
+                artifacts,
+                cached,
+            } => self.detail(format!(
+                "built {package} [{target}]: {artifacts} artifact(s){}",
+                if *cached { " (cached)" } else { "" }
+            )),
+            Event::ArtifactPackaged {
+                filename, bytes, ..
+            } => self.detail(format!("{filename} ({bytes} bytes)")),
+            Event::AssetUploaded { filename, .. } => self.detail(format!("uploaded {filename}")),
+            Event::StageFailed { stage, .. } => self.warn(format!("{stage:?} stage failed")),
+            _ => {}
+        }
+    }
+
+    fn paint(&self, code: &str, msg: impl Display) -> String {
+        if self.color {
+            format!("\x1b[{code}m{msg}\x1b[0m")
+        } else {
+            msg.to_string()
+        }
+    }
+}
+
+fn color_enabled(choice: ColorChoice, ci: bool) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            if ci || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+                return false;
+            }
+            if std::env::var("CLICOLOR_FORCE").is_ok_and(|v| v != "0") {
+                return true;
+            }
+            if std::env::var("CLICOLOR").is_ok_and(|v| v == "0") {
+                return false;
+            }
+            std::io::stdout().is_terminal()
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! What a release got through before it failed: each stage's status, each
+//! package's targets, and the command that failed, printed and written to
+//! `dist/summary.json` instead of only an error chain.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use serde::Serialize;
+use shippo_core::events::{Event, Stage, Subscriber};
+use shippo_core::exec::{CommandRecord, COMMANDS_FILE};
+pub use shippo_core::SUMMARY_FILE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Succeeded,
+    Failed,
+    NotRun,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Succeeded => "succeeded",
+            Status::Failed => "failed",
+            Status::NotRun => "not run",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub success: bool,
+    pub error: Option<String>,
+    pub stages: Vec<StageSummary>,
+    pub packages: Vec<PackageSummary>,
+    /// The last command that failed during the failing stage.
+    pub failed_command: Option<CommandRecord>,
+    /// Every command the run started, as JSON lines.
+    pub command_log: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageSummary {
+    pub stage: Stage,
+    pub status: Status,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageSummary {
+    pub package: String,
+    pub targets: Vec<TargetSummary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetSummary {
+    pub target: String,
+    /// Whether it was built.
+    pub status: Status,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    stages: Vec<(StageSummary, Option<SystemTime>)>,
+    packages: Vec<PackageSummary>,
+}
+
+/// [`Subscriber`] that tracks stage and build progress from pipeline
+/// events. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct SummaryRecorder {
+    state: Arc<Mutex<State>>,
+}
+
+impl SummaryRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The summary so far. `expected` are the stages the command runs, so
+    /// ones never reached show as not run; `error` is the run's error, if
+    /// it failed. The failing command is looked up in `dist`'s command log.
+    pub fn finish(&self, expected: &[Stage], error: Option<String>, dist: &Path) -> RunSummary {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut stages: Vec<StageSummary> = state.stages.iter().map(|(s, _)| s.clone()).collect();
+        for stage in expected {
+            if !stages.iter().any(|s| s.stage == *stage) {
+                stages.push(StageSummary {
+                    stage: *stage,
+                    status: Status::NotRun,
+                    error: None,
+                });
+            }
+        }
+        let build_failed = stages
+            .iter()
+            .any(|s| s.stage == Stage::Build && s.status == Status::Failed);
+        let mut packages = state.packages.clone();
+        for target in packages.iter_mut().flat_map(|p| &mut p.targets) {
+            if target.status == Status::NotRun && build_failed {
+                target.status = Status::Failed;
+            }
+        }
+        let log = dist.join(COMMANDS_FILE);
+        let failed_since = state
+            .stages
+            .iter()
+            .find(|(s, _)| s.status == Status::Failed)
+            .and_then(|(_, started)| *started);
+        let failed_command = failed_since.and_then(|since| last_failed_command(&log, since));
+        RunSummary {
+            success: error.is_none(),
+            error,
+            stages,
+            packages,
+            failed_command,
+            command_log: log.exists().then_some(log),
+        }
+    }
+}
+
+impl Subscriber for SummaryRecorder {
+    fn on_event(&self, event: &Event) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut set_stage = |stage: Stage, status: Status, error: Option<&String>| {
+            let summary = StageSummary {
+                stage,
+                status,
+                error: error.cloned(),
+            };
+            match state.stages.iter_mut().find(|(s, _)| s.stage == stage) {
+                Some((existing, _)) => *existing = summary,
+                None => state.stages.push((summary, Some(SystemTime::now()))),
+            }
+        };
+        match event {
+            Event::StageStarted { stage } => set_stage(*stage, Status::NotRun, None),
+            Event::StageFinished { stage } => set_stage(*stage, Status::Succeeded, None),
+            Event::StageFailed { stage, error } => set_stage(*stage, Status::Failed, Some(error)),
+            Event::PackageBuildStarted { package, targets } => {
+                state.packages.push(PackageSummary {
+                    package: package.clone(),
+                    targets: targets
+                        .iter()
+                        .map(|t| TargetSummary {
+                            target: t.clone(),
+                            status: Status::NotRun,
+                        })
+                        .collect(),
+                })
+            }
+            Event::PackageBuilt {
+                package, target, ..
+            } => {
+                if let Some(pkg) = state.packages.iter_mut().find(|p| &p.package == package) {
+                    match pkg.targets.iter_mut().find(|t| &t.target == target) {
+                        Some(t) => t.status = Status::Succeeded,
+                        None => pkg.targets.push(TargetSummary {
+                            target: target.clone(),
+                            status: Status::Succeeded,
+                        }),
+                    }
+                }
+            }
+            Event::ArtifactPackaged { .. } | Event::AssetUploaded { .. } => {}
+        }
+    }
+}
+
+impl RunSummary {
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json + "\n")
+    }
+}
+
+/// The last entry of the command log started at or after `since` that
+/// exited non-zero or could not be spawned.
+fn last_failed_command(log: &Path, since: SystemTime) -> Option<CommandRecord> {
+    fs::read_to_string(log)
+        .ok()?
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<CommandRecord>(line).ok())
+        .take_while(|r| SystemTime::from(r.started_at) >= since)
+        .find(|r| r.error.is_some() || r.exit_code != Some(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shippo_core::exec::{
+        CommandOutput, CommandSpec, Executor, MockExecutor, RecordingExecutor,
+    };
+
+    #[test]
+    fn test_summary_of_failed_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = SummaryRecorder::new();
+        let mock = MockExecutor::new();
+        mock.respond(
+            "go",
+            CommandOutput {
+                code: Some(2),
+                ..Default::default()
+            },
+        );
+        let executor = RecordingExecutor::wrap(mock, dir.path().join(COMMANDS_FILE));
+        executor.run(&CommandSpec::new("git")).unwrap();
+        for event in [
+            Event::StageStarted { stage: Stage::Plan },
+            Event::StageFinished { stage: Stage::Plan },
+            Event::StageStarted {
+                stage: Stage::Build,
+            },
+            Event::PackageBuildStarted {
+                package: "cli".into(),
+                targets: vec!["linux-amd64".into(), "darwin-arm64".into()],
+            },
+            Event::PackageBuilt {
+                package: "cli".into(),
+                target: "linux-amd64".into(),
+                artifacts: 1,
+                cached: false,
+            },
+        ] {
+            recorder.on_event(&event);
+        }
+        let mut go = CommandSpec::new("go");
+        go.args(["build", "-o", "dist-build/darwin-arm64/cli"]);
+        executor.run(&go).unwrap();
+        recorder.on_event(&Event::StageFailed {
+            stage: Stage::Build,
+            error: "command go build failed".into(),
+        });
+
+        let summary = recorder.finish(
+            &[Stage::Plan, Stage::Build, Stage::Package, Stage::Publish],
+            Some("build failed".into()),
+            dir.path(),
+        );
+        assert!(!summary.success);
+        let stages: Vec<_> = summary.stages.iter().map(|s| (s.stage, s.status)).collect();
+        assert_eq!(
+            stages,
+            [
+                (Stage::Plan, Status::Succeeded),
+                (Stage::Build, Status::Failed),
+                (Stage::Package, Status::NotRun),
+                (Stage::Publish, Status::NotRun),
+            ]
+        );
+        let targets: Vec<_> = summary.packages[0]
+            .targets
+            .iter()
+            .map(|t| (t.target.as_str(), t.status))
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                ("linux-amd64", Status::Succeeded),
+                ("darwin-arm64", Status::Failed)
+            ]
+        );
+        let failed = summary.failed_command.unwrap();
+        assert_eq!(failed.program, "go");
+        assert_eq!(failed.exit_code, Some(2));
+        assert_eq!(summary.command_log, Some(dir.path().join(COMMANDS_FILE)));
+    }
+}
@@ -0,0 +1,75 @@
+//! Runs `[wasm_plugins]` from the pipeline.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use camino::Utf8PathBuf;
+use shippo_core::{Manifest, ShippoConfig};
+use shippo_pack::BuiltOutput;
+use shippo_wasm::{Artifact, Release, WasmPlugin};
+
+fn plugins(config: &ShippoConfig, kind: &str) -> Result<Vec<WasmPlugin>> {
+    let mut loaded = Vec::new();
+    for (name, cfg) in config.wasm_plugins.iter().filter(|(_, c)| c.kind == kind) {
+        loaded.push(WasmPlugin::load(name, cfg)?);
+    }
+    Ok(loaded)
+}
+
+/// Passes every built file through the transformers in name order, writing
+/// results under `scratch` so build outputs are left untouched.
+pub(crate) fn transform_outputs(
+    config: &ShippoConfig,
+    scratch: &Path,
+    outputs: &mut [BuiltOutput],
+) -> Result<()> {
+    let transformers = plugins(config, "transformer")?;
+    if transformers.is_empty() {
+        return Ok(());
+    }
+    for output in outputs.iter_mut() {
+        let dir = scratch.join(&output.package).join(&output.target);
+        fs::create_dir_all(&dir)?;
+        for path in output.artifacts.iter_mut() {
+            if !path.is_file() {
+                continue;
+            }
+            let mut artifact = Artifact {
+                name: path.file_name().unwrap_or("artifact").to_string(),
+                target: output.target.clone(),
+                contents: fs::read(&*path)?,
+            };
+            for plugin in &transformers {
+                artifact = plugin.transform(&artifact)?;
+            }
+            let dest = dir.join(&artifact.name);
+            fs::write(&dest, &artifact.contents)?;
+            *path = Utf8PathBuf::from_path_buf(dest)
+                .map_err(|p| anyhow::anyhow!("non UTF-8 path {}", p.display()))?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn notify(config: &ShippoConfig, manifest: &Manifest) -> Result<()> {
+    let release = Release {
+        project: manifest
+            .packages
+            .first()
+            .map(|p| p.name.clone())
+            .unwrap_or_default(),
+        version: manifest.project.version.clone(),
+        artifacts: manifest
+            .packages
+            .iter()
+            .flat_map(|p| &p.targets)
+            .flat_map(|t| &t.artifacts)
+            .map(|a| a.filename.clone())
+            .collect(),
+    };
+    for plugin in plugins(config, "notifier")? {
+        plugin.notify(&release)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! Container images for `[docker]`: built next to the package's targets,
+//! tagged for the release, and pushed by the publish stage.
+
+use shippo_core::exec::CommandSpec;
+use shippo_core::{DockerConfig, ManifestImage, PackagePlan};
+
+use crate::{run, BuildContext, BuildError, Result};
+
+/// Builds `plan`'s image with every tag of `docker` for `ctx.version` and
+/// returns it, unpushed.
+pub fn build_image(
+    plan: &PackagePlan,
+    docker: &DockerConfig,
+    ctx: &BuildContext,
+) -> Result<ManifestImage> {
+    let dir = ctx.workspace_root.join(plan.path.as_str());
+    let repository = docker.repository(&plan.name);
+    let tags = docker.image_tags(ctx.version);
+    let mut cmd = CommandSpec::new("docker");
+    cmd.args(["build", "--file"])
+        .arg(dir.join(&docker.dockerfile).to_string_lossy());
+    for tag in &tags {
+        cmd.args(["--tag", &format!("{repository}:{tag}")]);
+    }
+    for (key, value) in &docker.build_args {
+        cmd.args(["--build-arg", &format!("{key}={value}")]);
+    }
+    cmd.args([
+        "--label",
+        &format!(
+            "org.opencontainers.image.version={}",
+            ctx.version.trim_start_matches('v')
+        ),
+    ])
+    .arg(dir.join(&docker.context).to_string_lossy())
+    .current_dir(&dir);
+    run(ctx, &cmd)?;
+
+    let reference = format!("{repository}:{}", tags[0]);
+    let mut inspect = CommandSpec::new("docker");
+    inspect.args(["image", "inspect", "--format", "{{.Id}}", &reference]);
+    let output = ctx
+        .executor
+        .output(&inspect)
+        .map_err(|source| BuildError::Spawn {
+            command: inspect.to_string(),
+            source,
+        })?;
+    let id = output.stdout.trim();
+    if !output.success() || id.is_empty() {
+        return Err(BuildError::CommandFailed {
+            command: inspect.to_string(),
+            status: output.status(),
+        });
+    }
+    Ok(ManifestImage {
+        repository,
+        tags,
+        id: id.to_string(),
+        digest: None,
+    })
+}
@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+
+use camino::Utf8PathBuf;
+use shippo_core::exec::CommandSpec;
+use shippo_core::provenance::GO_VARIABLE;
+use shippo_core::{targets, PackagePlan};
+
+use crate::{run, BuildContext, BuildError, Builder, BuiltTarget, Result};
+
+/// Where builds are written, under the package directory, in a directory
+/// per target so targets don't overwrite each other and binaries keep their
+/// names; `[build] scratch_dir` replaces it.
+const GO_OUTPUT_DIR: &str = "dist-build";
+
+pub struct GoBuilder;
+
+impl Builder for GoBuilder {
+    fn name(&self) -> &str {
+        "go"
+    }
+
+    fn detect(&self, dir: &Path) -> bool {
+        dir.join("go.mod").exists()
+    }
+
+    fn build(&self, plan: &PackagePlan, target: &str, ctx: &BuildContext) -> Result<BuiltTarget> {
+        let workspace_root = ctx.workspace_root;
+        let version = ctx.version;
+        let parts: Vec<&str> = target.split(['-', '/']).collect();
+        let (goos, goarch) = match targets::resolve(target).and_then(|t| t.go) {
+            Some(go) => go,
+            None if parts.len() >= 2 => (parts[0], parts[1]),
+            None => ("", ""),
+        };
+        let mut ldflags = format!("-X main.version={} -X main.commit=", version);
+        if let Some(provenance) = ctx.provenance(self.name(), plan, target) {
+            // quoted, as the JSON may not be a single ldflags word
+            ldflags.push_str(&format!(" '-X {GO_VARIABLE}={}'", provenance.blob()));
+        }
+        let bins = plan.go.as_ref().map(|g| &g.bins).filter(|b| !b.is_empty());
+        let bins: Vec<(&str, Option<&str>)> = match bins {
+            Some(bins) => bins
+                .iter()
+                .map(|(name, main)| (name.as_str(), Some(main.as_str())))
+                .collect(),
+            None => vec![(plan.name.as_str(), None)],
+        };
+        let pkg_dir = workspace_root.join(plan.path.as_str());
+        let exe = if goos == "windows" { ".exe" } else { "" };
+        let out_dir = ctx
+            .scratch_dir(plan)
+            .unwrap_or_else(|| PathBuf::from(GO_OUTPUT_DIR))
+            .join(target.replace(['/', '\\'], "-"));
+        std::fs::create_dir_all(pkg_dir.join(&out_dir))?;
+        let mut artifacts = Vec::new();
+        for (name, main) in bins {
+            let output = out_dir.join(format!("{name}{exe}"));
+            let mut cmd = CommandSpec::new("go");
+            cmd.arg("build");
+            if !goos.is_empty() {
+                cmd.env("GOOS", goos);
+            }
+            if !goarch.is_empty() {
+                cmd.env("GOARCH", goarch);
+            }
+            cmd.arg("-ldflags").arg(&ldflags);
+            cmd.arg("-o").arg(output.to_string_lossy());
+            if let Some(main) = main {
+                cmd.arg(main);
+            }
+            cmd.current_dir(&pkg_dir);
+            run(ctx, &cmd)?;
+            let bin = pkg_dir.join(output);
+            if bin.exists() {
+                artifacts.push(Utf8PathBuf::from_path_buf(bin).map_err(BuildError::NonUtf8Path)?);
+            }
+        }
+        Ok(BuiltTarget {
+            target: target.to_string(),
+            artifacts,
+            origin: None,
+        })
+    }
+}
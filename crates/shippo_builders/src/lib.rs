@@ -1,295 +1,363 @@
+use std::collections::BTreeMap;
+use std::fmt;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::sync::Arc;
+use std::time::Instant;
 
-use anyhow::{anyhow, Context, Result};
 use camino::Utf8PathBuf;
-use shippo_core::{NodeBinaryConfig, PackagePlan, ProjectType};
-use tracing::info;
+use shippo_core::cache::{source_fingerprint, ArtifactCache, CacheKey};
+use shippo_core::exec::{CommandDigest, CommandSpec, Executor, SystemExecutor};
+use shippo_core::plugin::{plugin_ref, PluginError};
+use shippo_core::provenance::{self, Provenance};
+use shippo_core::redact::redact;
+use shippo_core::{ArtifactOrigin, PackagePlan, ToolingInfo};
+use tracing::{info, warn};
+
+mod docker;
+mod go;
+mod node;
+mod plugin;
+mod python;
+mod rust;
+mod schedule;
+
+pub use docker::build_image;
+pub use go::GoBuilder;
+pub use node::NodeBuilder;
+pub use plugin::PluginBuilder;
+pub use python::PythonBuilder;
+pub use rust::RustBuilder;
+pub use schedule::{parallel_map, TaggedExecutor};
+
+#[derive(thiserror::Error, Debug)]
+pub enum BuildError {
+    #[error("failed to spawn command {command}: {source}")]
+    Spawn {
+        command: String,
+        source: std::io::Error,
+    },
+    #[error("command {command} failed with status {status}")]
+    CommandFailed { command: String, status: String },
+    #[error("no binaries produced for {package}")]
+    NoOutputs { package: String },
+    #[error("frontend build_dir '{build_dir}' not found after build in {}", .dir.display())]
+    MissingBuildDir { build_dir: String, dir: PathBuf },
+    #[error("configuration error: {0}")]
+    Config(String),
+    #[error("no builder registered for {0}")]
+    UnknownBuilder(String),
+    #[error("path is not valid UTF-8: {}", .0.display())]
+    NonUtf8Path(PathBuf),
+    #[error(transparent)]
+    Plugin(#[from] PluginError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T, E = BuildError> = std::result::Result<T, E>;
 
 #[derive(Debug, Clone)]
 pub struct BuiltTarget {
     pub target: String,
     pub artifacts: Vec<Utf8PathBuf>,
+    /// Filled in by [`BuilderRegistry::build_package`]; builders leave it
+    /// `None`.
+    pub origin: Option<ArtifactOrigin>,
 }
 
-pub fn build_package(
-    plan: &PackagePlan,
-    workspace_root: &Path,
-    version: &str,
-    verbose: bool,
-) -> Result<Vec<BuiltTarget>> {
-    let mut outputs = Vec::new();
-    for target in &plan.targets {
-        match plan.project_type {
-            ProjectType::Rust => outputs.push(build_rust(plan, workspace_root, target, verbose)?),
-            ProjectType::Go => {
-                outputs.push(build_go(plan, workspace_root, target, verbose, version)?)
-            }
-            ProjectType::Node => outputs.push(build_node(plan, workspace_root, target, verbose)?),
-            ProjectType::Python => {
-                outputs.push(build_python(plan, workspace_root, target, verbose)?)
-            }
-        }
-    }
-    Ok(outputs)
+/// Paths and flags shared by every [`Builder::build`] call.
+#[derive(Clone, Copy)]
+pub struct BuildContext<'a> {
+    pub workspace_root: &'a Path,
+    pub version: &'a str,
+    pub verbose: bool,
+    pub executor: &'a dyn Executor,
+    /// Reuse and store outputs here when set.
+    pub cache: Option<&'a ArtifactCache>,
+    /// Embed this into binaries (package, target and builder are filled
+    /// in per build) when `[build] provenance` is on.
+    pub provenance: Option<&'a Provenance>,
+    /// `[build] scratch_dir`, absolute; builders write under
+    /// `<scratch>/<package>` instead of the package directory.
+    pub scratch: Option<&'a Path>,
 }
 
-fn build_rust(
-    plan: &PackagePlan,
-    workspace_root: &Path,
-    target: &str,
-    verbose: bool,
-) -> Result<BuiltTarget> {
-    let use_cross = std::env::var("SHIPPO_USE_CROSS").is_ok()
-        || (target != "native" && which::which("cross").is_ok());
-    let mut cmd = if use_cross && target != "native" {
-        let mut c = Command::new("cross");
-        c.arg("build").arg("--release").arg("--target").arg(target);
-        c
-    } else {
-        let mut c = Command::new("cargo");
-        c.arg("build").arg("--release");
-        if target != "native" {
-            c.arg("--target").arg(target);
-        }
-        c
-    };
-    cmd.current_dir(workspace_root.join(plan.path.as_str()));
-    run(cmd, verbose)?;
-    let target_root = std::env::var("CARGO_TARGET_DIR")
-        .map(PathBuf::from)
-        .map(|p| {
-            if p.is_absolute() {
-                p
-            } else {
-                workspace_root.join(p)
-            }
+impl BuildContext<'_> {
+    /// The provenance to embed for `plan` at `target`, if enabled.
+    pub fn provenance(
+        &self,
+        builder: &str,
+        plan: &PackagePlan,
+        target: &str,
+    ) -> Option<Provenance> {
+        self.provenance.map(|p| Provenance {
+            package: plan.name.clone(),
+            target: target.to_string(),
+            builder: builder.to_string(),
+            ..p.clone()
         })
-        .unwrap_or_else(|_| workspace_root.join(plan.path.as_str()).join("target"));
-    let binary_dir = if target == "native" {
-        target_root.join("release")
-    } else {
-        target_root.join(target).join("release")
-    };
-    let mut artifacts = Vec::new();
-    if binary_dir.exists() {
-        for entry in std::fs::read_dir(&binary_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() && is_executable(&path) {
-                if let Ok(p) = Utf8PathBuf::from_path_buf(path) {
-                    artifacts.push(p);
-                }
-            }
-        }
     }
-    if artifacts.is_empty() {
-        return Err(anyhow!("no binaries produced for {}", plan.name));
+
+    /// Where `plan`'s build outputs go when out of the source tree.
+    pub fn scratch_dir(&self, plan: &PackagePlan) -> Option<PathBuf> {
+        self.scratch.map(|s| s.join(&plan.name))
     }
-    Ok(BuiltTarget {
-        target: target.to_string(),
-        artifacts,
-    })
 }
 
-fn build_go(
-    plan: &PackagePlan,
-    workspace_root: &Path,
-    target: &str,
-    verbose: bool,
-    version: &str,
-) -> Result<BuiltTarget> {
-    let parts: Vec<&str> = target.split(['-', '/']).collect();
-    let (goos, goarch) = if parts.len() >= 2 {
-        (parts[0], parts[1])
-    } else {
-        ("", "")
-    };
-    let mut cmd = Command::new("go");
-    cmd.arg("build");
-    if !goos.is_empty() {
-        cmd.env("GOOS", goos);
+/// One ecosystem's build logic. Implementations are looked up in a
+/// [`BuilderRegistry`] by [`Builder::name`].
+pub trait Builder: Send + Sync {
+    /// Registry key: a project type such as `rust`, or `plugin:<name>`.
+    fn name(&self) -> &str;
+
+    /// Whether `dir` looks like a project this builder handles.
+    fn detect(&self, dir: &Path) -> bool;
+
+    /// Targets to build for `plan`; the configured targets by default.
+    fn plan(&self, plan: &PackagePlan) -> Result<Vec<String>> {
+        Ok(plan.targets.clone())
     }
-    if !goarch.is_empty() {
-        cmd.env("GOARCH", goarch);
+
+    fn build(&self, plan: &PackagePlan, target: &str, ctx: &BuildContext) -> Result<BuiltTarget>;
+}
+
+#[derive(Clone, Default)]
+pub struct BuilderRegistry {
+    builders: BTreeMap<String, Arc<dyn Builder>>,
+}
+
+impl BuilderRegistry {
+    /// Registry with the Rust, Go, Node, and Python builders.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::default();
+        registry.register(RustBuilder);
+        registry.register(GoBuilder);
+        registry.register(NodeBuilder);
+        registry.register(PythonBuilder);
+        registry
     }
-    cmd.arg("-ldflags")
-        .arg(format!("-X main.version={} -X main.commit=", version));
-    cmd.current_dir(workspace_root.join(plan.path.as_str()));
-    run(cmd, verbose)?;
-    let mut artifacts = Vec::new();
-    let bin = workspace_root
-        .join(plan.path.as_str())
-        .join(plan.name.clone());
-    if bin.exists() {
-        artifacts
-            .push(Utf8PathBuf::from_path_buf(bin).map_err(|e| anyhow!(e.display().to_string()))?);
+
+    /// Adds `builder`, replacing any builder registered under the same name.
+    pub fn register(&mut self, builder: impl Builder + 'static) {
+        self.builders
+            .insert(builder.name().to_string(), Arc::new(builder));
     }
-    Ok(BuiltTarget {
-        target: target.to_string(),
-        artifacts,
-    })
-}
 
-fn build_node(
-    plan: &PackagePlan,
-    workspace_root: &Path,
-    target: &str,
-    verbose: bool,
-) -> Result<BuiltTarget> {
-    let mut node_cfg = plan.node.clone().unwrap_or_default();
-    let project_dir = workspace_root.join(plan.path.as_str());
-    let mut npm_ci = Command::new("npm");
-    npm_ci.arg("ci").current_dir(&project_dir);
-    run(npm_ci, verbose)?;
-    if node_cfg.mode == "frontend" {
-        if let Some(cmd) = node_cfg.frontend.as_ref().and_then(|f| f.build_cmd.clone()) {
-            run(shell_cmd(&cmd, &project_dir), verbose)?;
-        } else {
-            let mut npm_build = Command::new("npm");
-            npm_build.arg("run").arg("build").current_dir(&project_dir);
-            run(npm_build, verbose)?;
-        }
-        let build_dir = node_cfg
-            .frontend
-            .as_ref()
-            .map(|f| f.build_dir.clone())
-            .unwrap_or_else(|| "dist".to_string());
-        let build_path = project_dir.join(&build_dir);
-        if !build_path.exists() {
-            return Err(anyhow!(
-                "frontend build_dir '{}' not found after build in {}",
-                build_dir,
-                project_dir.display()
-            ));
-        }
-        let path =
-            Utf8PathBuf::from_path_buf(build_path).map_err(|e| anyhow!(e.display().to_string()))?;
-        Ok(BuiltTarget {
-            target: target.to_string(),
-            artifacts: vec![path],
-        })
-    } else {
-        if node_cfg.binary.is_none() {
-            node_cfg.binary = Some(NodeBinaryConfig {
-                tool: "pkg".into(),
-                entry: Some("index.js".into()),
-                targets: vec![target.to_string()],
-            });
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Builder>> {
+        self.builders.get(name).cloned()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.builders.keys().map(String::as_str).collect()
+    }
+
+    /// Names of the builders that recognise `dir`.
+    pub fn detect(&self, dir: &Path) -> Vec<&str> {
+        self.builders
+            .values()
+            .filter(|b| b.detect(dir))
+            .map(|b| b.name())
+            .collect()
+    }
+
+    /// Resolves the builder for `plan`: `[build] builder` if set, otherwise
+    /// its project type. Unregistered `plugin:<name>` builders are looked up
+    /// in `plugins` and on PATH.
+    pub fn resolve(
+        &self,
+        plan: &PackagePlan,
+        plugins: &BTreeMap<String, String>,
+    ) -> Result<Arc<dyn Builder>> {
+        let key = plan
+            .builder
+            .as_deref()
+            .unwrap_or(plan.project_type.as_str());
+        if let Some(builder) = self.get(key) {
+            return Ok(builder);
         }
-        let bin_cfg = node_cfg
-            .binary
-            .ok_or_else(|| anyhow!("node.cli-binary requires [node.binary]"))?;
-        let entry = bin_cfg.entry.unwrap_or_else(|| "index.js".to_string());
-        let mut cmd = Command::new(&bin_cfg.tool);
-        cmd.arg(entry);
-        if !bin_cfg.targets.is_empty() {
-            cmd.arg("--targets").arg(bin_cfg.targets.join(","));
+        match plugin_ref(key) {
+            Some(name) => Ok(Arc::new(PluginBuilder::new(shippo_core::plugin::find(
+                name, plugins,
+            )?))),
+            None => Err(BuildError::UnknownBuilder(key.to_string())),
         }
-        cmd.current_dir(&project_dir);
-        run(cmd, verbose)?;
-        let mut artifacts = Vec::new();
-        for entry in std::fs::read_dir(&project_dir)? {
-            let entry = entry?;
-            if entry.file_name().to_string_lossy().contains(&plan.name) {
-                if let Ok(p) = Utf8PathBuf::from_path_buf(entry.path()) {
-                    artifacts.push(p);
+    }
+
+    /// Builds every target of `plan`.
+    pub fn build_package(
+        &self,
+        plan: &PackagePlan,
+        plugins: &BTreeMap<String, String>,
+        ctx: &BuildContext,
+    ) -> Result<Vec<BuiltTarget>> {
+        let builder = self.resolve(plan, plugins)?;
+        let cached = ctx.cache.and_then(|cache| cache_inputs(plan, ctx, cache));
+        builder
+            .plan(plan)?
+            .iter()
+            .map(|target| {
+                let _span = tracing::info_span!("target", target = %target).entered();
+                let started = Instant::now();
+                let commands = CommandDigest::wrap(ctx.executor);
+                let ctx = &BuildContext {
+                    executor: &commands,
+                    ..*ctx
+                };
+                let origin = |cached: bool| ArtifactOrigin {
+                    builder: builder.name().to_string(),
+                    source: plan.path.to_string(),
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    command_sha256: commands.digest(),
+                    cached,
+                };
+                let Some((cache, source, toolchain)) = &cached else {
+                    let mut built = builder.build(plan, target, ctx)?;
+                    built.origin = Some(origin(false));
+                    return Ok(built);
+                };
+                let key = CacheKey {
+                    package: &plan.name,
+                    source,
+                    target,
+                    version: ctx.version,
+                    builder: builder.name(),
+                    toolchain: toolchain.as_deref(),
+                    settings: serde_json::json!({
+                        "node": plan.node,
+                        "python": plan.python,
+                        "go": plan.go,
+                    }),
                 }
-            }
-        }
-        if artifacts.is_empty() {
-            return Err(anyhow!("node binary build produced no outputs"));
-        }
-        Ok(BuiltTarget {
-            target: target.to_string(),
-            artifacts,
-        })
+                .digest();
+                match cache.get(&key) {
+                    Ok(Some(paths)) => {
+                        info!("cache hit for {} [{target}]", plan.name);
+                        return Ok(BuiltTarget {
+                            target: target.clone(),
+                            artifacts: paths
+                                .into_iter()
+                                .map(|p| {
+                                    Utf8PathBuf::from_path_buf(p).map_err(BuildError::NonUtf8Path)
+                                })
+                                .collect::<Result<_>>()?,
+                            origin: Some(origin(true)),
+                        });
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("artifact cache lookup failed: {e}"),
+                }
+                let mut built = builder.build(plan, target, ctx)?;
+                built.origin = Some(origin(false));
+                let paths: Vec<PathBuf> =
+                    built.artifacts.iter().map(|a| a.clone().into()).collect();
+                if let Err(e) = cache.put(&key, &plan.name, target, ctx.version, &paths) {
+                    warn!(
+                        "failed to store {} [{target}] in the artifact cache: {e}",
+                        plan.name
+                    );
+                }
+                Ok(built)
+            })
+            .collect()
     }
 }
 
-fn build_python(
+impl fmt::Debug for BuildContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BuildContext")
+            .field("workspace_root", &self.workspace_root)
+            .field("version", &self.version)
+            .field("verbose", &self.verbose)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for BuilderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BuilderRegistry")
+            .field("builders", &self.names())
+            .finish()
+    }
+}
+
+/// Builds every target of `plan` with the default builders. `plugins` maps
+/// plugin names to executables for packages whose builder is `plugin:<name>`.
+pub fn build_package(
     plan: &PackagePlan,
+    plugins: &BTreeMap<String, String>,
     workspace_root: &Path,
-    target: &str,
+    version: &str,
     verbose: bool,
-) -> Result<BuiltTarget> {
-    let py_cfg = plan.python.clone().unwrap_or_default();
-    let project_dir = workspace_root.join(plan.path.as_str());
-    if py_cfg.mode == "pyinstaller" {
-        let mut cmd = Command::new("pyinstaller");
-        let entry = py_cfg
-            .pyinstaller
-            .as_ref()
-            .and_then(|p| p.entry.clone())
-            .unwrap_or_else(|| "main.py".to_string());
-        cmd.arg("--noconfirm");
-        if let Some(pi) = py_cfg.pyinstaller.as_ref() {
-            if pi.mode == "onefile" {
-                cmd.arg("--onefile");
-            }
-            for hidden in &pi.hidden_imports {
-                cmd.arg("--hidden-import").arg(hidden);
-            }
-        }
-        cmd.arg(entry);
-        cmd.current_dir(&project_dir);
-        run(cmd, verbose)?;
-        let mut artifacts = Vec::new();
-        let dist_dir = project_dir.join("dist");
-        if dist_dir.exists() {
-            for entry in std::fs::read_dir(dist_dir)? {
-                let entry = entry?;
-                if let Ok(p) = Utf8PathBuf::from_path_buf(entry.path()) {
-                    artifacts.push(p);
-                }
-            }
-        }
-        Ok(BuiltTarget {
-            target: target.to_string(),
-            artifacts,
-        })
-    } else {
-        let mut py_build = Command::new("python");
-        py_build.args(["-m", "build"]).current_dir(&project_dir);
-        run(py_build, verbose)?;
-        let mut artifacts = Vec::new();
-        let dist_dir = project_dir.join("dist");
-        if dist_dir.exists() {
-            for entry in std::fs::read_dir(dist_dir)? {
-                let entry = entry?;
-                if let Ok(p) = Utf8PathBuf::from_path_buf(entry.path()) {
-                    artifacts.push(p);
-                }
-            }
+) -> Result<Vec<BuiltTarget>> {
+    let ctx = BuildContext {
+        workspace_root,
+        version,
+        verbose,
+        executor: &SystemExecutor,
+        cache: None,
+        provenance: None,
+        scratch: None,
+    };
+    BuilderRegistry::with_defaults().build_package(plan, plugins, &ctx)
+}
+
+/// Source fingerprint and toolchain version for cache keys; `None` (no
+/// caching) if the sources can't be read.
+fn cache_inputs<'a>(
+    plan: &PackagePlan,
+    ctx: &BuildContext<'a>,
+    cache: &'a ArtifactCache,
+) -> Option<(&'a ArtifactCache, String, Option<String>)> {
+    let dir = ctx.workspace_root.join(plan.path.as_str());
+    let source = match source_fingerprint(&dir) {
+        Ok(source) => source,
+        Err(e) => {
+            warn!("not caching {}: {e}", plan.name);
+            return None;
         }
-        Ok(BuiltTarget {
-            target: target.to_string(),
-            artifacts,
-        })
-    }
+    };
+    let tooling = ToolingInfo::probe_for(ctx.executor, std::slice::from_ref(&plan.project_type));
+    let toolchain = tooling.get(&plan.project_type).map(str::to_string);
+    Some((cache, source, toolchain))
+}
+
+/// Writes `provenance` as [`provenance::DATA_FILE`] into `dir`, a build
+/// output directory, for ecosystems that ship files rather than a single
+/// binary. Returns the file.
+pub(crate) fn write_provenance_file(dir: &Path, provenance: &Provenance) -> Result<Utf8PathBuf> {
+    let json = serde_json::to_string_pretty(provenance)
+        .map_err(|e| BuildError::Config(format!("cannot serialize provenance: {e}")))?;
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(provenance::DATA_FILE);
+    std::fs::write(&path, json + "\n")?;
+    Utf8PathBuf::from_path_buf(path).map_err(BuildError::NonUtf8Path)
 }
 
-fn run(mut cmd: Command, verbose: bool) -> Result<()> {
-    let printable = format!("{:?}", cmd);
-    if verbose {
-        info!("running" = ?cmd);
+pub(crate) fn run(ctx: &BuildContext, cmd: &CommandSpec) -> Result<()> {
+    let printable = redact(&cmd.to_string());
+    if ctx.verbose {
+        info!("running {printable}");
     }
-    let status = cmd
-        .status()
-        .with_context(|| format!("failed to spawn command {printable}"))?;
-    if !status.success() {
-        return Err(anyhow!("command {printable} failed with status {status}"));
+    let output = ctx.executor.run(cmd).map_err(|source| BuildError::Spawn {
+        command: printable.clone(),
+        source,
+    })?;
+    if !output.success() {
+        return Err(BuildError::CommandFailed {
+            command: printable,
+            status: output.status(),
+        });
     }
     Ok(())
 }
 
-fn shell_cmd(cmd: &str, dir: &Path) -> Command {
+pub(crate) fn shell_cmd(cmd: &str, dir: &Path) -> CommandSpec {
     let mut command = if cfg!(target_os = "windows") {
-        let mut c = Command::new("cmd");
+        let mut c = CommandSpec::new("cmd");
         c.args(["/C", cmd]);
         c
     } else {
-        let mut c = Command::new("sh");
+        let mut c = CommandSpec::new("sh");
         c.args(["-c", cmd]);
         c
     };
@@ -297,7 +365,7 @@ fn shell_cmd(cmd: &str, dir: &Path) -> Command {
     command
 }
 
-fn is_executable(path: &Path) -> bool {
+pub(crate) fn is_executable(path: &Path) -> bool {
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
@@ -0,0 +1,283 @@
+use std::path::{Path, PathBuf};
+
+use camino::Utf8PathBuf;
+use shippo_core::exec::CommandSpec;
+use shippo_core::{targets, NodeBinaryConfig, PackagePlan};
+
+use crate::{
+    run, shell_cmd, write_provenance_file, BuildContext, BuildError, Builder, BuiltTarget, Result,
+};
+
+pub struct NodeBuilder;
+
+impl Builder for NodeBuilder {
+    fn name(&self) -> &str {
+        "node"
+    }
+
+    fn detect(&self, dir: &Path) -> bool {
+        dir.join("package.json").exists()
+    }
+
+    fn build(&self, plan: &PackagePlan, target: &str, ctx: &BuildContext) -> Result<BuiltTarget> {
+        let workspace_root = ctx.workspace_root;
+        let mut node_cfg = plan.node.clone().unwrap_or_default();
+        let project_dir = workspace_root.join(plan.path.as_str());
+        let provenance = ctx.provenance(self.name(), plan, target);
+        // a workspace member installs and runs scripts from the top
+        let node_workspace = match node_cfg.workspace {
+            Some(false) => None,
+            _ => find_node_workspace(&project_dir, workspace_root),
+        };
+        let install_dir = node_workspace.as_deref().unwrap_or(&project_dir);
+        let manifest = read_package_json(install_dir);
+        let manager = PackageManager::detect(
+            node_cfg.package_manager.as_deref(),
+            &manifest,
+            install_dir,
+            workspace_root,
+        );
+        let pinned = manifest["packageManager"].as_str().is_some();
+        if node_cfg
+            .corepack
+            .unwrap_or(pinned && manager != PackageManager::Npm)
+        {
+            let mut corepack = CommandSpec::new("corepack");
+            corepack.arg("enable").current_dir(install_dir);
+            run(ctx, &corepack)?;
+        }
+        let mut install = manager.frozen_install();
+        install.current_dir(install_dir);
+        run(ctx, &install)?;
+        if node_cfg.mode == "frontend" {
+            if let Some(cmd) = node_cfg.frontend.as_ref().and_then(|f| f.build_cmd.clone()) {
+                run(ctx, &shell_cmd(&cmd, &project_dir))?;
+            } else {
+                let member = node_workspace.as_ref().map(|_| {
+                    read_package_json(&project_dir)["name"]
+                        .as_str()
+                        .unwrap_or(&plan.name)
+                        .to_string()
+                });
+                let mut build = manager.run_script("build", member.as_deref());
+                build.current_dir(install_dir);
+                run(ctx, &build)?;
+            }
+            let build_dir = node_cfg
+                .frontend
+                .as_ref()
+                .map(|f| f.build_dir.clone())
+                .unwrap_or_else(|| "dist".to_string());
+            let build_path = project_dir.join(&build_dir);
+            if !build_path.exists() {
+                return Err(BuildError::MissingBuildDir {
+                    build_dir,
+                    dir: project_dir,
+                });
+            }
+            if let Some(provenance) = &provenance {
+                write_provenance_file(&build_path, provenance)?;
+            }
+            let path = Utf8PathBuf::from_path_buf(build_path).map_err(BuildError::NonUtf8Path)?;
+            Ok(BuiltTarget {
+                target: target.to_string(),
+                artifacts: vec![path],
+                origin: None,
+            })
+        } else {
+            if node_cfg.binary.is_none() {
+                node_cfg.binary = Some(NodeBinaryConfig {
+                    tool: "pkg".into(),
+                    entry: Some("index.js".into()),
+                    targets: vec![pkg_target(target)],
+                });
+            }
+            let bin_cfg = node_cfg.binary.ok_or_else(|| {
+                BuildError::Config("node.cli-binary requires [node.binary]".into())
+            })?;
+            let entry = bin_cfg.entry.unwrap_or_else(|| "index.js".to_string());
+            let mut cmd = CommandSpec::new(&bin_cfg.tool);
+            cmd.arg(entry);
+            if !bin_cfg.targets.is_empty() {
+                cmd.arg("--targets").arg(bin_cfg.targets.join(","));
+            }
+            // other tools have no known output flag and write in place
+            let out_dir = ctx
+                .scratch_dir(plan)
+                .filter(|_| matches!(bin_cfg.tool.as_str(), "pkg" | "nexe"));
+            if let Some(dir) = &out_dir {
+                std::fs::create_dir_all(dir)?;
+                match bin_cfg.tool.as_str() {
+                    "pkg" => cmd.arg("--out-path").arg(dir.to_string_lossy()),
+                    _ => cmd
+                        .arg("--output")
+                        .arg(dir.join(&plan.name).to_string_lossy()),
+                };
+            }
+            cmd.current_dir(&project_dir);
+            run(ctx, &cmd)?;
+            let mut artifacts = Vec::new();
+            for entry in std::fs::read_dir(out_dir.as_ref().unwrap_or(&project_dir))? {
+                let entry = entry?;
+                if entry.file_name().to_string_lossy().contains(&plan.name) {
+                    if let Ok(p) = Utf8PathBuf::from_path_buf(entry.path()) {
+                        artifacts.push(p);
+                    }
+                }
+            }
+            if artifacts.is_empty() {
+                return Err(BuildError::NoOutputs {
+                    package: plan.name.clone(),
+                });
+            }
+            if let Some(provenance) = &provenance {
+                // shipped next to the binaries; tools writing in place get
+                // the package's `dist`
+                let dir = out_dir.unwrap_or_else(|| project_dir.join("dist"));
+                artifacts.push(write_provenance_file(&dir, provenance)?);
+            }
+            Ok(BuiltTarget {
+                target: target.to_string(),
+                artifacts,
+                origin: None,
+            })
+        }
+    }
+}
+
+/// The package manager that installs a package's dependencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PackageManager {
+    Npm,
+    Pnpm,
+    /// `berry` for Yarn 2 and later, which replaced `--frozen-lockfile`
+    /// with `--immutable`.
+    Yarn {
+        berry: bool,
+    },
+    Bun,
+}
+
+/// Lockfiles by the package manager that writes them, in detection order.
+const LOCKFILES: &[(&str, &str)] = &[
+    ("pnpm-lock.yaml", "pnpm"),
+    ("yarn.lock", "yarn"),
+    ("bun.lock", "bun"),
+    ("bun.lockb", "bun"),
+    ("package-lock.json", "npm"),
+    ("npm-shrinkwrap.json", "npm"),
+];
+
+impl PackageManager {
+    /// `[node] package_manager`, else the `packageManager` field of
+    /// `package.json` (`pnpm@9.1.0`), else the first lockfile found in
+    /// `dir` or a parent up to `root`, as workspaces keep theirs at the top.
+    /// npm without any of these.
+    fn detect(
+        configured: Option<&str>,
+        package_json: &serde_json::Value,
+        dir: &Path,
+        root: &Path,
+    ) -> Self {
+        let pinned = package_json["packageManager"].as_str();
+        let lockfile_dir = dir
+            .ancestors()
+            .take_while(|d| d.starts_with(root))
+            .find(|d| LOCKFILES.iter().any(|(file, _)| d.join(file).exists()));
+        let name = configured
+            .or_else(|| pinned.and_then(|p| p.split('@').next()))
+            .or_else(|| {
+                let dir = lockfile_dir?;
+                LOCKFILES
+                    .iter()
+                    .find(|(file, _)| dir.join(file).exists())
+                    .map(|(_, name)| *name)
+            })
+            .unwrap_or("npm");
+        match name {
+            "pnpm" => Self::Pnpm,
+            "bun" => Self::Bun,
+            "yarn" => {
+                let major = pinned
+                    .and_then(|p| p.strip_prefix("yarn@"))
+                    .and_then(|v| v.split('.').next()?.parse::<u32>().ok());
+                let berry = match major {
+                    Some(major) => major >= 2,
+                    None => lockfile_dir.unwrap_or(dir).join(".yarnrc.yml").exists(),
+                };
+                Self::Yarn { berry }
+            }
+            _ => Self::Npm,
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Self::Npm => "npm",
+            Self::Pnpm => "pnpm",
+            Self::Yarn { .. } => "yarn",
+            Self::Bun => "bun",
+        }
+    }
+
+    /// Runs `script` from `package.json`; in a workspace, `member`'s script
+    /// run from the workspace root.
+    fn run_script(self, script: &str, member: Option<&str>) -> CommandSpec {
+        let mut cmd = CommandSpec::new(self.program());
+        match (self, member) {
+            (_, None) => cmd.arg("run").arg(script),
+            (Self::Npm, Some(member)) => cmd.args(["run", script, "--workspace", member]),
+            (Self::Pnpm, Some(member)) => cmd.args(["--filter", member, "run", script]),
+            (Self::Yarn { .. }, Some(member)) => cmd.args(["workspace", member, "run", script]),
+            (Self::Bun, Some(member)) => cmd.args(["run", "--filter", member, script]),
+        };
+        cmd
+    }
+
+    /// Installs exactly what the lockfile pins, failing if it is out of
+    /// date.
+    fn frozen_install(self) -> CommandSpec {
+        let mut cmd = CommandSpec::new(self.program());
+        match self {
+            Self::Npm => cmd.arg("ci"),
+            Self::Yarn { berry: true } => cmd.arg("install").arg("--immutable"),
+            Self::Pnpm | Self::Yarn { berry: false } | Self::Bun => {
+                cmd.arg("install").arg("--frozen-lockfile")
+            }
+        };
+        cmd
+    }
+}
+
+/// The npm, pnpm, Yarn or Bun workspace `dir` is a member of: the closest
+/// parent up to `root` with a `pnpm-workspace.yaml`, or a `package.json`
+/// listing `workspaces`.
+fn find_node_workspace(dir: &Path, root: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .skip(1)
+        .take_while(|d| d.starts_with(root))
+        .find(|d| {
+            d.join("pnpm-workspace.yaml").exists() || !read_package_json(d)["workspaces"].is_null()
+        })
+        .map(Path::to_path_buf)
+}
+
+/// `package.json` in `dir`, or null when it is missing or invalid.
+fn read_package_json(dir: &Path) -> serde_json::Value {
+    std::fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// pkg's name for `target`, e.g. `linux-x64` for `x86_64-unknown-linux-gnu`.
+/// Targets pinning a Node version (`node18-linux-x64`) are passed as given.
+fn pkg_target(target: &str) -> String {
+    if target.starts_with("node") {
+        return target.to_string();
+    }
+    targets::resolve(target)
+        .and_then(|t| t.pkg)
+        .unwrap_or(target)
+        .to_string()
+}
@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use camino::Utf8PathBuf;
+use shippo_core::plugin::{BuildParams, BuildReply, Plugin};
+use shippo_core::PackagePlan;
+
+use crate::{BuildContext, BuildError, Builder, BuiltTarget, Result};
+
+/// Delegates builds to an exec plugin's `build` method.
+pub struct PluginBuilder {
+    key: String,
+    plugin: Plugin,
+}
+
+impl PluginBuilder {
+    pub fn new(plugin: Plugin) -> Self {
+        Self {
+            key: format!("plugin:{}", plugin.name),
+            plugin,
+        }
+    }
+}
+
+impl Builder for PluginBuilder {
+    fn name(&self) -> &str {
+        &self.key
+    }
+
+    fn detect(&self, _dir: &Path) -> bool {
+        false
+    }
+
+    fn build(&self, plan: &PackagePlan, target: &str, ctx: &BuildContext) -> Result<BuiltTarget> {
+        let project_dir = ctx.workspace_root.join(plan.path.as_std_path());
+        let params = BuildParams {
+            package: plan.name.clone(),
+            path: project_dir.to_string_lossy().to_string(),
+            target: target.to_string(),
+            version: ctx.version.to_string(),
+        };
+        let reply: BuildReply = self.plugin.call("build", &params)?;
+        if reply.artifacts.is_empty() {
+            return Err(BuildError::NoOutputs {
+                package: plan.name.clone(),
+            });
+        }
+        Ok(BuiltTarget {
+            target: target.to_string(),
+            artifacts: reply.artifacts.into_iter().map(Utf8PathBuf::from).collect(),
+            origin: None,
+        })
+    }
+}
@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use camino::Utf8PathBuf;
+use shippo_core::exec::CommandSpec;
+use shippo_core::{targets, PackagePlan};
+
+use crate::{run, write_provenance_file, BuildContext, Builder, BuiltTarget, Result};
+
+pub struct PythonBuilder;
+
+impl Builder for PythonBuilder {
+    fn name(&self) -> &str {
+        "python"
+    }
+
+    fn detect(&self, dir: &Path) -> bool {
+        dir.join("pyproject.toml").exists() || dir.join("setup.py").exists()
+    }
+
+    fn build(&self, plan: &PackagePlan, target: &str, ctx: &BuildContext) -> Result<BuiltTarget> {
+        let workspace_root = ctx.workspace_root;
+        let py_cfg = plan.python.clone().unwrap_or_default();
+        let project_dir = workspace_root.join(plan.path.as_str());
+        let provenance = ctx.provenance(self.name(), plan, target);
+        let scratch = ctx.scratch_dir(plan);
+        let dist_dir = scratch.as_ref().unwrap_or(&project_dir).join("dist");
+        if py_cfg.mode == "pyinstaller" {
+            let mut cmd = CommandSpec::new("pyinstaller");
+            if let Some(provenance) = &provenance {
+                // bundled at the root of the app, next to the entry script,
+                // from PyInstaller's work directory
+                let work_dir = scratch.as_ref().unwrap_or(&project_dir).join("build");
+                let data = write_provenance_file(&work_dir, provenance)?;
+                cmd.arg("--add-data").arg(format!("{data}:."));
+            }
+            if let Some(dir) = &scratch {
+                cmd.arg("--distpath").arg(dist_dir.to_string_lossy());
+                cmd.arg("--workpath")
+                    .arg(dir.join("build").to_string_lossy());
+                cmd.arg("--specpath").arg(dir.to_string_lossy());
+            }
+            let entry = py_cfg
+                .pyinstaller
+                .as_ref()
+                .and_then(|p| p.entry.clone())
+                .unwrap_or_else(|| "main.py".to_string());
+            cmd.arg("--noconfirm");
+            // PyInstaller only cross-builds between macOS architectures
+            if let Some(arch) = targets::resolve(target).and_then(|t| t.pyinstaller_arch()) {
+                cmd.arg("--target-arch").arg(arch);
+            }
+            if let Some(pi) = py_cfg.pyinstaller.as_ref() {
+                if pi.mode == "onefile" {
+                    cmd.arg("--onefile");
+                }
+                for hidden in &pi.hidden_imports {
+                    cmd.arg("--hidden-import").arg(hidden);
+                }
+            }
+            cmd.arg(entry);
+            cmd.current_dir(&project_dir);
+            run(ctx, &cmd)?;
+            let mut artifacts = Vec::new();
+            if dist_dir.exists() {
+                for entry in std::fs::read_dir(&dist_dir)? {
+                    let entry = entry?;
+                    if let Ok(p) = Utf8PathBuf::from_path_buf(entry.path()) {
+                        artifacts.push(p);
+                    }
+                }
+            }
+            Ok(BuiltTarget {
+                target: target.to_string(),
+                artifacts,
+                origin: None,
+            })
+        } else {
+            let mut py_build = CommandSpec::new("python");
+            py_build.args(["-m", "build"]).current_dir(&project_dir);
+            if scratch.is_some() {
+                py_build.arg("--outdir").arg(dist_dir.to_string_lossy());
+            }
+            run(ctx, &py_build)?;
+            if let Some(provenance) = &provenance {
+                write_provenance_file(&dist_dir, provenance)?;
+            }
+            let mut artifacts = Vec::new();
+            if dist_dir.exists() {
+                for entry in std::fs::read_dir(&dist_dir)? {
+                    let entry = entry?;
+                    if let Ok(p) = Utf8PathBuf::from_path_buf(entry.path()) {
+                        artifacts.push(p);
+                    }
+                }
+            }
+            Ok(BuiltTarget {
+                target: target.to_string(),
+                artifacts,
+                origin: None,
+            })
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+
+use camino::Utf8PathBuf;
+use shippo_core::exec::CommandSpec;
+use shippo_core::provenance::RUST_INCLUDE_ENV;
+use shippo_core::{targets, PackagePlan};
+
+use crate::{is_executable, run, BuildContext, BuildError, Builder, BuiltTarget, Result};
+
+pub struct RustBuilder;
+
+impl Builder for RustBuilder {
+    fn name(&self) -> &str {
+        "rust"
+    }
+
+    fn detect(&self, dir: &Path) -> bool {
+        dir.join("Cargo.toml").exists()
+    }
+
+    fn build(&self, plan: &PackagePlan, target: &str, ctx: &BuildContext) -> Result<BuiltTarget> {
+        let workspace_root = ctx.workspace_root;
+        // aliases such as `linux-x64` become the rustc triple
+        let triple = targets::resolve(target).map_or(target, |t| t.triple);
+        let use_cross = std::env::var("SHIPPO_USE_CROSS").is_ok()
+            || (target != "native" && which::which("cross").is_ok());
+        let mut cmd = if use_cross && target != "native" {
+            let mut c = CommandSpec::new("cross");
+            c.arg("build").arg("--release").arg("--target").arg(triple);
+            c
+        } else {
+            let mut c = CommandSpec::new("cargo");
+            c.arg("build").arg("--release");
+            if target != "native" {
+                c.arg("--target").arg(triple);
+            }
+            c
+        };
+        cmd.current_dir(workspace_root.join(plan.path.as_str()));
+        let scratch = ctx.scratch_dir(plan).map(|d| d.join("target"));
+        if let Some(dir) = &scratch {
+            cmd.env("CARGO_TARGET_DIR", dir.to_string_lossy());
+        }
+        let target_root = match scratch {
+            Some(dir) => dir,
+            None => std::env::var("CARGO_TARGET_DIR")
+                .map(PathBuf::from)
+                .map(|p| {
+                    if p.is_absolute() {
+                        p
+                    } else {
+                        workspace_root.join(p)
+                    }
+                })
+                .unwrap_or_else(|_| workspace_root.join(plan.path.as_str()).join("target")),
+        };
+        if let Some(provenance) = ctx.provenance(self.name(), plan, target) {
+            // the crate opts in with `include!(env!("SHIPPO_PROVENANCE_RS"))`
+            let include = target_root.join("shippo").join("provenance.rs");
+            std::fs::create_dir_all(target_root.join("shippo"))?;
+            std::fs::write(&include, provenance.rust_include())?;
+            cmd.env(RUST_INCLUDE_ENV, include.to_string_lossy());
+        }
+        run(ctx, &cmd)?;
+        let binary_dir = if target == "native" {
+            target_root.join("release")
+        } else {
+            target_root.join(triple).join("release")
+        };
+        let mut artifacts = Vec::new();
+        if binary_dir.exists() {
+            for entry in std::fs::read_dir(&binary_dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                if path.is_file() && is_executable(&path) {
+                    if let Ok(p) = Utf8PathBuf::from_path_buf(path) {
+                        artifacts.push(p);
+                    }
+                }
+            }
+        }
+        if artifacts.is_empty() {
+            return Err(BuildError::NoOutputs {
+                package: plan.name.clone(),
+            });
+        }
+        Ok(BuiltTarget {
+            target: target.to_string(),
+            artifacts,
+            origin: None,
+        })
+    }
+}
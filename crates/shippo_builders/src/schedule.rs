@@ -0,0 +1,126 @@
+//! Concurrent builds for `--jobs`: a small worker pool, and an executor
+//! that tags each line of a command's output with what it belongs to.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use shippo_core::exec::{CommandOutput, CommandSpec, Executor};
+
+/// Calls `f` on each item, up to `jobs` at a time, and returns the results
+/// in item order. Once a call fails no new items are started, and the
+/// first error in item order is returned. `jobs` of 0 uses every CPU.
+pub fn parallel_map<T, R, E, F>(jobs: usize, items: &[T], f: F) -> Result<Vec<R>, E>
+where
+    T: Sync,
+    R: Send,
+    E: Send,
+    F: Fn(&T) -> Result<R, E> + Sync,
+{
+    let jobs = match jobs {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        jobs => jobs,
+    };
+    if jobs <= 1 || items.len() <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results: Mutex<Vec<Option<Result<R, E>>>> =
+        Mutex::new(items.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.min(items.len()) {
+            scope.spawn(|| {
+                while !failed.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        break;
+                    };
+                    let result = f(item);
+                    if result.is_err() {
+                        failed.store(true, Ordering::Relaxed);
+                    }
+                    results.lock().unwrap()[i] = Some(result);
+                }
+            });
+        }
+    });
+    // items are handed out in order, so any skipped ones come after the
+    // failure
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Wraps an executor so commands run with captured output, which is then
+/// written to stderr with every line prefixed by `[tag]`. Lines of one
+/// command stay together; commands of concurrent builds interleave.
+pub struct TaggedExecutor<'a> {
+    inner: &'a dyn Executor,
+    tag: String,
+}
+
+impl<'a> TaggedExecutor<'a> {
+    pub fn new(inner: &'a dyn Executor, tag: impl Into<String>) -> Self {
+        Self {
+            inner,
+            tag: tag.into(),
+        }
+    }
+}
+
+impl Executor for TaggedExecutor<'_> {
+    fn run(&self, cmd: &CommandSpec) -> io::Result<CommandOutput> {
+        let output = self.inner.output(cmd)?;
+        let mut stderr = io::stderr().lock();
+        for line in output.stdout.lines().chain(output.stderr.lines()) {
+            writeln!(stderr, "[{}] {line}", self.tag)?;
+        }
+        Ok(CommandOutput {
+            code: output.code,
+            ..Default::default()
+        })
+    }
+
+    fn output(&self, cmd: &CommandSpec) -> io::Result<CommandOutput> {
+        self.inner.output(cmd)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.inner.var(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_map_keeps_order_and_stops_on_error() {
+        let items: Vec<u32> = (0..20).collect();
+        let doubled = parallel_map(4, &items, |i| Ok::<_, String>(i * 2)).unwrap();
+        assert_eq!(doubled, items.iter().map(|i| i * 2).collect::<Vec<_>>());
+
+        let started = AtomicUsize::new(0);
+        let err = parallel_map(1, &items, |i| {
+            started.fetch_add(1, Ordering::Relaxed);
+            if *i == 3 {
+                Err(format!("item {i} failed"))
+            } else {
+                Ok(*i)
+            }
+        })
+        .unwrap_err();
+        assert_eq!(err, "item 3 failed");
+        assert_eq!(started.load(Ordering::Relaxed), 4);
+
+        let err = parallel_map(3, &items, |i| match i {
+            5 | 9 => Err(*i),
+            _ => Ok(()),
+        })
+        .unwrap_err();
+        assert_eq!(err, 5);
+    }
+}
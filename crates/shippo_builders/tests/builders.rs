@@ -0,0 +1,518 @@
+use std::fs;
+
+use camino::Utf8PathBuf;
+use shippo_builders::{build_image, BuildContext, BuildError, BuilderRegistry};
+use shippo_core::cache::ArtifactCache;
+use shippo_core::exec::{CommandOutput, MockExecutor};
+use shippo_core::provenance::{self, Provenance};
+use shippo_core::{
+    DockerConfig, GoConfig, NodeConfig, PackageConfig, PackagePlan, ProjectType, SbomConfig,
+    SignConfig,
+};
+use tempfile::tempdir;
+
+fn plan(name: &str, project_type: ProjectType, targets: &[&str]) -> PackagePlan {
+    PackagePlan {
+        name: name.into(),
+        project_type,
+        path: Utf8PathBuf::from("."),
+        targets: targets.iter().map(|t| t.to_string()).collect(),
+        package: PackageConfig {
+            formats: vec!["tar.gz".into()],
+            name_template: "{name}-{version}-{target}".into(),
+            include: vec![],
+            exclude: vec![],
+            target_style: None,
+            keep_last: None,
+            contents: Default::default(),
+            transform: Vec::new(),
+            compression: Default::default(),
+            duplicates: None,
+        },
+        sbom: SbomConfig {
+            enabled: false,
+            format: "cyclonedx".into(),
+            mode: "auto".into(),
+            tool: None,
+            formats: Vec::new(),
+        },
+        sign: SignConfig {
+            enabled: false,
+            method: "cosign".into(),
+            cosign_mode: "keyless".into(),
+            ssh_key: None,
+            ssh_namespace: None,
+            cosign: None,
+            gpg: None,
+        },
+        node: None,
+        python: None,
+        go: None,
+        builder: None,
+        docker: None,
+    }
+}
+
+#[test]
+fn go_build_sets_platform_and_version() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("dist-build/linux-arm64")).unwrap();
+    fs::write(dir.path().join("dist-build/linux-arm64/svc"), "bin").unwrap();
+    let mock = MockExecutor::new();
+    let ctx = BuildContext {
+        workspace_root: dir.path(),
+        version: "v1.2.3",
+        verbose: false,
+        executor: &mock,
+        cache: None,
+        provenance: None,
+        scratch: None,
+    };
+    let built = BuilderRegistry::with_defaults()
+        .build_package(
+            &plan("svc", ProjectType::Go, &["linux-arm64"]),
+            &Default::default(),
+            &ctx,
+        )
+        .unwrap();
+    assert_eq!(built[0].artifacts.len(), 1);
+    assert!(built[0].artifacts[0].ends_with("dist-build/linux-arm64/svc"));
+    let calls = mock.calls();
+    assert_eq!(calls[0].program, "go");
+    assert!(calls[0]
+        .args
+        .ends_with(&["-o".into(), "dist-build/linux-arm64/svc".into()]));
+    assert_eq!(calls[0].env["GOOS"], "linux");
+    assert_eq!(calls[0].env["GOARCH"], "arm64");
+    assert!(calls[0]
+        .args
+        .iter()
+        .any(|a| a.contains("main.version=v1.2.3")));
+}
+
+#[test]
+fn docker_image_built_with_release_tags() {
+    let dir = tempdir().unwrap();
+    let mock = MockExecutor::new();
+    mock.respond(
+        "docker",
+        CommandOutput {
+            code: Some(0),
+            ..Default::default()
+        },
+    )
+    .respond(
+        "docker",
+        CommandOutput {
+            code: Some(0),
+            stdout: "sha256:0123abcd\n".into(),
+            ..Default::default()
+        },
+    );
+    let ctx = BuildContext {
+        workspace_root: dir.path(),
+        version: "v1.4.0",
+        verbose: false,
+        executor: &mock,
+        cache: None,
+        provenance: None,
+        scratch: None,
+    };
+    let docker = DockerConfig {
+        dockerfile: "Dockerfile".into(),
+        context: ".".into(),
+        registry: Some("ghcr.io".into()),
+        image: "acme/{name}".into(),
+        tags: vec!["{version}".into(), "{minor}".into(), "stable".into()],
+        build_args: [("RUST_VERSION".to_string(), "1.80".to_string())].into(),
+    };
+    let image = build_image(&plan("svc", ProjectType::Go, &["native"]), &docker, &ctx).unwrap();
+    assert_eq!(image.repository, "ghcr.io/acme/svc");
+    assert_eq!(image.tags, ["1.4.0", "1.4", "stable"]);
+    assert_eq!(image.id, "sha256:0123abcd");
+    let calls = mock.calls();
+    let build = calls[0].args.join(" ");
+    assert!(build.starts_with("build --file "));
+    assert!(build.contains("--tag ghcr.io/acme/svc:1.4.0 --tag ghcr.io/acme/svc:1.4 "));
+    assert!(build.contains("--build-arg RUST_VERSION=1.80"));
+    assert!(build.contains("org.opencontainers.image.version=1.4.0"));
+    assert_eq!(calls[1].args.last().unwrap(), "ghcr.io/acme/svc:1.4.0");
+
+    // prereleases don't move the minor tag
+    assert_eq!(docker.image_tags("v1.5.0-rc.1"), ["1.5.0-rc.1", "stable"]);
+}
+
+#[test]
+fn target_aliases_translated_per_ecosystem() {
+    let dir = tempdir().unwrap();
+    let mock = MockExecutor::new();
+    let ctx = BuildContext {
+        workspace_root: dir.path(),
+        version: "v1.2.3",
+        verbose: false,
+        executor: &mock,
+        cache: None,
+        provenance: None,
+        scratch: None,
+    };
+    let registry = BuilderRegistry::with_defaults();
+    let built = registry
+        .build_package(
+            &plan("svc", ProjectType::Go, &["aarch64-apple-darwin"]),
+            &Default::default(),
+            &ctx,
+        )
+        .unwrap();
+    // artifacts keep the configured name
+    assert_eq!(built[0].target, "aarch64-apple-darwin");
+    let _ = registry.build_package(
+        &plan("cli", ProjectType::Rust, &["linux-x64"]),
+        &Default::default(),
+        &ctx,
+    );
+    let _ = registry.build_package(
+        &plan("tool", ProjectType::Node, &["x86_64-pc-windows-msvc"]),
+        &Default::default(),
+        &ctx,
+    );
+    let calls = mock.calls();
+    assert_eq!(calls[0].env["GOOS"], "darwin");
+    assert_eq!(calls[0].env["GOARCH"], "arm64");
+    let rust = &calls[1].args;
+    let at = rust.iter().position(|a| a == "--target").unwrap();
+    assert_eq!(rust[at + 1], "x86_64-unknown-linux-gnu");
+    let pkg = calls.iter().find(|c| c.program == "pkg").unwrap();
+    assert!(pkg.args.ends_with(&["--targets".into(), "win-x64".into()]));
+}
+
+#[test]
+fn provenance_embedded_per_ecosystem() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("dist")).unwrap();
+    let mock = MockExecutor::new();
+    let provenance = Provenance {
+        version: "v1.2.3".into(),
+        commit: Some("0123abc".into()),
+        ..Default::default()
+    };
+    let ctx = BuildContext {
+        workspace_root: dir.path(),
+        version: "v1.2.3",
+        verbose: false,
+        executor: &mock,
+        cache: None,
+        provenance: Some(&provenance),
+        scratch: None,
+    };
+    let registry = BuilderRegistry::with_defaults();
+    registry
+        .build_package(
+            &plan("svc", ProjectType::Go, &["linux-amd64"]),
+            &Default::default(),
+            &ctx,
+        )
+        .unwrap();
+    let ldflags = &mock.calls()[0].args[2];
+    assert!(
+        ldflags.contains(r#"'-X main.shippoProvenance=shippo-provenance:{"package":"svc","target":"linux-amd64","version":"v1.2.3","commit":"0123abc","builder":"go""#),
+        "{ldflags}"
+    );
+
+    // pkg writes `web-linux` in place, as the mock doesn't
+    fs::write(dir.path().join("web-linux"), "binary").unwrap();
+    let built = registry
+        .build_package(
+            &plan("web", ProjectType::Node, &["native"]),
+            &Default::default(),
+            &ctx,
+        )
+        .unwrap();
+    let file = dir.path().join("dist").join(provenance::DATA_FILE);
+    assert!(built[0].artifacts.iter().any(|a| a == &file), "{built:?}");
+    let written = Provenance::find(&fs::read(&file).unwrap()).unwrap();
+    assert_eq!(written.package, "web");
+    assert_eq!(written.builder, "node");
+    // the source tree is left alone
+    assert!(!dir.path().join(provenance::DATA_FILE).exists());
+}
+
+#[test]
+fn failed_command_is_reported() {
+    let dir = tempdir().unwrap();
+    let mock = MockExecutor::new();
+    mock.respond(
+        "cargo",
+        CommandOutput {
+            code: Some(101),
+            ..Default::default()
+        },
+    );
+    let ctx = BuildContext {
+        workspace_root: dir.path(),
+        version: "v1.0.0",
+        verbose: false,
+        executor: &mock,
+        cache: None,
+        provenance: None,
+        scratch: None,
+    };
+    let err = BuilderRegistry::with_defaults()
+        .build_package(
+            &plan("app", ProjectType::Rust, &["native"]),
+            &Default::default(),
+            &ctx,
+        )
+        .unwrap_err();
+    assert!(matches!(err, BuildError::CommandFailed { .. }));
+    assert_eq!(mock.calls()[0].args, vec!["build", "--release"]);
+}
+
+#[test]
+fn cached_build_skips_builder() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::create_dir_all(src.join("dist-build/linux-amd64")).unwrap();
+    fs::write(src.join("dist-build/linux-amd64/svc"), "bin").unwrap();
+    let cache = ArtifactCache::new(dir.path().join("cache"));
+    let mock = MockExecutor::new();
+    let ctx = BuildContext {
+        workspace_root: &src,
+        version: "v1.0.0",
+        verbose: false,
+        executor: &mock,
+        cache: Some(&cache),
+        provenance: None,
+        scratch: None,
+    };
+    let registry = BuilderRegistry::with_defaults();
+    let svc = plan("svc", ProjectType::Go, &["linux-amd64"]);
+    let built = registry
+        .build_package(&svc, &Default::default(), &ctx)
+        .unwrap();
+    let origin = built[0].origin.as_ref().unwrap();
+    assert_eq!(
+        (origin.builder.as_str(), origin.source.as_str()),
+        ("go", ".")
+    );
+    assert!(!origin.cached);
+    assert_eq!(origin.command_sha256.as_ref().map(String::len), Some(64));
+    let cached = registry
+        .build_package(&svc, &Default::default(), &ctx)
+        .unwrap();
+    assert!(cached[0].artifacts[0].starts_with(cache.root()));
+    let origin = cached[0].origin.as_ref().unwrap();
+    assert!(origin.cached);
+    assert_eq!(origin.command_sha256, None);
+    let builds = mock
+        .calls()
+        .iter()
+        .filter(|c| c.args.first().is_some_and(|a| a == "build"))
+        .count();
+    assert_eq!(builds, 1);
+}
+
+#[test]
+fn go_builds_each_bin_per_target() {
+    let dir = tempdir().unwrap();
+    let out = dir.path().join("dist-build");
+    for bin in [
+        "linux-amd64/api",
+        "windows-amd64/api.exe",
+        "linux-amd64/worker",
+    ] {
+        let path = out.join(bin);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "bin").unwrap();
+    }
+    let mut svc = plan("svc", ProjectType::Go, &["linux-amd64", "windows-amd64"]);
+    svc.go = Some(GoConfig {
+        bins: [("api", "./cmd/api"), ("worker", "./cmd/worker")]
+            .map(|(name, main)| (name.to_string(), main.to_string()))
+            .into(),
+    });
+    let mock = MockExecutor::new();
+    let ctx = BuildContext {
+        workspace_root: dir.path(),
+        version: "v1.0.0",
+        verbose: false,
+        executor: &mock,
+        cache: None,
+        provenance: None,
+        scratch: None,
+    };
+    let built = BuilderRegistry::with_defaults()
+        .build_package(&svc, &Default::default(), &ctx)
+        .unwrap();
+    let names = |i: usize| -> Vec<&str> {
+        built[i]
+            .artifacts
+            .iter()
+            .map(|a| a.file_name().unwrap())
+            .collect()
+    };
+    // binaries keep their names, so archives hold `api`, not `api-linux-amd64`
+    assert_eq!(names(0), ["api", "worker"]);
+    // the windows worker was not produced
+    assert_eq!(names(1), ["api.exe"]);
+    let calls = mock.calls();
+    assert_eq!(calls.len(), 4);
+    assert!(calls[1].args.ends_with(&[
+        "-o".into(),
+        "dist-build/linux-amd64/worker".into(),
+        "./cmd/worker".into()
+    ]));
+}
+
+#[test]
+fn scratch_dir_keeps_outputs_out_of_tree() {
+    let dir = tempdir().unwrap();
+    let scratch = dir.path().join("scratch");
+    fs::create_dir_all(scratch.join("app/dist")).unwrap();
+    fs::write(scratch.join("app/dist/app-1.0.0.whl"), "wheel").unwrap();
+    let mock = MockExecutor::new();
+    let ctx = BuildContext {
+        workspace_root: dir.path(),
+        version: "v1.0.0",
+        verbose: false,
+        executor: &mock,
+        cache: None,
+        provenance: None,
+        scratch: Some(&scratch),
+    };
+    let registry = BuilderRegistry::with_defaults();
+    let _ = registry.build_package(
+        &plan("svc", ProjectType::Go, &["linux-amd64"]),
+        &Default::default(),
+        &ctx,
+    );
+    let built = registry
+        .build_package(
+            &plan("app", ProjectType::Python, &["native"]),
+            &Default::default(),
+            &ctx,
+        )
+        .unwrap();
+    assert_eq!(
+        built[0].artifacts,
+        [Utf8PathBuf::from_path_buf(scratch.join("app/dist/app-1.0.0.whl")).unwrap()]
+    );
+    let calls = mock.calls();
+    let output = scratch.join("svc/linux-amd64/svc");
+    assert!(calls[0]
+        .args
+        .ends_with(&["-o".into(), output.to_string_lossy().into_owned()]));
+    let outdir = scratch.join("app/dist");
+    assert!(calls[1]
+        .args
+        .ends_with(&["--outdir".into(), outdir.to_string_lossy().into_owned()]));
+    assert!(!dir.path().join("dist-build").exists());
+}
+
+#[test]
+fn node_installs_with_detected_package_manager() {
+    let dir = tempdir().unwrap();
+    let web = dir.path().join("web");
+    fs::create_dir_all(web.join("dist")).unwrap();
+    fs::write(web.join("package.json"), r#"{"name": "web"}"#).unwrap();
+    // a workspace lockfile above the package
+    fs::write(dir.path().join("pnpm-lock.yaml"), "lockfileVersion: '9.0'").unwrap();
+    let mut web_plan = plan("web", ProjectType::Node, &["native"]);
+    web_plan.path = Utf8PathBuf::from("web");
+    web_plan.node = Some(NodeConfig {
+        mode: "frontend".into(),
+        ..Default::default()
+    });
+    let build = |web_plan: &PackagePlan| {
+        let mock = MockExecutor::new();
+        let ctx = BuildContext {
+            workspace_root: dir.path(),
+            version: "v1.0.0",
+            verbose: false,
+            executor: &mock,
+            cache: None,
+            provenance: None,
+            scratch: None,
+        };
+        BuilderRegistry::with_defaults()
+            .build_package(web_plan, &Default::default(), &ctx)
+            .unwrap();
+        mock.calls()
+            .iter()
+            .map(|c| format!("{} {}", c.program, c.args.join(" ")))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        build(&web_plan),
+        ["pnpm install --frozen-lockfile", "pnpm run build"]
+    );
+
+    fs::write(
+        web.join("package.json"),
+        r#"{"name": "web", "packageManager": "yarn@4.1.0"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        build(&web_plan),
+        [
+            "corepack enable",
+            "yarn install --immutable",
+            "yarn run build"
+        ]
+    );
+
+    web_plan.node.as_mut().unwrap().package_manager = Some("npm".into());
+    assert_eq!(build(&web_plan), ["npm ci", "npm run build"]);
+}
+
+#[test]
+fn node_workspace_member_built_from_workspace_root() {
+    let dir = tempdir().unwrap();
+    let web = dir.path().join("packages/web");
+    fs::create_dir_all(web.join("dist")).unwrap();
+    fs::write(web.join("package.json"), r#"{"name": "@acme/web"}"#).unwrap();
+    fs::write(
+        dir.path().join("package.json"),
+        r#"{"private": true, "packageManager": "pnpm@9.1.0"}"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("pnpm-workspace.yaml"),
+        "packages: ['packages/*']",
+    )
+    .unwrap();
+    let mut web_plan = plan("web", ProjectType::Node, &["native"]);
+    web_plan.path = Utf8PathBuf::from("packages/web");
+    web_plan.node = Some(NodeConfig {
+        mode: "frontend".into(),
+        ..Default::default()
+    });
+    let mock = MockExecutor::new();
+    let ctx = BuildContext {
+        workspace_root: dir.path(),
+        version: "v1.0.0",
+        verbose: false,
+        executor: &mock,
+        cache: None,
+        provenance: None,
+        scratch: None,
+    };
+    let built = BuilderRegistry::with_defaults()
+        .build_package(&web_plan, &Default::default(), &ctx)
+        .unwrap();
+    assert!(built[0].artifacts[0].ends_with("packages/web/dist"));
+    let calls = mock.calls();
+    let commands: Vec<_> = calls
+        .iter()
+        .map(|c| format!("{} {}", c.program, c.args.join(" ")))
+        .collect();
+    assert_eq!(
+        commands,
+        [
+            "corepack enable",
+            "pnpm install --frozen-lockfile",
+            "pnpm --filter @acme/web run build"
+        ]
+    );
+    assert!(calls.iter().all(|c| c.cwd.as_deref() == Some(dir.path())));
+}
@@ -0,0 +1,348 @@
+//! Local artifact cache shared across branches and repositories.
+//!
+//! Entries are keyed by a hash of everything that determines a build's
+//! output: the package sources, target, version, builder and toolchain
+//! version. Layout under the cache root (`~/.cache/shippo` by default):
+//!
+//! ```text
+//! artifacts/<key[..2]>/<key>/entry.json
+//! artifacts/<key[..2]>/<key>/files/<artifact>...
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::sha256_file;
+
+/// Overrides the cache root.
+pub const CACHE_DIR_ENV: &str = "SHIPPO_CACHE_DIR";
+
+const ENTRY_FILE: &str = "entry.json";
+const FILES_DIR: &str = "files";
+
+/// Build inputs hashed into a cache key.
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheKey<'a> {
+    pub package: &'a str,
+    /// From [`source_fingerprint`].
+    pub source: &'a str,
+    pub target: &'a str,
+    pub version: &'a str,
+    pub builder: &'a str,
+    /// Toolchain version string, e.g. `rustc 1.80.0 (...)`.
+    pub toolchain: Option<&'a str>,
+    /// Builder settings that affect outputs (node/python config).
+    pub settings: serde_json::Value,
+}
+
+impl CacheKey<'_> {
+    pub fn digest(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        hex::encode(Sha256::digest(&json))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub package: String,
+    pub target: String,
+    /// Absent in entries stored before versions were recorded.
+    #[serde(default)]
+    pub version: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// File or directory names under `files/`.
+    pub artifacts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArtifactCache {
+    root: PathBuf,
+}
+
+impl ArtifactCache {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// `$SHIPPO_CACHE_DIR`, else `$XDG_CACHE_HOME/shippo`, else
+    /// `~/.cache/shippo`.
+    pub fn default_root() -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os(CACHE_DIR_ENV) {
+            return Some(PathBuf::from(dir));
+        }
+        if let Some(dir) = std::env::var_os("XDG_CACHE_HOME") {
+            return Some(PathBuf::from(dir).join("shippo"));
+        }
+        std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".cache").join("shippo"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn entry_dir(&self, key: &str) -> PathBuf {
+        self.root
+            .join("artifacts")
+            .join(&key[..key.len().min(2)])
+            .join(key)
+    }
+
+    /// Paths of the cached artifacts for `key`, if present.
+    pub fn get(&self, key: &str) -> io::Result<Option<Vec<PathBuf>>> {
+        let dir = self.entry_dir(key);
+        let data = match fs::read_to_string(dir.join(ENTRY_FILE)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let Ok(entry) = serde_json::from_str::<CacheEntry>(&data) else {
+            return Ok(None);
+        };
+        let paths: Vec<PathBuf> = entry
+            .artifacts
+            .iter()
+            .map(|name| dir.join(FILES_DIR).join(name))
+            .collect();
+        Ok(paths.iter().all(|p| p.exists()).then_some(paths))
+    }
+
+    /// Copies `artifacts` into the cache under `key`. Concurrent writers of
+    /// the same key are harmless: the first complete entry wins.
+    pub fn put(
+        &self,
+        key: &str,
+        package: &str,
+        target: &str,
+        version: &str,
+        artifacts: &[PathBuf],
+    ) -> io::Result<()> {
+        let dir = self.entry_dir(key);
+        if dir.join(ENTRY_FILE).exists() {
+            return Ok(());
+        }
+        let staging = self
+            .root
+            .join("tmp")
+            .join(format!("{key}-{}", std::process::id()));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        fs::create_dir_all(staging.join(FILES_DIR))?;
+        let mut names = Vec::new();
+        for artifact in artifacts {
+            let Some(name) = artifact.file_name() else {
+                continue;
+            };
+            copy_recursive(artifact, &staging.join(FILES_DIR).join(name))?;
+            names.push(name.to_string_lossy().to_string());
+        }
+        let entry = CacheEntry {
+            package: package.to_string(),
+            target: target.to_string(),
+            version: Some(version.to_string()),
+            created_at: Utc::now(),
+            artifacts: names,
+        };
+        let json = serde_json::to_string_pretty(&entry).map_err(io::Error::other)?;
+        fs::write(staging.join(ENTRY_FILE), json)?;
+        if let Some(parent) = dir.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::rename(&staging, &dir).is_err() {
+            // another process stored the same key first
+            fs::remove_dir_all(&staging)?;
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self) -> io::Result<CacheStats> {
+        let artifacts = self.root.join("artifacts");
+        let mut stats = CacheStats::default();
+        if !artifacts.exists() {
+            return Ok(stats);
+        }
+        for entry in WalkDir::new(&artifacts) {
+            let entry = entry.map_err(io::Error::other)?;
+            if entry.file_type().is_file() {
+                stats.bytes += entry.metadata().map_err(io::Error::other)?.len();
+                if entry.file_name() == ENTRY_FILE {
+                    stats.entries += 1;
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Removes the entries `keep` returns false for and returns what was
+    /// removed. Unreadable entries are kept.
+    pub fn retain(&self, keep: impl Fn(&CacheEntry) -> bool) -> io::Result<CacheStats> {
+        let mut removed = CacheStats::default();
+        for (dir, entry) in self.walk_entries()? {
+            if keep(&entry) {
+                continue;
+            }
+            for file in WalkDir::new(&dir) {
+                let file = file.map_err(io::Error::other)?;
+                if file.file_type().is_file() {
+                    removed.bytes += file.metadata().map_err(io::Error::other)?.len();
+                }
+            }
+            removed.entries += 1;
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(removed)
+    }
+
+    /// Every readable entry.
+    pub fn entries(&self) -> io::Result<Vec<CacheEntry>> {
+        Ok(self.walk_entries()?.into_iter().map(|(_, e)| e).collect())
+    }
+
+    fn walk_entries(&self) -> io::Result<Vec<(PathBuf, CacheEntry)>> {
+        let artifacts = self.root.join("artifacts");
+        if !artifacts.exists() {
+            return Ok(Vec::new());
+        }
+        let mut entries = Vec::new();
+        for file in WalkDir::new(&artifacts).min_depth(3).max_depth(3) {
+            let file = file.map_err(io::Error::other)?;
+            if file.file_name() != ENTRY_FILE {
+                continue;
+            }
+            let Some(dir) = file.path().parent() else {
+                continue;
+            };
+            let Ok(data) = fs::read_to_string(file.path()) else {
+                continue;
+            };
+            if let Ok(entry) = serde_json::from_str::<CacheEntry>(&data) {
+                entries.push((dir.to_path_buf(), entry));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Removes every entry and returns what was removed.
+    pub fn clear(&self) -> io::Result<CacheStats> {
+        let stats = self.stats()?;
+        for dir in ["artifacts", "tmp"] {
+            let path = self.root.join(dir);
+            if path.exists() {
+                fs::remove_dir_all(path)?;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// Hashes the files under `dir` that are not ignored by `.gitignore`, so
+/// build outputs and dependency directories don't change the fingerprint.
+pub fn source_fingerprint(dir: &Path) -> io::Result<String> {
+    let mut files = Vec::new();
+    let walker = ignore::WalkBuilder::new(dir)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|e| {
+            !matches!(
+                e.file_name().to_str(),
+                Some(".git" | "target" | "dist-build" | "node_modules" | "__pycache__")
+            )
+        })
+        .build();
+    for entry in walker {
+        let entry = entry.map_err(io::Error::other)?;
+        if entry.file_type().is_some_and(|t| t.is_file()) {
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+    let mut hasher = Sha256::new();
+    for path in files {
+        let rel = path.strip_prefix(dir).unwrap_or(&path);
+        hasher.update(rel.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(sha256_file(&path)?.as_bytes());
+        hasher.update([0]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        for entry in WalkDir::new(from) {
+            let entry = entry.map_err(io::Error::other)?;
+            let rel = entry.path().strip_prefix(from).map_err(io::Error::other)?;
+            let dest = to.join(rel);
+            if entry.file_type().is_dir() {
+                fs::create_dir_all(&dest)?;
+            } else {
+                fs::copy(entry.path(), &dest)?;
+            }
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_get_stats_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("target")).unwrap();
+        fs::write(src.join("main.rs"), "fn main() {}").unwrap();
+        let before = source_fingerprint(&src).unwrap();
+        fs::write(src.join("target").join("app"), "binary").unwrap();
+        assert_eq!(source_fingerprint(&src).unwrap(), before);
+
+        let cache = ArtifactCache::new(dir.path().join("cache"));
+        let key = CacheKey {
+            package: "app",
+            source: &before,
+            target: "native",
+            version: "v1.0.0",
+            builder: "rust",
+            toolchain: Some("rustc 1.80.0"),
+            settings: serde_json::Value::Null,
+        }
+        .digest();
+        assert_eq!(cache.get(&key).unwrap(), None);
+        let app = src.join("target").join("app");
+        cache
+            .put(&key, "app", "native", "v1.0.0", std::slice::from_ref(&app))
+            .unwrap();
+        let hit = cache.get(&key).unwrap().unwrap();
+        assert_eq!(fs::read_to_string(&hit[0]).unwrap(), "binary");
+        assert_eq!(cache.stats().unwrap().entries, 1);
+
+        cache
+            .put("ab12", "app", "native", "v0.9.0", &[app])
+            .unwrap();
+        assert_eq!(cache.entries().unwrap().len(), 2);
+        let removed = cache
+            .retain(|e| e.version.as_deref() != Some("v0.9.0"))
+            .unwrap();
+        assert_eq!(removed.entries, 1);
+        assert!(cache.get("ab12").unwrap().is_none());
+        assert_eq!(cache.clear().unwrap().entries, 1);
+        assert_eq!(cache.stats().unwrap(), CacheStats::default());
+    }
+}
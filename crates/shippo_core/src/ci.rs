@@ -0,0 +1,106 @@
+//! Detection of the CI provider a build runs on, recorded in the manifest so
+//! artifacts can be traced back to the exact CI run.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CiRunInfo {
+    /// `github-actions`, `gitlab-ci`, `buildkite`, or `circleci`.
+    pub provider: String,
+    pub run_id: Option<String>,
+    pub workflow: Option<String>,
+    pub runner_image: Option<String>,
+    pub git_ref: Option<String>,
+    pub run_url: Option<String>,
+}
+
+pub fn detect_ci() -> Option<CiRunInfo> {
+    detect_ci_from(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+}
+
+/// Same as [`detect_ci`] with an injectable environment lookup.
+pub fn detect_ci_from(env: impl Fn(&str) -> Option<String>) -> Option<CiRunInfo> {
+    let is_true = |name: &str| env(name).is_some_and(|v| v == "true");
+    if is_true("GITHUB_ACTIONS") {
+        let run_id = env("GITHUB_RUN_ID");
+        let run_url = match (env("GITHUB_SERVER_URL"), env("GITHUB_REPOSITORY"), &run_id) {
+            (Some(server), Some(repo), Some(id)) => {
+                Some(format!("{server}/{repo}/actions/runs/{id}"))
+            }
+            _ => None,
+        };
+        let runner_image = match (env("ImageOS"), env("ImageVersion")) {
+            (Some(os), Some(version)) => Some(format!("{os}/{version}")),
+            (os, _) => os.or_else(|| env("RUNNER_OS")),
+        };
+        return Some(CiRunInfo {
+            provider: "github-actions".into(),
+            run_id,
+            workflow: env("GITHUB_WORKFLOW"),
+            runner_image,
+            git_ref: env("GITHUB_REF"),
+            run_url,
+        });
+    }
+    if is_true("GITLAB_CI") {
+        return Some(CiRunInfo {
+            provider: "gitlab-ci".into(),
+            run_id: env("CI_PIPELINE_ID"),
+            workflow: env("CI_JOB_NAME"),
+            runner_image: env("CI_JOB_IMAGE"),
+            git_ref: env("CI_COMMIT_REF_NAME"),
+            run_url: env("CI_JOB_URL"),
+        });
+    }
+    if is_true("BUILDKITE") {
+        return Some(CiRunInfo {
+            provider: "buildkite".into(),
+            run_id: env("BUILDKITE_BUILD_ID"),
+            workflow: env("BUILDKITE_PIPELINE_SLUG"),
+            runner_image: env("BUILDKITE_AGENT_NAME"),
+            git_ref: env("BUILDKITE_TAG").or_else(|| env("BUILDKITE_BRANCH")),
+            run_url: env("BUILDKITE_BUILD_URL"),
+        });
+    }
+    if is_true("CIRCLECI") {
+        return Some(CiRunInfo {
+            provider: "circleci".into(),
+            run_id: env("CIRCLE_WORKFLOW_ID").or_else(|| env("CIRCLE_BUILD_NUM")),
+            workflow: env("CIRCLE_JOB"),
+            runner_image: None,
+            git_ref: env("CIRCLE_TAG").or_else(|| env("CIRCLE_BRANCH")),
+            run_url: env("CIRCLE_BUILD_URL"),
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_detect_github_actions() {
+        let env: HashMap<&str, &str> = [
+            ("GITHUB_ACTIONS", "true"),
+            ("GITHUB_RUN_ID", "42"),
+            ("GITHUB_WORKFLOW", "release"),
+            ("GITHUB_REF", "refs/tags/v1.0.0"),
+            ("GITHUB_SERVER_URL", "https://github.com"),
+            ("GITHUB_REPOSITORY", "acme/app"),
+            ("ImageOS", "ubuntu22"),
+            ("ImageVersion", "20240101.1"),
+        ]
+        .into_iter()
+        .collect();
+        let info = detect_ci_from(|k| env.get(k).map(|v| v.to_string())).unwrap();
+        assert_eq!(info.provider, "github-actions");
+        assert_eq!(
+            info.run_url.as_deref(),
+            Some("https://github.com/acme/app/actions/runs/42")
+        );
+        assert_eq!(info.runner_image.as_deref(), Some("ubuntu22/20240101.1"));
+        assert!(detect_ci_from(|_| None).is_none());
+    }
+}
@@ -0,0 +1,130 @@
+//! Structured pipeline events and the bus that fans them out to subscribers.
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+/// Pipeline stages in execution order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Plan,
+    Build,
+    Test,
+    Scan,
+    Package,
+    Publish,
+}
+
+impl Stage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Plan => "plan",
+            Stage::Build => "build",
+            Stage::Test => "test",
+            Stage::Scan => "scan",
+            Stage::Package => "package",
+            Stage::Publish => "publish",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    StageStarted {
+        stage: Stage,
+    },
+    StageFinished {
+        stage: Stage,
+    },
+    StageFailed {
+        stage: Stage,
+        error: String,
+    },
+    PackageBuildStarted {
+        package: String,
+        targets: Vec<String>,
+    },
+    PackageBuilt {
+        package: String,
+        target: String,
+        artifacts: usize,
+        /// Restored from the artifact cache instead of built.
+        #[serde(default)]
+        cached: bool,
+    },
+    ArtifactPackaged {
+        package: String,
+        target: String,
+        filename: String,
+        bytes: u64,
+    },
+    AssetUploaded {
+        filename: String,
+        bytes: u64,
+    },
+}
+
+pub trait Subscriber: Send + Sync {
+    fn on_event(&self, event: &Event);
+}
+
+impl<F> Subscriber for F
+where
+    F: Fn(&Event) + Send + Sync,
+{
+    fn on_event(&self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Cheap-to-clone fan-out of [`Event`]s to every registered [`Subscriber`].
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Vec<Arc<dyn Subscriber>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, subscriber: impl Subscriber + 'static) {
+        self.subscribers.push(Arc::new(subscriber));
+    }
+
+    pub fn emit(&self, event: Event) {
+        for subscriber in &self.subscribers {
+            subscriber.on_event(&event);
+        }
+    }
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventBus")
+            .field("subscribers", &self.subscribers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_bus_fan_out_and_json_shape() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut bus = EventBus::default();
+        for _ in 0..2 {
+            let sink = seen.clone();
+            bus.subscribe(move |e: &Event| sink.lock().unwrap().push(e.clone()));
+        }
+        bus.emit(Event::AssetUploaded {
+            filename: "a.tar.gz".into(),
+            bytes: 3,
+        });
+        assert_eq!(seen.lock().unwrap().len(), 2);
+        let json = serde_json::to_value(&seen.lock().unwrap()[0]).unwrap();
+        assert_eq!(json["event"], "asset_uploaded");
+    }
+}
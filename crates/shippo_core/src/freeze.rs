@@ -0,0 +1,272 @@
+//! `[[policy.freeze]]`: change-freeze windows in which `shippo release`
+//! refuses to publish, either a range of dates or the minutes a cron
+//! expression matches, all in UTC.
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+
+use crate::{FreezeOverride, FreezeWindow};
+
+/// A parsed five-field cron expression: minute, hour, day of month,
+/// month and day of week (0 or 7 is Sunday), each a bitmask of values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day-of-month and day-of-week fields were `*`; when
+    /// both are restricted, a day matching either one matches, like cron.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+/// The values of one cron field: `*`, `5`, `1-5`, `*/15`, `9-17/2` and
+/// comma-separated lists of them.
+fn field(spec: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in spec.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("bad step in {part:?}"))?,
+            ),
+            None => (part, 1),
+        };
+        let value = |v: &str| {
+            v.parse::<u32>()
+                .ok()
+                .filter(|v| (min..=max).contains(v))
+                .ok_or_else(|| format!("{v:?} is not in {min}-{max}"))
+        };
+        let (from, to) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((from, to)) => (value(from)?, value(to)?),
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if from > to {
+            return Err(format!("{part:?} is an empty range"));
+        }
+        for v in (from..=to).step_by(step as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+impl Cron {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!(
+                "{expr:?} must have five fields: minute hour day month weekday"
+            ));
+        };
+        let mut weekdays = field(weekday, 0, 7)?;
+        // 7 is Sunday too
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+        Ok(Self {
+            minutes: field(minute, 0, 59)?,
+            hours: field(hour, 0, 23)?,
+            days: field(day, 1, 31)?,
+            months: field(month, 1, 12)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    /// Whether the minute of `at` matches.
+    pub fn matches(&self, at: DateTime<Utc>) -> bool {
+        let has = |bits: u64, v: u32| bits & (1 << v) != 0;
+        let day = has(self.days, at.day());
+        let weekday = has(self.weekdays, at.weekday().num_days_from_sunday());
+        let day_matches = if self.any_day || self.any_weekday {
+            day && weekday
+        } else {
+            day || weekday
+        };
+        has(self.minutes, at.minute())
+            && has(self.hours, at.hour())
+            && has(self.months, at.month())
+            && day_matches
+    }
+}
+
+/// `value` as an instant: an RFC 3339 time, or a date's midnight; `end`
+/// dates are inclusive, so they mean the midnight after.
+fn instant(value: &str, end: bool) -> Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("{value:?} is neither a date (2026-12-20) nor an RFC 3339 time"))?;
+    let date = if end {
+        date.succ_opt().unwrap_or(date)
+    } else {
+        date
+    };
+    Ok(Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN)))
+}
+
+impl FreezeWindow {
+    /// Checks the window's fields, as [`contains`](Self::contains) would.
+    pub fn validate(&self) -> Result<(), String> {
+        self.contains(Utc::now()).map(|_| ())
+    }
+
+    /// Whether `at` falls in the window.
+    pub fn contains(&self, at: DateTime<Utc>) -> Result<bool, String> {
+        match (&self.start, &self.end, &self.cron) {
+            (None, None, Some(cron)) => Ok(Cron::parse(cron)?.matches(at)),
+            (Some(start), Some(end), None) => {
+                let (start, end) = (instant(start, false)?, instant(end, true)?);
+                if start >= end {
+                    return Err("end must be after start".into());
+                }
+                Ok(start <= at && at < end)
+            }
+            _ => Err("needs either start and end, or cron".into()),
+        }
+    }
+
+    /// How the window is shown: its name, or its position in the list.
+    pub fn label(&self, index: usize) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("policy.freeze[{index}]"),
+        }
+    }
+}
+
+/// Who is releasing, from the CI provider's variables or `USER`.
+pub fn actor() -> Option<String> {
+    [
+        "GITHUB_ACTOR",
+        "GITLAB_USER_LOGIN",
+        "BUILDKITE_BUILD_CREATOR",
+        "CIRCLE_USERNAME",
+        "USER",
+        "USERNAME",
+    ]
+    .into_iter()
+    .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+}
+
+/// The label of the first window in `windows` that `at` falls in.
+pub fn active_freeze(
+    windows: &[FreezeWindow],
+    at: DateTime<Utc>,
+) -> Result<Option<String>, String> {
+    for (i, window) in windows.iter().enumerate() {
+        if window
+            .contains(at)
+            .map_err(|e| format!("{}: {e}", window.label(i)))?
+        {
+            return Ok(Some(window.label(i)));
+        }
+    }
+    Ok(None)
+}
+
+/// Checks `windows` at `at`: nothing to record outside a freeze, the
+/// override to record inside one given a `reason`, an error without.
+pub fn check_freeze(
+    windows: &[FreezeWindow],
+    at: DateTime<Utc>,
+    reason: Option<&str>,
+) -> Result<Option<FreezeOverride>, String> {
+    let Some(window) = active_freeze(windows, at)? else {
+        return Ok(None);
+    };
+    match reason.map(str::trim).filter(|r| !r.is_empty()) {
+        Some(reason) => Ok(Some(FreezeOverride {
+            window,
+            reason: reason.to_string(),
+            by: actor(),
+            at,
+        })),
+        None => Err(format!(
+            "releases are frozen ({window}); pass --override-freeze \"<reason>\" to publish anyway"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_freeze_windows() {
+        let year_end = FreezeWindow {
+            name: Some("year-end".into()),
+            start: Some("2026-12-20".into()),
+            end: Some("2027-01-04".into()),
+            cron: None,
+        };
+        assert!(year_end.contains(at("2026-12-20T00:00:00Z")).unwrap());
+        assert!(year_end.contains(at("2027-01-04T23:59:00Z")).unwrap());
+        assert!(!year_end.contains(at("2027-01-05T00:00:00Z")).unwrap());
+
+        // Friday from 16:00, and all weekend
+        let weekends = |cron: &str| FreezeWindow {
+            name: None,
+            start: None,
+            end: None,
+            cron: Some(cron.into()),
+        };
+        let windows = [year_end, weekends("* 16-23 * * 5"), weekends("* * * * 6,7")];
+        let active = |s: &str| active_freeze(&windows, at(s)).unwrap();
+        assert_eq!(
+            active("2026-10-16T17:30:00Z").as_deref(),
+            Some("policy.freeze[1]")
+        );
+        assert_eq!(
+            active("2026-10-18T09:00:00Z").as_deref(),
+            Some("policy.freeze[2]")
+        );
+        assert_eq!(active("2026-10-16T15:59:00Z"), None);
+        assert_eq!(active("2026-12-24T12:00:00Z").as_deref(), Some("year-end"));
+
+        let friday = at("2026-10-16T17:30:00Z");
+        let err = check_freeze(&windows, friday, Some("  ")).unwrap_err();
+        assert!(err.contains("frozen (policy.freeze[1])"));
+        let recorded = check_freeze(&windows, friday, Some("hotfix for INC-42"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(recorded.window, "policy.freeze[1]");
+        assert_eq!(recorded.reason, "hotfix for INC-42");
+        assert_eq!(
+            check_freeze(&windows, at("2026-10-15T12:00:00Z"), None),
+            Ok(None)
+        );
+
+        // either the 1st or a Monday once both are given
+        let cron = Cron::parse("*/30 9 1 * 1").unwrap();
+        assert!(cron.matches(at("2026-10-01T09:30:00Z")));
+        assert!(cron.matches(at("2026-10-12T09:00:00Z")));
+        assert!(!cron.matches(at("2026-10-12T09:15:00Z")));
+
+        assert!(Cron::parse("* * *").is_err());
+        assert!(Cron::parse("60 * * * *").is_err());
+        assert!(weekends("* * * * 8").validate().is_err());
+        assert!(FreezeWindow {
+            cron: None,
+            ..weekends("")
+        }
+        .validate()
+        .is_err());
+    }
+}
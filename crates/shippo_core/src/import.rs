@@ -0,0 +1,386 @@
+//! Translate configs of other release tools (goreleaser, cargo-dist) into a
+//! [`ShippoConfig`].
+
+use serde_yaml::Value as Yaml;
+use toml::Value as Toml;
+
+use crate::{
+    BuildConfig, ConfigError, GitHubReleaseConfig, PackageConfig, ProjectConfig, ProjectType,
+    ReleaseConfig, ShippoConfig, SignConfig,
+};
+
+/// Result of an import: the translated config plus notes about settings that
+/// could not be carried over.
+#[derive(Debug, Clone)]
+pub struct ImportReport {
+    pub config: ShippoConfig,
+    pub notes: Vec<String>,
+}
+
+pub fn import_goreleaser(content: &str, fallback_name: &str) -> Result<ImportReport, ConfigError> {
+    let doc: Yaml = serde_yaml::from_str(content)
+        .map_err(|e| ConfigError::Message(format!("invalid goreleaser yaml: {e}")))?;
+    let mut notes = Vec::new();
+    let name = doc
+        .get("project_name")
+        .and_then(Yaml::as_str)
+        .unwrap_or(fallback_name)
+        .to_string();
+
+    let mut targets = Vec::new();
+    for build in yaml_seq(&doc, "builds") {
+        let goos = yaml_strings(build, "goos");
+        let goarch = yaml_strings(build, "goarch");
+        for os in &goos {
+            for arch in &goarch {
+                let target = format!("{os}-{arch}");
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+        }
+        if build.get("ignore").is_some() {
+            notes.push("builds.ignore is not supported; review generated targets".into());
+        }
+    }
+    if targets.is_empty() {
+        targets.push("native".into());
+    }
+
+    let mut package = None;
+    if let Some(archive) = yaml_seq(&doc, "archives").first() {
+        let mut formats = yaml_strings(archive, "formats");
+        if let Some(format) = archive.get("format").and_then(Yaml::as_str) {
+            formats.push(format.to_string());
+        }
+        if formats.is_empty() {
+            formats.push("tar.gz".into());
+        }
+        let has_zip_override = yaml_seq(archive, "format_overrides").iter().any(|o| {
+            o.get("format").and_then(Yaml::as_str) == Some("zip")
+                || yaml_strings(o, "formats").iter().any(|f| f == "zip")
+        });
+        if has_zip_override && !formats.iter().any(|f| f == "zip") {
+            formats.push("zip".into());
+            notes.push(
+                "archives.format_overrides flattened: zip is produced for every target".into(),
+            );
+        }
+        formats.retain(|f| f.ends_with("tar.gz") || f == "tar.zst" || f == "zip");
+        let name_template = archive
+            .get("name_template")
+            .and_then(Yaml::as_str)
+            .map(go_template_to_shippo)
+            .unwrap_or_else(crate::default_template);
+        package = Some(PackageConfig {
+            formats,
+            name_template,
+            include: yaml_strings(archive, "files"),
+            exclude: Vec::new(),
+            target_style: None,
+            keep_last: None,
+            contents: Default::default(),
+            transform: Vec::new(),
+            compression: Default::default(),
+            duplicates: None,
+        });
+    }
+
+    let mut sign = None;
+    if let Some(signer) = yaml_seq(&doc, "signs").first() {
+        let cmd = signer.get("cmd").and_then(Yaml::as_str).unwrap_or("gpg");
+        let method = if cmd.contains("cosign") {
+            "cosign"
+        } else {
+            "gpg"
+        };
+        sign = Some(SignConfig {
+            enabled: true,
+            method: method.into(),
+            cosign_mode: crate::default_cosign_mode(),
+            ssh_key: None,
+            ssh_namespace: None,
+            cosign: None,
+            gpg: None,
+        });
+    }
+
+    for section in ["brews", "scoops", "nfpms", "dockers", "snapcrafts"] {
+        if !yaml_seq(&doc, section).is_empty() {
+            notes.push(format!(
+                "{section} are not supported by shippo yet; skipped"
+            ));
+        }
+    }
+
+    let github = doc
+        .get("release")
+        .and_then(|r| r.get("github"))
+        .and_then(|gh| {
+            Some(GitHubReleaseConfig {
+                owner: gh.get("owner")?.as_str()?.to_string(),
+                repo: gh.get("name")?.as_str()?.to_string(),
+            })
+        });
+    let draft = doc
+        .get("release")
+        .and_then(|r| r.get("draft"))
+        .and_then(Yaml::as_bool)
+        .unwrap_or(true);
+
+    let config = ShippoConfig {
+        project: Some(ProjectConfig {
+            name,
+            project_type: ProjectType::Go,
+            path: ".".into(),
+        }),
+        build: Some(BuildConfig {
+            targets,
+            env: Default::default(),
+            builder: None,
+            provenance: false,
+            scratch_dir: None,
+        }),
+        package,
+        sign,
+        release: Some(release_config(github, draft)),
+        ..Default::default()
+    };
+    Ok(ImportReport { config, notes })
+}
+
+pub fn import_cargo_dist(content: &str, fallback_name: &str) -> Result<ImportReport, ConfigError> {
+    let doc: Toml = toml::from_str(content)
+        .map_err(|e| ConfigError::Message(format!("invalid Cargo.toml: {e}")))?;
+    let mut notes = Vec::new();
+    let dist = doc
+        .get("workspace")
+        .and_then(|w| w.get("metadata"))
+        .and_then(|m| m.get("dist"))
+        .or_else(|| {
+            doc.get("package")
+                .and_then(|p| p.get("metadata"))
+                .and_then(|m| m.get("dist"))
+        })
+        .ok_or_else(|| {
+            ConfigError::Message(
+                "no [workspace.metadata.dist] or [package.metadata.dist] found".into(),
+            )
+        })?;
+    let name = doc
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(Toml::as_str)
+        .unwrap_or(fallback_name)
+        .to_string();
+
+    let mut targets = toml_strings(dist, "targets");
+    if targets.is_empty() {
+        targets.push("native".into());
+    }
+    let mut formats = Vec::new();
+    for key in ["unix-archive", "windows-archive"] {
+        let format = dist
+            .get(key)
+            .and_then(Toml::as_str)
+            .unwrap_or(if key == "unix-archive" {
+                ".tar.xz"
+            } else {
+                ".zip"
+            })
+            .trim_start_matches('.')
+            .replace("tar.zstd", "tar.zst");
+        if matches!(format.as_str(), "tar.gz" | "tar.zst" | "zip") {
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        } else {
+            notes.push(format!("{key} = {format} is not supported; using tar.gz"));
+            if !formats.iter().any(|f| f == "tar.gz") {
+                formats.push("tar.gz".into());
+            }
+        }
+    }
+    for installer in toml_strings(dist, "installers") {
+        notes.push(format!(
+            "installer {installer} is not supported by shippo yet; skipped"
+        ));
+    }
+
+    let repository = doc
+        .get("package")
+        .and_then(|p| p.get("repository"))
+        .or_else(|| {
+            doc.get("workspace")
+                .and_then(|w| w.get("package"))
+                .and_then(|p| p.get("repository"))
+        })
+        .and_then(Toml::as_str);
+    let github = repository.and_then(github_coordinates);
+
+    let config = ShippoConfig {
+        project: Some(ProjectConfig {
+            name,
+            project_type: ProjectType::Rust,
+            path: ".".into(),
+        }),
+        build: Some(BuildConfig {
+            targets,
+            env: Default::default(),
+            builder: None,
+            provenance: false,
+            scratch_dir: None,
+        }),
+        package: Some(PackageConfig {
+            formats,
+            name_template: crate::default_template(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            target_style: None,
+            keep_last: None,
+            contents: Default::default(),
+            transform: Vec::new(),
+            compression: Default::default(),
+            duplicates: None,
+        }),
+        release: Some(release_config(github, true)),
+        ..Default::default()
+    };
+    Ok(ImportReport { config, notes })
+}
+
+fn release_config(github: Option<GitHubReleaseConfig>, draft: bool) -> ReleaseConfig {
+    ReleaseConfig {
+        provider: "github".into(),
+        draft,
+        prerelease: None,
+        update_latest: false,
+        strategy: "combined".into(),
+        github,
+        s3: None,
+        plugins: Vec::new(),
+        signed_tag: None,
+        approval: None,
+        homebrew: None,
+        scoop: None,
+        npm: None,
+        pypi: None,
+        crates: None,
+        name_template: None,
+        codename: None,
+        body: Default::default(),
+        assets: Default::default(),
+    }
+}
+
+fn go_template_to_shippo(template: &str) -> String {
+    let mut out = template.to_string();
+    for (from, to) in [
+        (".ProjectName", "{name}"),
+        (".Version", "{version}"),
+        (".Tag", "{version}"),
+    ] {
+        out = out.replace(&format!("{{{{ {from} }}}}"), to);
+        out = out.replace(&format!("{{{{{from}}}}}"), to);
+    }
+    let os_arch = regex::Regex::new(r"\{\{-?\s*\.Os\s*-?\}\}[_-]\{\{-?\s*\.Arch\s*-?\}\}").unwrap();
+    out = os_arch.replace_all(&out, "{target}").into_owned();
+    if out.contains("{{") {
+        crate::default_template()
+    } else {
+        out
+    }
+}
+
+fn github_coordinates(url: &str) -> Option<GitHubReleaseConfig> {
+    let remote = shippo_git::RemoteUrl::parse(url).filter(|r| r.provider() == Some("github"))?;
+    Some(GitHubReleaseConfig {
+        owner: remote.owner,
+        repo: remote.repo,
+    })
+}
+
+fn yaml_seq<'a>(doc: &'a Yaml, key: &str) -> Vec<&'a Yaml> {
+    doc.get(key)
+        .and_then(Yaml::as_sequence)
+        .map(|s| s.iter().collect())
+        .unwrap_or_default()
+}
+
+fn yaml_strings(doc: &Yaml, key: &str) -> Vec<String> {
+    yaml_seq(doc, key)
+        .into_iter()
+        .filter_map(|v| v.as_str().map(str::to_string))
+        .collect()
+}
+
+fn toml_strings(doc: &Toml, key: &str) -> Vec<String> {
+    doc.get(key)
+        .and_then(Toml::as_array)
+        .map(|a| {
+            a.iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_goreleaser() {
+        let yaml = r#"
+project_name: hello
+builds:
+  - goos: [linux, darwin]
+    goarch: [amd64]
+archives:
+  - format: tar.gz
+    name_template: "{{ .ProjectName }}_{{ .Version }}_{{ .Os }}_{{ .Arch }}"
+signs:
+  - cmd: cosign
+brews:
+  - name: hello
+release:
+  github:
+    owner: acme
+    name: hello
+"#;
+        let report = import_goreleaser(yaml, "fallback").unwrap();
+        let cfg = report.config;
+        assert_eq!(cfg.project.unwrap().name, "hello");
+        assert_eq!(
+            cfg.build.unwrap().targets,
+            vec!["linux-amd64", "darwin-amd64"]
+        );
+        assert_eq!(
+            cfg.package.unwrap().name_template,
+            "{name}_{version}_{target}"
+        );
+        assert_eq!(cfg.sign.unwrap().method, "cosign");
+        assert_eq!(cfg.release.unwrap().github.unwrap().owner, "acme");
+        assert_eq!(report.notes.len(), 1);
+    }
+
+    #[test]
+    fn test_import_cargo_dist() {
+        let manifest = r#"
+[package]
+name = "tool"
+repository = "https://github.com/acme/tool"
+
+[workspace.metadata.dist]
+targets = ["x86_64-unknown-linux-gnu", "aarch64-apple-darwin"]
+unix-archive = ".tar.gz"
+installers = ["shell"]
+"#;
+        let report = import_cargo_dist(manifest, "fallback").unwrap();
+        let cfg = report.config;
+        assert_eq!(cfg.build.unwrap().targets.len(), 2);
+        assert_eq!(cfg.package.unwrap().formats, vec!["tar.gz", "zip"]);
+        assert_eq!(cfg.release.unwrap().github.unwrap().repo, "tool");
+        assert_eq!(report.notes.len(), 1);
+    }
+}
@@ -2,15 +2,33 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
-use anyhow::{anyhow, Result};
 use camino::{Utf8Path, Utf8PathBuf};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use unicode_normalization::UnicodeNormalization;
 use walkdir::WalkDir;
 
+pub mod cache;
+pub mod ci;
+pub mod events;
+pub mod exec;
+pub mod freeze;
+pub mod import;
+pub mod limits;
+pub mod lock;
+pub mod metadata;
+pub mod plugin;
+pub mod provenance;
+pub mod redact;
+pub mod remote;
+pub mod sandbox;
+pub mod scaffold;
+pub mod targets;
+pub mod tools;
+
 pub static DEFAULT_CONFIG: &str =
-    "# Shippo configuration\n[project]\nname = \"example\"\ntype = \"rust\"\npath = \".\"\n\n[version]\nsource = \"git\"\n\n[build]\ntargets = [\"native\"]\n\n[package]\nformats = [\"tar.gz\", \"zip\"]\nname_template = \"{name}-{version}-{target}\"\n\n[sbom]\nenabled = true\nformat = \"cyclonedx\"\nmode = \"auto\"\n\n[sign]\nenabled = false\nmethod = \"cosign\"\ncosign_mode = \"keyless\"\n\n[release]\nprovider = \"github\"\ndraft = true\nprerelease = false\n\n[release.github]\nowner = \"acme\"\nrepo = \"example\"\n\n[changelog]\nmode = \"auto\"\n";
+    "# Shippo configuration\n[project]\nname = \"example\"\ntype = \"rust\"\npath = \".\"\n\n[version]\nsource = \"git\"\n\n[build]\ntargets = [\"native\"]\n\n[package]\nformats = [\"tar.gz\", \"zip\"]\nname_template = \"{name}-{version}-{target}\"\n\n[sbom]\nenabled = true\nformat = \"cyclonedx\"\nmode = \"auto\"\n\n[sign]\nenabled = false\nmethod = \"cosign\"\ncosign_mode = \"keyless\"\n\n[release]\nprovider = \"github\"\ndraft = true\n\n[release.github]\nowner = \"acme\"\nrepo = \"example\"\n\n[changelog]\nmode = \"auto\"\n";
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -21,6 +39,17 @@ pub enum ProjectType {
     Python,
 }
 
+impl ProjectType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectType::Rust => "rust",
+            ProjectType::Go => "go",
+            ProjectType::Node => "node",
+            ProjectType::Python => "python",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ProjectConfig {
     pub name: String,
@@ -47,6 +76,9 @@ pub struct VersionConfig {
     pub source: VersionSource,
     #[serde(default)]
     pub manual: Option<String>,
+    /// Glob selecting this project's release tags, e.g. `cli-v*`.
+    #[serde(default)]
+    pub tag_pattern: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -55,6 +87,18 @@ pub struct BuildConfig {
     pub targets: Vec<String>,
     #[serde(default)]
     pub env: BTreeMap<String, String>,
+    /// `plugin:<name>` hands builds to a plugin instead of the language toolchain.
+    #[serde(default)]
+    pub builder: Option<String>,
+    /// Embed version, commit, builder and config hash into binaries; see
+    /// [`provenance`].
+    #[serde(default)]
+    pub provenance: bool,
+    /// Write build outputs here instead of the source tree, one directory
+    /// per package; relative to the dist directory. `{version}` keeps
+    /// builds of several versions apart.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scratch_dir: Option<String>,
 }
 
 fn default_targets() -> Vec<String> {
@@ -71,6 +115,89 @@ pub struct PackageConfig {
     pub include: Vec<String>,
     #[serde(default)]
     pub exclude: Vec<String>,
+    /// How `{target}` renders: `triple` (`x86_64-unknown-linux-gnu`) or
+    /// `goish` (`linux-amd64`), whatever the ecosystem calls the target.
+    /// Unset keeps targets as configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_style: Option<String>,
+    /// Source-tree files to pack, as glob -> archive path, e.g.
+    /// `"docs/**" = "share/doc/{name}/"`.
+    #[serde(default)]
+    pub contents: BTreeMap<String, String>,
+    /// Steps applied to built files before archiving, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transform: Vec<TransformConfig>,
+    /// How many versions' outputs to keep under the versioned build
+    /// directory, with their cache entries; older ones are pruned after
+    /// packaging. Only read from the top-level `[package]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_last: Option<usize>,
+    /// Levels and methods for each archive format.
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    /// What to do with an archive byte-identical to another target's:
+    /// `alias` lists the other file for both targets, `skip` leaves it out.
+    /// Unset keeps every copy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicates: Option<String>,
+}
+
+/// `[package.compression]`, e.g. `{ gzip = 9, zstd = 19, zip = "store" }`.
+/// Unset entries keep the compressor's default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CompressionConfig {
+    /// `tar.gz` level, 0 (none) to 9 (smallest).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gzip: Option<u32>,
+    /// `tar.zst` level, 1 to 22.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zstd: Option<i32>,
+    /// `deflate` or `store`; storing suits contents that are compressed
+    /// already, such as wheels and jars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zip: Option<String>,
+}
+
+impl PackageConfig {
+    /// `target` as the `{target}` placeholder renders it.
+    pub fn target_name(&self, target: &str) -> String {
+        targets::styled(target, self.target_style.as_deref())
+    }
+}
+
+/// One `[[package.transform]]` step for the built files whose name matches
+/// `match`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TransformConfig {
+    /// Glob on the file name; `*` and `?` are wildcards.
+    #[serde(rename = "match", default = "default_transform_match")]
+    pub pattern: String,
+    /// New file name; `{name}`, `{version}`, `{target}` and `{ext}` (the
+    /// original extension with its dot, e.g. `.exe`) are filled in.
+    #[serde(default)]
+    pub rename: Option<String>,
+    /// Octal permissions, e.g. `"0755"`.
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// Launcher script put in the file's place, relative to the package
+    /// directory; the file moves to `libexec/`.
+    #[serde(default)]
+    pub wrap: Option<String>,
+}
+
+impl TransformConfig {
+    /// `mode` as permission bits, `None` when unset or not octal.
+    pub fn mode_bits(&self) -> Option<u32> {
+        let mode = self.mode.as_deref()?;
+        u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+            .ok()
+            .filter(|bits| *bits <= 0o7777)
+    }
+}
+
+fn default_transform_match() -> String {
+    "*".to_string()
 }
 
 fn default_formats() -> Vec<String> {
@@ -89,6 +216,24 @@ pub struct SbomConfig {
     pub format: String,
     #[serde(default = "default_sbom_mode")]
     pub mode: String,
+    /// `syft` or `builtin` forces that generator; unset, syft is used when
+    /// it is on `PATH` unless `mode = "fallback"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Several formats at once, e.g. `["cyclonedx", "spdx"]`; replaces
+    /// `format` when set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formats: Vec<String>,
+}
+
+impl SbomConfig {
+    /// `formats`, or else `format`.
+    pub fn output_formats(&self) -> Vec<&str> {
+        match self.formats.as_slice() {
+            [] => vec![self.format.as_str()],
+            formats => formats.iter().map(String::as_str).collect(),
+        }
+    }
 }
 
 fn default_true() -> bool {
@@ -111,12 +256,143 @@ pub struct SignConfig {
     pub method: String,
     #[serde(default = "default_cosign_mode")]
     pub cosign_mode: String,
+    /// `ssh`: the private key to sign with, or the public key of one held
+    /// by `ssh-agent`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_key: Option<String>,
+    /// `ssh`: the signature namespace; `file` unless set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_namespace: Option<String>,
+    /// `[sign.cosign]`: the key of `cosign_mode = "key"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cosign: Option<CosignKeyConfig>,
+    /// `[sign.gpg]`: the key and keyring `gpg` signs with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpg: Option<GpgKeyConfig>,
+}
+
+impl SignConfig {
+    /// Why this configuration can't sign on this machine, checked before
+    /// anything is built: with `cosign_mode = "key"`, a missing key file
+    /// (relative to `root`), a malformed KMS URI, both or neither of them,
+    /// or an unset password variable; with `gpg`, a missing homedir or an
+    /// unset passphrase variable.
+    pub fn check_keys(&self, root: &Path) -> Result<(), String> {
+        match self.method.as_str() {
+            _ if !self.enabled => Ok(()),
+            "cosign" if self.cosign_mode == "key" => self.check_cosign_key(root),
+            "gpg" => self.check_gpg_key(root),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_gpg_key(&self, root: &Path) -> Result<(), String> {
+        let gpg = self.gpg.clone().unwrap_or_default();
+        if let Some(homedir) = gpg.homedir.filter(|h| !root.join(h).is_dir()) {
+            return Err(format!("gpg homedir {homedir} not found"));
+        }
+        if let Some(var) = gpg.passphrase_env {
+            if std::env::var_os(&var).is_none() {
+                return Err(format!("gpg passphrase variable {var} is not set"));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_cosign_key(&self, root: &Path) -> Result<(), String> {
+        let cosign = self.cosign.clone().unwrap_or_default();
+        match (&cosign.key, &cosign.kms) {
+            (Some(_), Some(_)) => {
+                return Err("[sign.cosign] sets both key and kms; pick one".into())
+            }
+            (None, None) => {
+                return Err("cosign_mode = \"key\" needs [sign.cosign] key or kms".into())
+            }
+            (Some(key), None) if !root.join(key).is_file() => {
+                return Err(format!("cosign key {key} not found"));
+            }
+            (None, Some(kms)) if !kms.contains("://") => {
+                return Err(format!(
+                    "cosign kms {kms:?} is not a KMS URI such as awskms:///alias/release"
+                ));
+            }
+            _ => {}
+        }
+        if let Some(var) = &cosign.password_env {
+            if std::env::var_os(var).is_none() {
+                return Err(format!("cosign key password variable {var} is not set"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `[sign.cosign]`: where `cosign sign-blob --key` gets its key.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CosignKeyConfig {
+    /// A private key file written by `cosign generate-key-pair`, relative
+    /// to the workspace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// A KMS key URI (`awskms://`, `gcpkms://`, `azurekms://`,
+    /// `hashivault://`, `k8s://`) used in place of `key`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kms: Option<String>,
+    /// The variable holding the key's password, passed to cosign as
+    /// `COSIGN_PASSWORD`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_env: Option<String>,
+    /// Upload signatures to rekor; `false` for air-gapped CI.
+    #[serde(default = "default_true")]
+    pub tlog_upload: bool,
+}
+
+/// `[sign.gpg]`: without it gpg signs with its default key in
+/// `~/.gnupg`, asking the agent for the passphrase.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct GpgKeyConfig {
+    /// Fingerprint, key ID or user ID of the signing key
+    /// (`--local-user`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+    /// The keyring directory (`--homedir`), relative to the workspace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homedir: Option<String>,
+    /// The variable holding the key's passphrase, given to gpg with
+    /// `--pinentry-mode loopback`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase_env: Option<String>,
+}
+
+impl Default for CosignKeyConfig {
+    fn default() -> Self {
+        Self {
+            key: None,
+            kms: None,
+            password_env: None,
+            tlog_upload: true,
+        }
+    }
 }
 
 fn default_false() -> bool {
     false
 }
 
+/// `[attest]`: an in-toto statement per target, naming its archives and
+/// SBOMs by digest with the build's provenance as the predicate.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct AttestConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Sign the statements like the archives when `[sign]` is enabled.
+    #[serde(default = "default_true")]
+    pub sign: bool,
+}
+
 fn default_sign_method() -> String {
     "cosign".to_string()
 }
@@ -127,21 +403,514 @@ fn default_cosign_mode() -> String {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ReleaseConfig {
+    /// `github`, `s3`, or `auto` to pick it from the `origin` remote's
+    /// host.
     #[serde(default = "default_release_provider")]
     pub provider: String,
     #[serde(default = "default_true")]
     pub draft: bool,
-    #[serde(default = "default_false")]
-    pub prerelease: bool,
+    /// Unset marks versions with a prerelease part (`1.2.0-rc.1`) as
+    /// prereleases, see [`ReleaseConfig::is_prerelease`].
+    #[serde(default)]
+    pub prerelease: Option<bool>,
+    /// Make each full release GitHub's latest one and move a `latest` tag
+    /// to it; drafts and prereleases leave both alone.
+    #[serde(default)]
+    pub update_latest: bool,
+    /// `combined` publishes one release for all packages; `per-package`
+    /// one per package, tagged `<package>-<version>`.
+    #[serde(default = "default_release_strategy")]
+    pub strategy: String,
+    /// Defaults to the owner and name of a GitHub `origin` remote.
     #[serde(default)]
     pub github: Option<GitHubReleaseConfig>,
+    /// The bucket of `provider = "s3"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s3: Option<S3Config>,
+    /// Plugins that publish the release after the provider.
+    #[serde(default)]
+    pub plugins: Vec<String>,
+    /// Refuse to publish unless the release tag is signed by one of these keys.
+    #[serde(default)]
+    pub signed_tag: Option<SignedTagConfig>,
+    /// Wait for someone to approve the release before uploading anything.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval: Option<ApprovalConfig>,
+    /// Update a Homebrew formula in a tap after the GitHub release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homebrew: Option<HomebrewConfig>,
+    /// Update a Scoop manifest in a bucket after the GitHub release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scoop: Option<ScoopConfig>,
+    /// Publish Node packages to npm or another registry before the GitHub
+    /// release, recording them in its manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub npm: Option<NpmConfig>,
+    /// Upload wheels and sdists to PyPI or another index after the GitHub
+    /// release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pypi: Option<PypiConfig>,
+    /// crates.io owners and yanking, for `shippo crates`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crates: Option<CratesConfig>,
+    /// Release title, e.g. `"{name} {version} — {codename}"`; the tag
+    /// without it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_template: Option<String>,
+    /// Where `{codename}` comes from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codename: Option<CodenameConfig>,
+    /// What the release notes are made of.
+    #[serde(default)]
+    pub body: ReleaseBodyConfig,
+    /// How uploaded files are labelled and typed.
+    #[serde(default)]
+    pub assets: ReleaseAssetsConfig,
 }
 
-fn default_release_provider() -> String {
+/// `[release.assets]`: the display label and MIME type of each uploaded
+/// file. GitHub shows the label in place of the file name.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseAssetsConfig {
+    /// Label archives, SBOMs and signatures from the manifest's targets;
+    /// `false` shows file names.
+    #[serde(default = "default_true")]
+    pub labels: bool,
+    /// Archive label with `{package}`, `{os}`, `{arch}`, `{libc}`,
+    /// `{target}` and `{format}`; defaults to
+    /// `"{os} {arch} {libc} ({format})"`, after the package with several.
+    #[serde(default)]
+    pub label_template: Option<String>,
+    /// Labels by file name, e.g. `"SHA256SUMS" = "Checksums"`.
+    #[serde(default)]
+    pub names: BTreeMap<String, String>,
+    /// MIME types by extension, e.g. `"tar.zst" = "application/zstd"`,
+    /// over the built-in ones.
+    #[serde(default)]
+    pub content_types: BTreeMap<String, String>,
+}
+
+impl Default for ReleaseAssetsConfig {
+    fn default() -> Self {
+        Self {
+            labels: true,
+            label_template: None,
+            names: BTreeMap::new(),
+            content_types: BTreeMap::new(),
+        }
+    }
+}
+
+/// `[release.body]`: the release notes' sections, in order.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseBodyConfig {
+    /// Any of [`BODY_SECTIONS`]; leaving one out turns it off.
+    #[serde(default = "default_body_sections")]
+    pub sections: Vec<String>,
+    /// Markdown file, relative to the workspace, for the `header` section;
+    /// the section is empty without it.
+    #[serde(default)]
+    pub header_file: Option<String>,
+}
+
+/// `header` is [`ReleaseBodyConfig::header_file`]'s text, `changelog` the
+/// commits since the previous release, `artifacts` a table of the release's
+/// files and `verification` how to check their checksums and signatures.
+pub const BODY_SECTIONS: [&str; 4] = ["header", "changelog", "artifacts", "verification"];
+
+fn default_body_sections() -> Vec<String> {
+    vec!["header".to_string(), "changelog".to_string()]
+}
+
+impl Default for ReleaseBodyConfig {
+    fn default() -> Self {
+        Self {
+            sections: default_body_sections(),
+            header_file: None,
+        }
+    }
+}
+
+/// `[release.codename]`: the first of `env`, `file` and `value` that gives
+/// one wins.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CodenameConfig {
+    #[serde(default)]
+    pub value: Option<String>,
+    /// Environment variable holding the codename.
+    #[serde(default)]
+    pub env: Option<String>,
+    /// TOML file, relative to the workspace, mapping versions or version
+    /// prefixes to codenames, e.g. `"1.4" = "Birch"`.
+    #[serde(default)]
+    pub file: Option<String>,
+}
+
+impl CodenameConfig {
+    /// The codename for `version` (without a leading `v`). In `file` the
+    /// longest key that is the version or a dot-separated prefix of it
+    /// wins.
+    pub fn resolve(&self, root: &Path, version: &str) -> Result<Option<String>, ConfigError> {
+        if let Some(value) = self
+            .env
+            .as_ref()
+            .and_then(|var| std::env::var(var).ok())
+            .filter(|v| !v.trim().is_empty())
+        {
+            return Ok(Some(value.trim().to_string()));
+        }
+        if let Some(file) = &self.file {
+            let path = root.join(file);
+            let content = fs::read_to_string(&path).map_err(|e| {
+                ConfigError::Message(format!("release.codename.file {}: {e}", path.display()))
+            })?;
+            let names: BTreeMap<String, String> = toml::from_str(&content).map_err(|e| {
+                ConfigError::Message(format!("release.codename.file {}: {e}", path.display()))
+            })?;
+            let found = names
+                .iter()
+                .filter(|(prefix, _)| {
+                    let prefix = prefix.trim_start_matches('v');
+                    version == prefix
+                        || version
+                            .strip_prefix(prefix)
+                            .is_some_and(|rest| rest.starts_with(['.', '-', '+']))
+                })
+                .max_by_key(|(prefix, _)| prefix.trim_start_matches('v').len());
+            if let Some((_, name)) = found {
+                return Ok(Some(name.clone()));
+            }
+        }
+        Ok(self.value.clone())
+    }
+}
+
+/// Fills a `release.name_template`. `{version}` has no leading `v`; a
+/// missing codename renders empty, along with any separator left dangling
+/// at the end.
+pub fn release_name(
+    template: &str,
+    name: &str,
+    tag: &str,
+    version: &str,
+    codename: Option<&str>,
+) -> String {
+    let rendered = template
+        .replace("{name}", name)
+        .replace("{tag}", tag)
+        .replace("{version}", version.trim_start_matches('v'))
+        .replace("{codename}", codename.unwrap_or_default());
+    rendered
+        .trim_end_matches(|c: char| c.is_whitespace() || "-—–:|".contains(c))
+        .trim_start()
+        .to_string()
+}
+
+/// `[release.homebrew]`: a formula for one package, committed to a tap
+/// repository on GitHub.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct HomebrewConfig {
+    /// `owner/repo` of the tap, e.g. `acme/homebrew-tap`.
+    pub tap: String,
+    /// Defaults to the package name.
+    #[serde(default)]
+    pub formula: Option<String>,
+    /// The package the formula installs; needed with several packages.
+    #[serde(default)]
+    pub package: Option<String>,
+    /// Defaults to the package's metadata, e.g. `Cargo.toml`'s.
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    #[serde(default)]
+    pub license: Option<String>,
+    /// Executables in the archive to install; defaults to the formula name.
+    #[serde(default)]
+    pub bins: Vec<String>,
+    /// Where formulae live in the tap.
+    #[serde(default = "default_formula_dir")]
+    pub directory: String,
+    /// Tera template rendering the formula in place of the generated one,
+    /// relative to the workspace.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Open a pull request from a new branch; `false` commits to the tap's
+    /// default branch.
+    #[serde(default = "default_true")]
+    pub pull_request: bool,
+    /// Environment variable with a token that can push to the tap; the
+    /// release token is used without it.
+    #[serde(default)]
+    pub token_env: Option<String>,
+}
+
+fn default_formula_dir() -> String {
+    "Formula".to_string()
+}
+
+/// `[release.scoop]`: a manifest for one package, committed to a bucket
+/// repository on GitHub.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ScoopConfig {
+    /// `owner/repo` of the bucket, e.g. `acme/scoop-bucket`.
+    pub bucket: String,
+    /// The app name, `<name>.json`; defaults to the package name.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The package the manifest installs; needed with several packages.
+    #[serde(default)]
+    pub package: Option<String>,
+    /// Defaults to the package's metadata, e.g. `Cargo.toml`'s.
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    #[serde(default)]
+    pub license: Option<String>,
+    /// Executables in the archive to shim; defaults to `<name>.exe`.
+    #[serde(default)]
+    pub bins: Vec<String>,
+    /// Where manifests live in the bucket.
+    #[serde(default = "default_scoop_dir")]
+    pub directory: String,
+    /// Tera template rendering the manifest in place of the generated one,
+    /// relative to the workspace.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Open a pull request from a new branch; `false` commits to the
+    /// bucket's default branch.
+    #[serde(default = "default_true")]
+    pub pull_request: bool,
+    /// Environment variable with a token that can push to the bucket; the
+    /// release token is used without it.
+    #[serde(default)]
+    pub token_env: Option<String>,
+}
+
+fn default_scoop_dir() -> String {
+    "bucket".to_string()
+}
+
+/// `[release.npm]`: `npm publish` for `type = "node"` packages.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct NpmConfig {
+    #[serde(default = "default_npm_registry")]
+    pub registry: String,
+    /// The dist-tag, e.g. `latest` or `next`; defaults to `next` for
+    /// prereleases and `latest` otherwise.
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// `public` or `restricted`; scoped packages are restricted by default.
+    #[serde(default)]
+    pub access: Option<String>,
+    /// Packages to publish; all Node packages when empty.
+    #[serde(default)]
+    pub packages: Vec<String>,
+    /// Environment variable with the registry's auth token.
+    #[serde(default = "default_npm_token_env")]
+    pub token_env: String,
+    /// Publish with `--provenance`, signed with the CI run's OIDC identity.
+    #[serde(default)]
+    pub provenance: bool,
+}
+
+fn default_npm_registry() -> String {
+    "https://registry.npmjs.org".to_string()
+}
+
+fn default_npm_token_env() -> String {
+    "NPM_TOKEN".to_string()
+}
+
+/// `[release.crates]`: the crates `shippo crates owners sync` and `shippo
+/// crates yank` manage through the registry API.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CratesConfig {
+    /// The registry's web API root.
+    #[serde(default = "default_crates_api")]
+    pub api: String,
+    /// Crate names; the `type = "rust"` packages' crates when empty.
+    #[serde(default)]
+    pub crates: Vec<String>,
+    /// Owners every crate should have: users by login (`alice`) and
+    /// GitHub teams (`github:acme:release`).
+    #[serde(default)]
+    pub owners: Vec<String>,
+    /// Also remove owners not in `owners`.
+    #[serde(default)]
+    pub remove_unlisted: bool,
+    /// Environment variable with the registry's API token.
+    #[serde(default = "default_crates_token_env")]
+    pub token_env: String,
+}
+
+fn default_crates_api() -> String {
+    "https://crates.io".to_string()
+}
+
+fn default_crates_token_env() -> String {
+    "CARGO_REGISTRY_TOKEN".to_string()
+}
+
+/// `[release.pypi]`: `twine upload` of the wheels and sdists that
+/// `type = "python"` packages build in `wheel` mode.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PypiConfig {
+    /// The index's upload endpoint.
+    #[serde(default = "default_pypi_repository")]
+    pub repository_url: String,
+    /// Packages to upload; all Python packages in `wheel` mode when empty.
+    #[serde(default)]
+    pub packages: Vec<String>,
+    /// Environment variable with an API token for the index.
+    #[serde(default = "default_pypi_token_env")]
+    pub token_env: String,
+    /// Leave files the index already has alone instead of failing, so a
+    /// re-run release can upload what's missing.
+    #[serde(default = "default_true")]
+    pub skip_existing: bool,
+}
+
+fn default_pypi_repository() -> String {
+    "https://upload.pypi.org/legacy/".to_string()
+}
+
+fn default_pypi_token_env() -> String {
+    "PYPI_TOKEN".to_string()
+}
+
+/// `[release.approval]`: a second person approves each release before
+/// anything is uploaded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ApprovalConfig {
+    /// `github`, an issue in the release repository approved by commenting
+    /// `/approve`, or `webhook`.
+    #[serde(default = "default_approval_provider")]
+    pub provider: String,
+    /// GitHub logins that may approve; anyone with write access to the
+    /// repository when empty. Whoever runs the release never can.
+    #[serde(default)]
+    pub approvers: Vec<String>,
+    /// Where `webhook` POSTs the release summary.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Environment variable with a bearer token for the webhook.
+    #[serde(default)]
+    pub token_env: Option<String>,
+    #[serde(default = "default_approval_timeout")]
+    pub timeout_minutes: u64,
+    #[serde(default = "default_approval_poll")]
+    pub poll_seconds: u64,
+}
+
+fn default_approval_provider() -> String {
     "github".to_string()
 }
 
+fn default_approval_timeout() -> u64 {
+    60
+}
+
+fn default_approval_poll() -> u64 {
+    30
+}
+
+/// `[release.s3]`: the bucket `provider = "s3"` uploads the dist to, with
+/// the AWS CLI and its usual credentials.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct S3Config {
+    pub bucket: String,
+    /// Key prefix of a release with `{tag}` and `{version}`.
+    #[serde(default = "default_s3_prefix")]
+    pub prefix: String,
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Endpoint of an S3-compatible store, e.g. MinIO or Cloudflare R2.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// AWS CLI profile to use instead of the environment's credentials.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Canned ACL for the uploaded objects, e.g. `public-read`.
+    #[serde(default)]
+    pub acl: Option<String>,
+}
+
+fn default_s3_prefix() -> String {
+    "{tag}".to_string()
+}
+
+impl S3Config {
+    /// `prefix` for a release, without surrounding slashes.
+    pub fn key_prefix(&self, tag: &str, version: &str) -> String {
+        self.prefix
+            .replace("{tag}", tag)
+            .replace("{version}", version.trim_start_matches('v'))
+            .trim_matches('/')
+            .to_string()
+    }
+}
+
+impl ReleaseConfig {
+    /// `prerelease` when set, otherwise whether `version` (after any tag
+    /// prefix) has a semver prerelease part such as `-rc.1` or `-beta`.
+    pub fn is_prerelease(&self, version: &str) -> bool {
+        self.prerelease
+            .unwrap_or_else(|| shippo_git::tag_version(version).is_some_and(|v| !v.pre.is_empty()))
+    }
+}
+
+/// Keys allowed to sign release tags. Paths are relative to the workspace.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedTagConfig {
+    /// Full fingerprints of GPG keys (or their primary keys) allowed to
+    /// sign; spaces are ignored.
+    #[serde(default)]
+    pub gpg_fingerprints: Vec<String>,
+    /// Keyring to check GPG signatures against instead of the default one.
+    /// Without `gpg_fingerprints`, any key in it may sign.
+    #[serde(default)]
+    pub gpg_keyring: Option<String>,
+    /// OpenSSH `allowed_signers` file for SSH-signed tags.
+    #[serde(default)]
+    pub ssh_allowed_signers: Option<String>,
+}
+
+impl SignedTagConfig {
+    /// Resolves key paths against `base`, the workspace root.
+    pub fn resolve(&self, base: &Path) -> Self {
+        let resolve = |path: &Option<String>| {
+            path.as_ref()
+                .map(|p| base.join(p).to_string_lossy().to_string())
+        };
+        Self {
+            gpg_fingerprints: self.gpg_fingerprints.clone(),
+            gpg_keyring: resolve(&self.gpg_keyring),
+            ssh_allowed_signers: resolve(&self.ssh_allowed_signers),
+        }
+    }
+}
+
+fn default_release_provider() -> String {
+    "auto".to_string()
+}
+
+fn default_release_strategy() -> String {
+    "combined".to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct GitHubReleaseConfig {
     pub owner: String,
     pub repo: String,
@@ -153,12 +922,49 @@ pub struct ChangelogConfig {
     pub mode: String,
     #[serde(default)]
     pub file: Option<String>,
+    /// Fetch the full history and tags when the checkout is shallow instead
+    /// of failing; needs `git` and access to `origin`.
+    #[serde(default)]
+    pub unshallow: bool,
+    /// Trailers listed after each entry when present, e.g. `Reviewed-by`
+    /// or `Ticket`.
+    #[serde(default)]
+    pub trailers: Vec<String>,
+    /// Line per commit with `{description}`, `{scope}`, `{subject}`, `{id}`,
+    /// `{sha}`, `{author}`, `{email}`, `{trailers}` and `{trailer:<Key>}`.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Longest release body, in characters; longer notes are cut and link
+    /// to the full `CHANGELOG.md` asset. GitHub's own limit applies anyway.
+    #[serde(default)]
+    pub max_length: Option<usize>,
 }
 
+/// Full release notes, written to the dist and uploaded when the release
+/// body had to be cut.
+pub const CHANGELOG_FILE: &str = "CHANGELOG.md";
+
+/// What a failed release got through, written to the dist for the person
+/// running it; never uploaded or verified.
+pub const SUMMARY_FILE: &str = "summary.json";
+
 fn default_changelog_mode() -> String {
     "auto".to_string()
 }
 
+impl Default for ChangelogConfig {
+    fn default() -> Self {
+        Self {
+            mode: default_changelog_mode(),
+            file: None,
+            unshallow: false,
+            trailers: Vec::new(),
+            template: None,
+            max_length: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct NodeBinaryConfig {
     #[serde(default = "default_node_tool")]
@@ -192,6 +998,19 @@ pub struct NodeConfig {
     pub binary: Option<NodeBinaryConfig>,
     #[serde(default)]
     pub frontend: Option<NodeFrontendConfig>,
+    /// `npm`, `pnpm`, `yarn` or `bun`; detected from `package.json` and
+    /// the lockfile when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_manager: Option<String>,
+    /// Run `corepack enable` before installing. Unset enables it when
+    /// `package.json` pins a `packageManager` other than npm.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corepack: Option<bool>,
+    /// Install at the root of the npm/pnpm/Yarn/Bun workspace the package
+    /// belongs to and build just this member. Unset detects the workspace;
+    /// `false` installs in the package directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<bool>,
 }
 
 fn default_node_mode() -> String {
@@ -204,6 +1023,9 @@ impl Default for NodeConfig {
             mode: default_node_mode(),
             binary: None,
             frontend: None,
+            package_manager: None,
+            corepack: None,
+            workspace: None,
         }
     }
 }
@@ -244,6 +1066,104 @@ impl Default for PythonConfig {
     }
 }
 
+/// `[go]` settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GoConfig {
+    /// Binaries to build, by name, from their main packages, e.g.
+    /// `server = "./cmd/server"`. With none, the package directory is
+    /// built as a binary named after the package.
+    #[serde(default)]
+    pub bins: BTreeMap<String, String>,
+}
+
+/// `[docker]` (or `[packages.docker]`): a container image built with the
+/// package and pushed on release.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct DockerConfig {
+    /// Relative to the package directory.
+    #[serde(default = "default_dockerfile")]
+    pub dockerfile: String,
+    /// Build context, relative to the package directory.
+    #[serde(default = "default_dot")]
+    pub context: String,
+    /// Registry host, e.g. `ghcr.io`; Docker Hub without it.
+    #[serde(default)]
+    pub registry: Option<String>,
+    /// Image name; `{name}` is the package, e.g. `acme/{name}`.
+    #[serde(default = "default_image")]
+    pub image: String,
+    /// Tag templates with `{version}`, `{major}` and `{minor}`.
+    #[serde(default = "default_image_tags")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub build_args: BTreeMap<String, String>,
+}
+
+fn default_dockerfile() -> String {
+    "Dockerfile".to_string()
+}
+
+fn default_image() -> String {
+    "{name}".to_string()
+}
+
+fn default_image_tags() -> Vec<String> {
+    vec!["{version}".to_string()]
+}
+
+impl DockerConfig {
+    /// The image without a tag, e.g. `ghcr.io/acme/cli`.
+    pub fn repository(&self, name: &str) -> String {
+        let image = self.image.replace("{name}", name);
+        match &self.registry {
+            Some(registry) => format!("{}/{image}", registry.trim_end_matches('/')),
+            None => image,
+        }
+    }
+
+    /// The rendered tags for `version`. `{version}` drops a leading `v`
+    /// and spells build metadata with `_`, as tags can't hold `+`; tags
+    /// with `{major}` or `{minor}` are left out for prereleases, which
+    /// shouldn't move them, leaving the version when nothing else is.
+    pub fn image_tags(&self, version: &str) -> Vec<String> {
+        let semver = shippo_git::tag_version(version).filter(|v| v.pre.is_empty());
+        let mut tags: Vec<String> = Vec::new();
+        for template in &self.tags {
+            let mut tag = template.replace(
+                "{version}",
+                &version.trim_start_matches('v').replace('+', "_"),
+            );
+            if tag.contains("{major}") || tag.contains("{minor}") {
+                let Some(v) = &semver else {
+                    continue;
+                };
+                tag = tag
+                    .replace("{major}", &v.major.to_string())
+                    .replace("{minor}", &format!("{}.{}", v.major, v.minor));
+            }
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        if tags.is_empty() {
+            tags.push(version.trim_start_matches('v').replace('+', "_"));
+        }
+        tags
+    }
+}
+
+/// Whether Docker accepts `tag`: up to 128 of `[A-Za-z0-9_.-]`, not
+/// starting with `.` or `-`.
+pub fn is_image_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag.len() <= 128
+        && !tag.starts_with(['.', '-'])
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct PackageEntry {
     pub name: String,
@@ -263,9 +1183,13 @@ pub struct PackageEntry {
     pub node: Option<NodeConfig>,
     #[serde(default)]
     pub python: Option<PythonConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub go: Option<GoConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docker: Option<DockerConfig>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ShippoConfig {
     #[serde(default)]
     pub project: Option<ProjectConfig>,
@@ -275,6 +1199,11 @@ pub struct ShippoConfig {
     pub node: Option<NodeConfig>,
     #[serde(default)]
     pub python: Option<PythonConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub go: Option<GoConfig>,
+    /// The single `[project]`'s image; `[[packages]]` set their own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docker: Option<DockerConfig>,
     #[serde(default)]
     pub version: Option<VersionConfig>,
     #[serde(default)]
@@ -286,9 +1215,546 @@ pub struct ShippoConfig {
     #[serde(default)]
     pub sign: Option<SignConfig>,
     #[serde(default)]
+    pub attest: Option<AttestConfig>,
+    #[serde(default)]
     pub release: Option<ReleaseConfig>,
     #[serde(default)]
     pub changelog: Option<ChangelogConfig>,
+    #[serde(default)]
+    pub ci: Option<CiConfig>,
+    /// Plugin name to executable path; `shippo-plugin-*` on PATH is found without an entry.
+    #[serde(default)]
+    pub plugins: BTreeMap<String, String>,
+    #[serde(default)]
+    pub wasm_plugins: BTreeMap<String, WasmPluginConfig>,
+    #[serde(default)]
+    pub sandbox: Option<SandboxConfig>,
+    #[serde(default)]
+    pub cache: Option<CacheConfig>,
+    /// Experimental: build some targets on other machines; see [`remote`].
+    #[serde(default)]
+    pub remote: BTreeMap<String, RemoteWorkerConfig>,
+    #[serde(default)]
+    pub verify: Option<VerifyConfig>,
+    #[serde(default)]
+    pub git: Option<GitConfig>,
+    #[serde(default)]
+    pub symbols: Option<SymbolsConfig>,
+    #[serde(default)]
+    pub tools: Option<ToolsConfig>,
+    /// Resource limits by stage (`build`, `test`, `scan`, `package`), or
+    /// `default` for stages without their own.
+    #[serde(default)]
+    pub limits: BTreeMap<String, LimitsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<PolicyConfig>,
+}
+
+/// `[policy]`: rules on when releases may be published.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// Change freezes; see [`freeze`].
+    #[serde(default)]
+    pub freeze: Vec<FreezeWindow>,
+}
+
+/// A change freeze: `start` to `end`, or the minutes `cron` matches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FreezeWindow {
+    /// Shown when the freeze blocks a release.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// First frozen day (`2026-12-20`) or instant (RFC 3339).
+    #[serde(default)]
+    pub start: Option<String>,
+    /// Last frozen day, or the instant the freeze ends.
+    #[serde(default)]
+    pub end: Option<String>,
+    /// Five-field cron expression in UTC, e.g. `* 16-23 * * 5` for
+    /// Friday evenings.
+    #[serde(default)]
+    pub cron: Option<String>,
+}
+
+/// Upload of split debug info after building, so crash reports from
+/// released binaries are symbolicated.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SymbolsConfig {
+    /// `sentry` (through `sentry-cli`) or `http` (PUT to a symbol server).
+    #[serde(default = "default_symbols_provider")]
+    pub provider: String,
+    /// Self-hosted Sentry URL, or the symbol server's base URL.
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub org: Option<String>,
+    #[serde(default)]
+    pub project: Option<String>,
+    /// Variable holding the auth token; defaults to `SENTRY_AUTH_TOKEN` or
+    /// `SYMBOL_SERVER_TOKEN`.
+    #[serde(default)]
+    pub token_env: Option<String>,
+}
+
+fn default_symbols_provider() -> String {
+    "sentry".to_string()
+}
+
+/// Repository preparation before builds.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GitConfig {
+    /// Run `git submodule update --init --recursive` before building.
+    #[serde(default)]
+    pub submodules: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VerifyConfig {
+    #[serde(default)]
+    pub tests: Option<TestsConfig>,
+    #[serde(default)]
+    pub keys: Option<VerifyKeysConfig>,
+    #[serde(default)]
+    pub scan: Option<ScanConfig>,
+    #[serde(default)]
+    pub metadata: Option<MetadataLintConfig>,
+    #[serde(default)]
+    pub signers: Option<SignersConfig>,
+}
+
+/// Trusted public keys for `shippo verify`, so signatures can be checked on
+/// machines that didn't make them. Paths are relative to the config file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VerifyKeysConfig {
+    /// cosign public key (`cosign.pub`).
+    #[serde(default)]
+    pub cosign: Option<String>,
+    /// Identity keyless signatures' certificates must name, e.g.
+    /// `https://github.com/acme/app/.github/workflows/release.yml@refs/tags/v1.0.0`.
+    #[serde(default)]
+    pub cosign_identity: Option<String>,
+    /// Regular expression the identity must match instead, e.g.
+    /// `^https://github.com/acme/app/.github/workflows/release.yml@`.
+    #[serde(default)]
+    pub cosign_identity_regexp: Option<String>,
+    /// OIDC issuer of keyless signatures' certificates, e.g.
+    /// `https://token.actions.githubusercontent.com`.
+    #[serde(default)]
+    pub cosign_oidc_issuer: Option<String>,
+    /// GPG keyring used instead of the default one, e.g. from
+    /// `gpg --export KEYID > release-keys.gpg`.
+    #[serde(default)]
+    pub gpg_keyring: Option<String>,
+    /// minisign public key file.
+    #[serde(default)]
+    pub minisign: Option<String>,
+    /// OpenSSH `allowed_signers` file.
+    #[serde(default)]
+    pub ssh_allowed_signers: Option<String>,
+    /// Namespace SSH signatures were made for; defaults to `file`.
+    #[serde(default)]
+    pub ssh_namespace: Option<String>,
+}
+
+impl VerifyKeysConfig {
+    /// Resolves key paths against `base`, the config file's directory.
+    pub fn resolve(&self, base: &Path) -> Self {
+        let resolve = |path: &Option<String>| {
+            path.as_ref()
+                .map(|p| base.join(p).to_string_lossy().to_string())
+        };
+        Self {
+            cosign: resolve(&self.cosign),
+            cosign_identity: self.cosign_identity.clone(),
+            cosign_identity_regexp: self.cosign_identity_regexp.clone(),
+            cosign_oidc_issuer: self.cosign_oidc_issuer.clone(),
+            gpg_keyring: resolve(&self.gpg_keyring),
+            minisign: resolve(&self.minisign),
+            ssh_allowed_signers: resolve(&self.ssh_allowed_signers),
+            ssh_namespace: self.ssh_namespace.clone(),
+        }
+    }
+}
+
+/// Who may sign a release, as `VERIFYING.md` tells its readers: from
+/// `[verify.keys]`, or for keyless signatures the `[release.github]`
+/// repository's workflows. Recorded in the plan and manifest so dists
+/// merged or signed without the config say the same.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignerTrust {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cosign_identity: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cosign_identity_regexp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cosign_oidc_issuer: Option<String>,
+    /// The lines of `[verify.keys] ssh_allowed_signers`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ssh_allowed_signers: Vec<String>,
+}
+
+/// Issuer of GitHub Actions' OIDC tokens, and so of their keyless
+/// certificates.
+pub const GITHUB_ACTIONS_ISSUER: &str = "https://token.actions.githubusercontent.com";
+
+impl SignerTrust {
+    /// `cfg`'s signers, reading the allowed_signers file relative to
+    /// `base`; `None` when it names none.
+    pub fn from_config(cfg: &ShippoConfig, base: &Path) -> Option<Self> {
+        let keys = cfg
+            .verify
+            .as_ref()
+            .and_then(|v| v.keys.clone())
+            .unwrap_or_default();
+        let github = cfg.release.as_ref().and_then(|r| r.github.as_ref());
+        let mut trust = Self {
+            cosign_identity: keys.cosign_identity,
+            cosign_identity_regexp: keys.cosign_identity_regexp,
+            cosign_oidc_issuer: keys.cosign_oidc_issuer,
+            ssh_allowed_signers: Vec::new(),
+        };
+        if let (None, None, Some(github)) = (
+            &trust.cosign_identity,
+            &trust.cosign_identity_regexp,
+            github,
+        ) {
+            // any workflow of the repository, e.g.
+            // https://github.com/acme/app/.github/workflows/release.yml@refs/tags/v1.0.0
+            trust.cosign_identity_regexp = Some(format!(
+                "^https://github\\.com/{}/{}/\\.github/workflows/",
+                regex::escape(&github.owner),
+                regex::escape(&github.repo)
+            ));
+            trust
+                .cosign_oidc_issuer
+                .get_or_insert_with(|| GITHUB_ACTIONS_ISSUER.to_string());
+        }
+        if let Some(path) = &keys.ssh_allowed_signers {
+            trust.ssh_allowed_signers = std::fs::read_to_string(base.join(path))
+                .unwrap_or_default()
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_string)
+                .collect();
+        }
+        (trust != Self::default()).then_some(trust)
+    }
+}
+
+/// `[verify.signers]`: people who each add their own signature to every
+/// artifact with `shippo sign`, and how many of them `shippo verify`
+/// requires. Paths are relative to the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SignersConfig {
+    /// Signatures from different signers every artifact and SBOM needs.
+    #[serde(default = "default_threshold")]
+    pub threshold: usize,
+    /// Signer name to minisign public key file.
+    #[serde(default)]
+    pub minisign: BTreeMap<String, String>,
+}
+
+fn default_threshold() -> usize {
+    1
+}
+
+impl SignersConfig {
+    /// Resolves key paths against `base`, the config file's directory.
+    pub fn resolve(&self, base: &Path) -> Self {
+        Self {
+            threshold: self.threshold,
+            minisign: self
+                .minisign
+                .iter()
+                .map(|(name, key)| (name.clone(), base.join(key).to_string_lossy().to_string()))
+                .collect(),
+        }
+    }
+
+    /// Why the threshold can never be met, or a signer name can't be part
+    /// of a file name.
+    pub fn check(&self) -> Result<(), String> {
+        if let Some(name) = self.minisign.keys().find(|n| !valid_signer(n)) {
+            return Err(format!(
+                "signer {name:?} must be letters, digits, `-` and `_`"
+            ));
+        }
+        if self.threshold == 0 || self.threshold > self.minisign.len() {
+            return Err(format!(
+                "threshold {} can't be met with {} signer(s)",
+                self.threshold,
+                self.minisign.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Whether `name` can name a signer, and so a signature file.
+pub fn valid_signer(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Test commands run by `shippo release --with-tests` before packaging.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TestsConfig {
+    /// Package name to command, e.g. `web = ["pnpm", "test"]`. Packages
+    /// without an entry run their project type's default test command.
+    #[serde(default)]
+    pub commands: BTreeMap<String, Vec<String>>,
+    /// Packages that have no tests to run.
+    #[serde(default)]
+    pub skip: Vec<String>,
+    /// Record failures in the manifest and release anyway.
+    #[serde(default)]
+    pub allow_failure: bool,
+}
+
+/// Pre-package check that each package has a license file, and a license,
+/// description and homepage in its ecosystem manifest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MetadataLintConfig {
+    /// Fail packaging on missing metadata instead of warning.
+    #[serde(default)]
+    pub strict: bool,
+    /// Packages not checked.
+    #[serde(default)]
+    pub skip: Vec<String>,
+}
+
+/// Malware scanner run over every built artifact before packaging.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ScanConfig {
+    /// Scanner command; artifact paths are appended. Any exit status other
+    /// than 0 fails the release, as `clamscan` exits 1 on a detection.
+    #[serde(default = "default_scan_command")]
+    pub command: Vec<String>,
+    /// Packages whose artifacts are not scanned.
+    #[serde(default)]
+    pub skip: Vec<String>,
+    /// Record failed scans in the manifest as not clean and release anyway.
+    #[serde(default)]
+    pub allow_failure: bool,
+}
+
+fn default_scan_command() -> Vec<String> {
+    vec![
+        "clamscan".into(),
+        "--no-summary".into(),
+        "--infected".into(),
+    ]
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            command: default_scan_command(),
+            skip: Vec::new(),
+            allow_failure: false,
+        }
+    }
+}
+
+impl TestsConfig {
+    /// The command testing `pkg`, or `None` when it is skipped.
+    pub fn command_for(&self, pkg: &PackagePlan) -> Option<Vec<String>> {
+        if self.skip.contains(&pkg.name) {
+            return None;
+        }
+        if let Some(command) = self.commands.get(&pkg.name) {
+            return Some(command.clone());
+        }
+        let default: &[&str] = match pkg.project_type {
+            ProjectType::Rust => &["cargo", "test", "--locked"],
+            ProjectType::Go => &["go", "test", "./..."],
+            ProjectType::Node => &["npm", "test"],
+            ProjectType::Python => &["python", "-m", "pytest"],
+        };
+        Some(default.iter().map(|s| s.to_string()).collect())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RemoteWorkerConfig {
+    /// SSH destination, e.g. `builder@mac-mini.local`.
+    pub host: String,
+    /// Targets built on this worker instead of locally.
+    pub targets: Vec<String>,
+    /// Workspace mirrors live under this directory on the worker.
+    #[serde(default = "default_remote_dir")]
+    pub dir: String,
+}
+
+fn default_remote_dir() -> String {
+    "shippo-builds".to_string()
+}
+
+/// Limits for the commands a stage runs; see [`limits`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Whole CPUs' worth of time.
+    #[serde(default)]
+    pub cpus: Option<u32>,
+    /// Memory cap such as `512M` or `4G`.
+    #[serde(default)]
+    pub memory: Option<String>,
+    /// Niceness from 0 to 19 (lowest priority).
+    #[serde(default)]
+    pub nice: Option<i32>,
+    /// I/O scheduling class: `idle` or `best-effort`.
+    #[serde(default)]
+    pub io: Option<String>,
+}
+
+/// Installing missing helper tools; see [`tools`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ToolsConfig {
+    /// Install helpers the plan needs but `PATH` lacks before building.
+    #[serde(default)]
+    pub auto_install: bool,
+    /// Install directory; defaults to `tools` in the cache root.
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// Tools to install even if the plan doesn't call them itself, e.g.
+    /// `cargo-zigbuild` for a custom build command.
+    #[serde(default)]
+    pub require: Vec<String>,
+    /// Versions to install instead of the defaults, by tool.
+    #[serde(default)]
+    pub pins: BTreeMap<String, ToolPin>,
+}
+
+impl ToolsConfig {
+    /// Resolves lockfile paths against `base`, the workspace root.
+    pub fn resolve(&self, base: &Path) -> Self {
+        let mut resolved = self.clone();
+        for pin in resolved.pins.values_mut() {
+            if let Some(lockfile) = &mut pin.lockfile {
+                *lockfile = base.join(&*lockfile).to_string_lossy().to_string();
+            }
+        }
+        resolved
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ToolPin {
+    pub version: String,
+    /// Expected SHA-256 of a downloaded binary (cosign).
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// What npm and pip tools are installed from, with a hash for every
+    /// package: a `package-lock.json` (pkg) or a requirements file with
+    /// `--hash` entries (pyinstaller). Relative to the workspace.
+    #[serde(default)]
+    pub lockfile: Option<String>,
+}
+
+/// Reuse build outputs from the local artifact cache; see [`cache`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Cache root; defaults to `~/.cache/shippo`.
+    #[serde(default)]
+    pub dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CiConfig {
+    /// `deny` turns plan warnings into errors under `--ci`; `warn` only logs them.
+    #[serde(default = "default_warning_policy")]
+    pub warnings: String,
+    /// Extra environment variables that must be set before a CI run starts.
+    #[serde(default)]
+    pub require_env: Vec<String>,
+}
+
+fn default_warning_policy() -> String {
+    "deny".to_string()
+}
+
+impl Default for CiConfig {
+    fn default() -> Self {
+        Self {
+            warnings: default_warning_policy(),
+            require_env: Vec::new(),
+        }
+    }
+}
+
+/// Isolation for builder commands; see [`sandbox`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SandboxConfig {
+    /// Sandbox every build without passing `--sandbox`.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Keep network access, e.g. for builds that download dependencies.
+    #[serde(default)]
+    pub network: bool,
+    /// Extra environment variables passed through to build commands.
+    #[serde(default)]
+    pub allow_env: Vec<String>,
+    /// Directories builds may write to, relative to the package or `~/`.
+    #[serde(default = "default_sandbox_writable")]
+    pub writable: Vec<String>,
+}
+
+fn default_sandbox_writable() -> Vec<String> {
+    [
+        "target",
+        "dist",
+        "build",
+        "dist-build",
+        "node_modules",
+        "~/.cache",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            network: false,
+            allow_env: Vec::new(),
+            writable: default_sandbox_writable(),
+        }
+    }
+}
+
+/// A sandboxed WebAssembly component run in-process.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WasmPluginConfig {
+    pub path: String,
+    /// `transformer` (rewrites built files before packaging) or `notifier`
+    /// (runs after publish).
+    pub kind: String,
+    /// Hosts the plugin may reach through `http-post`.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// Instruction budget per call.
+    #[serde(default = "default_wasm_fuel")]
+    pub fuel: u64,
+    #[serde(default = "default_wasm_memory_mb")]
+    pub memory_mb: u64,
+}
+
+fn default_wasm_fuel() -> u64 {
+    1_000_000_000
+}
+
+fn default_wasm_memory_mb() -> u64 {
+    256
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -302,43 +1768,299 @@ pub struct PackagePlan {
     pub sign: SignConfig,
     pub node: Option<NodeConfig>,
     pub python: Option<PythonConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub go: Option<GoConfig>,
+    #[serde(default)]
+    pub builder: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docker: Option<DockerConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Plan {
     pub version: String,
     pub packages: Vec<PackagePlan>,
+    #[serde(default)]
+    pub plugins: BTreeMap<String, String>,
+    /// Fingerprint of the full plan when this one was narrowed to some of
+    /// its targets, e.g. by `--target` on a matrix runner.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub narrowed_from: Option<String>,
+    /// Hex SHA-256 of the config file the plan was built from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_sha256: Option<String>,
+    /// Submodule commits of the repository the plan was built in.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub submodules: Vec<SubmodulePin>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attest: Option<AttestConfig>,
+    /// Unix time archive entries are stamped with, so archives of the same
+    /// commit are byte for byte the same: `SOURCE_DATE_EPOCH`, or when the
+    /// commit the plan was built at was made.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_date_epoch: Option<i64>,
+    /// `[verify.signers]` names, whose signatures `shippo sign` adds as
+    /// `<file>.<signer>.minisig`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trust: Option<SignerTrust>,
+}
+
+/// Who published through a freeze, and why (`--override-freeze`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FreezeOverride {
+    /// The freeze's name, or its position in `[[policy.freeze]]`.
+    pub window: String,
+    pub reason: String,
+    /// The user or CI actor that ran the release, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// The approval a release waited for before publishing.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ManifestApproval {
+    pub by: String,
+    pub at: DateTime<Utc>,
+    /// Where it was approved, e.g. the approval issue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// A git submodule and the commit the superproject pins it to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubmodulePin {
+    pub path: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    pub commit: String,
+}
+
+impl Plan {
+    /// Hex SHA-256 of the configuration that shapes the release: packages
+    /// and their targets, packaging, SBOM, signing and builder settings, and
+    /// plugin names. The version and machine-specific plugin paths are left
+    /// out, and ordering is canonical, so two releases share a fingerprint
+    /// exactly when they were produced from the same effective configuration.
+    pub fn fingerprint(&self) -> String {
+        if let Some(fingerprint) = &self.narrowed_from {
+            return fingerprint.clone();
+        }
+        let mut packages = self.packages.clone();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        for pkg in &mut packages {
+            pkg.targets.sort();
+        }
+        // serde_json maps are sorted, so the encoding is canonical
+        let canonical = serde_json::json!({
+            "packages": packages,
+            "plugins": self.plugins.keys().collect::<Vec<_>>(),
+        });
+        hex::encode(Sha256::digest(canonical.to_string().as_bytes()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ManifestArtifact {
+    pub filename: String,
+    pub bytes: u64,
+    pub sha256: String,
+    /// Files inside an archive, as packed from the build outputs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contents: Vec<ArchiveEntry>,
+    /// With `[package] duplicates = "alias"`, the name this target's
+    /// archive would have had; `filename` is the byte-identical archive of
+    /// another target, which is uploaded once for both.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub path: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ManifestSignature {
+    pub filename: String,
+    #[serde(default)]
+    pub method: String,
+    /// Sigstore bundle of a cosign signature, with the certificate of a
+    /// keyless one and the transparency-log entry. For a `.sigstore.json`
+    /// bundle it is `filename` itself, since the bundle holds the
+    /// signature too.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<String>,
+    /// The transparency-log entry cosign made for a keyless signature, as
+    /// read from its bundle when signing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rekor: Option<RekorEntry>,
+    /// The `[verify.signers]` name of a signature added with `shippo
+    /// sign`, which is `<file>.<signer>.minisig`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    /// Made with `[sign.cosign]`'s key rather than keyless, so it is
+    /// checked with the public key instead of a certificate.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub key: bool,
+    /// Made with `[sign.cosign] tlog_upload = false`: no rekor entry
+    /// vouches for it, so cosign is told not to look for one.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub offline: bool,
+}
+
+/// An entry of the rekor transparency log, which can be looked up with
+/// `rekor-cli get --uuid` or on search.sigstore.dev.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RekorEntry {
+    pub log_index: u64,
+    /// Hex SHA-256 Merkle leaf hash of the entry.
+    pub uuid: String,
+}
+
+/// Suffix of sigstore bundles (`cosign sign-blob --new-bundle-format`).
+pub const SIGSTORE_BUNDLE_SUFFIX: &str = ".sigstore.json";
+
+impl ManifestSignature {
+    /// The name of the file this signs: its own without `.sig`, `.minisig`
+    /// or `.sigstore.json`, and without the signer's name.
+    pub fn signed_file(&self) -> Option<&str> {
+        let file = [".sig", ".minisig", SIGSTORE_BUNDLE_SUFFIX]
+            .into_iter()
+            .find_map(|suffix| self.filename.strip_suffix(suffix))?;
+        match &self.signer {
+            Some(signer) => file.strip_suffix(signer.as_str())?.strip_suffix('.'),
+            None => Some(file),
+        }
+    }
+
+    /// Whether the signature is inside its bundle rather than a file of
+    /// its own.
+    pub fn in_bundle(&self) -> bool {
+        self.bundle.as_deref() == Some(self.filename.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ManifestTarget {
+    pub target: String,
+    pub artifacts: Vec<ManifestArtifact>,
+    pub sbom: Option<ManifestArtifact>,
+    /// SBOMs in the further `[sbom] formats`, after `sbom`'s.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_sboms: Vec<ManifestArtifact>,
+    /// The in-toto statement about the target's archives and SBOMs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<ManifestArtifact>,
+    pub signatures: Vec<ManifestSignature>,
+    /// How the target's binaries were built.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<ArtifactOrigin>,
+}
+
+/// Where a target's artifacts came from, for audits.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ArtifactOrigin {
+    /// The builder, e.g. `rust` or `plugin:<name>`.
+    pub builder: String,
+    /// The package's source directory, relative to the workspace.
+    pub source: String,
+    pub duration_ms: u64,
+    /// SHA256 of the commands the build ran, one command line per line;
+    /// `None` when it ran none, e.g. on a cache hit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_sha256: Option<String>,
+    /// Taken from the artifact cache rather than built.
+    #[serde(default)]
+    pub cached: bool,
+}
+
+impl ManifestTarget {
+    /// Every SBOM of the target, `sbom` first.
+    pub fn sboms(&self) -> impl Iterator<Item = &ManifestArtifact> {
+        self.sbom.iter().chain(&self.extra_sboms)
+    }
+
+    /// The target's files in the dist: artifacts, SBOMs, its attestation,
+    /// signatures and signature bundles.
+    pub fn files(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self.artifacts.iter().map(|a| a.filename.as_str()).collect();
+        files.extend(self.sboms().map(|s| s.filename.as_str()));
+        files.extend(self.attestation.iter().map(|a| a.filename.as_str()));
+        for sig in &self.signatures {
+            files.push(&sig.filename);
+            files.extend(sig.bundle.as_deref().filter(|_| !sig.in_bundle()));
+        }
+        files
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ManifestPackage {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub project_type: ProjectType,
+    pub path: String,
+    pub targets: Vec<ManifestTarget>,
+    /// Set when the release ran with `--with-tests`.
+    #[serde(default)]
+    pub tests: Option<ManifestTestRun>,
+    /// Set when `[verify.scan]` is configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scan: Option<ManifestScan>,
+    /// License, description and homepage, for package managers' metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<metadata::PackageMetadata>,
+    /// The container image, when `[docker]` is configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<ManifestImage>,
+    /// The npm package, once `[release.npm]` has published it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub npm: Option<ManifestNpm>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct ManifestArtifact {
-    pub filename: String,
-    pub bytes: u64,
-    pub sha256: String,
+pub struct ManifestNpm {
+    /// `name@version` as published.
+    pub package: String,
+    pub registry: String,
+    /// Where the registry's provenance statement was logged, e.g.
+    /// `https://search.sigstore.dev/?logIndex=…`, with `provenance = true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct ManifestSignature {
-    pub filename: String,
-    #[serde(default)]
-    pub method: String,
+pub struct ManifestImage {
+    /// `registry/name`, without a tag.
+    pub repository: String,
+    pub tags: Vec<String>,
+    /// The local image ID, the sha256 of the image config.
+    pub id: String,
+    /// The registry's manifest digest, `sha256:…`, once pushed; pull
+    /// `<repository>@<digest>` to get exactly this image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct ManifestTarget {
-    pub target: String,
-    pub artifacts: Vec<ManifestArtifact>,
-    pub sbom: Option<ManifestArtifact>,
-    pub signatures: Vec<ManifestSignature>,
+pub struct ManifestTestRun {
+    pub command: String,
+    pub passed: bool,
+    pub duration_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct ManifestPackage {
-    pub name: String,
-    #[serde(rename = "type")]
-    pub project_type: ProjectType,
-    pub path: String,
-    pub targets: Vec<ManifestTarget>,
+pub struct ManifestScan {
+    pub command: String,
+    /// Whether the scanner exited 0.
+    pub clean: bool,
+    /// Artifacts scanned, across all targets.
+    pub files: usize,
+    pub duration_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -346,6 +2068,23 @@ pub struct ManifestProject {
     pub repo_url: Option<String>,
     pub commit: Option<String>,
     pub version: String,
+    /// [`Plan::fingerprint`] of the plan the release was built from.
+    #[serde(default)]
+    pub plan_fingerprint: Option<String>,
+    /// Hex SHA-256 of the `.shippo.toml` the release was built from.
+    #[serde(default)]
+    pub config_sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub submodules: Vec<SubmodulePin>,
+    /// Set when the release was published during a `[[policy.freeze]]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freeze_override: Option<FreezeOverride>,
+    /// Who approved the release under `[release.approval]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval: Option<ManifestApproval>,
+    /// Who may sign the release, from the plan.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trust: Option<SignerTrust>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -356,15 +2095,110 @@ pub struct ToolingInfo {
     pub python: Option<String>,
 }
 
+impl ToolingInfo {
+    /// Asks every toolchain on PATH for its version.
+    pub fn probe(executor: &dyn exec::Executor) -> Self {
+        Self {
+            rust: tool_version(executor, "rustc --version"),
+            go: tool_version(executor, "go version"),
+            node: tool_version(executor, "node --version"),
+            python: tool_version(executor, "python --version"),
+        }
+    }
+
+    pub fn get(&self, project_type: &ProjectType) -> Option<&str> {
+        match project_type {
+            ProjectType::Rust => self.rust.as_deref(),
+            ProjectType::Go => self.go.as_deref(),
+            ProjectType::Node => self.node.as_deref(),
+            ProjectType::Python => self.python.as_deref(),
+        }
+    }
+
+    /// Like [`probe`](Self::probe), but only for the given project types.
+    pub fn probe_for(executor: &dyn exec::Executor, types: &[ProjectType]) -> Self {
+        let probe = |ty: ProjectType, cmd: &str| {
+            types
+                .contains(&ty)
+                .then(|| tool_version(executor, cmd))
+                .flatten()
+        };
+        Self {
+            rust: probe(ProjectType::Rust, "rustc --version"),
+            go: probe(ProjectType::Go, "go version"),
+            node: probe(ProjectType::Node, "node --version"),
+            python: probe(ProjectType::Python, "python --version"),
+        }
+    }
+}
+
+/// v1 manifests had no version field, and older releases wrote signatures
+/// without `method` and build environments without `ci_run`.
+fn upgrade_manifest_v1(value: &mut serde_json::Value) {
+    use serde_json::Value;
+
+    let Some(obj) = value.as_object_mut() else {
+        return;
+    };
+    obj.insert("manifest_version".into(), Value::from(2));
+    if let Some(env) = obj.get_mut("build_env").and_then(Value::as_object_mut) {
+        env.entry("ci_run").or_insert(Value::Null);
+    }
+    let targets = obj
+        .get_mut("packages")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(|pkg| pkg.get_mut("targets").and_then(Value::as_array_mut))
+        .flatten();
+    for target in targets {
+        let signatures = target
+            .get_mut("signatures")
+            .and_then(Value::as_array_mut)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_object_mut);
+        for sig in signatures {
+            sig.entry("method").or_insert(Value::from(""));
+        }
+    }
+}
+
+fn tool_version(executor: &dyn exec::Executor, cmd: &str) -> Option<String> {
+    let mut parts = cmd.split_whitespace();
+    let mut spec = exec::CommandSpec::new(parts.next()?);
+    spec.args(parts);
+    let output = executor.output(&spec).ok()?;
+    if !output.success() {
+        return None;
+    }
+    Some(output.stdout.trim().to_string())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct BuildEnvInfo {
     pub os: String,
     pub arch: String,
     pub ci: bool,
+    #[serde(default)]
+    pub ci_run: Option<ci::CiRunInfo>,
+}
+
+/// Schema version written to new manifests. Manifests without a
+/// `manifest_version` field predate versioning and count as version 1.
+pub const MANIFEST_VERSION: u32 = 2;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ManifestError {
+    #[error("invalid manifest: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("manifest version {found} is newer than this shippo supports ({MANIFEST_VERSION}); upgrade shippo")]
+    Unsupported { found: u64 },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Manifest {
+    pub manifest_version: u32,
     pub shippo_version: String,
     pub generated_at: DateTime<Utc>,
     pub project: ManifestProject,
@@ -374,7 +2208,30 @@ pub struct Manifest {
 }
 
 impl Manifest {
-    pub fn to_json(&self) -> Result<String> {
+    /// Parses a manifest of any supported version, upgrading older schemas
+    /// in memory.
+    pub fn from_json(data: &str) -> Result<Self, ManifestError> {
+        Ok(Self::from_json_versioned(data)?.0)
+    }
+
+    /// Like [`from_json`](Self::from_json), also returning the version the
+    /// manifest was written with.
+    pub fn from_json_versioned(data: &str) -> Result<(Self, u32), ManifestError> {
+        let mut value: serde_json::Value = serde_json::from_str(data)?;
+        let found = value
+            .get("manifest_version")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(1);
+        if found > MANIFEST_VERSION as u64 {
+            return Err(ManifestError::Unsupported { found });
+        }
+        if found < 2 {
+            upgrade_manifest_v1(&mut value);
+        }
+        Ok((serde_json::from_value(value)?, found as u32))
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
         let mut value = serde_json::to_value(self)?;
         if let Some(obj) = value.as_object_mut() {
             // ensure deterministic order by sorting keys
@@ -386,7 +2243,43 @@ impl Manifest {
             }
             value = serde_json::Value::Object(sorted);
         }
-        Ok(serde_json::to_string_pretty(&value)?)
+        serde_json::to_string_pretty(&value)
+    }
+
+    /// Renders the packaged artifacts as a markdown table.
+    pub fn artifacts_markdown(&self) -> String {
+        let mut out = String::from("| Package | Target | Artifact | Size | SHA256 |\n");
+        out.push_str("|---|---|---|---:|---|\n");
+        for pkg in &self.packages {
+            for target in &pkg.targets {
+                for art in target.artifacts.iter().chain(target.sboms()) {
+                    out.push_str(&format!(
+                        "| {} | {} | `{}` | {} | `{}` |\n",
+                        pkg.name,
+                        target.target,
+                        art.filename,
+                        human_bytes(art.bytes),
+                        &art.sha256[..art.sha256.len().min(12)]
+                    ));
+                }
+            }
+        }
+        out
+    }
+}
+
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
     }
 }
 
@@ -394,8 +2287,8 @@ impl Manifest {
 pub enum ConfigError {
     #[error("configuration error: {0}")]
     Message(String),
-    #[error(transparent)]
-    Other(#[from] anyhow::Error),
+    #[error("no packages selected")]
+    NoPackagesSelected,
 }
 
 pub fn load_config(path: &Path) -> Result<ShippoConfig, ConfigError> {
@@ -409,6 +2302,16 @@ pub fn load_config(path: &Path) -> Result<ShippoConfig, ConfigError> {
     Ok(cfg)
 }
 
+/// Whether `name` is the project or one of the `[[packages]]`, of type
+/// `project_type`.
+fn has_package(cfg: &ShippoConfig, name: &str, project_type: ProjectType) -> bool {
+    cfg.packages
+        .iter()
+        .map(|p| (&p.name, &p.project_type))
+        .chain(cfg.project.iter().map(|p| (&p.name, &p.project_type)))
+        .any(|(n, t)| n == name && *t == project_type)
+}
+
 fn validate_config(cfg: &mut ShippoConfig) -> Result<(), ConfigError> {
     if cfg.project.is_none() && cfg.packages.is_empty() {
         return Err(ConfigError::Message(
@@ -430,6 +2333,239 @@ fn validate_config(cfg: &mut ShippoConfig) -> Result<(), ConfigError> {
     for pkg in &cfg.packages {
         validate_package_entry(pkg)?;
     }
+    for (name, plugin) in &cfg.wasm_plugins {
+        if !matches!(plugin.kind.as_str(), "transformer" | "notifier") {
+            return Err(ConfigError::Message(format!(
+                "wasm_plugins.{name}.kind must be transformer or notifier"
+            )));
+        }
+    }
+    if let Some(release) = &cfg.release {
+        if release.provider == "s3" {
+            let Some(s3) = &release.s3 else {
+                return Err(ConfigError::Message(
+                    "release.provider = \"s3\" needs [release.s3]".to_string(),
+                ));
+            };
+            if s3.bucket.is_empty() || s3.bucket.contains(['/', ':']) {
+                return Err(ConfigError::Message(format!(
+                    "release.s3.bucket must be a bucket name, not {:?}",
+                    s3.bucket
+                )));
+            }
+            if !s3.prefix.contains("{tag}") && !s3.prefix.contains("{version}") {
+                return Err(ConfigError::Message(format!(
+                    "release.s3.prefix {:?} needs {{tag}} or {{version}}, or every release would go to the same keys",
+                    s3.prefix
+                )));
+            }
+            if let Some(endpoint) = &s3.endpoint {
+                if !endpoint.starts_with("https://") && !endpoint.starts_with("http://") {
+                    return Err(ConfigError::Message(format!(
+                        "release.s3.endpoint must be an http(s) URL, not {endpoint:?}"
+                    )));
+                }
+            }
+            if release.homebrew.is_some() || release.scoop.is_some() {
+                return Err(ConfigError::Message(
+                    "release.homebrew and release.scoop link GitHub release downloads, so they need provider = \"github\"".to_string(),
+                ));
+            }
+        }
+        if let Some(approval) = &release.approval {
+            match approval.provider.as_str() {
+                "github" if release.provider == "s3" && release.github.is_none() => {
+                    return Err(ConfigError::Message(
+                        "release.approval: provider = \"github\" needs [release.github] with provider = \"s3\"".to_string(),
+                    ));
+                }
+                "github" => {}
+                "webhook" => {
+                    let url = approval.url.as_deref().unwrap_or_default();
+                    if !url.starts_with("https://") && !url.starts_with("http://") {
+                        return Err(ConfigError::Message(format!(
+                            "release.approval.url must be an http(s) URL for provider = \"webhook\", not {url:?}"
+                        )));
+                    }
+                }
+                other => {
+                    return Err(ConfigError::Message(format!(
+                        "release.approval.provider must be github or webhook, not {other:?}"
+                    )));
+                }
+            }
+            if approval.poll_seconds == 0 || approval.timeout_minutes == 0 {
+                return Err(ConfigError::Message(
+                    "release.approval.poll_seconds and timeout_minutes must be at least 1"
+                        .to_string(),
+                ));
+            }
+        }
+        if !matches!(release.strategy.as_str(), "combined" | "per-package") {
+            return Err(ConfigError::Message(
+                "release.strategy must be combined or per-package".to_string(),
+            ));
+        }
+        for (i, section) in release.body.sections.iter().enumerate() {
+            if !BODY_SECTIONS.contains(&section.as_str()) {
+                return Err(ConfigError::Message(format!(
+                    "release.body.sections: unknown section {section:?}; expected one of {}",
+                    BODY_SECTIONS.join(", ")
+                )));
+            }
+            if release.body.sections[..i].contains(section) {
+                return Err(ConfigError::Message(format!(
+                    "release.body.sections lists {section} twice"
+                )));
+            }
+        }
+        if let Some(template) = &release.name_template {
+            if template.contains("{codename}") && release.codename.is_none() {
+                return Err(ConfigError::Message(
+                    "release.name_template uses {codename} without [release.codename]".to_string(),
+                ));
+            }
+        }
+        if let Some(brew) = &release.homebrew {
+            if brew.tap.split('/').filter(|s| !s.is_empty()).count() != 2 {
+                return Err(ConfigError::Message(format!(
+                    "release.homebrew.tap must be owner/repo, not {:?}",
+                    brew.tap
+                )));
+            }
+            if brew.package.is_none() && cfg.packages.len() > 1 {
+                return Err(ConfigError::Message(
+                    "release.homebrew.package must name one of the [[packages]]".to_string(),
+                ));
+            }
+        }
+        if let Some(scoop) = &release.scoop {
+            if scoop.bucket.split('/').filter(|s| !s.is_empty()).count() != 2 {
+                return Err(ConfigError::Message(format!(
+                    "release.scoop.bucket must be owner/repo, not {:?}",
+                    scoop.bucket
+                )));
+            }
+            if scoop.package.is_none() && cfg.packages.len() > 1 {
+                return Err(ConfigError::Message(
+                    "release.scoop.package must name one of the [[packages]]".to_string(),
+                ));
+            }
+        }
+        if let Some(npm) = &release.npm {
+            if !matches!(npm.access.as_deref(), None | Some("public" | "restricted")) {
+                return Err(ConfigError::Message(
+                    "release.npm.access must be public or restricted".to_string(),
+                ));
+            }
+            if !npm.registry.starts_with("https://") && !npm.registry.starts_with("http://") {
+                return Err(ConfigError::Message(format!(
+                    "release.npm.registry must be an http(s) URL, not {:?}",
+                    npm.registry
+                )));
+            }
+            for name in &npm.packages {
+                if !has_package(cfg, name, ProjectType::Node) {
+                    return Err(ConfigError::Message(format!(
+                        "release.npm.packages: {name} is not a node package"
+                    )));
+                }
+            }
+        }
+        let fingerprints = release.signed_tag.iter().flat_map(|t| &t.gpg_fingerprints);
+        for fpr in fingerprints {
+            // gpg reports full fingerprints, which key IDs would never match
+            let hex: String = fpr.chars().filter(|c| *c != ' ').collect();
+            if !matches!(hex.len(), 40 | 64) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(ConfigError::Message(format!(
+                    "release.signed_tag.gpg_fingerprints: {fpr:?} is not a full key fingerprint; key IDs are not accepted"
+                )));
+            }
+        }
+        if let Some(pypi) = &release.pypi {
+            let url = &pypi.repository_url;
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                return Err(ConfigError::Message(format!(
+                    "release.pypi.repository_url must be an http(s) URL, not {url:?}"
+                )));
+            }
+            for name in &pypi.packages {
+                if !has_package(cfg, name, ProjectType::Python) {
+                    return Err(ConfigError::Message(format!(
+                        "release.pypi.packages: {name} is not a python package"
+                    )));
+                }
+            }
+        }
+    }
+    let package_sboms = cfg.packages.iter().filter_map(|p| p.sbom.as_ref());
+    for sbom in cfg.sbom.iter().chain(package_sboms) {
+        if !matches!(sbom.tool.as_deref(), None | Some("syft" | "builtin")) {
+            return Err(ConfigError::Message(
+                "sbom.tool must be syft or builtin".to_string(),
+            ));
+        }
+    }
+    let windows = cfg.policy.iter().flat_map(|p| &p.freeze);
+    for (i, window) in windows.enumerate() {
+        if let Err(e) = window.validate() {
+            return Err(ConfigError::Message(format!("{}: {e}", window.label(i))));
+        }
+    }
+    for (stage, limits) in &cfg.limits {
+        let problem = if !matches!(
+            stage.as_str(),
+            "default" | "build" | "test" | "scan" | "package"
+        ) {
+            Some("is not a stage with limits (build, test, scan, package or default)".to_string())
+        } else if limits.cpus == Some(0) {
+            Some("cpus must be at least 1".to_string())
+        } else if let Some(memory) = limits
+            .memory
+            .as_ref()
+            .filter(|m| limits::memory_bytes(m).is_none())
+        {
+            Some(format!(
+                "memory must be bytes or a size such as 4G, not {memory:?}"
+            ))
+        } else if limits.nice.is_some_and(|n| !(0..=19).contains(&n)) {
+            Some("nice must be between 0 and 19".to_string())
+        } else {
+            limits
+                .io
+                .as_ref()
+                .filter(|io| !matches!(io.as_str(), "idle" | "best-effort"))
+                .map(|io| format!("io must be idle or best-effort, not {io:?}"))
+        };
+        if let Some(problem) = problem {
+            return Err(ConfigError::Message(format!("limits.{stage}: {problem}")));
+        }
+    }
+    if let Some(tools) = &cfg.tools {
+        for name in tools.require.iter().chain(tools.pins.keys()) {
+            if tools::tool(name).is_none() {
+                return Err(ConfigError::Message(format!(
+                    "tools: {}",
+                    tools::ToolError::Unknown(name.clone())
+                )));
+            }
+        }
+    }
+    let mut remote_targets = BTreeMap::new();
+    for (name, worker) in &cfg.remote {
+        if worker.host.trim().is_empty() || worker.targets.is_empty() {
+            return Err(ConfigError::Message(format!(
+                "remote.{name} needs a host and at least one target"
+            )));
+        }
+        for target in &worker.targets {
+            if let Some(other) = remote_targets.insert(target.as_str(), name.as_str()) {
+                return Err(ConfigError::Message(format!(
+                    "target {target} is assigned to both remote.{other} and remote.{name}"
+                )));
+            }
+        }
+    }
     Ok(())
 }
 
@@ -452,6 +2588,14 @@ fn validate_package_entry(pkg: &PackageEntry) -> Result<(), ConfigError> {
                 "node.cli-binary requires [node.binary]".to_string(),
             ));
         }
+        if let Some(manager) = &node.package_manager {
+            if !matches!(manager.as_str(), "npm" | "pnpm" | "yarn" | "bun") {
+                return Err(ConfigError::Message(format!(
+                    "{}: node.package_manager must be npm, pnpm, yarn or bun, not {manager:?}",
+                    pkg.name
+                )));
+            }
+        }
     }
     Ok(())
 }
@@ -462,7 +2606,13 @@ pub struct VersionInfo {
     pub source: VersionSource,
 }
 
-pub fn resolve_version(cfg: &ShippoConfig, tag_override: Option<String>) -> Result<VersionInfo> {
+/// Resolves the release version; `git` and `tag` sources read the latest tag
+/// of the repository containing `repo`.
+pub fn resolve_version(
+    cfg: &ShippoConfig,
+    tag_override: Option<String>,
+    repo: &Path,
+) -> Result<VersionInfo, ConfigError> {
     if let Some(tag) = tag_override {
         return Ok(VersionInfo {
             value: tag,
@@ -472,21 +2622,23 @@ pub fn resolve_version(cfg: &ShippoConfig, tag_override: Option<String>) -> Resu
     let version_cfg = cfg.version.as_ref().cloned().unwrap_or(VersionConfig {
         source: VersionSource::Git,
         manual: None,
+        tag_pattern: None,
     });
+    let pattern = version_cfg.tag_pattern.as_deref();
     match version_cfg.source {
         VersionSource::Manual => Ok(VersionInfo {
             value: version_cfg.manual.unwrap_or_else(|| "0.1.0".to_string()),
             source: VersionSource::Manual,
         }),
         VersionSource::Tag => {
-            let tag = latest_tag().unwrap_or_else(|| "v0.1.0".to_string());
+            let tag = shippo_git::latest_tag(repo, pattern).unwrap_or_else(|| "v0.1.0".to_string());
             Ok(VersionInfo {
                 value: tag,
                 source: VersionSource::Tag,
             })
         }
         VersionSource::Git => {
-            let tag = latest_tag().unwrap_or_else(|| "v0.1.0".to_string());
+            let tag = shippo_git::latest_tag(repo, pattern).unwrap_or_else(|| "v0.1.0".to_string());
             Ok(VersionInfo {
                 value: tag,
                 source: VersionSource::Git,
@@ -495,28 +2647,13 @@ pub fn resolve_version(cfg: &ShippoConfig, tag_override: Option<String>) -> Resu
     }
 }
 
-fn latest_tag() -> Option<String> {
-    let output = std::process::Command::new("git")
-        .args(["describe", "--tags", "--abbrev=0"])
-        .output()
-        .ok()?;
-    if !output.status.success() {
-        return None;
-    }
-    let tag = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    if tag.is_empty() {
-        None
-    } else {
-        Some(tag)
-    }
-}
-
 pub fn build_plan(
     cfg: &ShippoConfig,
     only: Option<&str>,
     tag_override: Option<String>,
-) -> Result<Plan> {
-    let version = resolve_version(cfg, tag_override)?.value;
+    repo: &Path,
+) -> Result<Plan, ConfigError> {
+    let version = resolve_version(cfg, tag_override, repo)?.value;
     let mut packages = Vec::new();
     if let Some(project) = &cfg.project {
         if only.is_some() && only != Some(project.name.as_str()) {
@@ -542,7 +2679,7 @@ pub fn build_plan(
         let package = pkg.package.as_ref().or(cfg.package.as_ref());
         let sbom = pkg.sbom.as_ref().or(cfg.sbom.as_ref());
         let sign = pkg.sign.as_ref().or(cfg.sign.as_ref());
-        packages.push(resolve_package_entry(
+        let mut resolved = resolve_package_entry(
             pkg,
             build,
             package,
@@ -550,12 +2687,70 @@ pub fn build_plan(
             sign,
             cfg.node.as_ref(),
             cfg.python.as_ref(),
-        )?);
+        )?;
+        resolved.go = resolved.go.or_else(|| cfg.go.clone());
+        packages.push(resolved);
     }
     if packages.is_empty() {
-        return Err(anyhow!("no packages selected"));
+        return Err(ConfigError::NoPackagesSelected);
     }
-    Ok(Plan { version, packages })
+    for pkg in &packages {
+        let template = &pkg.package.name_template;
+        for target in &pkg.targets {
+            let target_name = pkg.package.target_name(target);
+            let name = normalize_file_name(&naming_template(
+                template,
+                &pkg.name,
+                &version,
+                &target_name,
+            ));
+            check_file_name(&name).map_err(|reason| {
+                ConfigError::Message(format!(
+                    "{}: [package] name_template {template:?} renders {name:?} for {target}, which {reason}",
+                    pkg.name
+                ))
+            })?;
+        }
+        if let Some(docker) = &pkg.docker {
+            let tags = docker.image_tags(&version);
+            if let Some(bad) = tags.iter().find(|t| !is_image_tag(t)) {
+                return Err(ConfigError::Message(format!(
+                    "{}: docker tag {bad:?} may only hold letters, digits, `_`, `.` and `-`",
+                    pkg.name
+                )));
+            }
+        }
+    }
+    Ok(Plan {
+        version,
+        packages,
+        plugins: cfg.plugins.clone(),
+        narrowed_from: None,
+        config_sha256: None,
+        attest: cfg.attest.clone().filter(|a| a.enabled),
+        signers: cfg
+            .verify
+            .as_ref()
+            .and_then(|v| v.signers.as_ref())
+            .map(|s| s.minisign.keys().cloned().collect())
+            .unwrap_or_default(),
+        trust: SignerTrust::from_config(cfg, repo),
+        source_date_epoch: std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|epoch| epoch.trim().parse().ok())
+            .or_else(|| shippo_git::commit_time(repo)),
+        submodules: shippo_git::submodules(repo)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|sm| {
+                Some(SubmodulePin {
+                    commit: sm.commit?,
+                    path: sm.path,
+                    url: sm.url,
+                })
+            })
+            .collect(),
+    })
 }
 
 fn resolve_package(
@@ -565,7 +2760,7 @@ fn resolve_package(
     sbom: Option<&SbomConfig>,
     sign: Option<&SignConfig>,
     cfg: &ShippoConfig,
-) -> Result<PackagePlan> {
+) -> Result<PackagePlan, ConfigError> {
     let pkg_entry = PackageEntry {
         name: project.name.clone(),
         project_type: project.project_type.clone(),
@@ -576,6 +2771,8 @@ fn resolve_package(
         sign: sign.cloned(),
         node: cfg.node.clone(),
         python: cfg.python.clone(),
+        go: cfg.go.clone(),
+        docker: cfg.docker.clone(),
     };
     resolve_package_entry(
         &pkg_entry,
@@ -596,7 +2793,7 @@ fn resolve_package_entry(
     sign: Option<&SignConfig>,
     node: Option<&NodeConfig>,
     python: Option<&PythonConfig>,
-) -> Result<PackagePlan> {
+) -> Result<PackagePlan, ConfigError> {
     let path = Utf8Path::new(&pkg.path).to_owned();
     let targets = build
         .map(|b| b.targets.clone())
@@ -611,6 +2808,12 @@ fn resolve_package_entry(
             name_template: default_template(),
             include: Vec::new(),
             exclude: Vec::new(),
+            target_style: None,
+            keep_last: None,
+            contents: BTreeMap::new(),
+            transform: Vec::new(),
+            compression: CompressionConfig::default(),
+            duplicates: None,
         });
     let sbom_cfg = pkg
         .sbom
@@ -620,6 +2823,8 @@ fn resolve_package_entry(
             enabled: true,
             format: default_sbom_format(),
             mode: default_sbom_mode(),
+            tool: None,
+            formats: Vec::new(),
         });
     let sign_cfg = pkg
         .sign
@@ -629,7 +2834,69 @@ fn resolve_package_entry(
             enabled: false,
             method: default_sign_method(),
             cosign_mode: default_cosign_mode(),
+            ssh_key: None,
+            ssh_namespace: None,
+            cosign: None,
+            gpg: None,
         });
+    let builder = pkg
+        .build
+        .as_ref()
+        .and_then(|b| b.builder.clone())
+        .or_else(|| build.and_then(|b| b.builder.clone()));
+    if let Some(style) = &pkg_cfg.target_style {
+        if !matches!(style.as_str(), "triple" | "goish") {
+            return Err(ConfigError::Message(format!(
+                "{}: package.target_style must be \"triple\" or \"goish\", not {style:?}",
+                pkg.name
+            )));
+        }
+    }
+    if let Some(policy) = &pkg_cfg.duplicates {
+        if !matches!(policy.as_str(), "alias" | "skip") {
+            return Err(ConfigError::Message(format!(
+                "{}: package.duplicates must be \"alias\" or \"skip\", not {policy:?}",
+                pkg.name
+            )));
+        }
+    }
+    let compression = &pkg_cfg.compression;
+    if compression.gzip.is_some_and(|level| level > 9) {
+        return Err(ConfigError::Message(format!(
+            "{}: package.compression.gzip must be 0 to 9",
+            pkg.name
+        )));
+    }
+    if compression
+        .zstd
+        .is_some_and(|level| !(1..=22).contains(&level))
+    {
+        return Err(ConfigError::Message(format!(
+            "{}: package.compression.zstd must be 1 to 22",
+            pkg.name
+        )));
+    }
+    if !matches!(compression.zip.as_deref(), None | Some("deflate" | "store")) {
+        return Err(ConfigError::Message(format!(
+            "{}: package.compression.zip must be \"deflate\" or \"store\"",
+            pkg.name
+        )));
+    }
+    for step in &pkg_cfg.transform {
+        if step.mode.is_some() && step.mode_bits().is_none() {
+            return Err(ConfigError::Message(format!(
+                "{}: package.transform mode must be octal permissions such as \"0755\", not {:?}",
+                pkg.name,
+                step.mode.as_deref().unwrap_or_default()
+            )));
+        }
+        if step.rename.is_none() && step.mode.is_none() && step.wrap.is_none() {
+            return Err(ConfigError::Message(format!(
+                "{}: package.transform for {:?} needs rename, mode or wrap",
+                pkg.name, step.pattern
+            )));
+        }
+    }
     Ok(PackagePlan {
         name: pkg.name.clone(),
         project_type: pkg.project_type.clone(),
@@ -640,9 +2907,92 @@ fn resolve_package_entry(
         sign: sign_cfg,
         node: pkg.node.clone().or_else(|| node.cloned()),
         python: pkg.python.clone().or_else(|| python.cloned()),
+        go: pkg.go.clone(),
+        builder,
+        docker: pkg.docker.clone(),
+    })
+}
+
+/// `target` when neither the [`targets`] registry nor the package's
+/// toolchain knows it, with the closest registry name. Without the
+/// toolchain only near misses of registry names are reported, as Rust and
+/// Go support far more targets than the registry lists.
+fn unknown_target(project_type: &ProjectType, target: &str) -> Option<String> {
+    if target == "native" || targets::resolve(target).is_some() {
+        return None;
+    }
+    let suggestion = targets::suggest(target);
+    let known = match targets::toolchain_knows(project_type, target) {
+        Some(known) => known,
+        None => suggestion.is_none() && matches!(project_type, ProjectType::Rust | ProjectType::Go),
+    };
+    if known {
+        return None;
+    }
+    Some(match suggestion {
+        Some(s) => format!("unknown target {target:?}; did you mean {s:?}?"),
+        None => format!("unknown target {target:?}"),
     })
 }
 
+/// Collects non-fatal configuration issues for a resolved plan.
+pub fn plan_warnings(plan: &Plan) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for pkg in &plan.packages {
+        // custom builders define their own targets
+        let targets = pkg.targets.iter().filter(|_| pkg.builder.is_none());
+        for problem in targets.filter_map(|t| unknown_target(&pkg.project_type, t)) {
+            warnings.push(format!("{}: {problem}", pkg.name));
+        }
+        for fmt in &pkg.package.formats {
+            if !(fmt.ends_with("tar.gz") || fmt.ends_with("tar.zst") || fmt == "zip") {
+                warnings.push(format!("{}: unsupported package format {fmt}", pkg.name));
+            }
+        }
+        let formats = pkg.sbom.output_formats();
+        for format in formats.iter().filter(|_| pkg.sbom.enabled) {
+            if !matches!(*format, "cyclonedx" | "spdx") && plugin::plugin_ref(format).is_none() {
+                warnings.push(format!(
+                    "{}: sbom format {format} is not supported, CycloneDX will be written",
+                    pkg.name
+                ));
+            }
+        }
+        if pkg.sign.enabled && pkg.sign.method == "ssh" && pkg.sign.ssh_key.is_none() {
+            warnings.push(format!(
+                "{}: sign method ssh needs ssh_key, falling back to checksum signatures",
+                pkg.name
+            ));
+        }
+        if pkg.sign.enabled
+            && pkg.sign.method == "cosign"
+            && pkg.sign.cosign_mode != "key"
+            && pkg.sign.cosign.is_some()
+        {
+            warnings.push(format!(
+                "{}: [sign.cosign] is only used with cosign_mode = \"key\"",
+                pkg.name
+            ));
+        }
+        if pkg.sign.enabled
+            && !matches!(pkg.sign.method.as_str(), "cosign" | "gpg" | "ssh")
+            && plugin::plugin_ref(&pkg.sign.method).is_none()
+        {
+            warnings.push(format!(
+                "{}: unknown sign method {}, falling back to checksum signatures",
+                pkg.name, pkg.sign.method
+            ));
+        }
+        let mut seen = std::collections::BTreeSet::new();
+        for target in &pkg.targets {
+            if !seen.insert(target) {
+                warnings.push(format!("{}: duplicate target {target}", pkg.name));
+            }
+        }
+    }
+    warnings
+}
+
 pub fn naming_template(template: &str, name: &str, version: &str, target: &str) -> String {
     template
         .replace("{name}", name)
@@ -650,7 +3000,53 @@ pub fn naming_template(template: &str, name: &str, version: &str, target: &str)
         .replace("{target}", target)
 }
 
-pub fn sha256_file(path: &Path) -> Result<String> {
+/// Longest file name written to the dist, in bytes; most filesystems allow
+/// no more.
+pub const MAX_FILE_NAME: usize = 255;
+
+/// `name` in Unicode NFC, so a template renders the same bytes whether the
+/// name came from macOS (which decomposes) or anywhere else.
+pub fn normalize_file_name(name: &str) -> String {
+    name.nfc().collect()
+}
+
+/// Why `name` can't be a file name on every platform, if it can't: a path
+/// separator, a character Windows rejects (`<>:"|?*` or a control
+/// character), a trailing dot or space, a reserved device name such as
+/// `CON` or `nul.tar.gz`, or more than [`MAX_FILE_NAME`] bytes.
+pub fn check_file_name(name: &str) -> Result<(), String> {
+    const RESERVED: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+    if name.is_empty() {
+        return Err("is empty".to_string());
+    }
+    if let Some(c) = name.chars().find(|c| {
+        matches!(*c, '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*') || c.is_control()
+    }) {
+        return Err(format!("contains {c:?}"));
+    }
+    if name.ends_with(['.', ' ']) {
+        return Err("ends with a dot or space".to_string());
+    }
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    let device = stem.to_ascii_uppercase();
+    let numbered = ["COM", "LPT"].iter().any(|prefix| {
+        device
+            .strip_prefix(prefix)
+            .is_some_and(|n| n.len() == 1 && n.as_bytes()[0].is_ascii_digit())
+    });
+    if RESERVED.contains(&device.as_str()) || numbered {
+        return Err(format!("starts with {stem:?}, a reserved name on Windows"));
+    }
+    if name.len() > MAX_FILE_NAME {
+        return Err(format!(
+            "is {} bytes long, more than {MAX_FILE_NAME}",
+            name.len()
+        ));
+    }
+    Ok(())
+}
+
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
     let mut file = std::fs::File::open(path)?;
     let mut hasher = Sha256::new();
     std::io::copy(&mut file, &mut hasher)?;
@@ -735,23 +3131,59 @@ mod tests {
         assert_eq!(out, "app-1.0-x86");
     }
 
+    #[test]
+    fn test_file_name_safety() {
+        // "é" decomposed, as macOS file names and some editors write it
+        assert_eq!(normalize_file_name("cafe\u{301}.zip"), "caf\u{e9}.zip");
+        assert!(check_file_name("app-1.0-linux-amd64.tar.gz").is_ok());
+        assert!(check_file_name("app-v1:2.zip").unwrap_err().contains("':'"));
+        assert!(check_file_name("app*.zip").is_err());
+        assert!(check_file_name("app.").is_err());
+        assert!(check_file_name("nul.tar.gz")
+            .unwrap_err()
+            .contains("reserved"));
+        assert!(check_file_name("COM1.zip").is_err());
+        assert!(check_file_name("console.zip").is_ok());
+        assert!(check_file_name(&"a".repeat(256)).is_err());
+
+        let toml = "[project]\nname='demo'\ntype='rust'\n\n[build]\ntargets=['linux-amd64']\n\n[package]\nname_template='{name}:{version}'\n";
+        let cfg: ShippoConfig = toml::from_str(toml).unwrap();
+        let err = build_plan(&cfg, None, Some("v1.0.0".into()), Path::new("."))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("\"demo:v1.0.0\""), "{err}");
+    }
+
     #[test]
     fn test_config_validation() {
         let toml =
             "[project]\nname='demo'\ntype='rust'\n\n[version]\nsource='manual'\nmanual='1.2.3'";
         let mut cfg: ShippoConfig = toml::from_str(toml).unwrap();
         validate_config(&mut cfg).unwrap();
+
+        let mut cfg: ShippoConfig =
+            toml::from_str(&format!("{toml}\n\n[release]\nstrategy='per-target'")).unwrap();
+        assert!(validate_config(&mut cfg).is_err());
+        cfg.release.as_mut().unwrap().strategy = "per-package".into();
+        validate_config(&mut cfg).unwrap();
     }
 
     #[test]
     fn test_manifest_json_deterministic() {
         let manifest = Manifest {
+            manifest_version: MANIFEST_VERSION,
             shippo_version: "0.1.0".into(),
             generated_at: Utc::now(),
             project: ManifestProject {
                 repo_url: None,
                 commit: None,
                 version: "v0.1.0".into(),
+                plan_fingerprint: None,
+                config_sha256: None,
+                submodules: Vec::new(),
+                freeze_override: None,
+                approval: None,
+                trust: None,
             },
             packages: vec![],
             tooling: ToolingInfo {
@@ -764,6 +3196,7 @@ mod tests {
                 os: "linux".into(),
                 arch: "x86_64".into(),
                 ci: false,
+                ci_run: None,
             },
         };
         let a = manifest.to_json().unwrap();
@@ -771,6 +3204,33 @@ mod tests {
         assert_eq!(a, b);
     }
 
+    #[test]
+    fn test_manifest_parses_unversioned() {
+        let legacy = r#"{
+            "shippo_version": "0.1.0",
+            "generated_at": "2024-01-01T00:00:00Z",
+            "project": {"repo_url": null, "commit": null, "version": "v0.1.0"},
+            "packages": [{"name": "demo", "type": "rust", "path": ".", "targets": [{
+                "target": "native", "artifacts": [], "sbom": null,
+                "signatures": [{"filename": "demo.tar.gz.sig"}]
+            }]}],
+            "tooling": {"rust": null, "go": null, "node": null, "python": null},
+            "build_env": {"os": "linux", "arch": "x86_64", "ci": false}
+        }"#;
+        let (manifest, found) = Manifest::from_json_versioned(legacy).unwrap();
+        assert_eq!(found, 1);
+        assert_eq!(manifest.manifest_version, MANIFEST_VERSION);
+        assert_eq!(manifest.packages[0].targets[0].signatures[0].method, "");
+        let future = manifest.to_json().unwrap().replace(
+            &format!("\"manifest_version\": {MANIFEST_VERSION}"),
+            "\"manifest_version\": 99",
+        );
+        assert!(matches!(
+            Manifest::from_json(&future),
+            Err(ManifestError::Unsupported { found: 99 })
+        ));
+    }
+
     #[test]
     fn test_detect_projects() {
         let dir = tempdir().unwrap();
@@ -784,8 +3244,268 @@ mod tests {
     fn test_plan_resolution() {
         let toml = "[project]\nname='demo'\ntype='rust'\n\n[build]\ntargets=['native']\n";
         let cfg: ShippoConfig = toml::from_str(toml).unwrap();
-        let plan = build_plan(&cfg, None, None).unwrap();
+        let plan = build_plan(&cfg, None, None, Path::new(".")).unwrap();
         assert_eq!(plan.packages.len(), 1);
         assert_eq!(plan.packages[0].name, "demo");
     }
+
+    #[test]
+    fn test_prerelease_inferred_from_version() {
+        let mut release: ReleaseConfig = toml::from_str("provider = 'github'").unwrap();
+        assert!(release.is_prerelease("v1.2.0-rc.1"));
+        assert!(release.is_prerelease("cli-v2.0.0-beta"));
+        assert!(!release.is_prerelease("v1.2.0"));
+        assert!(!release.is_prerelease("nightly"));
+        release.prerelease = Some(false);
+        assert!(!release.is_prerelease("v1.2.0-rc.1"));
+        release.prerelease = Some(true);
+        assert!(release.is_prerelease("v1.2.0"));
+    }
+
+    #[test]
+    fn test_s3_release_config() {
+        let load = |release: &str| {
+            let mut cfg: ShippoConfig = toml::from_str(&format!(
+                "[project]\nname='demo'\ntype='rust'\n\n[release]\nprovider='s3'\n{release}"
+            ))
+            .unwrap();
+            validate_config(&mut cfg).map(|_| cfg)
+        };
+        let cfg = load("[release.s3]\nbucket='acme'\nprefix='tools/{version}/'\n").unwrap();
+        let s3 = cfg.release.unwrap().s3.unwrap();
+        assert_eq!(s3.key_prefix("v1.2.0", "v1.2.0"), "tools/1.2.0");
+        assert!(load("").unwrap_err().to_string().contains("[release.s3]"));
+        assert!(load("[release.s3]\nbucket='s3://acme'\n").is_err());
+        let err = load("[release.s3]\nbucket='acme'\nprefix='tools/'\n").unwrap_err();
+        assert!(err.to_string().contains("{tag} or {version}"), "{err}");
+    }
+
+    #[test]
+    fn test_signer_trust() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("allowed_signers"),
+            "# release keys\nrelease ssh-ed25519 AAAAkey\n\n",
+        )
+        .unwrap();
+        let trust = |extra: &str| {
+            let cfg: ShippoConfig =
+                toml::from_str(&format!("[project]\nname='demo'\ntype='rust'\n\n{extra}")).unwrap();
+            SignerTrust::from_config(&cfg, dir.path())
+        };
+        assert_eq!(trust(""), None);
+
+        let github = trust("[release.github]\nowner='acme'\nrepo='app.rs'\n").unwrap();
+        assert_eq!(
+            github.cosign_identity_regexp.as_deref(),
+            Some("^https://github\\.com/acme/app\\.rs/\\.github/workflows/")
+        );
+        assert_eq!(
+            github.cosign_oidc_issuer.as_deref(),
+            Some(GITHUB_ACTIONS_ISSUER)
+        );
+
+        let keys = trust(
+            "[release.github]\nowner='acme'\nrepo='app'\n\n[verify.keys]\ncosign_identity='release@acme.dev'\ncosign_oidc_issuer='https://accounts.google.com'\nssh_allowed_signers='allowed_signers'\n",
+        )
+        .unwrap();
+        assert_eq!(keys.cosign_identity.as_deref(), Some("release@acme.dev"));
+        assert_eq!(keys.cosign_identity_regexp, None);
+        assert_eq!(
+            keys.cosign_oidc_issuer.as_deref(),
+            Some("https://accounts.google.com")
+        );
+        assert_eq!(keys.ssh_allowed_signers, ["release ssh-ed25519 AAAAkey"]);
+    }
+
+    #[test]
+    fn test_signed_tag_fingerprints() {
+        let load = |fpr: &str| {
+            let mut cfg: ShippoConfig = toml::from_str(&format!(
+                "[project]\nname='demo'\ntype='rust'\n\n[release.signed_tag]\ngpg_fingerprints=['{fpr}']\n"
+            ))
+            .unwrap();
+            validate_config(&mut cfg)
+        };
+        assert!(load("AB12CD34EF56AB12CD34EF56AB12CD34EF56AB12").is_ok());
+        assert!(load("AB12 CD34 EF56 AB12 CD34  EF56 AB12 CD34 EF56 AB12").is_ok());
+        let err = load("3AA5C34371567BD2").unwrap_err().to_string();
+        assert!(err.contains("key IDs are not accepted"), "{err}");
+    }
+
+    #[test]
+    fn test_release_name_and_codename() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("codenames.toml"),
+            "\"1\" = \"Alder\"\n\"1.4\" = \"Birch\"\n\"1.40\" = \"Cedar\"\n",
+        )
+        .unwrap();
+        let codename = CodenameConfig {
+            value: Some("Unnamed".into()),
+            env: Some("SHIPPO_TEST_CODENAME_UNSET".into()),
+            file: Some("codenames.toml".into()),
+        };
+        let resolve = |version: &str| codename.resolve(dir.path(), version).unwrap();
+        assert_eq!(resolve("1.4.2").as_deref(), Some("Birch"));
+        assert_eq!(resolve("1.40.0-rc.1").as_deref(), Some("Cedar"));
+        assert_eq!(resolve("1.5.0").as_deref(), Some("Alder"));
+        assert_eq!(resolve("2.0.0").as_deref(), Some("Unnamed"));
+
+        let template = "{name} {version} — {codename}";
+        assert_eq!(
+            release_name(template, "shippo", "v1.4.2", "v1.4.2", Some("Birch")),
+            "shippo 1.4.2 — Birch"
+        );
+        assert_eq!(
+            release_name(template, "shippo", "v1.4.2", "v1.4.2", None),
+            "shippo 1.4.2"
+        );
+        assert_eq!(
+            release_name("{tag}", "cli", "cli-v2.0.0", "v2.0.0", None),
+            "cli-v2.0.0"
+        );
+
+        let mut cfg: ShippoConfig = toml::from_str(
+            "[project]\nname='demo'\ntype='rust'\n\n[release]\nname_template='{version} {codename}'\n",
+        )
+        .unwrap();
+        let err = validate_config(&mut cfg).unwrap_err();
+        assert!(err.to_string().contains("[release.codename]"));
+    }
+
+    #[test]
+    fn test_plan_warns_about_unknown_targets() {
+        let warnings = |project_type: &str, target: &str| {
+            let toml = format!(
+                "[project]\nname='demo'\ntype='{project_type}'\n\n[build]\ntargets=['{target}']\n"
+            );
+            let cfg: ShippoConfig = toml::from_str(&toml).unwrap();
+            plan_warnings(&build_plan(&cfg, None, None, Path::new(".")).unwrap())
+        };
+        assert!(warnings("go", "linux-x64").is_empty());
+        assert!(warnings("rust", "thumbv7em-none-eabihf").is_empty());
+        // rustc lists far more triples than the registry
+        for triple in ["i686-unknown-linux-musl", "x86_64-unknown-netbsd"] {
+            assert!(warnings("rust", triple).is_empty(), "{triple}");
+        }
+        assert_eq!(
+            warnings("go", "linux-amd46"),
+            ["demo: unknown target \"linux-amd46\"; did you mean \"linux-amd64\"?"]
+        );
+        assert_eq!(
+            warnings("node", "plan9-amd64"),
+            ["demo: unknown target \"plan9-amd64\""]
+        );
+    }
+
+    #[test]
+    fn test_plan_fingerprint() {
+        let toml = "[project]\nname='demo'\ntype='rust'\n\n[build]\ntargets=['native']\n";
+        let cfg: ShippoConfig = toml::from_str(toml).unwrap();
+        let v1 = build_plan(&cfg, None, Some("v1.0.0".into()), Path::new(".")).unwrap();
+        let v2 = build_plan(&cfg, None, Some("v2.0.0".into()), Path::new(".")).unwrap();
+        assert_eq!(v1.fingerprint(), v2.fingerprint());
+        assert_eq!(v1.fingerprint().len(), 64);
+
+        let mut other = v1.clone();
+        other.packages[0].targets.push("linux-arm64".into());
+        assert_ne!(other.fingerprint(), v1.fingerprint());
+        other.narrowed_from = Some(v1.fingerprint());
+        assert_eq!(other.fingerprint(), v1.fingerprint());
+    }
+
+    #[test]
+    fn test_human_bytes() {
+        assert_eq!(human_bytes(512), "512 B");
+        assert_eq!(human_bytes(1536), "1.5 KiB");
+        assert_eq!(human_bytes(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn test_plan_warnings() {
+        let toml = "[project]\nname='demo'\ntype='rust'\n\n[build]\ntargets=['native','native']\n\n[sbom]\nformat='swid'\n";
+        let cfg: ShippoConfig = toml::from_str(toml).unwrap();
+        let plan = build_plan(&cfg, None, None, Path::new(".")).unwrap();
+        let warnings = plan_warnings(&plan);
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_check_cosign_key() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("cosign.key"), "key").unwrap();
+        let sign = |cosign: &str| -> SignConfig {
+            let toml = format!("enabled = true\ncosign_mode = 'key'\n{cosign}");
+            toml::from_str(&toml).unwrap()
+        };
+        let check = |cosign: &str| sign(cosign).check_keys(dir.path());
+        assert!(check("[cosign]\nkey = 'cosign.key'").is_ok());
+        assert!(check("[cosign]\nkms = 'awskms:///alias/release'").is_ok());
+        assert!(check("[cosign]\nkey = 'missing.key'")
+            .unwrap_err()
+            .contains("missing.key not found"));
+        assert!(check("")
+            .unwrap_err()
+            .contains("needs [sign.cosign] key or kms"));
+        assert!(check("[cosign]\nkey = 'cosign.key'\nkms = 'gcpkms://k'").is_err());
+        assert!(check("[cosign]\nkms = 'alias/release'").is_err());
+        assert!(
+            check("[cosign]\nkey = 'cosign.key'\npassword_env = 'SHIPPO_TEST_UNSET_PASSWORD'")
+                .unwrap_err()
+                .contains("SHIPPO_TEST_UNSET_PASSWORD")
+        );
+        assert!(sign("[cosign]\ntlog_upload = false")
+            .cosign
+            .unwrap()
+            .key
+            .is_none());
+        let keyless = SignConfig {
+            cosign_mode: "keyless".into(),
+            ..sign("")
+        };
+        assert!(keyless.check_keys(dir.path()).is_ok());
+
+        fs::create_dir(dir.path().join("gnupg")).unwrap();
+        let gpg = |config: &str| {
+            let toml = format!("enabled = true\nmethod = 'gpg'\n[gpg]\n{config}");
+            toml::from_str::<SignConfig>(&toml)
+                .unwrap()
+                .check_keys(dir.path())
+        };
+        assert!(gpg("homedir = 'gnupg'\nkey_id = 'ABCD'").is_ok());
+        assert!(gpg("homedir = 'missing'")
+            .unwrap_err()
+            .contains("homedir missing not found"));
+        assert!(gpg("passphrase_env = 'SHIPPO_TEST_UNSET_PASSPHRASE'").is_err());
+    }
+
+    #[test]
+    fn test_signers_threshold() {
+        let signers = |toml: &str| toml::from_str::<SignersConfig>(toml).unwrap().check();
+        let keys = "[minisign]\nalice = 'keys/alice.pub'\nbob = 'keys/bob.pub'\n";
+        assert!(signers(&format!("threshold = 2\n{keys}")).is_ok());
+        assert!(signers(keys).is_ok());
+        assert!(signers(&format!("threshold = 3\n{keys}"))
+            .unwrap_err()
+            .contains("can't be met with 2 signer(s)"));
+        assert!(signers("threshold = 0").is_err());
+        assert!(signers("[minisign]\n'alice.smith' = 'a.pub'").is_err());
+
+        let sig = ManifestSignature {
+            filename: "cli.tar.gz.alice.minisig".into(),
+            method: "minisign".into(),
+            bundle: None,
+            rekor: None,
+            signer: Some("alice".into()),
+            key: false,
+            offline: false,
+        };
+        assert_eq!(sig.signed_file(), Some("cli.tar.gz"));
+        let unsigned = ManifestSignature {
+            signer: None,
+            ..sig
+        };
+        assert_eq!(unsigned.signed_file(), Some("cli.tar.gz.alice"));
+    }
 }
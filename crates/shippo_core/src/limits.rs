@@ -0,0 +1,399 @@
+//! CPU, memory and priority limits for the commands a stage runs, so a
+//! release on a shared build box doesn't starve other work.
+//!
+//! On Linux, CPU and memory caps put each command in its own cgroup with
+//! `systemd-run --user --scope`, given a systemd user session to ask;
+//! priority uses `nice` and `ionice`. On Windows the command runs in a job
+//! object capping its CPU rate, memory and priority class. Other platforms
+//! only get `nice`: [`LimitedExecutor::unenforced`] lists the settings that
+//! are ignored so the caller can warn.
+//!
+//! Wrap the limits around the sandbox, not inside it: `systemd-run` has to
+//! reach the user manager, which bubblewrap's mounts and scrubbed
+//! environment would hide.
+
+use std::ffi::OsStr;
+use std::io;
+use std::path::Path;
+use std::process::Child;
+
+use crate::exec::{CommandOutput, CommandSpec, Executor};
+use crate::sandbox::on_path;
+use crate::LimitsConfig;
+
+/// What `systemd-run --user` needs to find the user manager.
+const SESSION_ENV: &[&str] = &["XDG_RUNTIME_DIR", "DBUS_SESSION_BUS_ADDRESS"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mechanism {
+    /// cgroups through `systemd-run`, plus `nice` and `ionice`.
+    Systemd,
+    /// `nice`, and `ionice` on Linux.
+    Priority,
+    /// A Windows job object, which the spawning executor sets up from the
+    /// command's [`CommandSpec::limits`].
+    JobObject,
+    /// Commands run unchanged.
+    None,
+}
+
+pub struct LimitedExecutor<E> {
+    inner: E,
+    limits: LimitsConfig,
+    mechanism: Mechanism,
+}
+
+impl<E: Executor> LimitedExecutor<E> {
+    /// Picks what this host can enforce.
+    pub fn new(limits: LimitsConfig, inner: E) -> Self {
+        let mechanism = if cfg!(windows) {
+            Mechanism::JobObject
+        } else if cfg!(target_os = "linux")
+            && on_path("systemd-run")
+            && user_session(std::env::var_os("XDG_RUNTIME_DIR").as_deref())
+        {
+            Mechanism::Systemd
+        } else {
+            Mechanism::Priority
+        };
+        Self::with_mechanism(limits, inner, mechanism)
+    }
+
+    pub fn with_mechanism(limits: LimitsConfig, inner: E, mechanism: Mechanism) -> Self {
+        Self {
+            inner,
+            limits,
+            mechanism,
+        }
+    }
+
+    /// Configured settings this host can't enforce, e.g. `["memory"]`.
+    pub fn unenforced(&self) -> Vec<&'static str> {
+        let cgroup = matches!(self.mechanism, Mechanism::Systemd | Mechanism::JobObject);
+        let priority = self.mechanism != Mechanism::None;
+        [
+            ("cpus", self.limits.cpus.is_some(), cgroup),
+            ("memory", self.limits.memory.is_some(), cgroup),
+            ("nice", self.limits.nice.is_some(), priority),
+            (
+                "io",
+                self.limits.io.is_some(),
+                matches!(self.mechanism, Mechanism::Systemd | Mechanism::Priority)
+                    && cfg!(target_os = "linux"),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, set, enforced)| *set && !enforced)
+        .map(|(name, _, _)| name)
+        .collect()
+    }
+
+    /// Rewrites `cmd` to run under the limits.
+    pub fn wrap(&self, cmd: &CommandSpec) -> CommandSpec {
+        let limits = &self.limits;
+        let mut wrapped = cmd.clone();
+        if self.mechanism == Mechanism::JobObject {
+            wrapped.limits = (*limits != LimitsConfig::default()).then(|| limits.clone());
+            return wrapped;
+        }
+        let mut words: Vec<String> = Vec::new();
+        if self.mechanism == Mechanism::Systemd
+            && (limits.cpus.is_some() || limits.memory.is_some())
+        {
+            words.extend(
+                ["systemd-run", "--user", "--scope", "--quiet", "--collect"].map(String::from),
+            );
+            if let Some(cpus) = limits.cpus {
+                words.extend(["-p".into(), format!("CPUQuota={}%", cpus * 100)]);
+            }
+            if let Some(memory) = &limits.memory {
+                words.extend(["-p".into(), format!("MemoryMax={memory}")]);
+            }
+            words.push("--".into());
+            if cmd.clear_env {
+                // passed on to the command too, as a scope runs it directly
+                for name in SESSION_ENV {
+                    if let Ok(value) = std::env::var(name) {
+                        wrapped.env.entry(name.to_string()).or_insert(value);
+                    }
+                }
+            }
+        }
+        if self.mechanism != Mechanism::None {
+            if let Some(nice) = limits.nice {
+                words.extend(["nice".into(), "-n".into(), nice.to_string()]);
+            }
+            if let (Some(io), true) = (&limits.io, cfg!(target_os = "linux")) {
+                let class = if io == "idle" { "3" } else { "2" };
+                words.extend(["ionice".into(), "-c".into(), class.into()]);
+            }
+        }
+        if let Some((program, args)) = words.split_first() {
+            wrapped.program = program.clone();
+            wrapped.args = args.to_vec();
+            wrapped.args.push(cmd.program.clone());
+            wrapped.args.extend(cmd.args.iter().cloned());
+        }
+        wrapped
+    }
+}
+
+impl<E: Executor> Executor for LimitedExecutor<E> {
+    fn run(&self, cmd: &CommandSpec) -> io::Result<CommandOutput> {
+        self.inner.run(&self.wrap(cmd))
+    }
+
+    fn output(&self, cmd: &CommandSpec) -> io::Result<CommandOutput> {
+        self.inner.output(&self.wrap(cmd))
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.inner.var(name)
+    }
+}
+
+/// Whether a systemd user manager listens in `runtime_dir`, the user's
+/// `XDG_RUNTIME_DIR`; without one, as over plain SSH or in containers,
+/// `systemd-run --user` fails.
+fn user_session(runtime_dir: Option<&OsStr>) -> bool {
+    runtime_dir.map(Path::new).is_some_and(|dir| {
+        dir.is_absolute() && (dir.join("systemd/private").exists() || dir.join("bus").exists())
+    })
+}
+
+/// Puts `child` in a job object enforcing `cmd.limits`, if any; the job
+/// lasts as long as the returned handle. Processes `child` starts before
+/// it joins escape the limits, which the instants after spawning make
+/// unlikely.
+pub(crate) fn confine(child: &Child, cmd: &CommandSpec) -> io::Result<Option<Job>> {
+    #[cfg(windows)]
+    if let Some(limits) = &cmd.limits {
+        return Job::assign(child, limits).map(Some);
+    }
+    #[cfg(not(windows))]
+    let _ = (child, cmd);
+    Ok(None)
+}
+
+#[cfg(windows)]
+pub(crate) use job::Job;
+
+/// There are no job objects off Windows.
+#[cfg(not(windows))]
+pub(crate) enum Job {}
+
+/// Bytes in a `[limits] memory` size such as `512M`; `None` if malformed.
+pub(crate) fn memory_bytes(memory: &str) -> Option<u64> {
+    let (digits, shift) = match memory.as_bytes().last()? {
+        b'K' => (&memory[..memory.len() - 1], 10),
+        b'M' => (&memory[..memory.len() - 1], 20),
+        b'G' => (&memory[..memory.len() - 1], 30),
+        b'T' => (&memory[..memory.len() - 1], 40),
+        _ => (memory, 0),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse::<u64>().ok()?.checked_mul(1 << shift)
+}
+
+#[cfg(windows)]
+mod job {
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectCpuRateControlInformation,
+        JobObjectExtendedLimitInformation, SetInformationJobObject, JOBOBJECTINFOCLASS,
+        JOBOBJECT_CPU_RATE_CONTROL_INFORMATION, JOBOBJECT_CPU_RATE_CONTROL_INFORMATION_0,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_CPU_RATE_CONTROL_ENABLE,
+        JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP, JOB_OBJECT_LIMIT_JOB_MEMORY,
+        JOB_OBJECT_LIMIT_PRIORITY_CLASS,
+    };
+    use windows_sys::Win32::System::Threading::{BELOW_NORMAL_PRIORITY_CLASS, IDLE_PRIORITY_CLASS};
+
+    use super::memory_bytes;
+    use crate::LimitsConfig;
+
+    pub(crate) struct Job(HANDLE);
+
+    impl Job {
+        pub(crate) fn assign(child: &Child, limits: &LimitsConfig) -> io::Result<Job> {
+            // SAFETY: no security attributes and an anonymous job
+            let handle = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let job = Job(handle);
+            let mut info = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+            if let Some(bytes) = limits.memory.as_deref().and_then(memory_bytes) {
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+                info.JobMemoryLimit = usize::try_from(bytes).unwrap_or(usize::MAX);
+            }
+            if let Some(nice) = limits.nice.filter(|n| *n > 0) {
+                info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PRIORITY_CLASS;
+                info.BasicLimitInformation.PriorityClass = match nice {
+                    15.. => IDLE_PRIORITY_CLASS,
+                    _ => BELOW_NORMAL_PRIORITY_CLASS,
+                };
+            }
+            job.set(JobObjectExtendedLimitInformation, &info)?;
+            if let Some(cpus) = limits.cpus {
+                let total = std::thread::available_parallelism().map_or(1, |n| n.get() as u32);
+                // hundredths of a percent of all the host's CPUs
+                let rate = JOBOBJECT_CPU_RATE_CONTROL_INFORMATION {
+                    ControlFlags: JOB_OBJECT_CPU_RATE_CONTROL_ENABLE
+                        | JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP,
+                    Anonymous: JOBOBJECT_CPU_RATE_CONTROL_INFORMATION_0 {
+                        CpuRate: cpus.min(total) * 10_000 / total,
+                    },
+                };
+                job.set(JobObjectCpuRateControlInformation, &rate)?;
+            }
+            // SAFETY: both handles are open for the duration of the call
+            if unsafe { AssignProcessToJobObject(job.0, child.as_raw_handle()) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(job)
+        }
+
+        fn set<T>(&self, class: JOBOBJECTINFOCLASS, info: &T) -> io::Result<()> {
+            let size = std::mem::size_of::<T>() as u32;
+            // SAFETY: `info` is the structure `class` names, `size` bytes long
+            let ok =
+                unsafe { SetInformationJobObject(self.0, class, (info as *const T).cast(), size) };
+            match ok {
+                0 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            // SAFETY: the handle came from CreateJobObjectW and is closed once
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::MockExecutor;
+    use crate::sandbox::{Isolation, SandboxExecutor};
+    use crate::SandboxConfig;
+
+    #[test]
+    fn test_limits_wrapping() {
+        let limits = LimitsConfig {
+            cpus: Some(2),
+            memory: Some("4G".into()),
+            nice: Some(10),
+            io: Some("idle".into()),
+        };
+        let mut cmd = CommandSpec::new("cargo");
+        cmd.args(["build", "--release"]).env("GOOS", "linux");
+
+        let systemd = LimitedExecutor::with_mechanism(
+            limits.clone(),
+            MockExecutor::new(),
+            Mechanism::Systemd,
+        );
+        let wrapped = systemd.wrap(&cmd);
+        assert_eq!(wrapped.program, "systemd-run");
+        assert!(wrapped.args.starts_with(&[
+            "--user".into(),
+            "--scope".into(),
+            "--quiet".into(),
+            "--collect".into(),
+            "-p".into(),
+            "CPUQuota=200%".into(),
+            "-p".into(),
+            "MemoryMax=4G".into(),
+            "--".into(),
+            "nice".into(),
+            "-n".into(),
+            "10".into(),
+        ]));
+        assert!(wrapped
+            .args
+            .ends_with(&["cargo".into(), "build".into(), "--release".into()]));
+        assert_eq!(wrapped.env["GOOS"], "linux");
+        assert!(systemd.unenforced().iter().all(|s| *s == "io"));
+
+        let priority = LimitedExecutor::with_mechanism(
+            LimitsConfig {
+                nice: Some(5),
+                ..limits.clone()
+            },
+            MockExecutor::new(),
+            Mechanism::Priority,
+        );
+        let wrapped = priority.wrap(&cmd);
+        assert_eq!(wrapped.program, "nice");
+        assert_eq!(wrapped.args[..2], ["-n", "5"]);
+        assert!(priority.unenforced().starts_with(&["cpus", "memory"]));
+
+        let job = LimitedExecutor::with_mechanism(
+            limits.clone(),
+            MockExecutor::new(),
+            Mechanism::JobObject,
+        );
+        let wrapped = job.wrap(&cmd);
+        assert_eq!(wrapped.program, "cargo");
+        assert_eq!(wrapped.limits.as_ref(), Some(&limits));
+        assert_eq!(job.unenforced(), ["io"]);
+
+        let none = LimitedExecutor::with_mechanism(limits, MockExecutor::new(), Mechanism::None);
+        assert_eq!(none.wrap(&cmd), cmd);
+        assert_eq!(none.unenforced(), ["cpus", "memory", "nice", "io"]);
+    }
+
+    #[test]
+    fn test_limits_wrap_the_sandbox() {
+        let mock = MockExecutor::new();
+        let limits = LimitsConfig {
+            memory: Some("1G".into()),
+            ..Default::default()
+        };
+        let limited = LimitedExecutor::with_mechanism(limits, &mock, Mechanism::Systemd);
+        let sandbox = SandboxExecutor::with_isolation(
+            SandboxConfig::default(),
+            &limited,
+            Isolation::Bubblewrap,
+        );
+        sandbox.run(&CommandSpec::new("make")).unwrap();
+        let call = &mock.calls()[0];
+        assert_eq!(call.program, "systemd-run");
+        let inner = call.args.iter().position(|a| a == "--").unwrap();
+        assert_eq!(call.args[inner + 1], "bwrap");
+        assert_eq!(call.args.last().unwrap(), "make");
+        assert!(call.clear_env);
+        if let Ok(dir) = std::env::var("XDG_RUNTIME_DIR") {
+            assert_eq!(call.env["XDG_RUNTIME_DIR"], dir);
+        }
+    }
+
+    #[test]
+    fn test_user_session() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!user_session(None));
+        assert!(!user_session(Some(dir.path().as_os_str())));
+        std::fs::create_dir(dir.path().join("systemd")).unwrap();
+        std::fs::write(dir.path().join("systemd/private"), "").unwrap();
+        assert!(user_session(Some(dir.path().as_os_str())));
+        assert!(!user_session(Some(OsStr::new("run/user/1000"))));
+    }
+
+    #[test]
+    fn test_memory_bytes() {
+        assert_eq!(memory_bytes("512M"), Some(512 << 20));
+        assert_eq!(memory_bytes("4G"), Some(4 << 30));
+        assert_eq!(memory_bytes("1000"), Some(1000));
+        for bad in ["", "G", "4GB", "1.5G", "-1"] {
+            assert_eq!(memory_bytes(bad), None, "{bad}");
+        }
+    }
+}
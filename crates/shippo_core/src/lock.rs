@@ -0,0 +1,192 @@
+//! `shippo.lock`: the release as planned, pinned for change control.
+//!
+//! `shippo plan` writes the lockfile; `build`, `package` and `release` compare
+//! the current plan against it and refuse to run when anything drifted.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Plan, ToolingInfo};
+
+pub const LOCK_FILE: &str = "shippo.lock";
+const LOCK_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LockError {
+    #[error("failed to access {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("failed to parse {path}: {message}")]
+    Parse { path: String, message: String },
+    #[error("release drifted from {path} (run `shippo plan` to accept):\n  {}", .changes.join("\n  "))]
+    Drift { path: String, changes: Vec<String> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Lockfile {
+    pub lock_version: u32,
+    pub version: String,
+    /// SHA-256 of the config file the plan was made from.
+    pub config_sha256: String,
+    pub tooling: ToolingInfo,
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub targets: Vec<String>,
+}
+
+impl Lockfile {
+    pub fn new(plan: &Plan, config_sha256: String, tooling: ToolingInfo) -> Self {
+        Self {
+            lock_version: LOCK_VERSION,
+            version: plan.version.clone(),
+            config_sha256,
+            tooling,
+            packages: plan
+                .packages
+                .iter()
+                .map(|p| LockedPackage {
+                    name: p.name.clone(),
+                    targets: p.targets.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns `None` when there is no lockfile.
+    pub fn read(path: &Path) -> Result<Option<Self>, LockError> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(io_err(path, source)),
+        };
+        toml::from_str(&content)
+            .map(Some)
+            .map_err(|e| LockError::Parse {
+                path: path.display().to_string(),
+                message: e.to_string(),
+            })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), LockError> {
+        let body = toml::to_string_pretty(self).map_err(|e| LockError::Parse {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        let content = format!("# Generated by `shippo plan`. Do not edit.\n{body}");
+        std::fs::write(path, content).map_err(|e| io_err(path, e))
+    }
+
+    /// Lists how `current` differs from this lock. A current plan restricted
+    /// with `--only` or `--target` matches as long as it is a subset.
+    pub fn drift(&self, current: &Lockfile) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.version != current.version {
+            changes.push(format!("version {} -> {}", self.version, current.version));
+        }
+        if self.config_sha256 != current.config_sha256 {
+            changes.push("config file changed".to_string());
+        }
+        for pkg in &current.packages {
+            let Some(locked) = self.packages.iter().find(|l| l.name == pkg.name) else {
+                changes.push(format!("package {} is not locked", pkg.name));
+                continue;
+            };
+            for target in pkg.targets.iter().filter(|t| !locked.targets.contains(t)) {
+                changes.push(format!(
+                    "package {} target {target} is not locked",
+                    pkg.name
+                ));
+            }
+        }
+        let tools = [
+            ("rust", &self.tooling.rust, &current.tooling.rust),
+            ("go", &self.tooling.go, &current.tooling.go),
+            ("node", &self.tooling.node, &current.tooling.node),
+            ("python", &self.tooling.python, &current.tooling.python),
+        ];
+        for (tool, locked, now) in tools {
+            if let Some(locked) = locked {
+                if now.as_ref() != Some(locked) {
+                    let now = now.as_deref().unwrap_or("missing");
+                    changes.push(format!("{tool} toolchain {locked} -> {now}"));
+                }
+            }
+        }
+        changes
+    }
+
+    /// Fails with [`LockError::Drift`] if `current` differs from this lock.
+    pub fn check(&self, current: &Lockfile, path: &Path) -> Result<(), LockError> {
+        let changes = self.drift(current);
+        if changes.is_empty() {
+            Ok(())
+        } else {
+            Err(LockError::Drift {
+                path: path.display().to_string(),
+                changes,
+            })
+        }
+    }
+}
+
+fn io_err(path: &Path, source: std::io::Error) -> LockError {
+    LockError::Io {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock(version: &str, targets: &[&str], rust: &str) -> Lockfile {
+        Lockfile {
+            lock_version: LOCK_VERSION,
+            version: version.into(),
+            config_sha256: "abc".into(),
+            tooling: ToolingInfo {
+                rust: Some(rust.into()),
+                go: None,
+                node: None,
+                python: None,
+            },
+            packages: vec![LockedPackage {
+                name: "app".into(),
+                targets: targets.iter().map(|t| t.to_string()).collect(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_lock_roundtrip_and_drift() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOCK_FILE);
+        let locked = lock("v1.0.0", &["linux-amd64", "darwin-arm64"], "rustc 1.80.0");
+        locked.write(&path).unwrap();
+        assert_eq!(Lockfile::read(&path).unwrap(), Some(locked.clone()));
+        assert_eq!(Lockfile::read(&dir.path().join("none.lock")).unwrap(), None);
+
+        // a per-runner subset still matches
+        assert!(locked
+            .check(&lock("v1.0.0", &["linux-amd64"], "rustc 1.80.0"), &path)
+            .is_ok());
+        let drift = locked.drift(&lock("v1.0.1", &["windows-amd64"], "rustc 1.81.0"));
+        assert_eq!(
+            drift,
+            vec![
+                "version v1.0.0 -> v1.0.1",
+                "package app target windows-amd64 is not locked",
+                "rust toolchain rustc 1.80.0 -> rustc 1.81.0",
+            ]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+//! License and descriptive metadata of a package, read from its ecosystem
+//! manifest (`Cargo.toml`, `package.json`, `pyproject.toml`, `go.mod`), and
+//! the pre-package lint behind `[verify.metadata]`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use toml::Value as Toml;
+
+use crate::{PackagePlan, ProjectType};
+
+/// File name prefixes recognised as license texts, compared ignoring case.
+const LICENSE_FILES: [&str; 3] = ["license", "licence", "copying"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PackageMetadata {
+    /// SPDX expression, or the license file named in the manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Homepage, or the repository URL when there is none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    /// License text found next to the package or at the workspace root,
+    /// relative to the workspace root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license_file: Option<String>,
+}
+
+impl PackageMetadata {
+    /// Reads `pkg`'s metadata; `root` is the workspace root. Missing or
+    /// unreadable manifests leave the fields empty.
+    pub fn read(root: &Path, pkg: &PackagePlan) -> Self {
+        let dir = root.join(pkg.path.as_str());
+        let mut meta = match pkg.project_type {
+            ProjectType::Rust => read_cargo(root, &dir),
+            ProjectType::Node => read_package_json(&dir),
+            ProjectType::Python => read_pyproject(&dir),
+            ProjectType::Go => read_go_mod(&dir),
+        };
+        meta.license_file = [dir.as_path(), root]
+            .into_iter()
+            .find_map(license_file)
+            .map(|path| {
+                path.strip_prefix(root)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            });
+        meta
+    }
+
+    /// What is missing, e.g. `no description in Cargo.toml`. `go.mod` has
+    /// no license or description, so Go packages only need a license file.
+    pub fn problems(&self, project_type: &ProjectType) -> Vec<String> {
+        let manifest = match project_type {
+            ProjectType::Rust => "Cargo.toml",
+            ProjectType::Node => "package.json",
+            ProjectType::Python => "pyproject.toml",
+            ProjectType::Go => "go.mod",
+        };
+        let mut problems = Vec::new();
+        if self.license_file.is_none() {
+            problems.push(
+                "no LICENSE, LICENCE or COPYING file in the package or workspace root".to_string(),
+            );
+        }
+        if *project_type != ProjectType::Go {
+            if self.license.is_none() {
+                problems.push(format!("no license in {manifest}"));
+            }
+            if self.description.is_none() {
+                problems.push(format!("no description in {manifest}"));
+            }
+        }
+        if self.homepage.is_none() {
+            problems.push(format!("no homepage or repository URL in {manifest}"));
+        }
+        problems
+    }
+}
+
+fn license_file(dir: &Path) -> Option<PathBuf> {
+    let mut found: Vec<_> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|e| e.path().is_file())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_lowercase();
+            LICENSE_FILES.iter().any(|prefix| name.starts_with(prefix))
+        })
+        .map(|e| e.path())
+        .collect();
+    found.sort();
+    found.into_iter().next()
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// The crate name in `dir/Cargo.toml`.
+pub fn crate_name(dir: &Path) -> Option<String> {
+    let doc = read_toml(&dir.join("Cargo.toml"))?;
+    non_empty(doc.get("package")?.get("name")?.as_str())
+}
+
+fn read_toml(path: &Path) -> Option<Toml> {
+    toml::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn read_cargo(root: &Path, dir: &Path) -> PackageMetadata {
+    let Some(doc) = read_toml(&dir.join("Cargo.toml")) else {
+        return PackageMetadata::default();
+    };
+    let Some(package) = doc.get("package") else {
+        return PackageMetadata::default();
+    };
+    // `license.workspace = true` takes the value from `[workspace.package]`
+    // in the nearest enclosing workspace
+    let workspace = dir
+        .ancestors()
+        .take_while(|d| d.starts_with(root))
+        .find_map(|d| {
+            read_toml(&d.join("Cargo.toml"))?
+                .get("workspace")?
+                .get("package")
+                .cloned()
+        });
+    let field = |key: &str| match package.get(key)? {
+        Toml::String(s) => non_empty(Some(s.as_str())),
+        Toml::Table(t) if t.get("workspace").and_then(Toml::as_bool) == Some(true) => {
+            non_empty(workspace.as_ref()?.get(key)?.as_str())
+        }
+        _ => None,
+    };
+    PackageMetadata {
+        license: field("license").or_else(|| field("license-file")),
+        description: field("description"),
+        homepage: field("homepage").or_else(|| field("repository")),
+        license_file: None,
+    }
+}
+
+fn read_package_json(dir: &Path) -> PackageMetadata {
+    let Some(doc) = fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str::<Json>(&s).ok())
+    else {
+        return PackageMetadata::default();
+    };
+    let repository = match doc.get("repository") {
+        Some(Json::Object(repo)) => non_empty(repo.get("url").and_then(Json::as_str)),
+        other => non_empty(other.and_then(Json::as_str)),
+    };
+    PackageMetadata {
+        license: non_empty(doc.get("license").and_then(Json::as_str)),
+        description: non_empty(doc.get("description").and_then(Json::as_str)),
+        homepage: non_empty(doc.get("homepage").and_then(Json::as_str)).or(repository),
+        license_file: None,
+    }
+}
+
+fn read_pyproject(dir: &Path) -> PackageMetadata {
+    let Some(project) =
+        read_toml(&dir.join("pyproject.toml")).and_then(|d| d.get("project").cloned())
+    else {
+        return PackageMetadata::default();
+    };
+    // PEP 621 allows `license = "MIT"`, `{ text = "..." }` or `{ file = "..." }`
+    let license = match project.get("license") {
+        Some(Toml::Table(t)) => non_empty(
+            t.get("text")
+                .or_else(|| t.get("file"))
+                .and_then(Toml::as_str),
+        ),
+        other => non_empty(other.and_then(Toml::as_str)),
+    };
+    let url = |names: &[&str]| {
+        let urls = project.get("urls")?.as_table()?;
+        urls.iter()
+            .find(|(k, _)| names.iter().any(|n| k.eq_ignore_ascii_case(n)))
+            .and_then(|(_, v)| non_empty(v.as_str()))
+    };
+    PackageMetadata {
+        license,
+        description: non_empty(project.get("description").and_then(Toml::as_str)),
+        homepage: url(&["homepage"]).or_else(|| url(&["repository", "source"])),
+        license_file: None,
+    }
+}
+
+/// Go modules have no metadata beyond their path, which is a URL for
+/// modules hosted on e.g. GitHub.
+fn read_go_mod(dir: &Path) -> PackageMetadata {
+    let module = fs::read_to_string(dir.join("go.mod")).ok().and_then(|s| {
+        s.lines().find_map(|l| {
+            l.trim()
+                .strip_prefix("module ")
+                .map(|m| m.trim().to_string())
+        })
+    });
+    let homepage = module
+        .filter(|m| m.split('/').next().is_some_and(|host| host.contains('.')))
+        .map(|m| format!("https://{m}"));
+    PackageMetadata {
+        homepage,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PackageConfig, SbomConfig, SignConfig};
+
+    fn plan(path: &str, project_type: ProjectType) -> PackagePlan {
+        PackagePlan {
+            name: path.into(),
+            project_type,
+            path: path.into(),
+            targets: vec!["native".into()],
+            package: PackageConfig {
+                formats: vec!["tar.gz".into()],
+                name_template: "{name}-{version}-{target}".into(),
+                include: vec![],
+                exclude: vec![],
+                target_style: None,
+                keep_last: None,
+                contents: Default::default(),
+                transform: Vec::new(),
+                compression: Default::default(),
+                duplicates: None,
+            },
+            sbom: SbomConfig {
+                enabled: false,
+                format: "cyclonedx".into(),
+                mode: "auto".into(),
+                tool: None,
+                formats: Vec::new(),
+            },
+            sign: SignConfig {
+                enabled: false,
+                method: "cosign".into(),
+                cosign_mode: "keyless".into(),
+                ssh_key: None,
+                ssh_namespace: None,
+                cosign: None,
+                gpg: None,
+            },
+            node: None,
+            python: None,
+            go: None,
+            builder: None,
+            docker: None,
+        }
+    }
+
+    #[test]
+    fn test_metadata_from_ecosystem_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers=['cli']\n[workspace.package]\nlicense='MIT'\nrepository='https://github.com/acme/tool'\n",
+        );
+        write("LICENSE-MIT", "MIT License");
+        write(
+            "cli/Cargo.toml",
+            "[package]\nname='cli'\nlicense.workspace=true\nrepository.workspace=true\ndescription='The CLI'\n",
+        );
+        write(
+            "web/package.json",
+            r#"{"name":"web","license":"ISC","repository":{"type":"git","url":"https://github.com/acme/web"}}"#,
+        );
+        write("web/COPYING", "ISC");
+        write("api/go.mod", "module github.com/acme/api\n\ngo 1.22\n");
+
+        let cli = PackageMetadata::read(root, &plan("cli", ProjectType::Rust));
+        assert_eq!(cli.license.as_deref(), Some("MIT"));
+        assert_eq!(
+            cli.homepage.as_deref(),
+            Some("https://github.com/acme/tool")
+        );
+        assert_eq!(cli.license_file.as_deref(), Some("LICENSE-MIT"));
+        assert!(cli.problems(&ProjectType::Rust).is_empty());
+
+        let web = PackageMetadata::read(root, &plan("web", ProjectType::Node));
+        assert_eq!(web.homepage.as_deref(), Some("https://github.com/acme/web"));
+        assert_eq!(web.license_file.as_deref(), Some("web/COPYING"));
+        assert_eq!(
+            web.problems(&ProjectType::Node),
+            vec!["no description in package.json"]
+        );
+
+        let api = PackageMetadata::read(root, &plan("api", ProjectType::Go));
+        assert_eq!(api.homepage.as_deref(), Some("https://github.com/acme/api"));
+        assert!(api.problems(&ProjectType::Go).is_empty());
+
+        let py = PackageMetadata::read(root, &plan("py", ProjectType::Python));
+        assert_eq!(py.problems(&ProjectType::Python).len(), 3);
+    }
+}
@@ -0,0 +1,240 @@
+//! External plugins: `shippo-plugin-<name>` executables that speak JSON-RPC 2.0
+//! over stdin/stdout.
+//!
+//! Each call spawns the plugin, writes one request line to its stdin and reads
+//! one response line from its stdout. Whatever the plugin writes to stderr is
+//! passed through to the user. Config values reference plugins as
+//! `plugin:<name>`, e.g. `[sign] method = "plugin:vault"`.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Stdio};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+pub const PLUGIN_PREFIX: &str = "shippo-plugin-";
+pub const PLUGIN_REF_PREFIX: &str = "plugin:";
+
+#[derive(thiserror::Error, Debug)]
+pub enum PluginError {
+    #[error("plugin {0} not found (no [plugins] entry and no shippo-plugin-{0} on PATH)")]
+    NotFound(String),
+    #[error("failed to run plugin {name}: {source}")]
+    Spawn {
+        name: String,
+        source: std::io::Error,
+    },
+    #[error("plugin {name} exited with {status}")]
+    Exited { name: String, status: ExitStatus },
+    #[error("plugin {name} sent an invalid response: {message}")]
+    Protocol { name: String, message: String },
+    #[error("plugin {name} failed ({code}): {message}")]
+    Remote {
+        name: String,
+        code: i64,
+        message: String,
+    },
+}
+
+/// Returns the plugin name from a `plugin:<name>` config value.
+pub fn plugin_ref(value: &str) -> Option<&str> {
+    value.strip_prefix(PLUGIN_REF_PREFIX)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plugin {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Reply to the `describe` method.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PluginDescription {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    /// Any of `builder`, `sbom`, `signer`, `publisher`.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildParams {
+    pub package: String,
+    pub path: String,
+    pub target: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildReply {
+    pub artifacts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SbomParams {
+    pub package: String,
+    pub version: String,
+    pub target: String,
+    pub artifacts: Vec<String>,
+    /// File the plugin must write the SBOM to.
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignParams {
+    pub file: String,
+    /// File the plugin must write the signature to.
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishParams {
+    pub tag: String,
+    pub dist: String,
+    pub manifest: String,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl Plugin {
+    pub fn describe(&self) -> Result<PluginDescription, PluginError> {
+        self.call("describe", &json!({}))
+    }
+
+    pub fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &impl Serialize,
+    ) -> Result<T, PluginError> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let spawn_err = |source| PluginError::Spawn {
+            name: self.name.clone(),
+            source,
+        };
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(spawn_err)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            writeln!(stdin, "{request}").map_err(spawn_err)?;
+        }
+        let output = child.wait_with_output().map_err(spawn_err)?;
+        if !output.status.success() {
+            return Err(PluginError::Exited {
+                name: self.name.clone(),
+                status: output.status,
+            });
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let line = stdout
+            .lines()
+            .rev()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or_default();
+        let protocol_err = |message: String| PluginError::Protocol {
+            name: self.name.clone(),
+            message,
+        };
+        let response: RpcResponse =
+            serde_json::from_str(line).map_err(|e| protocol_err(e.to_string()))?;
+        if let Some(err) = response.error {
+            return Err(PluginError::Remote {
+                name: self.name.clone(),
+                code: err.code,
+                message: err.message,
+            });
+        }
+        serde_json::from_value(response.result.unwrap_or(Value::Null))
+            .map_err(|e| protocol_err(e.to_string()))
+    }
+}
+
+/// Lists `[plugins]` entries plus every `shippo-plugin-*` executable on PATH.
+/// Configured entries win over PATH.
+pub fn discover(configured: &BTreeMap<String, String>) -> Vec<Plugin> {
+    let mut found: BTreeMap<String, PathBuf> = BTreeMap::new();
+    if let Some(path) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&path) {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let Some(name) = file_name.strip_prefix(PLUGIN_PREFIX) else {
+                    continue;
+                };
+                let name = name.strip_suffix(".exe").unwrap_or(name);
+                if !name.is_empty() && entry.path().is_file() {
+                    found.entry(name.to_string()).or_insert(entry.path());
+                }
+            }
+        }
+    }
+    for (name, path) in configured {
+        found.insert(name.clone(), PathBuf::from(path));
+    }
+    found
+        .into_iter()
+        .map(|(name, path)| Plugin { name, path })
+        .collect()
+}
+
+pub fn find(name: &str, configured: &BTreeMap<String, String>) -> Result<Plugin, PluginError> {
+    discover(configured)
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| PluginError::NotFound(name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_call_configured_plugin() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("echo-plugin");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\nread req\necho '{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"name\":\"echo\",\"capabilities\":[\"signer\"]}}'\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let configured =
+            BTreeMap::from([("echo".to_string(), script.to_string_lossy().to_string())]);
+        let plugin = find("echo", &configured).unwrap();
+        let desc = plugin.describe().unwrap();
+        assert_eq!(desc.capabilities, vec!["signer"]);
+        assert_eq!(plugin_ref("plugin:echo"), Some("echo"));
+        assert!(matches!(
+            find("missing-xyz", &configured),
+            Err(PluginError::NotFound(_))
+        ));
+    }
+}
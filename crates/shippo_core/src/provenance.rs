@@ -0,0 +1,105 @@
+//! Build information embedded into released binaries with `[build]
+//! provenance`, and read back by `shippo inspect`.
+//!
+//! Binaries carry the JSON after [`MARKER`]: Rust programs include a
+//! generated file defining a static, Go programs get it through `-ldflags
+//! -X`; both must reference it, or the linker drops it as unused. Node and
+//! Python builds get a [`DATA_FILE`] with the plain JSON next to their
+//! outputs.
+
+use serde::{Deserialize, Serialize};
+
+/// Precedes the JSON in binaries.
+pub const MARKER: &str = "shippo-provenance:";
+/// Written to Node and Python build output directories.
+pub const DATA_FILE: &str = "shippo-provenance.json";
+/// Points Rust builds at the generated file to `include!`.
+pub const RUST_INCLUDE_ENV: &str = "SHIPPO_PROVENANCE_RS";
+/// Go variable set with `-X`; it must be declared and referenced.
+pub const GO_VARIABLE: &str = "main.shippoProvenance";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Provenance {
+    pub package: String,
+    pub target: String,
+    pub version: String,
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// Builder that made the binary, e.g. `rust` or `plugin:zig`.
+    pub builder: String,
+    /// SHA-256 of the config file the release was planned from.
+    #[serde(default)]
+    pub config_sha256: Option<String>,
+}
+
+impl Provenance {
+    /// [`MARKER`] followed by compact JSON, as embedded into binaries.
+    pub fn blob(&self) -> String {
+        let json = serde_json::to_string(self).expect("provenance serializes");
+        format!("{MARKER}{json}")
+    }
+
+    /// Source of the file Rust programs `include!`. `#[used]` only keeps
+    /// the static from the compiler; linkers collect unreferenced sections
+    /// all the same, so the program has to reference it.
+    pub fn rust_include(&self) -> String {
+        format!(
+            "// Generated by shippo: release provenance, read by `shippo inspect`.\n\
+             // Reference it, e.g. `std::hint::black_box(SHIPPO_PROVENANCE);` in `main`,\n\
+             // so the linker keeps it.\n\
+             #[used]\n\
+             pub static SHIPPO_PROVENANCE: &str = {:?};\n",
+            self.blob()
+        )
+    }
+
+    /// Reads provenance from a binary, or from a [`DATA_FILE`].
+    pub fn find(data: &[u8]) -> Option<Self> {
+        if let Ok(provenance) = serde_json::from_slice(data) {
+            return Some(provenance);
+        }
+        let marker = MARKER.as_bytes();
+        let mut start = 0;
+        while let Some(pos) = data[start..]
+            .windows(marker.len())
+            .position(|w| w == marker)
+        {
+            let json = start + pos + marker.len();
+            // whatever follows the JSON in the binary is ignored
+            let mut values = serde_json::Deserializer::from_slice(&data[json..]).into_iter();
+            if let Some(Ok(provenance)) = values.next() {
+                return Some(provenance);
+            }
+            start = json;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provenance_found_in_binary_data() {
+        let provenance = Provenance {
+            package: "cli".into(),
+            target: "x86_64-unknown-linux-gnu".into(),
+            version: "1.2.0".into(),
+            commit: Some("0123abc".into()),
+            builder: "rust".into(),
+            config_sha256: None,
+        };
+        let mut binary = b"\x7fELF\0\0shippo-provenance:%s\0".to_vec();
+        binary.extend_from_slice(provenance.blob().as_bytes());
+        binary.extend_from_slice(b"\0\x01\x02more strings");
+        assert_eq!(Provenance::find(&binary), Some(provenance.clone()));
+
+        let file = serde_json::to_vec_pretty(&provenance).unwrap();
+        assert_eq!(Provenance::find(&file), Some(provenance.clone()));
+        assert_eq!(Provenance::find(b"\x7fELF no provenance"), None);
+        assert!(provenance.rust_include().contains(
+            r#"pub static SHIPPO_PROVENANCE: &str = "shippo-provenance:{\"package\":\"cli\""#
+        ));
+    }
+}
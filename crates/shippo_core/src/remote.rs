@@ -0,0 +1,195 @@
+//! Experimental remote build workers reached over SSH.
+//!
+//! [`SshExecutor`] runs build commands on a worker instead of locally: before
+//! the first command it mirrors the workspace to the worker with `rsync`
+//! (skipping `.gitignore`d files), each command runs there through `ssh` in
+//! the matching directory, and afterwards build outputs are copied back so
+//! builders find their artifacts where they expect them.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::exec::{CommandOutput, CommandSpec, Executor};
+use crate::RemoteWorkerConfig;
+
+/// Not copied back from workers: dependency caches and intermediate files.
+const PULL_EXCLUDES: &[&str] = &[
+    ".git/",
+    "node_modules/",
+    "target/**/deps/",
+    "target/**/build/",
+    "target/**/.fingerprint/",
+    "target/**/incremental/",
+];
+
+pub struct SshExecutor<E> {
+    inner: E,
+    host: String,
+    local_root: PathBuf,
+    remote_root: String,
+    synced: Mutex<bool>,
+}
+
+impl<E: Executor> SshExecutor<E> {
+    /// `workspace_root` is mirrored to `<worker dir>/<project>` on the worker.
+    pub fn new(
+        worker: &RemoteWorkerConfig,
+        project: &str,
+        workspace_root: &Path,
+        inner: E,
+    ) -> Self {
+        Self {
+            inner,
+            host: worker.host.clone(),
+            local_root: workspace_root.to_path_buf(),
+            remote_root: format!("{}/{project}", worker.dir.trim_end_matches('/')),
+            synced: Mutex::new(false),
+        }
+    }
+
+    /// The `ssh` invocation that runs `cmd` on the worker.
+    pub fn remote_command(&self, cmd: &CommandSpec) -> io::Result<CommandSpec> {
+        let rel = match &cmd.cwd {
+            Some(cwd) => cwd.strip_prefix(&self.local_root).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is outside the workspace", cwd.display()),
+                )
+            })?,
+            None => Path::new(""),
+        };
+        let dir = Path::new(&self.remote_root).join(rel);
+        let mut script = format!("cd {} && env", sh_quote(&dir.to_string_lossy()));
+        for (key, value) in &cmd.env {
+            script.push(' ');
+            script.push_str(&sh_quote(&format!("{key}={value}")));
+        }
+        for word in std::iter::once(&cmd.program).chain(&cmd.args) {
+            script.push(' ');
+            script.push_str(&sh_quote(word));
+        }
+        let mut ssh = CommandSpec::new("ssh");
+        ssh.args(["-o", "BatchMode=yes", &self.host, &script]);
+        Ok(ssh)
+    }
+
+    fn push(&self) -> io::Result<()> {
+        let mut synced = self.synced.lock().unwrap_or_else(|e| e.into_inner());
+        if *synced {
+            return Ok(());
+        }
+        let mut mkdir = CommandSpec::new("ssh");
+        mkdir.args([
+            "-o",
+            "BatchMode=yes",
+            &self.host,
+            &format!("mkdir -p {}", sh_quote(&self.remote_root)),
+        ]);
+        self.check(self.inner.output(&mkdir)?, "create the remote workspace")?;
+        let mut rsync = CommandSpec::new("rsync");
+        rsync
+            .args([
+                "-az",
+                "--delete",
+                "--exclude=.git/",
+                "--filter=:- .gitignore",
+            ])
+            .arg(format!("{}/", self.local_root.display()))
+            .arg(format!("{}:{}/", self.host, self.remote_root));
+        self.check(self.inner.output(&rsync)?, "upload the workspace")?;
+        *synced = true;
+        Ok(())
+    }
+
+    fn pull(&self) -> io::Result<()> {
+        let mut rsync = CommandSpec::new("rsync");
+        rsync.arg("-az");
+        for pattern in PULL_EXCLUDES {
+            rsync.arg(format!("--exclude={pattern}"));
+        }
+        rsync
+            .arg(format!("{}:{}/", self.host, self.remote_root))
+            .arg(format!("{}/", self.local_root.display()));
+        self.check(self.inner.output(&rsync)?, "download build outputs")
+    }
+
+    fn check(&self, output: CommandOutput, what: &str) -> io::Result<()> {
+        if output.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "failed to {what} on {} ({}): {}",
+                self.host,
+                output.status(),
+                output.stderr.trim()
+            )))
+        }
+    }
+}
+
+impl<E: Executor> Executor for SshExecutor<E> {
+    fn run(&self, cmd: &CommandSpec) -> io::Result<CommandOutput> {
+        self.push()?;
+        let output = self.inner.run(&self.remote_command(cmd)?)?;
+        if output.success() {
+            self.pull()?;
+        }
+        Ok(output)
+    }
+
+    fn output(&self, cmd: &CommandSpec) -> io::Result<CommandOutput> {
+        self.push()?;
+        self.inner.output(&self.remote_command(cmd)?)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.inner.var(name)
+    }
+}
+
+fn sh_quote(word: &str) -> String {
+    if !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@,+".contains(c))
+    {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::MockExecutor;
+
+    #[test]
+    fn test_ssh_round_trip() {
+        let worker = RemoteWorkerConfig {
+            host: "ci@mac-mini".into(),
+            targets: vec!["darwin-arm64".into()],
+            dir: "shippo-builds".into(),
+        };
+        let remote = SshExecutor::new(&worker, "demo", Path::new("/src/demo"), MockExecutor::new());
+        let mut cmd = CommandSpec::new("go");
+        cmd.args(["build", "-ldflags", "-X main.version=v1"])
+            .env("GOOS", "darwin")
+            .current_dir("/src/demo/cmd/app");
+        assert!(remote.run(&cmd).unwrap().success());
+        let calls = remote.inner.calls();
+        let programs: Vec<_> = calls.iter().map(|c| c.program.as_str()).collect();
+        assert_eq!(programs, ["ssh", "rsync", "ssh", "rsync"]);
+        assert_eq!(
+            calls[2].args[3],
+            "cd shippo-builds/demo/cmd/app && env GOOS=darwin go build -ldflags '-X main.version=v1'"
+        );
+        assert_eq!(calls[3].args.last().unwrap(), "/src/demo/");
+
+        // the workspace is only uploaded once
+        remote.output(&CommandSpec::new("go")).unwrap();
+        assert_eq!(remote.inner.calls().len(), 5);
+        let outside = CommandSpec::new("go").current_dir("/elsewhere").clone();
+        assert!(remote.run(&outside).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Restricted execution for build commands.
+//!
+//! On Linux, commands run under bubblewrap (`bwrap`): the filesystem is
+//! mounted read-only except the configured writable directories, and the
+//! network namespace is unshared. Without bwrap, `unshare --net` still cuts
+//! the network but leaves the filesystem writable. The environment is always
+//! scrubbed down to toolchain variables plus `allow_env`.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::exec::{CommandOutput, CommandSpec, Executor};
+use crate::redact::is_secret_name;
+use crate::SandboxConfig;
+
+/// Variables builds commonly need; anything else is dropped.
+const BASE_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "LANG",
+    "LC_ALL",
+    "TERM",
+    "TMPDIR",
+    "SOURCE_DATE_EPOCH",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+    "CARGO_TARGET_DIR",
+    "GOPATH",
+    "GOROOT",
+    "GOCACHE",
+    "GOMODCACHE",
+    "GOFLAGS",
+    "NODE_PATH",
+    "VIRTUAL_ENV",
+    "PYTHONPATH",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Isolation {
+    /// bubblewrap: read-only filesystem and no network.
+    Bubblewrap,
+    /// `unshare --net`: no network; filesystem stays writable.
+    NetworkOnly,
+}
+
+pub struct SandboxExecutor<E> {
+    inner: E,
+    config: SandboxConfig,
+    isolation: Isolation,
+}
+
+impl<E: Executor> SandboxExecutor<E> {
+    /// Picks the strongest isolation available on this host.
+    pub fn new(config: SandboxConfig, inner: E) -> io::Result<Self> {
+        let isolation = if !cfg!(target_os = "linux") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "sandboxed builds are only supported on Linux",
+            ));
+        } else if on_path("bwrap") {
+            Isolation::Bubblewrap
+        } else if on_path("unshare") {
+            Isolation::NetworkOnly
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "sandboxed builds need bubblewrap (bwrap) or unshare on PATH",
+            ));
+        };
+        Ok(Self::with_isolation(config, inner, isolation))
+    }
+
+    pub fn with_isolation(config: SandboxConfig, inner: E, isolation: Isolation) -> Self {
+        Self {
+            inner,
+            config,
+            isolation,
+        }
+    }
+
+    pub fn isolation(&self) -> Isolation {
+        self.isolation
+    }
+
+    /// Rewrites `cmd` to run inside the sandbox.
+    pub fn wrap(&self, cmd: &CommandSpec) -> io::Result<CommandSpec> {
+        let cwd = match &cmd.cwd {
+            Some(dir) => dir.clone(),
+            None => std::env::current_dir()?,
+        };
+        let mut wrapped = match self.isolation {
+            Isolation::Bubblewrap => {
+                let mut w = CommandSpec::new("bwrap");
+                w.args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"]);
+                w.args(["--tmpfs", "/tmp", "--die-with-parent"]);
+                if !self.config.network {
+                    w.arg("--unshare-net");
+                }
+                for dir in self.writable_dirs(&cwd)? {
+                    let dir = dir.to_string_lossy().to_string();
+                    w.args(["--bind", &dir, &dir]);
+                }
+                w.arg("--chdir").arg(cwd.to_string_lossy());
+                w.arg("--");
+                w
+            }
+            Isolation::NetworkOnly if !self.config.network => {
+                let mut w = CommandSpec::new("unshare");
+                w.args(["--net", "--map-root-user", "--"]);
+                w
+            }
+            Isolation::NetworkOnly => CommandSpec::new(cmd.program.clone()),
+        };
+        if wrapped.program != cmd.program {
+            wrapped.arg(&cmd.program);
+        }
+        wrapped.args(&cmd.args);
+        wrapped.current_dir(&cwd);
+        wrapped.clear_env = true;
+        wrapped.stdin = cmd.stdin.clone();
+        wrapped.env = self.scrubbed_env(&cmd.env);
+        Ok(wrapped)
+    }
+
+    fn writable_dirs(&self, cwd: &Path) -> io::Result<Vec<PathBuf>> {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let mut dirs = Vec::new();
+        for entry in &self.config.writable {
+            let dir = match (entry.strip_prefix("~/"), &home) {
+                (Some(rest), Some(home)) => home.join(rest),
+                (Some(_), None) => continue,
+                (None, _) => cwd.join(entry),
+            };
+            if dir.starts_with(cwd) {
+                std::fs::create_dir_all(&dir)?;
+            }
+            if dir.exists() {
+                dirs.push(dir);
+            }
+        }
+        Ok(dirs)
+    }
+
+    fn scrubbed_env(&self, overrides: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+        let mut env: BTreeMap<String, String> = std::env::vars()
+            .filter(|(k, _)| {
+                self.config.allow_env.contains(k)
+                    || (BASE_ENV.contains(&k.as_str()) && !is_secret_name(k))
+            })
+            .collect();
+        env.extend(overrides.clone());
+        env
+    }
+}
+
+impl<E: Executor> Executor for SandboxExecutor<E> {
+    fn run(&self, cmd: &CommandSpec) -> io::Result<CommandOutput> {
+        self.inner.run(&self.wrap(cmd)?)
+    }
+
+    fn output(&self, cmd: &CommandSpec) -> io::Result<CommandOutput> {
+        self.inner.output(&self.wrap(cmd)?)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.inner.var(name)
+    }
+}
+
+pub(crate) fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::MockExecutor;
+
+    #[test]
+    fn test_bubblewrap_wrapping() {
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = SandboxExecutor::with_isolation(
+            SandboxConfig::default(),
+            MockExecutor::new(),
+            Isolation::Bubblewrap,
+        );
+        let mut cmd = CommandSpec::new("cargo");
+        cmd.args(["build", "--release"])
+            .current_dir(dir.path())
+            .env("GOOS", "linux");
+        let wrapped = sandbox.wrap(&cmd).unwrap();
+        assert_eq!(wrapped.program, "bwrap");
+        assert!(wrapped.args.contains(&"--unshare-net".to_string()));
+        let target = dir.path().join("target").to_string_lossy().to_string();
+        assert!(wrapped
+            .args
+            .windows(3)
+            .any(|w| w[0] == "--bind" && w[1] == target));
+        assert!(wrapped.args.ends_with(&[
+            "--".into(),
+            "cargo".into(),
+            "build".into(),
+            "--release".into()
+        ]));
+        assert!(wrapped.clear_env);
+        assert_eq!(wrapped.env["GOOS"], "linux");
+        assert!(!wrapped.env.contains_key("GITHUB_TOKEN"));
+    }
+}
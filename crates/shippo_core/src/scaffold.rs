@@ -0,0 +1,119 @@
+//! CI workflow scaffolding for `shippo init --ci <provider>`.
+
+use crate::{ConfigError, PackagePlan, Plan, ProjectType};
+
+pub const GITHUB_WORKFLOW_PATH: &str = ".github/workflows/release.yml";
+pub const GITLAB_CI_PATH: &str = ".gitlab-ci.yml";
+
+/// Returns the path and contents of the release workflow for `provider`.
+pub fn ci_workflow(provider: &str, plan: &Plan) -> Result<(&'static str, String), ConfigError> {
+    match provider {
+        "github" => Ok((GITHUB_WORKFLOW_PATH, github_workflow(plan))),
+        "gitlab" => Ok((GITLAB_CI_PATH, gitlab_ci(plan))),
+        other => Err(ConfigError::Message(format!(
+            "unsupported ci provider {other} (expected github or gitlab)"
+        ))),
+    }
+}
+
+pub fn github_workflow(plan: &Plan) -> String {
+    let keyless = plan
+        .packages
+        .iter()
+        .any(|p| p.sign.enabled && p.sign.method == "cosign" && p.sign.cosign_mode == "keyless");
+    let mut out = String::new();
+    out.push_str("name: release\non:\n  push:\n    tags: [\"v*\"]\n");
+    out.push_str("permissions:\n  contents: write\n");
+    if keyless {
+        out.push_str("  id-token: write\n");
+    }
+    out.push_str("jobs:\n  release:\n    runs-on: ubuntu-latest\n    steps:\n");
+    out.push_str("      - uses: actions/checkout@v4\n        with:\n          fetch-depth: 0\n");
+    if has_type(plan, ProjectType::Rust) {
+        out.push_str("      - uses: dtolnay/rust-toolchain@stable\n");
+        let triples = rust_triples(plan);
+        if !triples.is_empty() {
+            out.push_str(&format!(
+                "        with:\n          targets: {}\n",
+                triples.join(",")
+            ));
+        }
+        out.push_str("      - uses: Swatinem/rust-cache@v2\n");
+    }
+    if has_type(plan, ProjectType::Go) {
+        out.push_str("      - uses: actions/setup-go@v5\n        with:\n          go-version: stable\n          cache: true\n");
+    }
+    if has_type(plan, ProjectType::Node) {
+        out.push_str("      - uses: actions/setup-node@v4\n        with:\n          node-version: lts/*\n          cache: npm\n");
+    }
+    if has_type(plan, ProjectType::Python) {
+        out.push_str("      - uses: actions/setup-python@v5\n        with:\n          python-version: \"3.x\"\n          cache: pip\n");
+        out.push_str("      - run: pip install build pyinstaller\n");
+    }
+    if keyless {
+        out.push_str("      - uses: sigstore/cosign-installer@v3\n");
+    }
+    out.push_str("      - run: cargo install shippo-release --locked\n");
+    out.push_str("      - run: shippo --ci release\n        env:\n          GITHUB_TOKEN: ${{ secrets.GITHUB_TOKEN }}\n");
+    out
+}
+
+pub fn gitlab_ci(plan: &Plan) -> String {
+    let image = if has_type(plan, ProjectType::Rust) {
+        "rust:latest"
+    } else if has_type(plan, ProjectType::Go) {
+        "golang:latest"
+    } else if has_type(plan, ProjectType::Node) {
+        "node:lts"
+    } else {
+        "python:3"
+    };
+    let mut out = String::new();
+    out.push_str("stages:\n  - release\n\nrelease:\n  stage: release\n");
+    out.push_str(&format!("  image: {image}\n"));
+    out.push_str("  rules:\n    - if: $CI_COMMIT_TAG\n");
+    out.push_str("  variables:\n    GIT_DEPTH: 0\n");
+    out.push_str("  cache:\n    key: shippo\n    paths:\n      - target/\n");
+    out.push_str("  script:\n");
+    for triple in rust_triples(plan) {
+        out.push_str(&format!("    - rustup target add {triple}\n"));
+    }
+    out.push_str("    - cargo install shippo-release --locked\n");
+    out.push_str("    - shippo --ci release\n");
+    out
+}
+
+fn has_type(plan: &Plan, ty: ProjectType) -> bool {
+    plan.packages.iter().any(|p| p.project_type == ty)
+}
+
+fn rust_triples(plan: &Plan) -> Vec<String> {
+    let mut triples: Vec<String> = plan
+        .packages
+        .iter()
+        .filter(|p| p.project_type == ProjectType::Rust)
+        .flat_map(|p: &PackagePlan| p.targets.iter().cloned())
+        .filter(|t| t != "native")
+        .collect();
+    triples.sort();
+    triples.dedup();
+    triples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_plan, ShippoConfig};
+
+    #[test]
+    fn test_github_workflow_uses_targets() {
+        let toml = "[project]\nname='demo'\ntype='rust'\n\n[build]\ntargets=['x86_64-unknown-linux-gnu']\n\n[sign]\nenabled=true\n";
+        let cfg: ShippoConfig = toml::from_str(toml).unwrap();
+        let plan = build_plan(&cfg, None, None, std::path::Path::new(".")).unwrap();
+        let (path, wf) = ci_workflow("github", &plan).unwrap();
+        assert_eq!(path, GITHUB_WORKFLOW_PATH);
+        assert!(wf.contains("targets: x86_64-unknown-linux-gnu"));
+        assert!(wf.contains("id-token: write"));
+        assert!(ci_workflow("jenkins", &plan).is_err());
+    }
+}
@@ -0,0 +1,344 @@
+//! Registry of the targets shippo knows how to build, with the names each
+//! ecosystem uses for them.
+//!
+//! `[build] targets` may use a rustc triple (`x86_64-unknown-linux-gnu`),
+//! a friendly alias (`linux-x64`), Go's `GOOS/GOARCH` (`linux/amd64` or
+//! `linux-amd64`), a pkg target (`node18-linux-x64`) or a Python platform
+//! (`win-amd64`). Builders translate whatever was configured through
+//! [`resolve`]. Plans warn about targets that neither the registry nor the
+//! ecosystem's toolchain ([`toolchain_knows`]) knows, with a suggestion.
+
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::ProjectType;
+
+/// A supported target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetInfo {
+    /// rustc target triple.
+    pub triple: &'static str,
+    /// Friendly names, the first being the normalized `os-arch` form.
+    pub aliases: &'static [&'static str],
+    /// `GOOS` and `GOARCH`.
+    pub go: Option<(&'static str, &'static str)>,
+    /// pkg target without the Node version, e.g. `linux-x64`.
+    pub pkg: Option<&'static str>,
+    /// Python platform (`sysconfig.get_platform()`), as PyInstaller names
+    /// its builds.
+    pub python: Option<&'static str>,
+}
+
+impl TargetInfo {
+    /// `GOOS-GOARCH`-style name, e.g. `linux-amd64`.
+    pub fn normalized(&self) -> &'static str {
+        self.aliases[0]
+    }
+
+    /// PyInstaller `--target-arch` for macOS targets.
+    pub fn pyinstaller_arch(&self) -> Option<&'static str> {
+        self.python?.strip_prefix("macosx-")
+    }
+
+    fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        std::iter::once(self.triple)
+            .chain(self.aliases.iter().copied())
+            .chain(self.pkg)
+            .chain(self.python)
+    }
+}
+
+const fn target(
+    triple: &'static str,
+    aliases: &'static [&'static str],
+    go: Option<(&'static str, &'static str)>,
+    pkg: Option<&'static str>,
+    python: Option<&'static str>,
+) -> TargetInfo {
+    TargetInfo {
+        triple,
+        aliases,
+        go,
+        pkg,
+        python,
+    }
+}
+
+/// Earlier entries win where names overlap, e.g. glibc over musl for
+/// `linux/amd64`.
+pub const TARGETS: &[TargetInfo] = &[
+    target(
+        "x86_64-unknown-linux-gnu",
+        &["linux-amd64", "linux-x64", "linux-x86_64"],
+        Some(("linux", "amd64")),
+        Some("linux-x64"),
+        Some("linux-x86_64"),
+    ),
+    target(
+        "aarch64-unknown-linux-gnu",
+        &["linux-arm64", "linux-aarch64"],
+        Some(("linux", "arm64")),
+        Some("linux-arm64"),
+        Some("linux-aarch64"),
+    ),
+    target(
+        "x86_64-unknown-linux-musl",
+        &["linux-amd64-musl", "linux-x64-musl", "alpine-x64"],
+        Some(("linux", "amd64")),
+        Some("alpine-x64"),
+        None,
+    ),
+    target(
+        "aarch64-unknown-linux-musl",
+        &["linux-arm64-musl", "alpine-arm64"],
+        Some(("linux", "arm64")),
+        Some("alpine-arm64"),
+        None,
+    ),
+    target(
+        "armv7-unknown-linux-gnueabihf",
+        &["linux-arm", "linux-armv7"],
+        Some(("linux", "arm")),
+        Some("linux-armv7"),
+        Some("linux-armv7l"),
+    ),
+    target(
+        "i686-unknown-linux-gnu",
+        &["linux-386", "linux-x86", "linux-i686"],
+        Some(("linux", "386")),
+        None,
+        Some("linux-i686"),
+    ),
+    target(
+        "riscv64gc-unknown-linux-gnu",
+        &["linux-riscv64"],
+        Some(("linux", "riscv64")),
+        None,
+        None,
+    ),
+    target(
+        "powerpc64le-unknown-linux-gnu",
+        &["linux-ppc64le"],
+        Some(("linux", "ppc64le")),
+        None,
+        Some("linux-ppc64le"),
+    ),
+    target(
+        "s390x-unknown-linux-gnu",
+        &["linux-s390x"],
+        Some(("linux", "s390x")),
+        None,
+        Some("linux-s390x"),
+    ),
+    target(
+        "x86_64-apple-darwin",
+        &["darwin-amd64", "macos-x64", "macos-amd64", "darwin-x64"],
+        Some(("darwin", "amd64")),
+        Some("macos-x64"),
+        Some("macosx-x86_64"),
+    ),
+    target(
+        "aarch64-apple-darwin",
+        &["darwin-arm64", "macos-arm64"],
+        Some(("darwin", "arm64")),
+        Some("macos-arm64"),
+        Some("macosx-arm64"),
+    ),
+    target(
+        "x86_64-pc-windows-msvc",
+        &["windows-amd64", "win-x64", "windows-x64"],
+        Some(("windows", "amd64")),
+        Some("win-x64"),
+        Some("win-amd64"),
+    ),
+    target(
+        "x86_64-pc-windows-gnu",
+        &["windows-amd64-gnu", "win-x64-gnu"],
+        Some(("windows", "amd64")),
+        None,
+        None,
+    ),
+    target(
+        "aarch64-pc-windows-msvc",
+        &["windows-arm64", "win-arm64"],
+        Some(("windows", "arm64")),
+        Some("win-arm64"),
+        Some("win-arm64"),
+    ),
+    target(
+        "i686-pc-windows-msvc",
+        &["windows-386", "win-x86"],
+        Some(("windows", "386")),
+        None,
+        Some("win32"),
+    ),
+    target(
+        "x86_64-unknown-freebsd",
+        &["freebsd-amd64", "freebsd-x64"],
+        Some(("freebsd", "amd64")),
+        None,
+        None,
+    ),
+    target(
+        "wasm32-wasip1",
+        &["wasip1-wasm", "wasi"],
+        Some(("wasip1", "wasm")),
+        None,
+        None,
+    ),
+];
+
+/// The registry entry `name` refers to under any of its names, ignoring
+/// case. `native` is the host and has none.
+pub fn resolve(name: &str) -> Option<&'static TargetInfo> {
+    let name = name.trim().to_ascii_lowercase();
+    // pkg targets may pin a Node version: node18-linux-x64
+    let name = match name.split_once('-') {
+        Some((node, rest)) if node.starts_with("node") && node[4..].parse::<u32>().is_ok() => {
+            rest.to_string()
+        }
+        _ => name,
+    };
+    let go = name.replace('/', "-");
+    TARGETS
+        .iter()
+        .find(|t| t.names().any(|n| n == name))
+        .or_else(|| {
+            TARGETS
+                .iter()
+                .find(|t| t.go.is_some_and(|(os, arch)| go == format!("{os}-{arch}")))
+        })
+}
+
+/// The registry entry for the machine shippo runs on.
+pub fn host() -> Option<&'static TargetInfo> {
+    let os = match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        "powerpc64" => "ppc64le",
+        arch => arch,
+    };
+    resolve(&format!("{os}-{arch}"))
+}
+
+/// `target` as `{target}` renders it under `[package] target_style`:
+/// `triple` gives the rustc triple, `goish` the `os-arch` form such as
+/// `linux-amd64`, and `native` becomes the host. Without a style, and for
+/// targets outside the registry, the name is kept as written.
+pub fn styled(target: &str, style: Option<&str>) -> String {
+    let info = match target {
+        "native" => host(),
+        _ => resolve(target),
+    };
+    match (style, info) {
+        (Some("triple"), Some(info)) => info.triple.to_string(),
+        (Some("goish"), Some(info)) => info.normalized().to_string(),
+        _ => target.to_string(),
+    }
+}
+
+/// The closest known name to an unknown `name`, for "did you mean".
+pub fn suggest(name: &str) -> Option<&'static str> {
+    let name = name.to_ascii_lowercase();
+    let (distance, best) = TARGETS
+        .iter()
+        .flat_map(|t| t.names())
+        .map(|n| (edit_distance(&name, n), n))
+        .min()?;
+    (distance <= (name.len() / 4).max(2)).then_some(best)
+}
+
+/// Whether the toolchain of `project_type` lists `target`: `rustc --print
+/// target-list` for Rust and `go tool dist list` for Go, where `linux-amd64`
+/// and `linux/amd64` are the same. `None` without the toolchain, and for
+/// ecosystems whose tools list no targets.
+pub fn toolchain_knows(project_type: &ProjectType, target: &str) -> Option<bool> {
+    static RUST: OnceLock<Option<Vec<String>>> = OnceLock::new();
+    static GO: OnceLock<Option<Vec<String>>> = OnceLock::new();
+    let (known, name) = match project_type {
+        ProjectType::Rust => (
+            RUST.get_or_init(|| listed("rustc", &["--print", "target-list"])),
+            target.to_string(),
+        ),
+        ProjectType::Go => (
+            GO.get_or_init(|| listed("go", &["tool", "dist", "list"])),
+            target.replacen('-', "/", 1),
+        ),
+        _ => return None,
+    };
+    known.as_ref().map(|k| k.contains(&name))
+}
+
+fn listed(program: &str, args: &[&str]) -> Option<Vec<String>> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let names: Vec<String> = stdout.lines().map(|l| l.trim().to_string()).collect();
+    (output.status.success() && !names.is_empty()).then_some(names)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_resolve_per_ecosystem() {
+        let linux = resolve("linux-x64").unwrap();
+        assert_eq!(linux.triple, "x86_64-unknown-linux-gnu");
+        assert_eq!(resolve("linux/amd64"), Some(linux));
+        assert_eq!(resolve("node18-linux-x64"), Some(linux));
+        assert_eq!(resolve("X86_64-unknown-linux-gnu"), Some(linux));
+        assert_eq!(linux.go, Some(("linux", "amd64")));
+
+        let mac = resolve("macosx-arm64").unwrap();
+        assert_eq!(mac.triple, "aarch64-apple-darwin");
+        assert_eq!(mac.pkg, Some("macos-arm64"));
+        assert_eq!(mac.pyinstaller_arch(), Some("arm64"));
+        assert_eq!(mac.normalized(), "darwin-arm64");
+        assert_eq!(resolve("win32").unwrap().go, Some(("windows", "386")));
+        assert_eq!(resolve("linux-amd64-musl").unwrap().pkg, Some("alpine-x64"));
+
+        assert_eq!(resolve("native"), None);
+        assert_eq!(suggest("linx-x64"), Some("linux-x64"));
+        assert_eq!(
+            suggest("x86_64-unknown-linux-gun"),
+            Some("x86_64-unknown-linux-gnu")
+        );
+        assert_eq!(suggest("thumbv7em-none-eabihf"), None);
+    }
+
+    #[test]
+    fn test_target_styles() {
+        for target in ["x86_64-unknown-linux-gnu", "linux-x64", "linux/amd64"] {
+            assert_eq!(styled(target, Some("goish")), "linux-amd64");
+            assert_eq!(styled(target, Some("triple")), "x86_64-unknown-linux-gnu");
+        }
+        assert_eq!(styled("macos-arm64", None), "macos-arm64");
+        assert_eq!(
+            styled("thumbv7em-none-eabihf", Some("goish")),
+            "thumbv7em-none-eabihf"
+        );
+        if let Some(host) = host() {
+            assert_eq!(styled("native", Some("triple")), host.triple);
+        }
+    }
+}
@@ -0,0 +1,623 @@
+//! Helper tools installed on demand with `[tools] auto_install`.
+//!
+//! [`needed`] lists the helpers a plan calls out to (cross, pkg,
+//! pyinstaller, cosign, plus any listed in `require`), and [`Toolbox`]
+//! installs the ones missing from `PATH` at pinned versions into
+//! `<dir>/bin`, which [`ToolsExecutor`] then puts first on the `PATH` of
+//! every command:
+//!
+//! - `cross` and `cargo-zigbuild` with `cargo install --locked`, which checks
+//!   each crate against the registry checksum;
+//! - `pkg` with `npm ci` from the pin's `package-lock.json`, which checks
+//!   every package against its recorded integrity;
+//! - `pyinstaller` into its own virtualenv with `pip install
+//!   --require-hashes` from the pin's requirements file;
+//! - `cosign` as a release binary, checked against the pin's SHA-256. A
+//!   checksums file from the same release would be swapped along with the
+//!   binary, so there is no default.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::exec::{CommandOutput, CommandSpec, Executor};
+use crate::{sha256_file, Plan, ToolsConfig};
+
+/// How a tool is installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Method {
+    Cargo,
+    Npm,
+    Pip,
+    Release,
+}
+
+/// A helper shippo knows how to install, at its default version.
+#[derive(Debug, Clone, Copy)]
+pub struct Tool {
+    pub name: &'static str,
+    pub version: &'static str,
+    method: Method,
+}
+
+pub const TOOLS: &[Tool] = &[
+    Tool {
+        name: "cross",
+        version: "0.2.5",
+        method: Method::Cargo,
+    },
+    Tool {
+        name: "cargo-zigbuild",
+        version: "0.19.8",
+        method: Method::Cargo,
+    },
+    Tool {
+        name: "pkg",
+        version: "5.8.1",
+        method: Method::Npm,
+    },
+    Tool {
+        name: "pyinstaller",
+        version: "6.11.1",
+        method: Method::Pip,
+    },
+    Tool {
+        name: "cosign",
+        version: "2.4.1",
+        method: Method::Release,
+    },
+];
+
+/// Versions installed into the tools directory, by tool.
+const INSTALLED_FILE: &str = "installed.json";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ToolError {
+    #[error(
+        "unknown tool {0}; shippo can install cross, cargo-zigbuild, pkg, pyinstaller and cosign"
+    )]
+    Unknown(String),
+    #[error("installing {tool} {version} failed: {message}")]
+    Install {
+        tool: String,
+        version: String,
+        message: String,
+    },
+    #[error("installing {tool} needs [tools.pins.{tool}] lockfile, {kind} with a hash for every package")]
+    Unpinned { tool: String, kind: &'static str },
+    #[error("installing {tool} needs [tools.pins.{tool}] sha256, the SHA-256 of {file}")]
+    UnpinnedHash { tool: String, file: String },
+    #[error("{tool} {version} download has SHA-256 {actual}, expected {expected}")]
+    Checksum {
+        tool: String,
+        version: String,
+        expected: String,
+        actual: String,
+    },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+pub fn tool(name: &str) -> Option<&'static Tool> {
+    TOOLS.iter().find(|t| t.name == name)
+}
+
+/// The helpers `plan` runs, in [`TOOLS`] order: `cross` when
+/// `SHIPPO_USE_CROSS` asks for it, `pkg` for Node binaries, `pyinstaller`
+/// and `cosign` when configured, and anything in `[tools] require`.
+pub fn needed(plan: &Plan, config: &ToolsConfig) -> Vec<&'static str> {
+    let uses = |name: &str| -> bool {
+        config.require.iter().any(|r| r == name)
+            || plan.packages.iter().any(|pkg| match name {
+                "cross" => {
+                    std::env::var_os("SHIPPO_USE_CROSS").is_some()
+                        && pkg.project_type == crate::ProjectType::Rust
+                        && pkg.targets.iter().any(|t| t != "native")
+                }
+                "pkg" => {
+                    pkg.project_type == crate::ProjectType::Node && {
+                        let node = pkg.node.clone().unwrap_or_default();
+                        node.mode != "frontend"
+                            && !matches!(&node.binary, Some(b) if b.tool != "pkg")
+                    }
+                }
+                "pyinstaller" => pkg.python.as_ref().is_some_and(|p| p.mode == "pyinstaller"),
+                "cosign" => pkg.sign.enabled && pkg.sign.method == "cosign",
+                _ => false,
+            })
+    };
+    TOOLS
+        .iter()
+        .map(|t| t.name)
+        .filter(|name| uses(name))
+        .collect()
+}
+
+/// Installs helpers into a directory; see the [module docs](self).
+pub struct Toolbox<'a> {
+    dir: PathBuf,
+    config: &'a ToolsConfig,
+    executor: &'a dyn Executor,
+    /// Where tools installed some other way are looked for.
+    path: Option<OsString>,
+}
+
+impl<'a> Toolbox<'a> {
+    /// `dir` is `[tools] dir`, or `tools` in the artifact cache root;
+    /// `config` is [resolved](ToolsConfig::resolve).
+    pub fn new(dir: PathBuf, config: &'a ToolsConfig, executor: &'a dyn Executor) -> Self {
+        Self {
+            dir,
+            config,
+            executor,
+            path: executor.var("PATH").map(OsString::from),
+        }
+    }
+
+    /// Looks for tools installed some other way on `path` instead of
+    /// `PATH`.
+    pub fn with_path(mut self, path: Option<OsString>) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Directory to put first on `PATH`.
+    pub fn bin_dir(&self) -> PathBuf {
+        self.dir.join("bin")
+    }
+
+    /// The version `name` is installed at: its `[tools.pins]` entry or
+    /// the default.
+    pub fn version(&self, name: &str) -> Result<String, ToolError> {
+        let tool = tool(name).ok_or_else(|| ToolError::Unknown(name.to_string()))?;
+        Ok(self
+            .config
+            .pins
+            .get(name)
+            .map_or(tool.version, |p| p.version.as_str())
+            .to_string())
+    }
+
+    /// Installs `name` unless the tools directory has it at the pinned
+    /// version, or it is found elsewhere on `PATH`. Returns the version
+    /// installed, if it was.
+    pub fn ensure(&self, name: &str) -> Result<Option<String>, ToolError> {
+        let tool = tool(name).ok_or_else(|| ToolError::Unknown(name.to_string()))?;
+        let version = self.version(name)?;
+        let bin = self.bin_dir().join(exe(name));
+        let mut installed = self.installed();
+        if bin.exists() {
+            if installed.get(name) == Some(&version) {
+                return Ok(None);
+            }
+        } else if self.on_path(name) {
+            return Ok(None);
+        }
+        let failed = |message: String| ToolError::Install {
+            tool: name.to_string(),
+            version: version.clone(),
+            message,
+        };
+        fs::create_dir_all(self.bin_dir())?;
+        match tool.method {
+            Method::Cargo => {
+                let mut cmd = CommandSpec::new("cargo");
+                cmd.args([
+                    "install",
+                    name,
+                    "--version",
+                    &version,
+                    "--locked",
+                    "--force",
+                ])
+                .arg("--root")
+                .arg(self.dir.to_string_lossy());
+                self.run(&cmd).map_err(failed)?;
+            }
+            Method::Npm => {
+                let lockfile = self.lockfile(name, "a package-lock.json")?;
+                // a project of its own whose only dependency is the tool
+                let root = self.dir.join(name);
+                fs::create_dir_all(&root)?;
+                fs::copy(lockfile, root.join("package-lock.json"))?;
+                let package = serde_json::json!({
+                    "name": format!("shippo-{name}"),
+                    "private": true,
+                    "dependencies": { name: version },
+                });
+                fs::write(root.join("package.json"), package.to_string())?;
+                let mut cmd = CommandSpec::new("npm");
+                cmd.args(["ci", "--no-audit", "--no-fund"])
+                    .current_dir(&root);
+                self.run(&cmd).map_err(failed)?;
+                link(&root.join("node_modules").join(".bin").join(name), &bin)?;
+            }
+            Method::Pip => {
+                let lockfile = self.lockfile(name, "a requirements file")?;
+                let pinned = fs::read_to_string(lockfile)?.lines().any(|line| {
+                    line.split_whitespace()
+                        .next()
+                        .is_some_and(|req| req.eq_ignore_ascii_case(&format!("{name}=={version}")))
+                });
+                if !pinned {
+                    return Err(failed(format!("{lockfile} does not pin {name}=={version}")));
+                }
+                let venv = self.dir.join(name);
+                let mut cmd = CommandSpec::new("python");
+                cmd.args(["-m", "venv"]).arg(venv.to_string_lossy());
+                self.run(&cmd).map_err(failed)?;
+                let scripts = venv.join(if cfg!(windows) { "Scripts" } else { "bin" });
+                let mut cmd = CommandSpec::new(scripts.join(exe("python")).to_string_lossy());
+                cmd.args(["-m", "pip", "install", "--disable-pip-version-check"])
+                    .args(["--require-hashes", "-r", lockfile]);
+                self.run(&cmd).map_err(failed)?;
+                link(&scripts.join(exe(name)), &bin)?;
+            }
+            Method::Release => self.download_cosign(&version, &bin)?,
+        }
+        if !bin.exists() {
+            return Err(failed(format!("{} was not created", bin.display())));
+        }
+        installed.insert(name.to_string(), version.clone());
+        let json = serde_json::to_string_pretty(&installed).map_err(io::Error::other)?;
+        fs::write(self.dir.join(INSTALLED_FILE), json)?;
+        Ok(Some(version))
+    }
+
+    /// The pin's lockfile for `name`, which must have one.
+    fn lockfile(&self, name: &str, kind: &'static str) -> Result<&str, ToolError> {
+        let pin = self.config.pins.get(name);
+        pin.and_then(|p| p.lockfile.as_deref())
+            .ok_or_else(|| ToolError::Unpinned {
+                tool: name.to_string(),
+                kind,
+            })
+    }
+
+    /// Whether `name` is on the search path outside the tools directory.
+    fn on_path(&self, name: &str) -> bool {
+        self.path.as_ref().is_some_and(|path| {
+            std::env::split_paths(path)
+                .filter(|dir| *dir != self.bin_dir())
+                .any(|dir| dir.join(exe(name)).is_file())
+        })
+    }
+
+    fn installed(&self) -> BTreeMap<String, String> {
+        fs::read_to_string(self.dir.join(INSTALLED_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn run(&self, cmd: &CommandSpec) -> Result<(), String> {
+        let output = self.executor.output(cmd).map_err(|e| e.to_string())?;
+        if output.success() {
+            Ok(())
+        } else {
+            Err(format!(
+                "{cmd} exited with {}: {}",
+                output.status(),
+                output.stderr.trim()
+            ))
+        }
+    }
+
+    fn download_cosign(&self, version: &str, bin: &Path) -> Result<(), ToolError> {
+        let failed = |message: String| ToolError::Install {
+            tool: "cosign".into(),
+            version: version.to_string(),
+            message,
+        };
+        let os = match std::env::consts::OS {
+            "macos" => "darwin",
+            os => os,
+        };
+        let arch = match std::env::consts::ARCH {
+            "x86_64" => "amd64",
+            "aarch64" => "arm64",
+            arch => arch,
+        };
+        let file = format!("cosign-{os}-{arch}{}", std::env::consts::EXE_SUFFIX);
+        let pin = self.config.pins.get("cosign");
+        let expected =
+            pin.and_then(|p| p.sha256.clone())
+                .ok_or_else(|| ToolError::UnpinnedHash {
+                    tool: "cosign".into(),
+                    file: format!("{file} from cosign v{version}"),
+                })?;
+        let base = format!("https://github.com/sigstore/cosign/releases/download/v{version}");
+        let downloads = self.dir.join("downloads");
+        fs::create_dir_all(&downloads)?;
+        let download = downloads.join(format!("{version}-{file}"));
+        let mut cmd = CommandSpec::new("curl");
+        cmd.args(["-fsSL", "-o"])
+            .arg(download.to_string_lossy())
+            .arg(format!("{base}/{file}"));
+        self.run(&cmd).map_err(failed)?;
+        let actual = sha256_file(&download)?;
+        if !actual.eq_ignore_ascii_case(&expected) {
+            let _ = fs::remove_file(&download);
+            return Err(ToolError::Checksum {
+                tool: "cosign".into(),
+                version: version.to_string(),
+                expected,
+                actual,
+            });
+        }
+        fs::rename(&download, bin)?;
+        set_executable(bin)?;
+        Ok(())
+    }
+}
+
+/// Runs commands with a [`Toolbox`]'s `bin` directory first on their
+/// `PATH`, leaving Shippo's own environment alone.
+pub struct ToolsExecutor<E> {
+    inner: E,
+    bin: Option<PathBuf>,
+}
+
+impl<E: Executor> ToolsExecutor<E> {
+    /// `bin` is [`Toolbox::bin_dir`]; without one commands are run as
+    /// they are.
+    pub fn new(inner: E, bin: Option<PathBuf>) -> Self {
+        Self { inner, bin }
+    }
+
+    fn with_path<'c>(&self, cmd: &'c CommandSpec) -> io::Result<Cow<'c, CommandSpec>> {
+        let Some(bin) = &self.bin else {
+            return Ok(Cow::Borrowed(cmd));
+        };
+        let path = match cmd.env.get("PATH") {
+            Some(path) => Some(path.clone()),
+            None if cmd.clear_env => None,
+            None => self.inner.var("PATH"),
+        }
+        .unwrap_or_default();
+        if std::env::split_paths(&path).any(|dir| dir == *bin) {
+            return Ok(Cow::Borrowed(cmd));
+        }
+        let path =
+            std::env::join_paths(std::iter::once(bin.clone()).chain(std::env::split_paths(&path)))
+                .map_err(io::Error::other)?;
+        let mut cmd = cmd.clone();
+        cmd.env("PATH", path.to_string_lossy());
+        Ok(Cow::Owned(cmd))
+    }
+}
+
+impl<E: Executor> Executor for ToolsExecutor<E> {
+    fn run(&self, cmd: &CommandSpec) -> io::Result<CommandOutput> {
+        self.inner.run(&*self.with_path(cmd)?)
+    }
+
+    fn output(&self, cmd: &CommandSpec) -> io::Result<CommandOutput> {
+        self.inner.output(&*self.with_path(cmd)?)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.inner.var(name)
+    }
+}
+
+fn exe(name: &str) -> String {
+    format!("{name}{}", std::env::consts::EXE_SUFFIX)
+}
+
+#[cfg(unix)]
+fn link(target: &Path, link: &Path) -> io::Result<()> {
+    if link.symlink_metadata().is_ok() {
+        fs::remove_file(link)?;
+    }
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(not(unix))]
+fn link(target: &Path, link: &Path) -> io::Result<()> {
+    fs::copy(target, link).map(|_| ())
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::MockExecutor;
+    use crate::ToolPin;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_ensure_installs_pinned_version_once() {
+        let dir = tempdir().unwrap();
+        let mock = MockExecutor::new();
+        let config = ToolsConfig {
+            auto_install: true,
+            pins: [(
+                "cargo-zigbuild".to_string(),
+                ToolPin {
+                    version: "0.19.0".into(),
+                    sha256: None,
+                    lockfile: None,
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let toolbox = Toolbox::new(dir.path().to_path_buf(), &config, &mock).with_path(None);
+        // stands in for what `cargo install` writes
+        fs::create_dir_all(toolbox.bin_dir()).unwrap();
+        fs::write(toolbox.bin_dir().join(exe("cargo-zigbuild")), "bin").unwrap();
+        assert_eq!(
+            toolbox.ensure("cargo-zigbuild").unwrap().as_deref(),
+            Some("0.19.0")
+        );
+        assert_eq!(toolbox.ensure("cargo-zigbuild").unwrap(), None);
+        let calls = mock.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].program, "cargo");
+        assert!(calls[0].args.starts_with(&[
+            "install".into(),
+            "cargo-zigbuild".into(),
+            "--version".into(),
+            "0.19.0".into()
+        ]));
+        assert!(matches!(toolbox.ensure("make"), Err(ToolError::Unknown(_))));
+    }
+
+    #[test]
+    fn test_cosign_download_checked_against_pin() {
+        let dir = tempdir().unwrap();
+        let mock = MockExecutor::new();
+        let config = ToolsConfig {
+            auto_install: true,
+            pins: [(
+                "cosign".to_string(),
+                ToolPin {
+                    version: "2.4.1".into(),
+                    sha256: Some("00".repeat(32)),
+                    lockfile: None,
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let unpinned = ToolsConfig::default();
+        let toolbox = Toolbox::new(dir.path().to_path_buf(), &unpinned, &mock).with_path(None);
+        let err = toolbox.ensure("cosign").unwrap_err();
+        assert!(matches!(err, ToolError::UnpinnedHash { .. }), "{err}");
+        assert!(
+            err.to_string().contains("[tools.pins.cosign] sha256"),
+            "{err}"
+        );
+        assert!(mock.calls().is_empty());
+
+        let toolbox = Toolbox::new(dir.path().to_path_buf(), &config, &mock).with_path(None);
+        let downloads = dir.path().join("downloads");
+        fs::create_dir_all(&downloads).unwrap();
+        for entry in ["linux-amd64", "linux-arm64", "darwin-amd64", "darwin-arm64"] {
+            fs::write(downloads.join(format!("2.4.1-cosign-{entry}")), "tampered").unwrap();
+        }
+        let err = toolbox.ensure("cosign").unwrap_err();
+        assert!(matches!(err, ToolError::Checksum { .. }), "{err}");
+        assert!(!toolbox.bin_dir().join("cosign").exists());
+        assert!(mock.calls()[0]
+            .args
+            .last()
+            .unwrap()
+            .starts_with("https://github.com/sigstore/cosign/releases/download/v2.4.1/cosign-"));
+    }
+
+    #[test]
+    fn test_tools_executor_puts_bin_first_on_path() {
+        let mock = MockExecutor::new();
+        let path = std::env::join_paths(["/usr/bin", "/bin"]).unwrap();
+        mock.set_var("PATH", &path.to_string_lossy());
+        let bin = PathBuf::from("/cache/tools/bin");
+        let tools = ToolsExecutor::new(&mock, Some(bin.clone()));
+        tools.run(&CommandSpec::new("cross")).unwrap();
+        let mut own = CommandSpec::new("cosign");
+        own.env("PATH", "/opt/bin");
+        tools.output(&own).unwrap();
+        ToolsExecutor::new(&mock, None)
+            .run(&CommandSpec::new("cross"))
+            .unwrap();
+        let calls = mock.calls();
+        let path = |i: usize| std::env::split_paths(&calls[i].env["PATH"]).collect::<Vec<_>>();
+        assert_eq!(path(0), [bin.clone(), "/usr/bin".into(), "/bin".into()]);
+        assert_eq!(path(1), [bin, "/opt/bin".into()]);
+        assert!(!calls[2].env.contains_key("PATH"));
+    }
+
+    #[test]
+    fn test_npm_and_pip_tools_install_from_hashed_lockfiles() {
+        let dir = tempdir().unwrap();
+        let mock = MockExecutor::new();
+        let unpinned = ToolsConfig::default();
+        let toolbox = Toolbox::new(dir.path().to_path_buf(), &unpinned, &mock).with_path(None);
+        let err = toolbox.ensure("pkg").unwrap_err();
+        assert!(matches!(err, ToolError::Unpinned { .. }), "{err}");
+        assert!(err.to_string().contains("package-lock.json"), "{err}");
+        assert!(mock.calls().is_empty());
+
+        let locks = dir.path().join("locks");
+        fs::create_dir_all(&locks).unwrap();
+        fs::write(locks.join("pkg-lock.json"), "{}").unwrap();
+        fs::write(
+            locks.join("pyinstaller.txt"),
+            "pyinstaller==6.11.1 \\\n    --hash=sha256:abcd\naltgraph==0.17.4 --hash=sha256:ef01\n",
+        )
+        .unwrap();
+        let pin = |version: &str, lockfile: &str| ToolPin {
+            version: version.into(),
+            sha256: None,
+            lockfile: Some(lockfile.into()),
+        };
+        let config = ToolsConfig {
+            pins: [
+                ("pkg".to_string(), pin("5.8.1", "locks/pkg-lock.json")),
+                (
+                    "pyinstaller".to_string(),
+                    pin("6.11.1", "locks/pyinstaller.txt"),
+                ),
+            ]
+            .into(),
+            ..Default::default()
+        }
+        .resolve(dir.path());
+        let tools = dir.path().join("tools");
+        let toolbox = Toolbox::new(tools.clone(), &config, &mock).with_path(None);
+        // stand in for what npm and pip install
+        let scripts = if cfg!(windows) { "Scripts" } else { "bin" };
+        for installed in [
+            tools.join("pkg/node_modules/.bin/pkg"),
+            tools
+                .join("pyinstaller")
+                .join(scripts)
+                .join(exe("pyinstaller")),
+        ] {
+            fs::create_dir_all(installed.parent().unwrap()).unwrap();
+            fs::write(installed, "bin").unwrap();
+        }
+        toolbox.ensure("pkg").unwrap();
+        toolbox.ensure("pyinstaller").unwrap();
+        let calls = mock.calls();
+        assert_eq!(calls[0].to_string(), "npm ci --no-audit --no-fund");
+        assert_eq!(calls[0].cwd.as_deref(), Some(tools.join("pkg").as_path()));
+        let package: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(tools.join("pkg/package.json")).unwrap())
+                .unwrap();
+        assert_eq!(package["dependencies"]["pkg"], "5.8.1");
+        assert!(tools.join("pkg/package-lock.json").exists());
+        assert!(calls[2].args.ends_with(&[
+            "--require-hashes".into(),
+            "-r".into(),
+            config.pins["pyinstaller"].lockfile.clone().unwrap()
+        ]));
+
+        // the requirements must pin the version installed
+        let other = ToolsConfig {
+            pins: [(
+                "pyinstaller".to_string(),
+                pin("6.0.0", "locks/pyinstaller.txt"),
+            )]
+            .into(),
+            ..Default::default()
+        }
+        .resolve(dir.path());
+        let toolbox = Toolbox::new(tools, &other, &mock).with_path(None);
+        let err = toolbox.ensure("pyinstaller").unwrap_err().to_string();
+        assert!(err.contains("does not pin pyinstaller==6.0.0"), "{err}");
+    }
+}
@@ -0,0 +1,149 @@
+//! `[attest]`: in-toto statements naming a target's archives and SBOMs by
+//! digest, with the release's build metadata as a SLSA provenance
+//! predicate, in DSSE envelopes for policy engines such as in-toto or
+//! witness.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+use shippo_core::{Manifest, ManifestTarget};
+
+/// Suffix of the statement files, one DSSE envelope per line.
+pub const INTOTO_SUFFIX: &str = ".intoto.jsonl";
+
+/// DSSE payload type of in-toto statements.
+pub const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+const PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v1";
+const BUILD_TYPE: &str =
+    "https://github.com/polsala/Shippo/blob/main/docs/signing.md#in-toto-attestations";
+const BUILDER_ID: &str = "https://github.com/polsala/Shippo";
+
+/// The in-toto statement for `target` of `package` in `manifest`, as JSON.
+pub fn statement(manifest: &Manifest, package: &str, target: &ManifestTarget) -> String {
+    let subject: Vec<Value> = target
+        .artifacts
+        .iter()
+        .chain(target.sboms())
+        .map(|a| json!({ "name": a.filename, "digest": { "sha256": a.sha256 } }))
+        .collect();
+    let project = &manifest.project;
+    let mut dependencies = Vec::new();
+    if let (Some(url), Some(commit)) = (&project.repo_url, &project.commit) {
+        dependencies.push(json!({
+            "uri": format!("git+{url}"),
+            "digest": { "gitCommit": commit },
+        }));
+    }
+    for sub in &project.submodules {
+        let uri = sub.url.clone().unwrap_or_else(|| sub.path.clone());
+        dependencies.push(json!({
+            "uri": format!("git+{uri}"),
+            "name": sub.path,
+            "digest": { "gitCommit": sub.commit },
+        }));
+    }
+    let mut metadata = json!({ "finishedOn": manifest.generated_at });
+    if let Some(run_url) = manifest
+        .build_env
+        .ci_run
+        .as_ref()
+        .and_then(|run| run.run_url.as_ref())
+    {
+        metadata["invocationId"] = json!(run_url);
+    }
+    let statement = json!({
+        "_type": STATEMENT_TYPE,
+        "subject": subject,
+        "predicateType": PREDICATE_TYPE,
+        "predicate": {
+            "buildDefinition": {
+                "buildType": BUILD_TYPE,
+                "externalParameters": {
+                    "package": package,
+                    "target": target.target,
+                    "version": project.version,
+                    "plan_fingerprint": project.plan_fingerprint,
+                    "config_sha256": project.config_sha256,
+                },
+                "internalParameters": {
+                    "shippo_version": manifest.shippo_version,
+                    "tooling": manifest.tooling,
+                    "build_env": {
+                        "os": manifest.build_env.os,
+                        "arch": manifest.build_env.arch,
+                    },
+                },
+                "resolvedDependencies": dependencies,
+            },
+            "runDetails": {
+                "builder": {
+                    "id": BUILDER_ID,
+                    "version": { "shippo": manifest.shippo_version },
+                },
+                "metadata": metadata,
+            },
+        },
+    });
+    statement.to_string()
+}
+
+/// DSSE's pre-authentication encoding of `payload`: what its signatures
+/// sign, so the payload type can't be swapped.
+pub fn pae(payload: &[u8]) -> Vec<u8> {
+    let mut out = format!(
+        "DSSEv1 {} {PAYLOAD_TYPE} {} ",
+        PAYLOAD_TYPE.len(),
+        payload.len()
+    )
+    .into_bytes();
+    out.extend_from_slice(payload);
+    out
+}
+
+/// The statement file's line: a DSSE envelope of `statement` with
+/// `signatures`, each a raw signature of the statement's [`pae`].
+pub fn envelope(statement: &str, signatures: &[Vec<u8>]) -> String {
+    let signatures: Vec<Value> = signatures
+        .iter()
+        .map(|sig| json!({ "keyid": "", "sig": STANDARD.encode(sig) }))
+        .collect();
+    let envelope = json!({
+        "payloadType": PAYLOAD_TYPE,
+        "payload": STANDARD.encode(statement),
+        "signatures": signatures,
+    });
+    format!("{envelope}\n")
+}
+
+/// The [`pae`] of the statement in an envelope line, which is what a
+/// signature of the statement file signs.
+pub fn envelope_pae(line: &str) -> Option<Vec<u8>> {
+    let envelope: Value = serde_json::from_str(line).ok()?;
+    if envelope["payloadType"] != PAYLOAD_TYPE {
+        return None;
+    }
+    let payload = STANDARD.decode(envelope["payload"].as_str()?).ok()?;
+    Some(pae(&payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope() {
+        assert_eq!(
+            pae(b"hello world"),
+            b"DSSEv1 28 application/vnd.in-toto+json 11 hello world"
+        );
+        let line = envelope("{}", &[b"sig".to_vec()]);
+        assert!(line.ends_with('\n') && line.lines().count() == 1);
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["payload"], "e30=");
+        assert_eq!(value["signatures"][0]["sig"], "c2ln");
+        assert_eq!(envelope_pae(&line), Some(pae(b"{}")));
+        assert_eq!(envelope_pae("{}"), None);
+    }
+}
@@ -0,0 +1,581 @@
+//! Reads the certificate and transparency-log entry out of cosign bundles,
+//! so keyless signatures stay checkable after their certificate expired,
+//! and checks the entry's inclusion proof against the log's root hash.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Fulcio certificates live for minutes; anything shorter than this only
+/// proves a signature while the certificate is valid, unless a log entry
+/// records when it was made.
+const SHORT_LIVED_HOURS: i64 = 24;
+
+/// Subject alternative name, where Fulcio puts the signer.
+const OID_SAN: &[u8] = &[0x55, 0x1d, 0x11];
+/// Fulcio's OIDC issuer extensions, 1.3.6.1.4.1.57264.1.8 (a UTF8String)
+/// and the deprecated 1.3.6.1.4.1.57264.1.1 (raw bytes).
+const OID_ISSUER: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0xbf, 0x30, 0x01, 0x08];
+const OID_ISSUER_V1: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0xbf, 0x30, 0x01, 0x01];
+
+/// What a bundle says about when and with what a blob was signed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleInfo {
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    /// Who the certificate was issued to: a workflow URL or an email.
+    pub identity: Option<String>,
+    /// The OIDC issuer that vouched for `identity`.
+    pub issuer: Option<String>,
+    pub tlog: Option<TlogEntry>,
+}
+
+/// A rekor entry for the signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlogEntry {
+    pub integrated_time: DateTime<Utc>,
+    pub log_index: Option<u64>,
+    /// Identifies the log shard; rekor rotates shards over time.
+    pub log_id: Option<String>,
+    /// A signed entry timestamp or inclusion proof, which lets the entry be
+    /// checked offline, even once its shard is frozen.
+    pub promise: bool,
+    /// Hex Merkle leaf hash of the logged entry, which rekor uses as its
+    /// UUID; `None` when the bundle doesn't carry the entry's body.
+    pub uuid: Option<String>,
+    pub proof: Option<InclusionProof>,
+}
+
+/// Proof that an entry is a leaf of the log's Merkle tree (RFC 9162).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    /// Index of the leaf in the shard's tree, unlike the entry's global
+    /// `log_index`.
+    pub log_index: u64,
+    pub tree_size: u64,
+    pub root_hash: Vec<u8>,
+    pub hashes: Vec<Vec<u8>>,
+    /// The signed note naming the tree size and root hash.
+    pub checkpoint: Option<String>,
+}
+
+impl BundleInfo {
+    /// Parses both the `cosign sign-blob --bundle` format and sigstore
+    /// protobuf bundles.
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(data).map_err(|e| format!("invalid JSON: {e}"))?;
+        let material = value.get("verificationMaterial");
+        let cert = value
+            .get("cert")
+            .or_else(|| material.and_then(|m| m.pointer("/certificate/rawBytes")))
+            .or_else(|| {
+                material.and_then(|m| m.pointer("/x509CertificateChain/certificates/0/rawBytes"))
+            })
+            .and_then(Value::as_str)
+            .ok_or("the bundle holds no certificate; it was signed with a key")?;
+        let der = decode_certificate(cert)?;
+        let (not_before, not_after) = certificate_validity(&der)?;
+        let (identity, issuer) = certificate_identity(&der);
+        let tlog = match (value.get("rekorBundle"), material) {
+            (Some(rekor), _) => Some(TlogEntry {
+                integrated_time: timestamp(rekor.pointer("/Payload/integratedTime"))?,
+                log_index: rekor.pointer("/Payload/logIndex").and_then(Value::as_u64),
+                log_id: string(rekor.pointer("/Payload/logID")),
+                promise: rekor.get("SignedEntryTimestamp").is_some(),
+                uuid: leaf_uuid(rekor.pointer("/Payload/body"))?,
+                proof: None,
+            }),
+            (None, Some(material)) => match material.pointer("/tlogEntries/0") {
+                Some(entry) => Some(TlogEntry {
+                    integrated_time: timestamp(entry.get("integratedTime"))?,
+                    log_index: string(entry.get("logIndex")).and_then(|i| i.parse().ok()),
+                    log_id: string(entry.pointer("/logId/keyId")),
+                    promise: entry.get("inclusionPromise").is_some()
+                        || entry.get("inclusionProof").is_some(),
+                    uuid: leaf_uuid(entry.get("canonicalizedBody"))?,
+                    proof: entry
+                        .get("inclusionProof")
+                        .map(inclusion_proof)
+                        .transpose()?,
+                }),
+                None => None,
+            },
+            (None, None) => None,
+        };
+        Ok(Self {
+            not_before,
+            not_after,
+            identity,
+            issuer,
+            tlog,
+        })
+    }
+
+    pub fn short_lived(&self) -> bool {
+        self.not_after - self.not_before < chrono::Duration::hours(SHORT_LIVED_HOURS)
+    }
+
+    /// `Ok` with a summary when the signature can be trusted at `now`, or
+    /// `Err` with the reason and whether it is only a warning. Short-lived
+    /// certificates without a log entry are warnings: they verify today but
+    /// not in a few years.
+    pub fn check(&self, now: DateTime<Utc>) -> Result<String, (String, bool)> {
+        let window = format!(
+            "certificate valid {} to {}",
+            self.not_before.to_rfc3339(),
+            self.not_after.to_rfc3339()
+        );
+        let Some(tlog) = &self.tlog else {
+            if self.short_lived() {
+                return Err((
+                    format!("{window} and no transparency-log entry records when it was used; the signature proves nothing once it expired"),
+                    true,
+                ));
+            }
+            if now < self.not_before || now > self.not_after {
+                return Err((format!("{window}, not now"), false));
+            }
+            return Ok(window);
+        };
+        let signed = tlog.integrated_time;
+        if signed < self.not_before || signed > self.not_after {
+            return Err((
+                format!("logged at {}, outside the {window}", signed.to_rfc3339()),
+                false,
+            ));
+        }
+        let entry = match (tlog.log_index, &tlog.log_id) {
+            (Some(index), Some(id)) => {
+                format!("rekor entry {index} in log {}", &id[..id.len().min(12)])
+            }
+            (Some(index), None) => format!("rekor entry {index}"),
+            _ => "rekor entry".to_string(),
+        };
+        if !tlog.promise {
+            return Err((
+                format!("{entry} has no signed timestamp or inclusion proof, so it cannot be checked once the log shard rotates"),
+                true,
+            ));
+        }
+        Ok(format!(
+            "{window}; logged at {} ({entry})",
+            signed.to_rfc3339()
+        ))
+    }
+}
+
+impl TlogEntry {
+    /// `Ok` with a summary when the inclusion proof leads from the entry to
+    /// the root hash its checkpoint names. `Err` when it doesn't, or when
+    /// there is no proof to check (only a warning).
+    pub fn check_inclusion(&self) -> Result<String, String> {
+        let Some(proof) = &self.proof else {
+            return Err("the bundle has no inclusion proof".into());
+        };
+        let Some(uuid) = &self.uuid else {
+            return Err("the bundle has no entry body to hash".into());
+        };
+        let leaf = hex::decode(uuid).map_err(|e| e.to_string())?;
+        let root = proof_root(proof.log_index, proof.tree_size, &leaf, &proof.hashes).ok_or_else(
+            || {
+                format!(
+                    "malformed proof for leaf {} of {}",
+                    proof.log_index, proof.tree_size
+                )
+            },
+        )?;
+        if root != proof.root_hash {
+            return Err(format!(
+                "the proof leads to root {}, not {}",
+                hex::encode(root),
+                hex::encode(&proof.root_hash)
+            ));
+        }
+        if let Some(checkpoint) = &proof.checkpoint {
+            // origin, tree size, base64 root hash, then the signatures
+            let mut lines = checkpoint.lines().skip(1);
+            let size = lines.next().and_then(|s| s.trim().parse::<u64>().ok());
+            let root = lines.next().and_then(|s| STANDARD.decode(s.trim()).ok());
+            if size != Some(proof.tree_size) || root.as_deref() != Some(&proof.root_hash[..]) {
+                return Err("the checkpoint names a different tree than the proof".into());
+            }
+        }
+        Ok(format!(
+            "entry {uuid} is leaf {} of {}",
+            proof.log_index, proof.tree_size
+        ))
+    }
+}
+
+/// The root hash an inclusion proof leads to, walking up from `leaf` as in
+/// RFC 9162 section 2.1.3.2.
+fn proof_root(index: u64, size: u64, leaf: &[u8], hashes: &[Vec<u8>]) -> Option<Vec<u8>> {
+    if index >= size {
+        return None;
+    }
+    let (mut fnode, mut snode) = (index, size - 1);
+    let mut root = leaf.to_vec();
+    for hash in hashes {
+        if snode == 0 {
+            return None;
+        }
+        if fnode & 1 == 1 || fnode == snode {
+            root = node_hash(hash, &root);
+            while fnode & 1 == 0 && fnode != 0 {
+                fnode >>= 1;
+                snode >>= 1;
+            }
+        } else {
+            root = node_hash(&root, hash);
+        }
+        fnode >>= 1;
+        snode >>= 1;
+    }
+    (snode == 0).then_some(root)
+}
+
+fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut digest = Sha256::new();
+    digest.update([1]);
+    digest.update(left);
+    digest.update(right);
+    digest.finalize().to_vec()
+}
+
+/// The rekor UUID of a base64 entry body: its Merkle leaf hash.
+fn leaf_uuid(body: Option<&Value>) -> Result<Option<String>, String> {
+    let Some(body) = body.and_then(Value::as_str) else {
+        return Ok(None);
+    };
+    let body = STANDARD
+        .decode(body)
+        .map_err(|e| format!("invalid entry body: {e}"))?;
+    let mut digest = Sha256::new();
+    digest.update([0]);
+    digest.update(body);
+    Ok(Some(hex::encode(digest.finalize())))
+}
+
+/// A protobuf bundle's `inclusionProof`.
+fn inclusion_proof(value: &Value) -> Result<InclusionProof, String> {
+    let number = |key: &str| {
+        string(value.get(key))
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| format!("the inclusion proof has no {key}"))
+    };
+    let decode = |hash: &Value| {
+        STANDARD
+            .decode(hash.as_str().unwrap_or_default())
+            .map_err(|e| format!("invalid inclusion proof hash: {e}"))
+    };
+    Ok(InclusionProof {
+        log_index: number("logIndex")?,
+        tree_size: number("treeSize")?,
+        root_hash: decode(value.get("rootHash").unwrap_or(&Value::Null))?,
+        hashes: value
+            .get("hashes")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .map(decode)
+            .collect::<Result<_, _>>()?,
+        checkpoint: string(value.pointer("/checkpoint/envelope")),
+    })
+}
+
+fn string(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Unix seconds, as a number (cosign) or a string (protobuf JSON).
+fn timestamp(value: Option<&Value>) -> Result<DateTime<Utc>, String> {
+    string(value)
+        .and_then(|s| s.parse::<i64>().ok())
+        .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+        .ok_or_else(|| "the log entry has no integratedTime".to_string())
+}
+
+/// DER bytes from base64 DER or base64 PEM.
+fn decode_certificate(encoded: &str) -> Result<Vec<u8>, String> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| format!("invalid certificate encoding: {e}"))?;
+    let Some(pem) = std::str::from_utf8(&bytes)
+        .ok()
+        .filter(|s| s.starts_with("-----BEGIN"))
+    else {
+        return Ok(bytes);
+    };
+    let body: String = pem.lines().filter(|l| !l.starts_with("-----")).collect();
+    STANDARD
+        .decode(body)
+        .map_err(|e| format!("invalid certificate PEM: {e}"))
+}
+
+/// A DER certificate's `tbsCertificate` fields from its validity on.
+fn certificate_fields(der: &[u8]) -> Option<&[u8]> {
+    let (cert, _) = der_element(der, 0x30)?;
+    let (mut tbs, _) = der_element(cert, 0x30)?;
+    // optional [0] version, then serial number, signature algorithm, issuer
+    if tbs.first() == Some(&0xa0) {
+        tbs = der_element(tbs, 0xa0)?.1;
+    }
+    for tag in [0x02, 0x30, 0x30] {
+        tbs = der_element(tbs, tag)?.1;
+    }
+    Some(tbs)
+}
+
+/// `notBefore` and `notAfter` of a DER certificate.
+fn certificate_validity(der: &[u8]) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    let invalid = || "malformed certificate".to_string();
+    let tbs = certificate_fields(der).ok_or_else(invalid)?;
+    let (validity, _) = der_element(tbs, 0x30).ok_or_else(invalid)?;
+    let (not_before, rest) = der_time(validity).ok_or_else(invalid)?;
+    let (not_after, _) = der_time(rest).ok_or_else(invalid)?;
+    Ok((not_before, not_after))
+}
+
+/// The signer named in a Fulcio certificate's subject alternative name (a
+/// URI or email) and the OIDC issuer that vouched for it.
+fn certificate_identity(der: &[u8]) -> (Option<String>, Option<String>) {
+    let extensions = certificate_extensions(der).unwrap_or_default();
+    let value = |oid: &[u8]| {
+        extensions
+            .iter()
+            .find(|(found, _)| *found == oid)
+            .map(|(_, value)| *value)
+    };
+    let identity = value(OID_SAN).and_then(|value| {
+        let (mut names, _) = der_element(value, 0x30)?;
+        while let Some(&tag) = names.first() {
+            let (name, rest) = der_element(names, tag)?;
+            // [6] uniformResourceIdentifier or [1] rfc822Name
+            if tag == 0x86 || tag == 0x81 {
+                return String::from_utf8(name.to_vec()).ok();
+            }
+            names = rest;
+        }
+        None
+    });
+    let issuer = value(OID_ISSUER)
+        .and_then(|value| der_element(value, 0x0c).map(|(text, _)| text))
+        .or_else(|| value(OID_ISSUER_V1))
+        .and_then(|text| String::from_utf8(text.to_vec()).ok());
+    (identity, issuer)
+}
+
+/// The OIDs and values of a DER certificate's extensions.
+fn certificate_extensions(der: &[u8]) -> Option<Vec<(&[u8], &[u8])>> {
+    let mut fields = certificate_fields(der)?;
+    // validity, subject, subject public key info, then optional unique IDs
+    for _ in 0..3 {
+        fields = der_element(fields, 0x30)?.1;
+    }
+    while let Some(&tag) = fields.first() {
+        let (contents, rest) = der_element(fields, tag)?;
+        if tag != 0xa3 {
+            fields = rest;
+            continue;
+        }
+        let (mut list, _) = der_element(contents, 0x30)?;
+        let mut extensions = Vec::new();
+        while !list.is_empty() {
+            let (extension, rest) = der_element(list, 0x30)?;
+            let (oid, mut extension) = der_element(extension, 0x06)?;
+            // skip the critical flag
+            if extension.first() == Some(&0x01) {
+                extension = der_element(extension, 0x01)?.1;
+            }
+            extensions.push((oid, der_element(extension, 0x04)?.0));
+            list = rest;
+        }
+        return Some(extensions);
+    }
+    None
+}
+
+/// Splits a DER element with the given tag into its contents and the rest.
+fn der_element(data: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    if *data.first()? != tag {
+        return None;
+    }
+    let first = *data.get(1)? as usize;
+    let (len, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let count = first & 0x7f;
+        let len = data
+            .get(2..2 + count)?
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (len, 2 + count)
+    };
+    let end = header.checked_add(len)?;
+    Some((data.get(header..end)?, data.get(end..)?))
+}
+
+/// A UTCTime or GeneralizedTime.
+fn der_time(data: &[u8]) -> Option<(DateTime<Utc>, &[u8])> {
+    let (format, tag) = match data.first()? {
+        0x17 => ("%y%m%d%H%M%SZ", 0x17),
+        0x18 => ("%Y%m%d%H%M%SZ", 0x18),
+        _ => return None,
+    };
+    let (contents, rest) = der_element(data, tag)?;
+    let time = NaiveDateTime::parse_from_str(std::str::from_utf8(contents).ok()?, format).ok()?;
+    Some((time.and_utc(), rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Self-signed, valid 2024-01-01 12:00 to 12:10 UTC like a Fulcio cert.
+    const CERT_DER: &str = "MIIBlDCCATugAwIBAgIUF/IYjofcmp9cc2myE05QujfkkKwwCgYIKoZIzj0EAwIwIDEeMBwGA1UEAwwVc2lnc3RvcmUtaW50ZXJtZWRpYXRlMB4XDTI0MDEwMTEyMDAwMFoXDTI0MDEwMTEyMTAwMFowIDEeMBwGA1UEAwwVc2lnc3RvcmUtaW50ZXJtZWRpYXRlMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE2TG3ZGYF5cjznMjksgxY3p8HhI5Z3CwD9oQZqQVCf5ibUsElX2CQK3QgP1wl9M70uwPdQu7uvlRXmdBhdLvxuKNTMFEwHQYDVR0OBBYEFOuNu6fwJEtgk31GKuEZbbWdlpnYMB8GA1UdIwQYMBaAFOuNu6fwJEtgk31GKuEZbbWdlpnYMA8GA1UdEwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDRwAwRAIgUQJReoub6GUG9GNyipF5SyQXrnErq8BFS5tgtwTciv8CIFWIbmKXRLMUGJ3ROu8hSROIWqB5pIqOYyGmAQ+LEHcu";
+
+    /// The same, issued to a GitHub Actions workflow.
+    const WORKFLOW_CERT_DER: &str = "MIIB/DCCAaOgAwIBAgIUNcoSrZAkaWHzIubgefRp3XP1bQUwCgYIKoZIzj0EAwIwIDEeMBwGA1UEAwwVc2lnc3RvcmUtaW50ZXJtZWRpYXRlMB4XDTI0MDEwMTEyMDAwMFoXDTI0MDEwMTEyMTAwMFowIDEeMBwGA1UEAwwVc2lnc3RvcmUtaW50ZXJtZWRpYXRlMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEk2Dnc+Gfb6xCc4TPC6tzmLzPqAoSv13pya28R5u0Srg5+2EckvaPBKsrgToTAC5Vo5gkOmp9daR3rS1fOiBl46OBujCBtzBZBgNVHREBAf8ETzBNhktodHRwczovL2dpdGh1Yi5jb20vYWNtZS90b29sLy5naXRodWIvd29ya2Zsb3dzL3JlbGVhc2UueW1sQHJlZnMvdGFncy92MS4wLjAwOwYKKwYBBAGDvzABCAQtDCtodHRwczovL3Rva2VuLmFjdGlvbnMuZ2l0aHVidXNlcmNvbnRlbnQuY29tMB0GA1UdDgQWBBTrxpENODpIFGwURFicTqGz7NsNtDAKBggqhkjOPQQDAgNHADBEAiByyiD88hUb6cvai2+zdBWCwJwS9Kc4Tmf/DzKOSI+DBwIgQfvrEqqAaKguLN5gRUbQ2Q52I5oaOYOLQvQDi9qFWCM=";
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn test_bundle_validity_and_tlog() {
+        let pem = STANDARD.encode(format!(
+            "-----BEGIN CERTIFICATE-----\n{CERT_DER}\n-----END CERTIFICATE-----\n"
+        ));
+        let cosign = serde_json::json!({
+            "base64Signature": "c2ln",
+            "cert": pem,
+            "rekorBundle": {
+                "SignedEntryTimestamp": "c2V0",
+                "Payload": {"integratedTime": 1704110700, "logIndex": 42, "logID": "c0d23d6ad406973f9559f3ba2d1ca01f84147d8ffc5b8445c224f98b9591801d"}
+            }
+        });
+        let info = BundleInfo::parse(&cosign.to_string()).unwrap();
+        assert_eq!(info.not_before, at("2024-01-01T12:00:00Z"));
+        assert_eq!(info.not_after, at("2024-01-01T12:10:00Z"));
+        assert!(info.short_lived());
+        assert_eq!(
+            (info.identity.as_deref(), info.issuer.as_deref()),
+            (None, None)
+        );
+        let summary = info.check(at("2030-01-01T00:00:00Z")).unwrap();
+        assert!(
+            summary.contains("rekor entry 42 in log c0d23d6ad406"),
+            "{summary}"
+        );
+
+        let protobuf = serde_json::json!({
+            "mediaType": "application/vnd.dev.sigstore.bundle.v0.3+json",
+            "verificationMaterial": {
+                "certificate": {"rawBytes": CERT_DER},
+                "tlogEntries": [{"logIndex": "7", "integratedTime": "1704111000"}]
+            }
+        });
+        let info = BundleInfo::parse(&protobuf.to_string()).unwrap();
+        let mut workflow = protobuf.clone();
+        workflow["verificationMaterial"]["certificate"]["rawBytes"] = WORKFLOW_CERT_DER.into();
+        let signer = BundleInfo::parse(&workflow.to_string()).unwrap();
+        assert_eq!(
+            signer.identity.as_deref(),
+            Some("https://github.com/acme/tool/.github/workflows/release.yml@refs/tags/v1.0.0")
+        );
+        assert_eq!(
+            signer.issuer.as_deref(),
+            Some("https://token.actions.githubusercontent.com")
+        );
+        assert_eq!(signer.not_after, info.not_after);
+        let (reason, warning) = info.check(at("2030-01-01T00:00:00Z")).unwrap_err();
+        assert!(reason.contains("inclusion proof"), "{reason}");
+        assert!(warning);
+
+        let mut late = info.clone();
+        late.tlog.as_mut().unwrap().integrated_time = at("2024-01-01T13:00:00Z");
+        let (reason, warning) = late.check(at("2030-01-01T00:00:00Z")).unwrap_err();
+        assert!(reason.contains("outside the certificate"), "{reason}");
+        assert!(!warning);
+
+        let untracked = BundleInfo { tlog: None, ..info };
+        let (reason, warning) = untracked.check(at("2024-01-01T12:05:00Z")).unwrap_err();
+        assert!(reason.contains("no transparency-log entry"), "{reason}");
+        assert!(warning);
+    }
+
+    #[test]
+    fn test_inclusion_proof() {
+        let leaf = |body: &[u8]| {
+            let mut digest = Sha256::new();
+            digest.update([0]);
+            digest.update(body);
+            digest.finalize().to_vec()
+        };
+        // a tree of three entries; ours is the first
+        let (ours, second, third) = (leaf(b"ours"), leaf(b"second"), leaf(b"third"));
+        let root = node_hash(&node_hash(&ours, &second), &third);
+        let proof = |root: &[u8], size: u64| {
+            serde_json::json!({
+                "logIndex": "0",
+                "treeSize": size.to_string(),
+                "rootHash": STANDARD.encode(root),
+                "hashes": [STANDARD.encode(&second), STANDARD.encode(&third)],
+                "checkpoint": {"envelope": format!(
+                    "rekor.sigstore.dev - 1193050959916656506\n3\n{}\n\n— rekor.sigstore.dev abc=\n",
+                    STANDARD.encode(root)
+                )}
+            })
+        };
+        let bundle = |proof: Value| {
+            serde_json::json!({
+                "verificationMaterial": {
+                    "certificate": {"rawBytes": CERT_DER},
+                    "tlogEntries": [{
+                        "logIndex": "123456",
+                        "integratedTime": "1704110700",
+                        "canonicalizedBody": STANDARD.encode("ours"),
+                        "inclusionProof": proof
+                    }]
+                }
+            })
+            .to_string()
+        };
+        let tlog = BundleInfo::parse(&bundle(proof(&root, 3)))
+            .unwrap()
+            .tlog
+            .unwrap();
+        assert_eq!(tlog.uuid.as_deref(), Some(hex::encode(&ours).as_str()));
+        assert_eq!(tlog.log_index, Some(123456));
+        let summary = tlog.check_inclusion().unwrap();
+        assert!(summary.contains("is leaf 0 of 3"), "{summary}");
+
+        let forged = BundleInfo::parse(&bundle(proof(&ours, 3)))
+            .unwrap()
+            .tlog
+            .unwrap();
+        let reason = forged.check_inclusion().unwrap_err();
+        assert!(reason.contains("leads to root"), "{reason}");
+
+        // the hashes also fit a bigger tree, which the checkpoint doesn't name
+        let mut resized = proof(&root, 3);
+        resized["treeSize"] = "4".into();
+        let reason = BundleInfo::parse(&bundle(resized))
+            .unwrap()
+            .tlog
+            .unwrap()
+            .check_inclusion()
+            .unwrap_err();
+        assert!(reason.contains("checkpoint"), "{reason}");
+        let mut other = tlog.clone();
+        other.proof.as_mut().unwrap().checkpoint = Some("origin\n3\nAAAA\n".into());
+        let reason = other.check_inclusion().unwrap_err();
+        assert!(reason.contains("checkpoint"), "{reason}");
+    }
+}
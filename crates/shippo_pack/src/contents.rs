@@ -0,0 +1,203 @@
+//! Source-tree files packed next to what the builder emitted: docs,
+//! examples or config templates selected by `[package.contents]` mappings
+//! and `[package] include`, minus `[package] exclude`.
+//!
+//! Patterns are relative to the package directory. `*` and `?` match
+//! within a path segment, `**` matches any number of segments.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use camino::Utf8PathBuf;
+use shippo_core::{naming_template, PackagePlan};
+
+use crate::{BuiltOutput, PackageError, Result};
+
+/// A source file and where it goes in the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentFile {
+    pub source: Utf8PathBuf,
+    pub path: String,
+}
+
+/// The extra files to pack for `built`; `root` is the workspace root.
+///
+/// A `[package.contents]` mapping such as `"docs/**" = "share/doc/{name}/"`
+/// puts each match under the destination, keeping its path below the
+/// pattern's literal prefix (`docs/guide/a.md` -> `share/doc/app/guide/a.md`).
+/// A destination without a trailing `/` renames a single file. `include`
+/// patterns keep the file's path from the package directory. Files inside
+/// `built`'s artifacts are skipped, since they are packed already.
+pub fn resolve_contents(
+    root: &Path,
+    pkg: &PackagePlan,
+    version: &str,
+    built: &BuiltOutput,
+) -> Result<Vec<ContentFile>> {
+    let config = &pkg.package;
+    if config.contents.is_empty() && config.include.is_empty() {
+        return Ok(Vec::new());
+    }
+    let dir = root.join(pkg.path.as_str());
+    let files = source_files(&dir)?;
+    let artifacts: Vec<_> = built
+        .artifacts
+        .iter()
+        .map(|a| a.canonicalize_utf8().unwrap_or_else(|_| a.clone()))
+        .collect();
+    let excluded = |rel: &str| {
+        config
+            .exclude
+            .iter()
+            .any(|pattern| matches(&segments(pattern), &segments(rel)))
+    };
+
+    let include = config.include.iter().map(|p| (p.as_str(), None));
+    let mappings = config
+        .contents
+        .iter()
+        .map(|(p, dest)| (p.as_str(), Some(dest.as_str())));
+    let mut packed: BTreeMap<String, Utf8PathBuf> = BTreeMap::new();
+    for (pattern, dest) in include.chain(mappings) {
+        let pattern = pattern.trim_matches('/');
+        let mut parts = segments(pattern);
+        // a directory stands for everything in it
+        if dir.join(pattern).is_dir() {
+            parts.push("**");
+        }
+        let prefix = parts.iter().take_while(|s| !is_wildcard(s)).count();
+        let literal = prefix == parts.len();
+        let mut matched = false;
+        for (rel, source) in &files {
+            let rel_parts = segments(rel);
+            if !matches(&parts, &rel_parts) {
+                continue;
+            }
+            matched = true;
+            if excluded(rel) {
+                continue;
+            }
+            let canonical = source
+                .canonicalize_utf8()
+                .unwrap_or_else(|_| source.clone());
+            if artifacts.iter().any(|a| canonical.starts_with(a)) {
+                continue;
+            }
+            let path = match dest {
+                None => rel.clone(),
+                Some(dest) => {
+                    let dest = naming_template(
+                        dest,
+                        &pkg.name,
+                        version,
+                        &config.target_name(&built.target),
+                    );
+                    let dest = dest.trim_start_matches('/');
+                    if literal && !dest.is_empty() && !dest.ends_with('/') {
+                        dest.to_string()
+                    } else {
+                        // a literal pattern keeps just the file name
+                        let keep = if literal { parts.len() - 1 } else { prefix };
+                        let below = rel_parts[keep.min(rel_parts.len() - 1)..].join("/");
+                        if dest.is_empty() {
+                            below
+                        } else {
+                            format!("{}/{below}", dest.trim_end_matches('/'))
+                        }
+                    }
+                }
+            };
+            match packed.get(&path) {
+                Some(first) if first != source => {
+                    return Err(PackageError::ContentsConflict {
+                        package: pkg.name.clone(),
+                        path,
+                        first: first.to_string(),
+                        second: source.to_string(),
+                    })
+                }
+                Some(_) => {}
+                None => {
+                    packed.insert(path, source.clone());
+                }
+            }
+        }
+        if !matched {
+            return Err(PackageError::NoContents {
+                package: pkg.name.clone(),
+                pattern: pattern.to_string(),
+            });
+        }
+    }
+    Ok(packed
+        .into_iter()
+        .map(|(path, source)| ContentFile { source, path })
+        .collect())
+}
+
+/// Files below `dir` by path relative to it, skipping `.git`.
+fn source_files(dir: &Path) -> Result<Vec<(String, Utf8PathBuf)>> {
+    let mut files = Vec::new();
+    let walk = walkdir::WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git");
+    for entry in walk {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(source) = Utf8PathBuf::from_path_buf(entry.path().to_path_buf()) else {
+            continue;
+        };
+        let rel = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+        files.push((rel.to_string_lossy().replace('\\', "/"), source));
+    }
+    Ok(files)
+}
+
+fn segments(path: &str) -> Vec<&str> {
+    path.split('/')
+        .filter(|s| !s.is_empty() && *s != ".")
+        .collect()
+}
+
+fn is_wildcard(segment: &str) -> bool {
+    segment.contains(['*', '?'])
+}
+
+fn matches(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| matches(rest, &path[skip..])),
+        Some((segment, rest)) => path.split_first().is_some_and(|(first, path)| {
+            wildcard(segment.as_bytes(), first.as_bytes()) && matches(rest, path)
+        }),
+    }
+}
+
+pub(crate) fn wildcard(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| wildcard(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && wildcard(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && wildcard(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_matching() {
+        let m = |pattern: &str, path: &str| matches(&segments(pattern), &segments(path));
+        assert!(m("docs/**", "docs/guide/intro.md"));
+        assert!(m("docs/**", "docs/index.md"));
+        assert!(m("**/*.md", "README.md"));
+        assert!(m("examples/*.toml", "examples/basic.toml"));
+        assert!(!m("examples/*.toml", "examples/nested/basic.toml"));
+        assert!(m("config/app.?ml", "config/app.yml"));
+        assert!(!m("docs/**", "src/docs.rs"));
+    }
+}
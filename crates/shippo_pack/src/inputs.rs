@@ -0,0 +1,124 @@
+//! Binaries built outside shippo, packaged with `shippo package
+//! --from-artifacts <dir>`. The directory holds them and an
+//! `artifacts.toml` saying what each one is:
+//!
+//! ```toml
+//! [[artifact]]
+//! package = "cli"           # optional with a single package
+//! target = "linux-amd64"    # one of the package's [build] targets
+//! files = ["linux/cli"]     # relative to the directory
+//! ```
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use shippo_core::{targets, Plan};
+
+use crate::{BuiltOutput, PackageError, Result};
+
+pub const ARTIFACT_INPUTS_FILE: &str = "artifacts.toml";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Inputs {
+    #[serde(default, rename = "artifact")]
+    artifacts: Vec<Input>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Input {
+    #[serde(default)]
+    package: Option<String>,
+    target: String,
+    files: Vec<String>,
+}
+
+/// Reads `artifacts.toml` in `dir` into build outputs for `plan`, as if
+/// the builders had produced them. Targets are matched against each
+/// package's `[build] targets` under any of their names, and every package
+/// in the plan needs at least one.
+pub fn load_artifact_inputs(dir: &Path, plan: &Plan) -> Result<Vec<BuiltOutput>> {
+    let path = dir.join(ARTIFACT_INPUTS_FILE);
+    let invalid = |message: String| PackageError::ArtifactInputs {
+        path: path.clone(),
+        message,
+    };
+    let text = std::fs::read_to_string(&path).map_err(|e| invalid(e.to_string()))?;
+    let inputs: Inputs = toml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+    let dir = Utf8PathBuf::from_path_buf(dir.to_path_buf())
+        .map_err(|d| invalid(format!("{} is not valid UTF-8", d.display())))?;
+
+    let mut outputs = Vec::new();
+    let mut seen = BTreeSet::new();
+    for input in inputs.artifacts {
+        let pkg = match &input.package {
+            Some(name) => plan.packages.iter().find(|p| &p.name == name),
+            None if plan.packages.len() == 1 => plan.packages.first(),
+            None => {
+                return Err(invalid(format!(
+                    "artifact for {} needs a package, the plan has several",
+                    input.target
+                )))
+            }
+        }
+        .ok_or_else(|| {
+            invalid(format!(
+                "package {} is not in the plan",
+                input.package.as_deref().unwrap_or_default()
+            ))
+        })?;
+        let same = |target: &str| {
+            target == input.target
+                || targets::resolve(target)
+                    .is_some_and(|t| Some(t) == targets::resolve(&input.target))
+        };
+        let target = pkg.targets.iter().find(|t| same(t)).ok_or_else(|| {
+            invalid(format!(
+                "{} is not a target of {} (expected one of {})",
+                input.target,
+                pkg.name,
+                pkg.targets.join(", ")
+            ))
+        })?;
+        if !seen.insert((pkg.name.clone(), target.clone())) {
+            return Err(invalid(format!(
+                "{} {} is listed more than once",
+                pkg.name, target
+            )));
+        }
+        if input.files.is_empty() {
+            return Err(invalid(format!("{} {} lists no files", pkg.name, target)));
+        }
+        let mut artifacts = Vec::new();
+        for file in &input.files {
+            let artifact = dir.join(file);
+            if !artifact.exists() {
+                return Err(PackageError::Missing {
+                    kind: "artifact",
+                    filename: artifact.to_string(),
+                });
+            }
+            artifacts.push(artifact);
+        }
+        outputs.push(BuiltOutput {
+            package: pkg.name.clone(),
+            target: target.clone(),
+            artifacts,
+            contents: Vec::new(),
+        });
+    }
+    if let Some(pkg) = plan
+        .packages
+        .iter()
+        .find(|p| !outputs.iter().any(|o| o.package == p.name))
+    {
+        return Err(invalid(format!(
+            "no artifacts for package {}; list some or leave it out with --only",
+            pkg.name
+        )));
+    }
+    Ok(outputs)
+}
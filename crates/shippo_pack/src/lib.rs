@@ -1,24 +1,153 @@
+use std::collections::BTreeMap;
 use std::fs::{self, File};
-use std::path::Path;
-use std::process::Command;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use camino::Utf8PathBuf;
 use chrono::Utc;
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use shippo_core::ci::detect_ci;
+use shippo_core::exec::{CommandSpec, Executor, SystemExecutor};
+use shippo_core::metadata::PackageMetadata;
+use shippo_core::plugin::{self, plugin_ref, PluginError, SbomParams, SignParams};
 use shippo_core::{
-    naming_template, sha256_file, BuildEnvInfo, Manifest, ManifestArtifact, ManifestPackage,
-    ManifestProject, ManifestSignature, ManifestTarget, Plan, ToolingInfo,
+    check_file_name, naming_template, normalize_file_name, sha256_file, ArchiveEntry,
+    ArtifactOrigin, AttestConfig, BuildEnvInfo, CompressionConfig, FreezeOverride, Manifest,
+    ManifestApproval, ManifestArtifact, ManifestError, ManifestImage, ManifestNpm, ManifestPackage,
+    ManifestProject, ManifestScan, ManifestSignature, ManifestTarget, ManifestTestRun, PackagePlan,
+    Plan, RekorEntry, SignConfig, ToolingInfo, MANIFEST_VERSION, SIGSTORE_BUNDLE_SUFFIX,
 };
 use zip::write::FileOptions;
 use zip::ZipWriter;
 
+mod attest;
+mod bundle;
+mod contents;
+mod inputs;
+mod lock;
+mod prune;
+mod signers;
+mod transform;
+mod verify;
+mod verifying;
+
+pub use contents::{resolve_contents, ContentFile};
+pub use inputs::{load_artifact_inputs, ARTIFACT_INPUTS_FILE};
+pub use lock::{DistLock, DIST_LOCK_FILE};
+pub use prune::{prune_versions, version_dirs, VersionDir};
+pub use signers::{merge_signatures, sign_as};
+pub use transform::{apply_transforms, TRANSFORM_DIR};
+pub use verify::{
+    compare_config, compare_rebuild, extract_files, inspect_artifact, verify_contents,
+    verify_manifest, verify_manifest_data, ArtifactInspection, Check, CheckStatus, VerifyCheck,
+    VerifyOptions, VerifyReport,
+};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const PARTIAL_MANIFEST_FILE: &str = "manifest.partial.json";
+pub const SUMS_FILE: &str = "SHA256SUMS";
+pub const PROVENANCE_FILE: &str = "provenance.json";
+/// How to check the release's checksums and signatures, for end users.
+pub const VERIFYING_FILE: &str = "VERIFYING.md";
+
+#[derive(thiserror::Error, Debug)]
+pub enum PackageError {
+    #[error("unsupported package format {0}")]
+    UnsupportedFormat(String),
+    #[error("missing {kind} {filename}")]
+    Missing {
+        kind: &'static str,
+        filename: String,
+    },
+    #[error("sha mismatch for {filename}")]
+    ChecksumMismatch { filename: String },
+    #[error("no partial dists given")]
+    NoPartials,
+    #[error("no partial manifest in {}", .0.display())]
+    NoPartialManifest(PathBuf),
+    #[error("partial {} has version {found}, expected {expected}", .partial.display())]
+    VersionMismatch {
+        partial: PathBuf,
+        found: String,
+        expected: String,
+    },
+    #[error("partial {} was built from a different configuration (plan {found}, expected {expected})", .partial.display())]
+    PlanMismatch {
+        partial: PathBuf,
+        found: String,
+        expected: String,
+    },
+    #[error("{} failed verification: {}", .dist.display(), .failures.join("; "))]
+    VerifyFailed {
+        dist: PathBuf,
+        failures: Vec<String>,
+    },
+    #[error("{} is in use by another shippo run ({holder}); wait for it or pass a different --output", .dist.display())]
+    DistLocked { dist: PathBuf, holder: String },
+    #[error("{} is a Git LFS pointer, not the file it stands for; fetch LFS objects before packaging (`git lfs pull`, or `lfs: true` on actions/checkout)", .0.display())]
+    LfsPointer(PathBuf),
+    #[error("{package}: package contents pattern {pattern:?} matched no files")]
+    NoContents { package: String, pattern: String },
+    #[error("{package}: both {first} and {second} would be packed as {path}")]
+    ContentsConflict {
+        package: String,
+        path: String,
+        first: String,
+        second: String,
+    },
+    #[error("{filename:?} {reason}, so it can't be written or extracted on every platform; change [package] name_template")]
+    InvalidName { filename: String, reason: String },
+    #[error("{first} and {second} would both write {filename} to the dist; {hint}")]
+    AssetCollision {
+        filename: String,
+        first: String,
+        second: String,
+        hint: String,
+    },
+    #[error("{package}: SBOM generation failed: {message}")]
+    Sbom { package: String, message: String },
+    #[error("{package}: package transform failed: {message}")]
+    Transform { package: String, message: String },
+    #[error("signing {filename} with {method} failed: {message}")]
+    Sign {
+        filename: String,
+        method: String,
+        message: String,
+    },
+    #[error("{}: {message}", .path.display())]
+    ArtifactInputs { path: PathBuf, message: String },
+    #[error("{} has {filename}, which signs a file this dist doesn't have or has with another digest", .input.display())]
+    SignatureMismatch { input: PathBuf, filename: String },
+    #[error("target {target} of {package} appears in more than one partial")]
+    DuplicateTarget { package: String, target: String },
+    #[error(transparent)]
+    Manifest(#[from] ManifestError),
+    #[error(transparent)]
+    Plugin(#[from] PluginError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("invalid manifest: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    #[error(transparent)]
+    Walk(#[from] walkdir::Error),
+}
+
+pub type Result<T, E = PackageError> = std::result::Result<T, E>;
+
 #[derive(Debug, Clone)]
 pub struct BuiltOutput {
     pub package: String,
     pub target: String,
     pub artifacts: Vec<Utf8PathBuf>,
+    /// Source-tree files packed alongside, see [`resolve_contents`].
+    pub contents: Vec<ContentFile>,
 }
 
 pub fn package_outputs(
@@ -28,86 +157,147 @@ pub fn package_outputs(
     repo_url: Option<String>,
     commit: Option<String>,
     sign: bool,
+    executor: &dyn Executor,
 ) -> Result<Manifest> {
+    check_asset_names(plan, built)?;
     fs::create_dir_all(dist)?;
     let mut manifest_packages = Vec::new();
     let mut checksum_entries: Vec<(String, String)> = Vec::new();
     for pkg in &plan.packages {
+        let _pkg_span =
+            tracing::info_span!("package", package = %pkg.name, version = %plan.version).entered();
         let mut targets = Vec::new();
+        // archive digest to the first of the package's archives with it
+        let mut packed: BTreeMap<String, String> = BTreeMap::new();
         for built_entry in built.iter().filter(|b| b.package == pkg.name) {
+            let _target_span =
+                tracing::info_span!("target", target = %built_entry.target).entered();
+            reject_lfs_pointers(&built_entry.artifacts)?;
+            let target_name = pkg.package.target_name(&built_entry.target);
+            let sources: Vec<_> = built_entry
+                .contents
+                .iter()
+                .map(|c| c.source.clone())
+                .collect();
+            reject_lfs_pointers(&sources)?;
             let mut artifacts_meta = Vec::new();
             for fmt in &pkg.package.formats {
-                let archive_name = format!(
-                    "{}.{}",
-                    naming_template(
-                        &pkg.package.name_template,
-                        &pkg.name,
-                        &plan.version,
-                        &built_entry.target
-                    ),
-                    fmt
-                );
+                let archive_name = archive_name(pkg, &plan.version, &target_name, fmt);
                 let archive_path = dist.join(&archive_name);
-                if fmt.ends_with("tar.gz") {
-                    create_tar_gz(&archive_path, &built_entry.artifacts)?;
+                let (inputs, extra) = (&built_entry.artifacts, &built_entry.contents);
+                let compression = &pkg.package.compression;
+                let mtime = plan.source_date_epoch.unwrap_or(0);
+                let contents = if fmt.ends_with("tar.gz") {
+                    create_tar_gz(&archive_path, inputs, extra, compression, mtime)?;
+                    archive_layout(inputs, extra, true)?
+                } else if fmt.ends_with("tar.zst") {
+                    create_tar_zst(&archive_path, inputs, extra, compression, mtime)?;
+                    archive_layout(inputs, extra, true)?
                 } else if fmt == "zip" {
-                    create_zip(&archive_path, &built_entry.artifacts)?;
+                    create_zip(&archive_path, inputs, extra, compression, mtime)?;
+                    archive_layout(inputs, extra, false)?
                 } else {
-                    return Err(anyhow!("unsupported package format {fmt}"));
-                }
+                    return Err(PackageError::UnsupportedFormat(fmt.clone()));
+                };
                 let sha = sha256_file(&archive_path)?;
+                let bytes = fs::metadata(&archive_path)?.len();
+                let policy = pkg.package.duplicates.as_deref();
+                if let Some(original) = packed.get(&sha).filter(|_| policy.is_some()) {
+                    fs::remove_file(&archive_path)?;
+                    if policy == Some("skip") {
+                        tracing::info!("{archive_name} is identical to {original}, skipped");
+                        continue;
+                    }
+                    tracing::info!("{archive_name} is identical to {original}, listed as it");
+                    artifacts_meta.push(ManifestArtifact {
+                        filename: original.clone(),
+                        bytes,
+                        sha256: sha,
+                        contents,
+                        alias: Some(archive_name),
+                    });
+                    continue;
+                }
+                packed.insert(sha.clone(), archive_name.clone());
                 checksum_entries.push((sha.clone(), archive_name.clone()));
                 let meta = ManifestArtifact {
                     filename: archive_name.clone(),
-                    bytes: fs::metadata(&archive_path)?.len() as u64,
+                    bytes,
                     sha256: sha,
+                    contents,
+                    alias: None,
                 };
                 artifacts_meta.push(meta);
             }
-            // sbom simple fallback
-            let sbom_file = format!(
-                "{}-sbom.cdx.json",
-                naming_template(
-                    &pkg.package.name_template,
-                    &pkg.name,
-                    &plan.version,
-                    &built_entry.target
-                )
-            );
-            let sbom_path = dist.join(&sbom_file);
-            write_sbom(&sbom_path, &pkg.name, &plan.version, &built_entry.target)?;
-            let sbom_sha = sha256_file(&sbom_path)?;
-            checksum_entries.push((sbom_sha.clone(), sbom_file.clone()));
-            let sbom_meta = ManifestArtifact {
-                filename: sbom_file.clone(),
-                bytes: fs::metadata(&sbom_path)?.len() as u64,
-                sha256: sbom_sha,
-            };
+            let mut sboms = Vec::new();
+            for format in pkg.sbom.output_formats() {
+                let sbom_file = sbom_name(pkg, &plan.version, &target_name, format);
+                let sbom_path = dist.join(&sbom_file);
+                if let Some(name) = plugin_ref(format) {
+                    let params = SbomParams {
+                        package: pkg.name.clone(),
+                        version: plan.version.clone(),
+                        target: built_entry.target.clone(),
+                        artifacts: built_entry
+                            .artifacts
+                            .iter()
+                            .map(|a| a.to_string())
+                            .collect(),
+                        output: sbom_path.to_string_lossy().to_string(),
+                    };
+                    let _: Value = plugin::find(name, &plan.plugins)?.call("sbom", &params)?;
+                    if !sbom_path.exists() {
+                        return Err(PackageError::Missing {
+                            kind: "sbom",
+                            filename: sbom_file,
+                        });
+                    }
+                } else {
+                    let input = SbomInput {
+                        pkg,
+                        version: &plan.version,
+                        built: built_entry,
+                        archives: &artifacts_meta,
+                    };
+                    write_sbom(&sbom_path, format, &input, executor)?;
+                }
+                let sbom_sha = sha256_file(&sbom_path)?;
+                checksum_entries.push((sbom_sha.clone(), sbom_file.clone()));
+                sboms.push(ManifestArtifact {
+                    filename: sbom_file,
+                    bytes: fs::metadata(&sbom_path)?.len() as u64,
+                    sha256: sbom_sha,
+                    contents: Vec::new(),
+                    alias: None,
+                });
+            }
             // signatures (optional)
             let mut signatures = Vec::new();
             if sign && pkg.sign.enabled {
-                for art in &artifacts_meta {
-                    if let Some(sig) = sign_file(dist, &art.filename, &pkg.sign.method)? {
-                        checksum_entries.push((sha256_file(&dist.join(&sig))?, sig.clone()));
-                        signatures.push(ManifestSignature {
-                            filename: sig,
-                            method: pkg.sign.method.clone(),
-                        });
+                // an alias's file is signed with the target that has it
+                let originals = artifacts_meta.iter().filter(|a| a.alias.is_none());
+                let signed = originals.chain(&sboms);
+                for art in signed {
+                    if let Some(sig) =
+                        sign_file(dist, &art.filename, &pkg.sign, &plan.plugins, executor)?
+                    {
+                        let bundle = sig.bundle.iter().filter(|_| !sig.in_bundle());
+                        for file in std::iter::once(&sig.filename).chain(bundle) {
+                            checksum_entries.push((sha256_file(&dist.join(file))?, file.clone()));
+                        }
+                        signatures.push(sig);
                     }
                 }
-                if let Some(sig) = sign_file(dist, &sbom_meta.filename, &pkg.sign.method)? {
-                    checksum_entries.push((sha256_file(&dist.join(&sig))?, sig.clone()));
-                    signatures.push(ManifestSignature {
-                        filename: sig,
-                        method: pkg.sign.method.clone(),
-                    });
-                }
             }
+            let mut sboms = sboms.into_iter();
             targets.push(ManifestTarget {
                 target: built_entry.target.clone(),
                 artifacts: artifacts_meta,
-                sbom: Some(sbom_meta),
+                sbom: sboms.next(),
+                extra_sboms: sboms.collect(),
+                attestation: None,
                 signatures,
+                origin: None,
             });
         }
         manifest_packages.push(ManifestPackage {
@@ -115,23 +305,30 @@ pub fn package_outputs(
             project_type: pkg.project_type.clone(),
             path: pkg.path.to_string(),
             targets,
+            tests: None,
+            scan: None,
+            metadata: None,
+            image: None,
+            npm: None,
         });
     }
 
-    let tooling = ToolingInfo {
-        rust: tool_version("rustc --version"),
-        go: tool_version("go version"),
-        node: tool_version("node --version"),
-        python: tool_version("python --version"),
-    };
+    let tooling = ToolingInfo::probe(executor);
 
-    let manifest = Manifest {
+    let mut manifest = Manifest {
+        manifest_version: MANIFEST_VERSION,
         shippo_version: env!("CARGO_PKG_VERSION").to_string(),
         generated_at: Utc::now(),
         project: ManifestProject {
             repo_url,
             commit,
             version: plan.version.clone(),
+            plan_fingerprint: Some(plan.fingerprint()),
+            config_sha256: plan.config_sha256.clone(),
+            submodules: plan.submodules.clone(),
+            freeze_override: None,
+            approval: None,
+            trust: plan.trust.clone(),
         },
         packages: manifest_packages,
         tooling,
@@ -139,202 +336,1103 @@ pub fn package_outputs(
             os: std::env::consts::OS.into(),
             arch: std::env::consts::ARCH.into(),
             ci: std::env::var("CI").is_ok(),
+            ci_run: detect_ci(),
         },
     };
+    if let Some(attest) = &plan.attest {
+        write_attestations(
+            plan,
+            &mut manifest,
+            dist,
+            &mut checksum_entries,
+            sign,
+            attest,
+            executor,
+        )?;
+    }
+    write_dist_index(dist, &manifest, checksum_entries)?;
+    Ok(manifest)
+}
+
+/// Writes each target's in-toto statement next to its archives, in a DSSE
+/// envelope, and lists it on the target. When `[attest] sign` and `[sign]`
+/// allow, the statement's PAE is signed: the signature goes in the envelope
+/// and, as for archives, next to it.
+fn write_attestations(
+    plan: &Plan,
+    manifest: &mut Manifest,
+    dist: &Path,
+    checksum_entries: &mut Vec<(String, String)>,
+    sign: bool,
+    attest: &AttestConfig,
+    executor: &dyn Executor,
+) -> Result<()> {
+    let snapshot = manifest.clone();
+    for (mp, snapshot_pkg) in manifest.packages.iter_mut().zip(&snapshot.packages) {
+        let Some(pkg) = plan.packages.iter().find(|p| p.name == mp.name) else {
+            continue;
+        };
+        for (target, snapshot_target) in mp.targets.iter_mut().zip(&snapshot_pkg.targets) {
+            let target_name = pkg.package.target_name(&target.target);
+            let file = normalize_file_name(&format!(
+                "{}{}",
+                naming_template(
+                    &pkg.package.name_template,
+                    &pkg.name,
+                    &plan.version,
+                    &target_name
+                ),
+                attest::INTOTO_SUFFIX
+            ));
+            let path = dist.join(&file);
+            let statement = attest::statement(&snapshot, &pkg.name, snapshot_target);
+            let pae = attest::pae(statement.as_bytes());
+            let mut signature = None;
+            if sign && pkg.sign.enabled && attest.sign {
+                // signed in the envelope's place, so the signature is named
+                // after the envelope
+                fs::write(&path, &pae)?;
+                signature = sign_file(dist, &file, &pkg.sign, &plan.plugins, executor)?;
+            }
+            let raw: Vec<Vec<u8>> = signature
+                .iter()
+                .filter_map(|sig| raw_signature(dist, sig, &pae))
+                .collect();
+            fs::write(&path, attest::envelope(&statement, &raw))?;
+            let sha = sha256_file(&path)?;
+            checksum_entries.push((sha.clone(), file.clone()));
+            target.attestation = Some(ManifestArtifact {
+                filename: file.clone(),
+                bytes: fs::metadata(&path)?.len(),
+                sha256: sha,
+                contents: Vec::new(),
+                alias: None,
+            });
+            if let Some(sig) = signature {
+                let bundle = sig.bundle.iter().filter(|_| !sig.in_bundle());
+                for file in std::iter::once(&sig.filename).chain(bundle) {
+                    checksum_entries.push((sha256_file(&dist.join(file))?, file.clone()));
+                }
+                target.signatures.push(sig);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The raw signature `sig` holds of `signed`, for a DSSE envelope: the
+/// signature file, or a bundle's message signature. `None` for the
+/// checksum written when signing failed.
+fn raw_signature(dist: &Path, sig: &ManifestSignature, signed: &[u8]) -> Option<Vec<u8>> {
+    let data = fs::read(dist.join(&sig.filename)).ok()?;
+    if sig.in_bundle() {
+        let bundle: Value = serde_json::from_slice(&data).ok()?;
+        let sig = bundle["messageSignature"]["signature"].as_str()?;
+        return STANDARD.decode(sig).ok();
+    }
+    let placeholder = hex::encode(Sha256::digest(signed));
+    (String::from_utf8_lossy(&data).trim() != placeholder).then_some(data)
+}
+
+fn archive_name(pkg: &PackagePlan, version: &str, target_name: &str, format: &str) -> String {
+    normalize_file_name(&format!(
+        "{}.{format}",
+        naming_template(&pkg.package.name_template, &pkg.name, version, target_name)
+    ))
+}
+
+fn sbom_name(pkg: &PackagePlan, version: &str, target_name: &str, format: &str) -> String {
+    let ext = match (plugin_ref(format), format) {
+        (Some(_), _) => "json",
+        (None, "spdx") => "spdx.json",
+        (None, _) => "cdx.json",
+    };
+    normalize_file_name(&format!(
+        "{}-sbom.{ext}",
+        naming_template(&pkg.package.name_template, &pkg.name, version, target_name)
+    ))
+}
+
+/// Fails when two package/target pairs in `built` would write the same
+/// archive or SBOM, which would otherwise silently overwrite one another,
+/// or when a name (or one of its signatures') can't be extracted everywhere.
+/// Signatures are named after these, so they can't collide on their own.
+fn check_asset_names(plan: &Plan, built: &[BuiltOutput]) -> Result<()> {
+    let mut seen: BTreeMap<String, (&PackagePlan, &str)> = BTreeMap::new();
+    for pkg in &plan.packages {
+        for entry in built.iter().filter(|b| b.package == pkg.name) {
+            let target_name = pkg.package.target_name(&entry.target);
+            let names = pkg
+                .package
+                .formats
+                .iter()
+                .map(|fmt| archive_name(pkg, &plan.version, &target_name, fmt))
+                .chain(
+                    pkg.sbom
+                        .output_formats()
+                        .into_iter()
+                        .map(|format| sbom_name(pkg, &plan.version, &target_name, format)),
+                );
+            for filename in names {
+                // the longest name signing adds, and the signers' signatures
+                let keyless = pkg.sign.method == "cosign" && pkg.sign.cosign_mode == "keyless";
+                let suffix = if keyless { "bundle" } else { "sig" };
+                let signature = pkg.sign.enabled.then(|| format!("{filename}.{suffix}"));
+                let signed = plan
+                    .signers
+                    .iter()
+                    .map(|signer| format!("{filename}.{signer}.minisig"));
+                let signatures: Vec<String> = signature.into_iter().chain(signed).collect();
+                for name in std::iter::once(&filename).chain(&signatures) {
+                    check_file_name(name).map_err(|reason| PackageError::InvalidName {
+                        filename: name.clone(),
+                        reason,
+                    })?;
+                }
+                let Some((other, other_target)) = seen.get(&filename).copied() else {
+                    seen.insert(filename, (pkg, &entry.target));
+                    continue;
+                };
+                if other.name == pkg.name && other_target == entry.target {
+                    // the same format listed twice
+                    continue;
+                }
+                let template = &pkg.package.name_template;
+                let hint = if other.name != pkg.name && !template.contains("{name}") {
+                    format!("add {{name}} to [package] name_template ({template:?})")
+                } else if other_target != entry.target && !template.contains("{target}") {
+                    format!("add {{target}} to [package] name_template ({template:?})")
+                } else if other_target != entry.target {
+                    "the targets render the same {target}; list each target once or change [package] target_style".to_string()
+                } else {
+                    "give the packages distinct name templates".to_string()
+                };
+                return Err(PackageError::AssetCollision {
+                    filename,
+                    first: format!("{} ({other_target})", other.name),
+                    second: format!("{} ({})", pkg.name, entry.target),
+                    hint,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes `manifest.json`, `SHA256SUMS`, `provenance.json` and
+/// `VERIFYING.md` for a dist whose files are already in place.
+fn write_dist_index(
+    dist: &Path,
+    manifest: &Manifest,
+    mut checksum_entries: Vec<(String, String)>,
+) -> Result<()> {
     let manifest_json = manifest.to_json()?;
-    let manifest_path = dist.join("manifest.json");
+    let manifest_path = dist.join(MANIFEST_FILE);
     fs::write(&manifest_path, manifest_json)?;
-    checksum_entries.push((sha256_file(&manifest_path)?, "manifest.json".into()));
+    checksum_entries.push((sha256_file(&manifest_path)?, MANIFEST_FILE.into()));
+    let verifying_path = dist.join(VERIFYING_FILE);
+    fs::write(&verifying_path, verifying::instructions(dist, manifest))?;
+    checksum_entries.push((sha256_file(&verifying_path)?, VERIFYING_FILE.into()));
 
-    let sha_file = dist.join("SHA256SUMS");
+    let sha_file = dist.join(SUMS_FILE);
     let mut out = String::new();
     for (sha, file) in &checksum_entries {
         out.push_str(&format!("{}  {}\n", sha, file));
     }
     fs::write(&sha_file, out)?;
 
-    let provenance_path = dist.join("provenance.json");
+    let provenance_path = dist.join(PROVENANCE_FILE);
     let provenance = serde_json::json!({
-        "version": plan.version,
+        "version": manifest.project.version,
         "generated_at": Utc::now(),
         "ci": std::env::var("CI").is_ok(),
     });
     fs::write(&provenance_path, serde_json::to_string_pretty(&provenance)?)?;
-    Ok(manifest)
+    Ok(())
 }
 
-pub fn verify_manifest(manifest_path: &Path, dist: &Path) -> Result<()> {
-    let data = fs::read_to_string(manifest_path)?;
-    let manifest: Manifest = serde_json::from_str(&data)?;
-    for pkg in &manifest.packages {
-        for target in &pkg.targets {
-            for art in &target.artifacts {
-                let path = dist.join(&art.filename);
-                if !path.exists() {
-                    return Err(anyhow!("missing artifact {}", art.filename));
+/// Rewrites the manifest in `dist` (or its partial manifest) in the current
+/// schema and updates its `SHA256SUMS` entry. Returns the version it had.
+pub fn upgrade_manifest(dist: &Path) -> Result<u32> {
+    let (name, path) = [MANIFEST_FILE, PARTIAL_MANIFEST_FILE]
+        .iter()
+        .map(|name| (*name, dist.join(name)))
+        .find(|(_, p)| p.exists())
+        .ok_or_else(|| PackageError::Missing {
+            kind: "manifest",
+            filename: dist.join(MANIFEST_FILE).display().to_string(),
+        })?;
+    let (manifest, found) = Manifest::from_json_versioned(&fs::read_to_string(&path)?)?;
+    if found == MANIFEST_VERSION {
+        return Ok(found);
+    }
+    rewrite_manifest(dist, name, &manifest)?;
+    Ok(found)
+}
+
+/// Records test results per package in the manifest in `dist`.
+pub fn record_tests(
+    dist: &Path,
+    manifest: &mut Manifest,
+    runs: &BTreeMap<String, ManifestTestRun>,
+) -> Result<()> {
+    for pkg in &mut manifest.packages {
+        if let Some(run) = runs.get(&pkg.name) {
+            pkg.tests = Some(run.clone());
+        }
+    }
+    update_manifest(dist, manifest)
+}
+
+/// Records a release published through a change freeze in the manifest
+/// in `dist`.
+pub fn record_freeze_override(
+    dist: &Path,
+    manifest: &mut Manifest,
+    freeze_override: &FreezeOverride,
+) -> Result<()> {
+    manifest.project.freeze_override = Some(freeze_override.clone());
+    update_manifest(dist, manifest)
+}
+
+/// Records who approved the release in a packaged manifest.
+pub fn record_approval(
+    dist: &Path,
+    manifest: &mut Manifest,
+    approval: &ManifestApproval,
+) -> Result<()> {
+    manifest.project.approval = Some(approval.clone());
+    update_manifest(dist, manifest)
+}
+
+/// Adds malware scan results, keyed by package, to a packaged manifest.
+pub fn record_scans(
+    dist: &Path,
+    manifest: &mut Manifest,
+    scans: &BTreeMap<String, ManifestScan>,
+) -> Result<()> {
+    for pkg in &mut manifest.packages {
+        if let Some(scan) = scans.get(&pkg.name) {
+            pkg.scan = Some(scan.clone());
+        }
+    }
+    update_manifest(dist, manifest)
+}
+
+/// Adds package metadata, keyed by package, to a packaged manifest.
+pub fn record_metadata(
+    dist: &Path,
+    manifest: &mut Manifest,
+    metadata: &BTreeMap<String, PackageMetadata>,
+) -> Result<()> {
+    for pkg in &mut manifest.packages {
+        if let Some(meta) = metadata.get(&pkg.name) {
+            pkg.metadata = Some(meta.clone());
+        }
+    }
+    update_manifest(dist, manifest)
+}
+
+/// Adds how each target was built, keyed by package and target, to a
+/// packaged manifest.
+pub fn record_origins(
+    dist: &Path,
+    manifest: &mut Manifest,
+    origins: &BTreeMap<String, BTreeMap<String, ArtifactOrigin>>,
+) -> Result<()> {
+    for pkg in &mut manifest.packages {
+        let Some(targets) = origins.get(&pkg.name) else {
+            continue;
+        };
+        for target in &mut pkg.targets {
+            if let Some(origin) = targets.get(&target.target) {
+                target.origin = Some(origin.clone());
+            }
+        }
+    }
+    update_manifest(dist, manifest)
+}
+
+/// Adds each package's container image, keyed by package, to a packaged
+/// manifest; called again once the images are pushed, for their digests.
+pub fn record_images(
+    dist: &Path,
+    manifest: &mut Manifest,
+    images: &BTreeMap<String, ManifestImage>,
+) -> Result<()> {
+    for pkg in &mut manifest.packages {
+        if let Some(image) = images.get(&pkg.name) {
+            pkg.image = Some(image.clone());
+        }
+    }
+    update_manifest(dist, manifest)
+}
+
+/// Records each package's `[release.npm]` publish, keyed by package, in a
+/// packaged manifest.
+pub fn record_npm(
+    dist: &Path,
+    manifest: &mut Manifest,
+    published: &BTreeMap<String, ManifestNpm>,
+) -> Result<()> {
+    for pkg in &mut manifest.packages {
+        if let Some(npm) = published.get(&pkg.name) {
+            pkg.npm = Some(npm.clone());
+        }
+    }
+    update_manifest(dist, manifest)
+}
+
+/// Rewrites the complete or partial manifest, whichever `dist` holds.
+fn update_manifest(dist: &Path, manifest: &Manifest) -> Result<()> {
+    let name = if dist.join(MANIFEST_FILE).exists() {
+        MANIFEST_FILE
+    } else {
+        PARTIAL_MANIFEST_FILE
+    };
+    rewrite_manifest(dist, name, manifest)
+}
+
+/// Writes `manifest` to `dist/<name>` and updates its `SHA256SUMS` entry.
+fn rewrite_manifest(dist: &Path, name: &str, manifest: &Manifest) -> Result<()> {
+    rewrite_index_file(dist, name, &manifest.to_json()?)
+}
+
+/// Writes `contents` to `dist/<name>` and updates its `SHA256SUMS` entry.
+pub(crate) fn rewrite_index_file(dist: &Path, name: &str, contents: &str) -> Result<()> {
+    let path = dist.join(name);
+    fs::write(&path, contents)?;
+    let sums = dist.join(SUMS_FILE);
+    if sums.exists() {
+        let sha = sha256_file(&path)?;
+        let mut out = String::new();
+        for line in fs::read_to_string(&sums)?.lines() {
+            match line.split_once("  ") {
+                Some((_, file)) if file == name => out.push_str(&format!("{sha}  {file}\n")),
+                _ => {
+                    out.push_str(line);
+                    out.push('\n');
                 }
-                let sha = sha256_file(&path)?;
-                if sha != art.sha256 {
-                    return Err(anyhow!("sha mismatch for {}", art.filename));
+            }
+        }
+        fs::write(&sums, out)?;
+    }
+    Ok(())
+}
+
+/// Turns a packaged dist into a partial dist for matrix builds: the manifest
+/// is renamed to `manifest.partial.json` and the index files are dropped so
+/// only [`merge_partials`] produces them.
+pub fn mark_partial(dist: &Path) -> Result<()> {
+    fs::rename(dist.join(MANIFEST_FILE), dist.join(PARTIAL_MANIFEST_FILE))?;
+    for name in [SUMS_FILE, PROVENANCE_FILE, VERIFYING_FILE] {
+        let path = dist.join(name);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Combines partial dists produced on separate runners into `out`, checking
+/// every file against its partial manifest and regenerating the unified
+/// manifest, `SHA256SUMS`, and provenance.
+pub fn merge_partials(inputs: &[PathBuf], out: &Path) -> Result<Manifest> {
+    if inputs.is_empty() {
+        return Err(PackageError::NoPartials);
+    }
+    fs::create_dir_all(out)?;
+    let mut merged: Option<Manifest> = None;
+    for input in inputs {
+        let manifest_path = [PARTIAL_MANIFEST_FILE, MANIFEST_FILE]
+            .iter()
+            .map(|name| input.join(name))
+            .find(|p| p.exists())
+            .ok_or_else(|| PackageError::NoPartialManifest(input.clone()))?;
+        let options = VerifyOptions::default();
+        let report = verify_manifest(&manifest_path, input, &options, &SystemExecutor)?;
+        for check in report
+            .checks
+            .iter()
+            .filter(|c| c.status == CheckStatus::Warn)
+        {
+            tracing::warn!("{}: {}", check.artifact, check.details);
+        }
+        report.ensure_ok(input)?;
+        let partial = Manifest::from_json(&fs::read_to_string(&manifest_path)?)?;
+        for pkg in &partial.packages {
+            for target in &pkg.targets {
+                for file in target.files() {
+                    fs::copy(input.join(file), out.join(file))?;
                 }
             }
-            if let Some(sbom) = &target.sbom {
-                let path = dist.join(&sbom.filename);
-                if !path.exists() {
-                    return Err(anyhow!("missing sbom {}", sbom.filename));
+        }
+        merged = Some(match merged {
+            None => partial,
+            Some(mut acc) => {
+                if acc.project.version != partial.project.version {
+                    return Err(PackageError::VersionMismatch {
+                        partial: input.clone(),
+                        found: partial.project.version,
+                        expected: acc.project.version,
+                    });
                 }
-                let sha = sha256_file(&path)?;
-                if sha != sbom.sha256 {
-                    return Err(anyhow!("sbom hash mismatch {}", sbom.filename));
+                if let (Some(expected), Some(found)) = (
+                    &acc.project.plan_fingerprint,
+                    &partial.project.plan_fingerprint,
+                ) {
+                    if expected != found {
+                        return Err(PackageError::PlanMismatch {
+                            partial: input.clone(),
+                            found: found.clone(),
+                            expected: expected.clone(),
+                        });
+                    }
                 }
+                merge_packages(&mut acc, partial)?;
+                acc
             }
-            for sig in &target.signatures {
-                let path = dist.join(&sig.filename);
-                if !path.exists() {
-                    return Err(anyhow!("missing signature {}", sig.filename));
+        });
+    }
+    let mut manifest = merged.expect("at least one input");
+    manifest.generated_at = Utc::now();
+    let mut checksum_entries: Vec<(String, String)> = Vec::new();
+    for pkg in &manifest.packages {
+        for target in &pkg.targets {
+            for file in target.files() {
+                // aliases list another target's file again
+                if checksum_entries.iter().any(|(_, f)| f == file) {
+                    continue;
                 }
-                if let Some(base) = sig.filename.strip_suffix(".sig") {
-                    let target_path = dist.join(base);
-                    if target_path.exists() {
-                        let sha = sha256_file(&target_path)?;
-                        if let Ok(contents) = fs::read_to_string(&path) {
-                            if contents.trim() == sha {
-                                continue;
-                            }
-                        }
-                        // attempt external verification best-effort
-                        if sig.method == "gpg" {
-                            let _ = std::process::Command::new("gpg")
-                                .args([
-                                    "--verify",
-                                    path.to_string_lossy().as_ref(),
-                                    target_path.to_string_lossy().as_ref(),
-                                ])
-                                .status();
-                        } else if sig.method == "cosign" && which::which("cosign").is_ok() {
-                            let _ = std::process::Command::new("cosign")
-                                .args([
-                                    "verify-blob",
-                                    target_path.to_string_lossy().as_ref(),
-                                    "--signature",
-                                    path.to_string_lossy().as_ref(),
-                                ])
-                                .status();
-                        }
+                checksum_entries.push((sha256_file(&out.join(file))?, file.to_string()));
+            }
+        }
+    }
+    write_dist_index(out, &manifest, checksum_entries)?;
+    Ok(manifest)
+}
+
+fn merge_packages(acc: &mut Manifest, partial: Manifest) -> Result<()> {
+    for pkg in partial.packages {
+        match acc.packages.iter_mut().find(|p| p.name == pkg.name) {
+            Some(existing) => {
+                // a failure on any runner marks the package as failed
+                if pkg.tests.is_some() && existing.tests.as_ref().is_none_or(|t| t.passed) {
+                    existing.tests = pkg.tests;
+                }
+                for target in pkg.targets {
+                    if existing.targets.iter().any(|t| t.target == target.target) {
+                        return Err(PackageError::DuplicateTarget {
+                            package: pkg.name,
+                            target: target.target,
+                        });
                     }
+                    existing.targets.push(target);
                 }
             }
+            None => acc.packages.push(pkg),
         }
     }
     Ok(())
 }
 
-fn create_tar_gz(path: &Path, inputs: &[Utf8PathBuf]) -> Result<()> {
-    let tar_gz = File::create(path)?;
-    let enc = GzEncoder::new(tar_gz, Compression::default());
-    let mut tar = tar::Builder::new(enc);
+/// `compression.gzip`, or flate2's default level.
+fn create_tar_gz(
+    path: &Path,
+    inputs: &[Utf8PathBuf],
+    contents: &[ContentFile],
+    compression: &CompressionConfig,
+    mtime: i64,
+) -> Result<()> {
+    let level = compression
+        .gzip
+        .map_or_else(Compression::default, Compression::new);
+    let enc = GzEncoder::new(BufWriter::new(File::create(path)?), level);
+    // finished explicitly: dropping the encoder would swallow a failed
+    // final write
+    append_tar(enc, inputs, contents, mtime)?
+        .finish()?
+        .flush()?;
+    Ok(())
+}
+
+/// `compression.zstd`, or zstd's default level.
+fn create_tar_zst(
+    path: &Path,
+    inputs: &[Utf8PathBuf],
+    contents: &[ContentFile],
+    compression: &CompressionConfig,
+    mtime: i64,
+) -> Result<()> {
+    let level = compression.zstd.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+    let enc = zstd::Encoder::new(BufWriter::new(File::create(path)?), level)?;
+    append_tar(enc, inputs, contents, mtime)?
+        .finish()?
+        .flush()?;
+    Ok(())
+}
+
+/// Streams each input from disk into a tarball written to `out`, so sizes
+/// aren't limited by memory, and returns `out` to be finished. Entries go
+/// in name order, stamped with `mtime`, so the same files always make the
+/// same tarball.
+fn append_tar<W: Write>(
+    out: W,
+    inputs: &[Utf8PathBuf],
+    contents: &[ContentFile],
+    mtime: i64,
+) -> Result<W> {
+    let mut tar = tar::Builder::new(out);
     for input in inputs {
         let input_path = input.as_std_path();
+        let name = Path::new(input.file_name().unwrap_or("artifact"));
+        let walk = walkdir::WalkDir::new(input_path)
+            .follow_links(true)
+            .sort_by_file_name();
+        for entry in walk {
+            let entry = entry?;
+            let path = match entry.path().strip_prefix(input_path).unwrap() {
+                rel if rel.as_os_str().is_empty() => name.to_path_buf(),
+                rel => name.join(rel),
+            };
+            append_tar_entry(&mut tar, entry.path(), &path, mtime)?;
+        }
+    }
+    for file in contents {
+        append_tar_entry(
+            &mut tar,
+            file.source.as_std_path(),
+            Path::new(&file.path),
+            mtime,
+        )?;
+    }
+    Ok(tar.into_inner()?)
+}
+
+/// Appends `source` as `path` with owner `0:0`, a `0644` or `0755` mode
+/// and `mtime`, leaving out everything about the machine that packed it.
+fn append_tar_entry<W: Write>(
+    tar: &mut tar::Builder<W>,
+    source: &Path,
+    path: &Path,
+    mtime: i64,
+) -> Result<()> {
+    let metadata = fs::metadata(source)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_metadata_in_mode(&metadata, tar::HeaderMode::Deterministic);
+    header.set_mtime(mtime.max(0) as u64);
+    if metadata.is_dir() {
+        tar.append_data(&mut header, path, std::io::empty())?;
+    } else {
+        tar.append_data(&mut header, path, File::open(source)?)?;
+    }
+    Ok(())
+}
+
+/// `mtime` as a zip timestamp, which can't go before 1980.
+fn zip_time(mtime: i64) -> zip::DateTime {
+    use chrono::{Datelike, Timelike};
+    chrono::DateTime::from_timestamp(mtime, 0)
+        .and_then(|t| {
+            zip::DateTime::from_date_and_time(
+                t.year().try_into().ok()?,
+                t.month() as u8,
+                t.day() as u8,
+                t.hour() as u8,
+                t.minute() as u8,
+                t.second() as u8,
+            )
+            .ok()
+        })
+        .unwrap_or_default()
+}
+
+/// The files a tarball (`keep_dir_name`) or [`create_zip`] packs
+/// from `inputs`: a tarball keeps an input directory's name as a prefix, a
+/// zip puts its contents at the root. `contents` go where they are mapped.
+fn archive_layout(
+    inputs: &[Utf8PathBuf],
+    contents: &[ContentFile],
+    keep_dir_name: bool,
+) -> Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    for input in inputs {
+        let input_path = input.as_std_path();
+        let name = input.file_name().unwrap_or("artifact");
         if input_path.is_dir() {
-            tar.append_dir_all(input.file_name().unwrap_or("artifact"), input_path)?;
+            for entry in walkdir::WalkDir::new(input_path).sort_by_file_name() {
+                let entry = entry?;
+                if entry.file_type().is_file() {
+                    let rel = entry.path().strip_prefix(input_path).unwrap();
+                    let rel = rel.to_string_lossy().replace('\\', "/");
+                    entries.push(ArchiveEntry {
+                        path: if keep_dir_name {
+                            format!("{name}/{rel}")
+                        } else {
+                            rel
+                        },
+                        bytes: entry.metadata()?.len(),
+                    });
+                }
+            }
         } else {
-            tar.append_path_with_name(input_path, input.file_name().unwrap())?;
+            entries.push(ArchiveEntry {
+                path: name.to_string(),
+                bytes: fs::metadata(input_path)?.len(),
+            });
         }
     }
-    tar.finish()?;
-    Ok(())
+    for file in contents {
+        entries.push(ArchiveEntry {
+            path: file.path.clone(),
+            bytes: fs::metadata(&file.source)?.len(),
+        });
+    }
+    Ok(entries)
+}
+
+/// `options` for packing `path`: its Unix mode, and Zip64 sizes for files
+/// of 4 GiB or more, which the zip writer refuses without them.
+fn entry_options(options: FileOptions, path: &Path) -> Result<FileOptions> {
+    let metadata = fs::metadata(path)?;
+    let options = options.large_file(metadata.len() >= u64::from(u32::MAX));
+    #[cfg(unix)]
+    let options = {
+        use std::os::unix::fs::PermissionsExt;
+        options.unix_permissions(metadata.permissions().mode() & 0o7777)
+    };
+    Ok(options)
 }
 
-fn create_zip(path: &Path, inputs: &[Utf8PathBuf]) -> Result<()> {
-    let file = File::create(path)?;
+/// Like [`create_tar_gz`], streams inputs; the writer switches to a Zip64
+/// central directory past 65,535 entries or 4 GiB. Entries are deflated
+/// unless `compression.zip` is `store`.
+fn create_zip(
+    path: &Path,
+    inputs: &[Utf8PathBuf],
+    contents: &[ContentFile],
+    compression: &CompressionConfig,
+    mtime: i64,
+) -> Result<()> {
+    let file = BufWriter::new(File::create(path)?);
     let mut zip = ZipWriter::new(file);
-    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let method = match compression.zip.as_deref() {
+        Some("store") => zip::CompressionMethod::Stored,
+        _ => zip::CompressionMethod::Deflated,
+    };
+    let options = FileOptions::default()
+        .compression_method(method)
+        .last_modified_time(zip_time(mtime));
     for input in inputs {
         let input_path = input.as_std_path();
         if input_path.is_dir() {
-            for entry in walkdir::WalkDir::new(input_path) {
+            for entry in walkdir::WalkDir::new(input_path).sort_by_file_name() {
                 let entry = entry?;
                 if entry.file_type().is_file() {
                     let rel = entry.path().strip_prefix(input_path).unwrap();
-                    zip.start_file(rel.to_string_lossy(), options)?;
+                    zip.start_file(rel.to_string_lossy(), entry_options(options, entry.path())?)?;
                     let mut f = File::open(entry.path())?;
                     std::io::copy(&mut f, &mut zip)?;
                 }
             }
         } else {
-            zip.start_file(input.file_name().unwrap_or("artifact").to_string(), options)?;
+            zip.start_file(
+                input.file_name().unwrap_or("artifact").to_string(),
+                entry_options(options, input_path)?,
+            )?;
             let mut f = File::open(input_path)?;
             std::io::copy(&mut f, &mut zip)?;
         }
     }
-    zip.finish()?;
+    for file in contents {
+        let large = fs::metadata(&file.source)?.len() >= u64::from(u32::MAX);
+        zip.start_file(file.path.as_str(), options.large_file(large))?;
+        std::io::copy(&mut File::open(&file.source)?, &mut zip)?;
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// Fails on files of `inputs` that are still Git LFS pointers, as left by a
+/// checkout without LFS, which would otherwise ship as ~130-byte stubs.
+fn reject_lfs_pointers(inputs: &[Utf8PathBuf]) -> Result<()> {
+    const POINTER: &[u8] = b"version https://git-lfs.github.com/spec/v1";
+    for input in inputs {
+        for entry in walkdir::WalkDir::new(input.as_std_path()) {
+            let entry = entry?;
+            // the spec caps pointer files at 1024 bytes
+            if !entry.file_type().is_file() || entry.metadata()?.len() >= 1024 {
+                continue;
+            }
+            if fs::read(entry.path())?.starts_with(POINTER) {
+                return Err(PackageError::LfsPointer(entry.path().to_path_buf()));
+            }
+        }
+    }
     Ok(())
 }
 
-fn write_sbom(path: &Path, name: &str, version: &str, target: &str) -> Result<()> {
-    let sbom = serde_json::json!({
+/// What an SBOM describes: one package target and its archives.
+struct SbomInput<'a> {
+    pkg: &'a PackagePlan,
+    version: &'a str,
+    built: &'a BuiltOutput,
+    archives: &'a [ManifestArtifact],
+}
+
+/// Writes an SPDX (`format = "spdx"`) or else CycloneDX SBOM, with syft
+/// when it is configured or `executor` can run it, else with the builtin
+/// generator, which lists no dependencies.
+fn write_sbom(path: &Path, format: &str, input: &SbomInput, executor: &dyn Executor) -> Result<()> {
+    let pkg = input.pkg;
+    let spdx = format == "spdx";
+    let use_syft = match pkg.sbom.tool.as_deref() {
+        Some(tool) => tool == "syft",
+        None => pkg.sbom.mode != "fallback",
+    };
+    let builtin = || {
+        if spdx {
+            builtin_spdx(input)
+        } else {
+            builtin_cyclonedx()
+        }
+    };
+    let mut sbom = if use_syft {
+        match syft_sbom(pkg, input.built, spdx, executor) {
+            Ok(Some(sbom)) => sbom,
+            Ok(None) if pkg.sbom.tool.is_none() => builtin(),
+            Ok(None) => {
+                return Err(PackageError::Sbom {
+                    package: pkg.name.clone(),
+                    message: "syft is not installed".into(),
+                })
+            }
+            Err(err) if pkg.sbom.tool.is_none() => {
+                tracing::warn!("{err}; writing the builtin SBOM instead");
+                builtin()
+            }
+            Err(err) => return Err(err),
+        }
+    } else {
+        builtin()
+    };
+    // syft names the document after what it scanned
+    if spdx {
+        sbom["name"] = Value::from(spdx_document_name(input));
+    } else {
+        let component = &mut sbom["metadata"]["component"];
+        component["name"] = Value::from(pkg.name.as_str());
+        component["version"] = Value::from(input.version);
+        // CycloneDX has no field for it, so it is a namespaced property
+        let metadata = &mut sbom["metadata"];
+        if !metadata["properties"].is_array() {
+            metadata["properties"] = Value::Array(Vec::new());
+        }
+        if let Some(properties) = metadata["properties"].as_array_mut() {
+            properties.retain(|p| p["name"] != "shippo:target");
+            properties.push(serde_json::json!({
+                "name": "shippo:target",
+                "value": input.built.target,
+            }));
+        }
+    }
+    fs::write(path, serde_json::to_string_pretty(&sbom)?)?;
+    Ok(())
+}
+
+fn builtin_cyclonedx() -> Value {
+    serde_json::json!({
         "bomFormat": "CycloneDX",
         "specVersion": "1.4",
         "version": 1,
-        "metadata": {
-            "component": {"name": name, "version": version, "target": target}
-        },
+        "metadata": {"component": {}},
         "components": []
-    });
-    fs::write(path, serde_json::to_string_pretty(&sbom)?)?;
-    Ok(())
+    })
+}
+
+fn spdx_document_name(input: &SbomInput) -> String {
+    format!(
+        "{}-{}-{}",
+        input.pkg.name, input.version, input.built.target
+    )
 }
 
-fn sign_file(dist: &Path, filename: &str, method: &str) -> Result<Option<String>> {
+/// An SPDX 2.3 document describing the package, contained in each of its
+/// archives. The namespace is derived from the archive digests, so it is
+/// unique per build and stable across re-packaging the same files.
+fn builtin_spdx(input: &SbomInput) -> Value {
+    let name = spdx_document_name(input);
+    let mut digest = Sha256::new();
+    for archive in input.archives {
+        digest.update(archive.sha256.as_bytes());
+    }
+    let namespace = format!(
+        "https://spdx.org/spdxdocs/{name}-{}",
+        &hex::encode(digest.finalize())[..16]
+    );
+    let package_id = format!("SPDXRef-Package-{}", spdx_id(&input.pkg.name));
+    let mut packages = vec![serde_json::json!({
+        "SPDXID": package_id,
+        "name": input.pkg.name,
+        "versionInfo": input.version,
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "licenseConcluded": "NOASSERTION",
+        "licenseDeclared": "NOASSERTION",
+        "copyrightText": "NOASSERTION",
+        "primaryPackagePurpose": "APPLICATION",
+    })];
+    let mut relationships = vec![serde_json::json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": package_id,
+    })];
+    for archive in input.archives {
+        let archive_id = format!("SPDXRef-Archive-{}", spdx_id(&archive.filename));
+        packages.push(serde_json::json!({
+            "SPDXID": archive_id,
+            "name": archive.filename,
+            "versionInfo": input.version,
+            "packageFileName": archive.filename,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "checksums": [{"algorithm": "SHA256", "checksumValue": archive.sha256}],
+            "primaryPackagePurpose": "ARCHIVE",
+        }));
+        relationships.push(serde_json::json!({
+            "spdxElementId": archive_id,
+            "relationshipType": "CONTAINS",
+            "relatedSpdxElement": package_id,
+        }));
+    }
+    serde_json::json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": name,
+        "documentNamespace": namespace,
+        "creationInfo": {
+            "creators": [format!("Tool: shippo-{}", env!("CARGO_PKG_VERSION"))],
+            "created": Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+/// `name` with the characters SPDX identifiers don't allow replaced.
+fn spdx_id(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+            _ => '-',
+        })
+        .collect()
+}
+
+/// Scans the built binary, or the package directory when the build produced
+/// several files, with `syft`; `None` when `executor` finds no syft.
+fn syft_sbom(
+    pkg: &PackagePlan,
+    built: &BuiltOutput,
+    spdx: bool,
+    executor: &dyn Executor,
+) -> Result<Option<Value>> {
+    let source = match built.artifacts.as_slice() {
+        [artifact] if artifact.is_file() => format!("file:{artifact}"),
+        _ => format!("dir:{}", pkg.path),
+    };
+    let failed = |message: String| PackageError::Sbom {
+        package: pkg.name.clone(),
+        message,
+    };
+    let output_format = if spdx { "spdx-json" } else { "cyclonedx-json" };
+    let output = match executor.output(CommandSpec::new("syft").args([
+        "scan",
+        source.as_str(),
+        "-o",
+        output_format,
+        "-q",
+    ])) {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(failed(format!("could not run syft: {e}"))),
+    };
+    if !output.success() {
+        return Err(failed(format!(
+            "syft exited with {}: {}",
+            output.status(),
+            output.stderr.trim()
+        )));
+    }
+    let sbom: Value = serde_json::from_str(&output.stdout)
+        .map_err(|e| failed(format!("syft printed invalid JSON: {e}")))?;
+    let valid = if spdx {
+        sbom["spdxVersion"].is_string()
+    } else {
+        sbom["metadata"].is_object()
+    };
+    if !valid {
+        return Err(failed(format!("syft printed no {output_format} document")));
+    }
+    Ok(Some(sbom))
+}
+
+/// Signs `dist/<filename>`. Keyless cosign signatures come with a bundle
+/// (`<file>.sigstore.json`) holding the signature, the signing certificate
+/// and its transparency-log entry, in place of a `.sig`; key-based ones use
+/// `[sign.cosign]`'s key or KMS URI. SSH signatures are made with
+/// `ssh-keygen -Y sign` and `[sign] ssh_key`.
+fn sign_file(
+    dist: &Path,
+    filename: &str,
+    sign: &SignConfig,
+    plugins: &BTreeMap<String, String>,
+    executor: &dyn Executor,
+) -> Result<Option<ManifestSignature>> {
+    let method = sign.method.as_str();
     let path = dist.join(filename);
     let sig_name = format!("{}.sig", filename);
     let sig_path = dist.join(&sig_name);
     let sha = sha256_file(&path)?;
+    let signature = |bundle: Option<String>| {
+        Ok(Some(ManifestSignature {
+            filename: sig_name.clone(),
+            method: method.to_string(),
+            bundle,
+            rekor: None,
+            signer: None,
+            key: false,
+            offline: false,
+        }))
+    };
+    if let Some(name) = plugin_ref(method) {
+        let params = SignParams {
+            file: path.to_string_lossy().to_string(),
+            output: sig_path.to_string_lossy().to_string(),
+        };
+        let _: Value = plugin::find(name, plugins)?.call("sign", &params)?;
+        if !sig_path.exists() {
+            return Err(PackageError::Missing {
+                kind: "signature",
+                filename: sig_name.clone(),
+            });
+        }
+        return signature(None);
+    }
     if method == "gpg" {
-        let status = Command::new("gpg")
-            .args([
-                "--batch",
-                "--yes",
-                "--detach-sign",
-                "-o",
-                sig_path.to_string_lossy().as_ref(),
-                path.to_string_lossy().as_ref(),
-            ])
-            .status();
-        if let Ok(status) = status {
-            if status.success() {
-                return Ok(Some(sig_name));
-            }
+        return sign_gpg(&path, &sig_path, sign, executor)
+            .map_err(|message| PackageError::Sign {
+                filename: filename.to_string(),
+                method: method.to_string(),
+                message,
+            })
+            .and_then(|()| signature(None));
+    } else if let ("ssh", Some(key)) = (method, &sign.ssh_key) {
+        // writes `<file>.sig` next to the file
+        let namespace = sign.ssh_namespace.as_deref().unwrap_or("file");
+        let status = executor.run(
+            CommandSpec::new("ssh-keygen")
+                .args(["-Y", "sign", "-f", key, "-n", namespace])
+                .arg(path.to_string_lossy()),
+        );
+        if status.is_ok_and(|s| s.success()) && sig_path.exists() {
+            return signature(None);
         }
-        // fall back to embedded signature file
-    } else if method == "cosign" && which::which("cosign").is_ok() {
-        let status = Command::new("cosign")
-            .args([
-                "sign-blob",
-                path.to_string_lossy().as_ref(),
-                "--output",
-                sig_path.to_string_lossy().as_ref(),
-            ])
-            .status();
-        if let Ok(status) = status {
-            if status.success() {
-                return Ok(Some(sig_name));
+    } else if method == "cosign" {
+        // the bundle holds the signature, a keyless signature's certificate
+        // and the rekor entry
+        let bundle = format!("{filename}{SIGSTORE_BUNDLE_SUFFIX}");
+        let mut cmd = CommandSpec::new("cosign");
+        cmd.arg("sign-blob")
+            .arg(path.to_string_lossy())
+            .args(["--yes", "--new-bundle-format", "--bundle"])
+            .arg(dist.join(&bundle).to_string_lossy());
+        let key = sign.cosign_mode != "keyless";
+        let cosign = sign.cosign.clone().unwrap_or_default();
+        let offline = key && !cosign.tlog_upload;
+        if key {
+            if let Some(key) = cosign.kms.as_ref().or(cosign.key.as_ref()) {
+                cmd.args(["--key", key]);
+            }
+            if offline {
+                cmd.arg("--tlog-upload=false");
+            }
+            if let Some(password) = cosign.password_env.and_then(|v| executor.var(&v)) {
+                cmd.env("COSIGN_PASSWORD", password);
             }
         }
+        // not installed falls back to the checksum below, like a failure
+        if executor.run(&cmd).is_ok_and(|s| s.success()) && dist.join(&bundle).exists() {
+            // note which rekor entry cosign got, if it uploaded one
+            let rekor = fs::read_to_string(dist.join(&bundle))
+                .ok()
+                .and_then(|data| bundle::BundleInfo::parse(&data).ok())
+                .and_then(|info| info.tlog)
+                .and_then(|tlog| {
+                    Some(RekorEntry {
+                        log_index: tlog.log_index?,
+                        uuid: tlog.uuid?,
+                    })
+                });
+            return Ok(Some(ManifestSignature {
+                filename: bundle.clone(),
+                method: method.to_string(),
+                bundle: Some(bundle),
+                rekor,
+                signer: None,
+                key,
+                offline,
+            }));
+        }
     }
     fs::write(&sig_path, sha)?;
-    Ok(Some(sig_name))
+    signature(None)
 }
 
-fn tool_version(cmd: &str) -> Option<String> {
-    let mut parts = cmd.split_whitespace();
-    let prog = parts.next()?;
-    let args: Vec<_> = parts.collect();
-    let output = Command::new(prog).args(args).output().ok()?;
-    if !output.status.success() {
-        return None;
+/// Writes `sig` with `gpg --detach-sign`, with `[sign.gpg]`'s key, homedir
+/// and passphrase. A passphrase is handed over in a file only the user can
+/// read, removed afterwards, so it never shows in the process list.
+fn sign_gpg(
+    path: &Path,
+    sig: &Path,
+    sign: &SignConfig,
+    executor: &dyn Executor,
+) -> std::result::Result<(), String> {
+    let gpg = sign.gpg.clone().unwrap_or_default();
+    let mut cmd = CommandSpec::new("gpg");
+    cmd.args(["--batch", "--yes"]);
+    if let Some(homedir) = &gpg.homedir {
+        cmd.args(["--homedir", homedir]);
+    }
+    if let Some(key_id) = &gpg.key_id {
+        cmd.args(["--local-user", key_id]);
     }
-    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    let mut passphrase_file = None;
+    if let Some(var) = &gpg.passphrase_env {
+        let passphrase = executor.var(var).ok_or(format!("{var} is not set"))?;
+        let file = tempfile::NamedTempFile::new().map_err(|e| e.to_string())?;
+        fs::write(file.path(), passphrase).map_err(|e| e.to_string())?;
+        cmd.args(["--pinentry-mode", "loopback", "--passphrase-file"])
+            .arg(file.path().to_string_lossy());
+        passphrase_file = Some(file);
+    }
+    cmd.arg("--detach-sign")
+        .arg("-o")
+        .arg(sig.to_string_lossy())
+        .arg(path.to_string_lossy());
+    let output = executor.output(&cmd).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => "gpg is not installed".to_string(),
+        _ => e.to_string(),
+    })?;
+    drop(passphrase_file);
+    if !output.success() {
+        return Err(format!("{}: {}", output.status(), output.stderr.trim()));
+    }
+    if !sig.exists() {
+        return Err("gpg wrote no signature".into());
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -350,9 +1448,138 @@ mod tests {
         let artifact = Utf8PathBuf::from_path_buf(file).unwrap();
         let out_dir = dir.path().join("dist");
         fs::create_dir_all(&out_dir).unwrap();
-        create_tar_gz(&out_dir.join("a.tar.gz"), std::slice::from_ref(&artifact)).unwrap();
-        create_zip(&out_dir.join("a.zip"), std::slice::from_ref(&artifact)).unwrap();
+        let inputs = std::slice::from_ref(&artifact);
+        let defaults = CompressionConfig::default();
+        create_tar_gz(&out_dir.join("a.tar.gz"), inputs, &[], &defaults, 0).unwrap();
+        create_zip(&out_dir.join("a.zip"), inputs, &[], &defaults, 0).unwrap();
         assert!(out_dir.join("a.tar.gz").exists());
         assert!(out_dir.join("a.zip").exists());
+
+        let tuned = CompressionConfig {
+            gzip: Some(9),
+            zstd: Some(19),
+            zip: Some("store".into()),
+        };
+        create_tar_zst(&out_dir.join("b.tar.zst"), inputs, &[], &tuned, 0).unwrap();
+        let decoder = zstd::Decoder::new(File::open(out_dir.join("b.tar.zst")).unwrap()).unwrap();
+        let mut tar = tar::Archive::new(decoder);
+        let mut entry = tar.entries().unwrap().next().unwrap().unwrap();
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut text).unwrap();
+        assert_eq!(
+            (entry.path().unwrap().to_str(), text.as_str()),
+            (Some("file.txt"), "hi")
+        );
+
+        create_zip(&out_dir.join("b.zip"), inputs, &[], &tuned, 0).unwrap();
+        let mut zip = zip::ZipArchive::new(File::open(out_dir.join("b.zip")).unwrap()).unwrap();
+        let stored = zip.by_index(0).unwrap();
+        assert_eq!(stored.compression(), zip::CompressionMethod::Stored);
+    }
+
+    #[test]
+    fn test_archives_ignore_file_times_and_order() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("app");
+        fs::create_dir_all(input.join("lib")).unwrap();
+        for name in ["lib/b.so", "app", "lib/a.so"] {
+            fs::write(input.join(name), name).unwrap();
+        }
+        let input = Utf8PathBuf::from_path_buf(input).unwrap();
+        let epoch = 1_700_000_000;
+        let pack = |stamp: std::time::SystemTime| {
+            for name in ["lib/b.so", "app", "lib/a.so"] {
+                let file = File::options().write(true).open(input.join(name)).unwrap();
+                file.set_modified(stamp).unwrap();
+            }
+            let inputs = std::slice::from_ref(&input);
+            let defaults = CompressionConfig::default();
+            let (tgz, zip) = (dir.path().join("a.tar.gz"), dir.path().join("a.zip"));
+            create_tar_gz(&tgz, inputs, &[], &defaults, epoch).unwrap();
+            create_zip(&zip, inputs, &[], &defaults, epoch).unwrap();
+            (fs::read(tgz).unwrap(), fs::read(zip).unwrap())
+        };
+        let first = pack(std::time::SystemTime::UNIX_EPOCH);
+        assert_eq!(pack(std::time::SystemTime::now()), first);
+
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(&first.0[..]));
+        let entries: Vec<_> = tar
+            .entries()
+            .unwrap()
+            .map(|e| {
+                let header = e.unwrap().header().clone();
+                assert_eq!(header.mtime().unwrap(), epoch as u64);
+                assert_eq!(header.uid().unwrap(), 0);
+                header.path().unwrap().to_string_lossy().into_owned()
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            ["app", "app/app", "app/lib", "app/lib/a.so", "app/lib/b.so"]
+        );
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(&first.1)).unwrap();
+        let modified = zip.by_index(0).unwrap().last_modified();
+        assert_eq!((modified.year(), modified.month()), (2023, 11));
+    }
+
+    #[test]
+    #[ignore = "writes and packs 70,000 files; run with --ignored"]
+    fn test_zip64_for_many_entries() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("data");
+        fs::create_dir_all(&input).unwrap();
+        for i in 0..70_000 {
+            fs::write(input.join(format!("{i:05}")), "").unwrap();
+        }
+        let input = Utf8PathBuf::from_path_buf(input).unwrap();
+        let zip_path = dir.path().join("many.zip");
+        create_zip(
+            &zip_path,
+            std::slice::from_ref(&input),
+            &[],
+            &Default::default(),
+            0,
+        )
+        .unwrap();
+        let zip = zip::ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        assert_eq!(zip.len(), 70_000);
+    }
+
+    #[test]
+    #[ignore = "packs a 4.5 GiB sparse file; run with --ignored"]
+    fn test_large_sparse_file_in_tar_and_zip() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("game.pak");
+        let size = 4_500_000_000u64;
+        let file = File::create(&input).unwrap();
+        file.set_len(size).unwrap();
+        drop(file);
+        let input = Utf8PathBuf::from_path_buf(input).unwrap();
+
+        let tar_path = dir.path().join("big.tar.gz");
+        create_tar_gz(
+            &tar_path,
+            std::slice::from_ref(&input),
+            &[],
+            &Default::default(),
+            0,
+        )
+        .unwrap();
+        let mut tar =
+            tar::Archive::new(flate2::read::GzDecoder::new(File::open(&tar_path).unwrap()));
+        let entry = tar.entries().unwrap().next().unwrap().unwrap();
+        assert_eq!(entry.size(), size);
+
+        let zip_path = dir.path().join("big.zip");
+        create_zip(
+            &zip_path,
+            std::slice::from_ref(&input),
+            &[],
+            &Default::default(),
+            0,
+        )
+        .unwrap();
+        let mut zip = zip::ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        assert_eq!(zip.by_index(0).unwrap().size(), size);
     }
 }
@@ -0,0 +1,51 @@
+//! Advisory lock on a dist directory, so concurrent shippo runs sharing an
+//! output directory cannot interleave writes to the manifest and checksums.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{PackageError, Result};
+
+pub const DIST_LOCK_FILE: &str = ".shippo-dist.lock";
+
+/// Holds the lock until dropped. The lock file itself is left in place;
+/// removing it would let a waiting process lock a file nobody else sees.
+#[derive(Debug)]
+pub struct DistLock {
+    _file: File,
+    path: PathBuf,
+}
+
+impl DistLock {
+    /// Locks `dist`, creating it if needed. Fails immediately with
+    /// [`PackageError::DistLocked`] if another process holds the lock.
+    pub fn acquire(dist: &Path) -> Result<Self> {
+        fs::create_dir_all(dist)?;
+        let path = dist.join(DIST_LOCK_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder = fs::read_to_string(&path).unwrap_or_default();
+                return Err(PackageError::DistLocked {
+                    dist: dist.to_path_buf(),
+                    holder: holder.trim().to_string(),
+                });
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        file.set_len(0)?;
+        writeln!(file, "pid {}", std::process::id())?;
+        Ok(Self { _file: file, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
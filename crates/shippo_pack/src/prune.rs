@@ -0,0 +1,193 @@
+//! Pruning of per-version output directories, e.g. `[build] scratch_dir =
+//! ".build/{version}"`, so a build machine keeps only the last few releases.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use semver::Version;
+use walkdir::WalkDir;
+
+/// One version's directory under a versioned output template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionDir {
+    pub version: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+/// The directories `template` (relative to `dist` or absolute, with a
+/// `{version}` placeholder) has produced, newest first: those whose version
+/// is one of this project's, with `current`'s prefix (`v`, `cli-v`) and a
+/// semver version after it. Empty when the template is not versioned.
+pub fn version_dirs(dist: &Path, template: &str, current: &str) -> io::Result<Vec<VersionDir>> {
+    let parts: Vec<_> = Path::new(template).components().collect();
+    let Some(at) = parts
+        .iter()
+        .position(|p| p.as_os_str().to_string_lossy().contains("{version}"))
+    else {
+        return Ok(Vec::new());
+    };
+    let parent = dist.join(parts[..at].iter().collect::<PathBuf>());
+    let versioned = parts[at].as_os_str().to_string_lossy();
+    let (prefix, suffix) = versioned.split_once("{version}").unwrap_or_default();
+    let entries = match fs::read_dir(&parent) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut dirs = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(version) = name
+            .strip_prefix(prefix)
+            .and_then(|n| n.strip_suffix(suffix))
+            .filter(|v| same_project(v, current))
+        else {
+            continue;
+        };
+        let modified = entry
+            .metadata()?
+            .modified()
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let dir = VersionDir {
+            version: version.to_string(),
+            bytes: dir_size(&entry.path())?,
+            path: entry.path(),
+        };
+        dirs.push((modified, dir));
+    }
+    dirs.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| b.1.version.cmp(&a.1.version)));
+    Ok(dirs.into_iter().map(|(_, dir)| dir).collect())
+}
+
+/// Removes all but the newest `keep` version directories of `template`,
+/// never `current`'s, and returns the removed ones.
+pub fn prune_versions(
+    dist: &Path,
+    template: &str,
+    keep: usize,
+    current: &str,
+) -> io::Result<Vec<VersionDir>> {
+    let mut dirs = version_dirs(dist, template, current)?;
+    // the current version counts towards `keep` wherever its mtime puts it
+    if let Some(at) = dirs.iter().position(|d| d.version == current) {
+        let dir = dirs.remove(at);
+        dirs.insert(0, dir);
+    }
+    let removed: Vec<VersionDir> = dirs
+        .into_iter()
+        .enumerate()
+        .filter(|(i, d)| *i >= keep && d.version != current)
+        .map(|(_, d)| d)
+        .collect();
+    for dir in &removed {
+        fs::remove_dir_all(&dir.path)?;
+    }
+    Ok(removed)
+}
+
+/// Whether `version` has `current`'s prefix followed by a semver version,
+/// so `v1.2.0` but not `cli-v1.2.0` or `build` for `v1.0.0`. Versions of
+/// a `current` that isn't semver only match it exactly.
+fn same_project(version: &str, current: &str) -> bool {
+    let split = |v: &str| {
+        v.char_indices()
+            .filter(|(_, c)| c.is_ascii_digit())
+            .find(|(i, _)| Version::parse(&v[*i..]).is_ok())
+            .map(|(i, _)| i)
+    };
+    match split(current) {
+        Some(at) => version
+            .strip_prefix(&current[..at])
+            .is_some_and(|rest| Version::parse(rest).is_ok()),
+        None => version == current,
+    }
+}
+
+fn dir_size(dir: &Path) -> io::Result<u64> {
+    let mut bytes = 0;
+    for entry in WalkDir::new(dir) {
+        let entry = entry.map_err(io::Error::other)?;
+        if entry.file_type().is_file() {
+            bytes += entry.metadata().map_err(io::Error::other)?.len();
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_prune_keeps_newest_and_current() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = SystemTime::now() - Duration::from_secs(3600);
+        for (i, version) in ["v1.0.0", "v1.1.0", "v1.2.0", "v2.0.0"].iter().enumerate() {
+            let path = dir.path().join(".build").join(format!("out-{version}"));
+            fs::create_dir_all(path.join("target")).unwrap();
+            fs::write(path.join("target").join("app"), "binary").unwrap();
+            let modified = base + Duration::from_secs(60 * i as u64);
+            fs::File::open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        fs::create_dir_all(dir.path().join(".build").join("other")).unwrap();
+
+        let template = ".build/out-{version}";
+        let found = version_dirs(dir.path(), template, "v2.0.0").unwrap();
+        let versions: Vec<_> = found.iter().map(|d| d.version.as_str()).collect();
+        assert_eq!(versions, ["v2.0.0", "v1.2.0", "v1.1.0", "v1.0.0"]);
+        assert_eq!(found[0].bytes, 6);
+
+        // rebuilding an old version keeps it
+        let removed = prune_versions(dir.path(), template, 2, "v1.0.0").unwrap();
+        let removed: Vec<_> = removed.iter().map(|d| d.version.as_str()).collect();
+        assert_eq!(removed, ["v1.2.0", "v1.1.0"]);
+        let left: Vec<_> = version_dirs(dir.path(), template, "v1.0.0")
+            .unwrap()
+            .into_iter()
+            .map(|d| d.version)
+            .collect();
+        assert_eq!(left, ["v2.0.0", "v1.0.0"]);
+        assert!(dir.path().join(".build").join("other").exists());
+        assert!(version_dirs(dir.path(), ".build", "v1.0.0")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_prune_only_this_projects_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["v1.0.0", "v1.1.0", "cli-v0.9.0", "v1.x", "sboms", "build"] {
+            fs::create_dir_all(dir.path().join(name)).unwrap();
+        }
+        let versions = |current: &str| -> Vec<String> {
+            let mut found: Vec<_> = version_dirs(dir.path(), "{version}", current)
+                .unwrap()
+                .into_iter()
+                .map(|d| d.version)
+                .collect();
+            found.sort();
+            found
+        };
+        assert_eq!(versions("v1.1.0"), ["v1.0.0", "v1.1.0"]);
+        assert_eq!(versions("cli-v1.0.0"), ["cli-v0.9.0"]);
+        assert_eq!(versions("nightly"), Vec::<String>::new());
+
+        let removed = prune_versions(dir.path(), "{version}", 1, "v1.1.0").unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].version, "v1.0.0");
+        for name in ["v1.1.0", "cli-v0.9.0", "v1.x", "sboms", "build"] {
+            assert!(dir.path().join(name).exists(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! `[verify.signers]`: signatures several people add to a packaged dist,
+//! each on their own machine with [`sign_as`], gathered from their copies
+//! into one dist with [`merge_signatures`].
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use shippo_core::exec::{CommandSpec, Executor};
+use shippo_core::{
+    check_file_name, sha256_file, valid_signer, Manifest, ManifestArtifact, ManifestSignature,
+    ManifestTarget,
+};
+
+use crate::{
+    rewrite_index_file, update_manifest, verifying, PackageError, Result, MANIFEST_FILE,
+    PARTIAL_MANIFEST_FILE, SUMS_FILE, VERIFYING_FILE,
+};
+
+/// Signs every archive and SBOM in `dist` with `signer`'s minisign secret
+/// `key`, as `<file>.<signer>.minisig`, and records the signatures in the
+/// manifest and `SHA256SUMS`. minisign asks for the key's password. Signing
+/// again replaces the signer's earlier signatures. Returns the signature
+/// files.
+pub fn sign_as(
+    dist: &Path,
+    signer: &str,
+    key: &Path,
+    executor: &dyn Executor,
+) -> Result<Vec<String>> {
+    let failed = |filename: &str, message: String| PackageError::Sign {
+        filename: filename.to_string(),
+        method: "minisign".into(),
+        message,
+    };
+    if !valid_signer(signer) {
+        return Err(failed(
+            signer,
+            "signer names are letters, digits, `-` and `_`".into(),
+        ));
+    }
+    let mut manifest = read_manifest(dist)?;
+    let mut added = Vec::new();
+    for target in manifest.packages.iter_mut().flat_map(|p| &mut p.targets) {
+        let files: Vec<String> = signed_artifacts(target)
+            .map(|a| a.filename.clone())
+            .collect();
+        for file in files {
+            let sig_name = format!("{file}.{signer}.minisig");
+            check_file_name(&sig_name).map_err(|reason| PackageError::InvalidName {
+                filename: sig_name.clone(),
+                reason,
+            })?;
+            let sig_path = dist.join(&sig_name);
+            let mut cmd = CommandSpec::new("minisign");
+            cmd.args(["-S", "-s"])
+                .arg(key.to_string_lossy())
+                .arg("-m")
+                .arg(dist.join(&file).to_string_lossy())
+                .arg("-x")
+                .arg(sig_path.to_string_lossy());
+            let output = executor.run(&cmd).map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => failed(&file, "minisign is not installed".into()),
+                _ => failed(&file, e.to_string()),
+            })?;
+            if !output.success() {
+                return Err(failed(&file, format!("minisign {}", output.status())));
+            }
+            if !sig_path.exists() {
+                return Err(failed(&file, "minisign wrote no signature".into()));
+            }
+            add_signature(
+                target,
+                ManifestSignature {
+                    filename: sig_name.clone(),
+                    method: "minisign".into(),
+                    bundle: None,
+                    rekor: None,
+                    signer: Some(signer.to_string()),
+                    key: false,
+                    offline: false,
+                },
+            );
+            added.push(sig_name);
+        }
+    }
+    record_signatures(dist, &manifest, &added)?;
+    Ok(added)
+}
+
+/// Copies the signers' signatures from `inputs`, copies of the dist each
+/// signed with [`sign_as`], into `dist` and records them. Every signed file
+/// must have the digest it has in `dist`'s manifest, so only signatures of
+/// this very dist are taken. Returns the signatures added.
+pub fn merge_signatures(dist: &Path, inputs: &[PathBuf]) -> Result<Vec<String>> {
+    let mut manifest = read_manifest(dist)?;
+    let mut added = Vec::new();
+    for input in inputs {
+        let other = read_manifest(input)?;
+        if other.project.version != manifest.project.version {
+            return Err(PackageError::VersionMismatch {
+                partial: input.clone(),
+                found: other.project.version,
+                expected: manifest.project.version,
+            });
+        }
+        for pkg in &other.packages {
+            for theirs in &pkg.targets {
+                let mut ours = manifest
+                    .packages
+                    .iter_mut()
+                    .filter(|p| p.name == pkg.name)
+                    .flat_map(|p| &mut p.targets)
+                    .find(|t| t.target == theirs.target);
+                for sig in theirs.signatures.iter().filter(|s| s.signer.is_some()) {
+                    let file = sig.signed_file().unwrap_or_default();
+                    let digest = |target: &ManifestTarget| {
+                        signed_artifacts(target)
+                            .find(|a| a.filename == file)
+                            .map(|a| a.sha256.clone())
+                    };
+                    let Some(ours) = ours
+                        .as_deref_mut()
+                        .filter(|t| digest(t).is_some() && digest(t) == digest(theirs))
+                    else {
+                        return Err(PackageError::SignatureMismatch {
+                            input: input.clone(),
+                            filename: sig.filename.clone(),
+                        });
+                    };
+                    if ours.signatures.iter().any(|s| s.filename == sig.filename) {
+                        continue;
+                    }
+                    fs::copy(input.join(&sig.filename), dist.join(&sig.filename))?;
+                    add_signature(ours, sig.clone());
+                    added.push(sig.filename.clone());
+                }
+            }
+        }
+    }
+    record_signatures(dist, &manifest, &added)?;
+    Ok(added)
+}
+
+/// What signers sign of `target`: its archives, except aliases, whose file
+/// is signed with the target that has it, and its SBOMs.
+pub(crate) fn signed_artifacts(target: &ManifestTarget) -> impl Iterator<Item = &ManifestArtifact> {
+    let originals = target.artifacts.iter().filter(|a| a.alias.is_none());
+    originals.chain(target.sboms())
+}
+
+fn add_signature(target: &mut ManifestTarget, sig: ManifestSignature) {
+    target.signatures.retain(|s| s.filename != sig.filename);
+    target.signatures.push(sig);
+}
+
+fn read_manifest(dist: &Path) -> Result<Manifest> {
+    let path = [MANIFEST_FILE, PARTIAL_MANIFEST_FILE]
+        .iter()
+        .map(|name| dist.join(name))
+        .find(|p| p.exists())
+        .ok_or_else(|| PackageError::Missing {
+            kind: "manifest",
+            filename: dist.join(MANIFEST_FILE).display().to_string(),
+        })?;
+    Ok(Manifest::from_json(&fs::read_to_string(path)?)?)
+}
+
+/// Writes `manifest`, lists the `added` signatures in `SHA256SUMS` in
+/// place of any earlier entries, and rewrites `VERIFYING.md` for them.
+fn record_signatures(dist: &Path, manifest: &Manifest, added: &[String]) -> Result<()> {
+    let sums = dist.join(SUMS_FILE);
+    if sums.exists() {
+        let mut out = String::new();
+        for line in fs::read_to_string(&sums)?.lines() {
+            match line.split_once("  ") {
+                Some((_, file)) if added.iter().any(|a| a == file) => {}
+                _ => {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+        }
+        for file in added {
+            out.push_str(&format!("{}  {file}\n", sha256_file(&dist.join(file))?));
+        }
+        fs::write(&sums, out)?;
+    }
+    update_manifest(dist, manifest)?;
+    if dist.join(VERIFYING_FILE).exists() {
+        rewrite_index_file(
+            dist,
+            VERIFYING_FILE,
+            &verifying::instructions(dist, manifest),
+        )?;
+    }
+    Ok(())
+}
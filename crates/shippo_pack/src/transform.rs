@@ -0,0 +1,130 @@
+//! `[[package.transform]]` steps: renaming built files, setting their
+//! permissions and wrapping them in launcher scripts before archiving.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use camino::Utf8PathBuf;
+use shippo_core::{naming_template, PackagePlan};
+
+use crate::contents::wildcard;
+use crate::{BuiltOutput, PackageError, Result};
+
+/// Scratch directory in the dist that transformed files are staged in.
+pub const TRANSFORM_DIR: &str = ".transform";
+
+/// The files to archive for `built` after its package's transform steps;
+/// `root` is the workspace root. Steps apply in order to every file whose
+/// name matches; a later `mode` or `wrap` overrides an earlier one.
+///
+/// Transformed files are copied to `stage/<package>/<target>`, leaving the
+/// builder's outputs alone. A wrapped file moves to `libexec/` and the
+/// launcher, rendered with `{name}`, `{version}`, `{target}` and `{binary}`
+/// (its path from the archive root), takes its name with mode 0755.
+/// Directories and files no step matches are packed as they are.
+pub fn apply_transforms(
+    root: &Path,
+    pkg: &PackagePlan,
+    version: &str,
+    built: &BuiltOutput,
+    stage: &Path,
+) -> Result<Vec<Utf8PathBuf>> {
+    let steps = &pkg.package.transform;
+    if steps.is_empty() {
+        return Ok(built.artifacts.clone());
+    }
+    let failed = |message: String| PackageError::Transform {
+        package: pkg.name.clone(),
+        message,
+    };
+    let stage = stage.join(&pkg.name).join(&built.target);
+    if stage.exists() {
+        fs::remove_dir_all(&stage)?;
+    }
+    let stage = Utf8PathBuf::from_path_buf(stage)
+        .map_err(|s| failed(format!("{} is not valid UTF-8", s.display())))?;
+    let target = pkg.package.target_name(&built.target);
+    let libexec = stage.join("libexec");
+
+    let mut artifacts = Vec::new();
+    for artifact in &built.artifacts {
+        let file_name = artifact.file_name().unwrap_or_default();
+        let matching: Vec<_> = steps
+            .iter()
+            .filter(|s| wildcard(s.pattern.as_bytes(), file_name.as_bytes()))
+            .collect();
+        if artifact.is_dir() || matching.is_empty() {
+            artifacts.push(artifact.clone());
+            continue;
+        }
+        let ext = artifact
+            .extension()
+            .map(|e| format!(".{e}"))
+            .unwrap_or_default();
+        let mut name = file_name.to_string();
+        let (mut mode, mut wrap) = (None, None);
+        for step in matching {
+            if let Some(rename) = &step.rename {
+                name = naming_template(rename, &pkg.name, version, &target).replace("{ext}", &ext);
+                if name.is_empty() || name.contains(['/', '\\']) {
+                    return Err(failed(format!(
+                        "rename of {file_name} gives {name:?}, which is not a file name"
+                    )));
+                }
+            }
+            mode = step.mode_bits().or(mode);
+            wrap = step.wrap.as_deref().or(wrap);
+        }
+        let dest = match wrap {
+            Some(_) => libexec.join(&name),
+            None => stage.join(&name),
+        };
+        if dest.exists() {
+            return Err(failed(format!("two files would be packed as {name}")));
+        }
+        fs::create_dir_all(dest.parent().unwrap_or(&stage))?;
+        fs::copy(artifact, &dest)?;
+        if let Some(bits) = mode {
+            set_mode(dest.as_std_path(), bits)?;
+        }
+        let Some(script) = wrap else {
+            artifacts.push(dest);
+            continue;
+        };
+        let script = root.join(pkg.path.as_str()).join(script);
+        let template = fs::read_to_string(&script)
+            .map_err(|e| failed(format!("launcher {}: {e}", script.display())))?;
+        let launcher = stage.join(&name);
+        fs::write(
+            &launcher,
+            naming_template(&template, &pkg.name, version, &target)
+                .replace("{binary}", &format!("libexec/{name}")),
+        )?;
+        set_mode(launcher.as_std_path(), 0o755)?;
+        artifacts.push(launcher);
+        if !artifacts.contains(&libexec) {
+            artifacts.push(libexec.clone());
+        }
+    }
+
+    let mut names = BTreeSet::new();
+    for artifact in &artifacts {
+        let name = artifact.file_name().unwrap_or_default();
+        if !names.insert(name) {
+            return Err(failed(format!("two files would be packed as {name}")));
+        }
+    }
+    Ok(artifacts)
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, bits: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(bits))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _bits: u32) -> std::io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,1067 @@
+//! Checks a dist against its manifest and reports every problem at once.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use shippo_core::exec::{CommandOutput, CommandSpec, Executor, COMMANDS_FILE};
+use shippo_core::provenance::Provenance;
+use shippo_core::{
+    sha256_file, Manifest, ManifestArtifact, ManifestSignature, Plan, SignersConfig,
+    VerifyKeysConfig, CHANGELOG_FILE, SIGSTORE_BUNDLE_SUFFIX, SUMMARY_FILE,
+};
+
+use crate::attest;
+use crate::bundle::BundleInfo;
+use crate::signers::signed_artifacts;
+use crate::{
+    PackageError, Result, MANIFEST_FILE, PARTIAL_MANIFEST_FILE, PROVENANCE_FILE, SUMS_FILE,
+    VERIFYING_FILE,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Check {
+    /// The file exists and matches the manifest's sha256.
+    Checksum,
+    /// A detached signature verifies against the file it signs.
+    Signature,
+    /// `SHA256SUMS` lists the file with the same digest as the manifest.
+    Sums,
+    /// The file is in the dist but not in the manifest, so nothing vouches
+    /// for it although it would be uploaded with the release.
+    Unlisted,
+    /// `provenance.json` is well-formed and matches the manifest.
+    Provenance,
+    /// An archive holds exactly the files and sizes the manifest records.
+    Contents,
+    /// A keyless signature's certificate was valid when the transparency
+    /// log recorded the signature.
+    Certificate,
+    /// The bundle's inclusion proof shows its rekor entry, the one the
+    /// manifest recorded, is in the log.
+    Inclusion,
+    /// Rebuilding from source produced the same digest.
+    Rebuild,
+    /// The config and plan recorded in the manifest match the working tree.
+    Config,
+    /// Enough `[verify.signers]` signed the file.
+    Threshold,
+}
+
+impl Check {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Check::Checksum => "checksum",
+            Check::Signature => "signature",
+            Check::Sums => "sha256sums",
+            Check::Unlisted => "unlisted",
+            Check::Provenance => "provenance",
+            Check::Contents => "contents",
+            Check::Certificate => "certificate",
+            Check::Inclusion => "inclusion",
+            Check::Rebuild => "rebuild",
+            Check::Config => "config",
+            Check::Threshold => "threshold",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    /// A problem that only fails strict verification.
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "pass",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "fail",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyOptions {
+    /// Fail on signatures that cannot be verified and on files the manifest
+    /// doesn't list, instead of warning.
+    pub strict: bool,
+    /// Also open every archive and compare its contents with the manifest.
+    pub deep: bool,
+    /// Public keys signatures are checked against, with paths resolved.
+    pub keys: VerifyKeysConfig,
+    /// `[verify.signers]`, with paths resolved: how many of them must have
+    /// signed every archive and SBOM, and their keys.
+    pub signers: Option<SignersConfig>,
+    /// The directory holds files a user downloaded from a published release
+    /// rather than a dist: files the manifest lists but that are not there
+    /// are warnings, except the signatures of files that are, and other
+    /// files in the directory are ignored.
+    pub downloaded: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifyCheck {
+    /// File name within the dist.
+    pub artifact: String,
+    pub check: Check,
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub details: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VerifyReport {
+    pub checks: Vec<VerifyCheck>,
+}
+
+impl VerifyReport {
+    pub fn ok(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &VerifyCheck> {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Fail)
+    }
+
+    /// Turns failed checks into [`PackageError::VerifyFailed`].
+    pub fn ensure_ok(self, dist: &Path) -> Result<Self> {
+        if self.ok() {
+            return Ok(self);
+        }
+        Err(PackageError::VerifyFailed {
+            dist: dist.to_path_buf(),
+            failures: self
+                .failures()
+                .map(|c| format!("{} {}: {}", c.artifact, c.check.as_str(), c.details))
+                .collect(),
+        })
+    }
+
+    fn push(&mut self, artifact: &str, check: Check, outcome: Result<(), String>) {
+        self.push_with(artifact, check, outcome, CheckStatus::Fail);
+    }
+
+    fn push_with(
+        &mut self,
+        artifact: &str,
+        check: Check,
+        outcome: Result<(), String>,
+        failed: CheckStatus,
+    ) {
+        let (status, details) = match outcome {
+            Ok(()) => (CheckStatus::Pass, String::new()),
+            Err(details) => (failed, details),
+        };
+        self.checks.push(VerifyCheck {
+            artifact: artifact.to_string(),
+            check,
+            status,
+            details,
+        });
+    }
+}
+
+/// Checks every file in the manifest against its checksum and verifies
+/// signatures with `gpg`/`cosign`. For a complete dist, `SHA256SUMS` and
+/// `provenance.json` are cross-checked against the manifest too. Signatures
+/// that cannot be verified (tool missing, rejected, or a checksum placeholder
+/// left when signing failed) and files the manifest doesn't list fail in
+/// strict mode and are warnings otherwise. With `[verify.signers]`, files
+/// short of the threshold fail in either mode. In deep mode archives are
+/// opened too; see [`verify_contents`].
+///
+/// Only an unreadable manifest is an error; problems with the dist are
+/// collected in the report.
+pub fn verify_manifest(
+    manifest_path: &Path,
+    dist: &Path,
+    options: &VerifyOptions,
+    executor: &dyn Executor,
+) -> Result<VerifyReport> {
+    let data = fs::read_to_string(manifest_path)?;
+    let name = manifest_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| MANIFEST_FILE.to_string());
+    verify_manifest_data(&data, &name, dist, options, executor)
+}
+
+/// [`verify_manifest`] for a manifest that was read elsewhere, e.g. fetched
+/// from a release page. `manifest_name` is the file name `SHA256SUMS` lists
+/// it under.
+pub fn verify_manifest_data(
+    data: &str,
+    manifest_name: &str,
+    dist: &Path,
+    options: &VerifyOptions,
+    executor: &dyn Executor,
+) -> Result<VerifyReport> {
+    let manifest = Manifest::from_json(data)?;
+    let mut report = VerifyReport::default();
+    // only a download may lack files; there a missing file is not a failure
+    let absent = |name: &str| options.downloaded && !dist.join(name).exists();
+    let not_downloaded = || Err("not downloaded".to_string());
+    let unverified = if options.strict {
+        CheckStatus::Fail
+    } else {
+        CheckStatus::Warn
+    };
+    // file name to the digest the manifest vouches for; signatures have none
+    let mut listed: BTreeMap<String, Option<String>> = BTreeMap::new();
+    for pkg in &manifest.packages {
+        for target in &pkg.targets {
+            let attestation = target.attestation.iter();
+            for art in target
+                .artifacts
+                .iter()
+                .chain(target.sboms())
+                .chain(attestation)
+            {
+                listed.insert(art.filename.clone(), Some(art.sha256.clone()));
+                if absent(&art.filename) {
+                    report.push_with(
+                        &art.filename,
+                        Check::Checksum,
+                        not_downloaded(),
+                        CheckStatus::Warn,
+                    );
+                    continue;
+                }
+                report.push(&art.filename, Check::Checksum, check_checksum(dist, art));
+            }
+            for sig in &target.signatures {
+                listed.insert(sig.filename.clone(), None);
+                if let Some(bundle) = &sig.bundle {
+                    listed.insert(bundle.clone(), None);
+                    if sig.key {
+                        // no certificate; cosign checks it with the key
+                    } else if absent(bundle) {
+                        report.push_with(
+                            bundle,
+                            Check::Certificate,
+                            not_downloaded(),
+                            CheckStatus::Warn,
+                        );
+                    } else {
+                        let (outcome, failed) = check_bundle(&dist.join(bundle), Utc::now());
+                        report.push_with(bundle, Check::Certificate, outcome, failed);
+                        if let Some((outcome, failed)) = check_inclusion(&dist.join(bundle), sig) {
+                            report.push_with(bundle, Check::Inclusion, outcome, failed);
+                        }
+                    }
+                }
+            }
+            // signers whose signature of each file verified
+            let mut signed_by: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+            for sig in &target.signatures {
+                let path = dist.join(&sig.filename);
+                if absent(&sig.filename) {
+                    // a downloaded file without its signature is unverified;
+                    // the threshold decides how many signers' signatures it needs
+                    let counted = sig.signer.is_some() && options.signers.is_some();
+                    let (outcome, failed) = match sig.signed_file() {
+                        Some(file) if !absent(file) && !counted => (
+                            Err(format!("not downloaded, so {file} is unverified")),
+                            unverified,
+                        ),
+                        _ => (not_downloaded(), CheckStatus::Warn),
+                    };
+                    report.push_with(&sig.filename, Check::Signature, outcome, failed);
+                    continue;
+                }
+                if !path.exists() {
+                    report.push(&sig.filename, Check::Signature, Err("missing".into()));
+                    continue;
+                }
+                let signed = sig.signed_file().map(|base| dist.join(base));
+                let bundle = sig.bundle.as_ref().map(|b| dist.join(b));
+                let (outcome, failed) = match signed {
+                    Some(signed) if signed.exists() => (
+                        signature_keys(sig, options).and_then(|keys| {
+                            check_signature(
+                                &path,
+                                &signed,
+                                sig,
+                                bundle.as_deref().filter(|b| b.exists()),
+                                &keys,
+                                executor,
+                            )
+                        }),
+                        unverified,
+                    ),
+                    Some(_) if options.downloaded => (
+                        Err("the signed file is not downloaded".to_string()),
+                        CheckStatus::Warn,
+                    ),
+                    _ => (
+                        Err("the signed file is not in the dist".to_string()),
+                        unverified,
+                    ),
+                };
+                if let (Ok(()), Some(signer), Some(file)) =
+                    (&outcome, &sig.signer, sig.signed_file())
+                {
+                    signed_by.entry(file).or_default().insert(signer);
+                }
+                report.push_with(&sig.filename, Check::Signature, outcome, failed);
+            }
+            if let Some(signers) = &options.signers {
+                for art in signed_artifacts(target).filter(|a| !absent(&a.filename)) {
+                    let outcome = check_threshold(signers, signed_by.get(art.filename.as_str()));
+                    report.push(&art.filename, Check::Threshold, outcome);
+                }
+            }
+        }
+    }
+    // partial dists get their index files when merged
+    if manifest_name != PARTIAL_MANIFEST_FILE {
+        let own = (manifest_name, hex::encode(Sha256::digest(data.as_bytes())));
+        if absent(SUMS_FILE) {
+            report.push_with(SUMS_FILE, Check::Sums, not_downloaded(), CheckStatus::Warn);
+        } else {
+            check_sums(dist, own, &listed, options.downloaded, &mut report);
+        }
+        if absent(PROVENANCE_FILE) {
+            report.push_with(
+                PROVENANCE_FILE,
+                Check::Provenance,
+                not_downloaded(),
+                CheckStatus::Warn,
+            );
+        } else {
+            report.push(
+                PROVENANCE_FILE,
+                Check::Provenance,
+                check_provenance(dist, &manifest),
+            );
+        }
+    }
+    // a download directory may hold anything; only a dist must be exact
+    let entries = if options.downloaded {
+        Vec::new()
+    } else {
+        fs::read_dir(dist)?.collect()
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let index = [
+            MANIFEST_FILE,
+            PARTIAL_MANIFEST_FILE,
+            SUMS_FILE,
+            PROVENANCE_FILE,
+            VERIFYING_FILE,
+            COMMANDS_FILE,
+            SUMMARY_FILE,
+            CHANGELOG_FILE,
+        ];
+        // dotfiles and directories are local state that is never uploaded
+        if !entry.file_type()?.is_file()
+            || name.starts_with('.')
+            || index.contains(&name.as_str())
+            || listed.contains_key(&name)
+        {
+            continue;
+        }
+        report.push_with(
+            &name,
+            Check::Unlisted,
+            Err("not listed in the manifest".into()),
+            unverified,
+        );
+    }
+    if options.deep {
+        // archives that are not downloaded were reported above
+        report.checks.extend(
+            verify_contents(dist, &manifest)
+                .checks
+                .into_iter()
+                .filter(|c| !absent(&c.artifact)),
+        );
+    }
+    Ok(report)
+}
+
+/// Lists the files inside each archive and compares names and sizes with
+/// the manifest, catching truncated or mis-assembled archives. Manifests
+/// from before contents were recorded get a warning per archive.
+pub fn verify_contents(dist: &Path, manifest: &Manifest) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    for pkg in &manifest.packages {
+        for target in &pkg.targets {
+            for art in &target.artifacts {
+                let (outcome, failed) = check_contents(dist, art);
+                report.push_with(&art.filename, Check::Contents, outcome, failed);
+            }
+        }
+    }
+    report
+}
+
+/// What `shippo inspect` reports about one file of a dist.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactInspection {
+    pub package: String,
+    pub target: String,
+    /// The file's manifest entry.
+    pub artifact: ManifestArtifact,
+    /// Files inside an archive and their sizes, as found.
+    pub contents: BTreeMap<String, u64>,
+    /// Provenance embedded with `[build] provenance`, keyed by the file
+    /// inside the archive, or by the file name for other files.
+    pub provenance: BTreeMap<String, Provenance>,
+    /// Checksum, archive contents, and the file's signatures.
+    pub report: VerifyReport,
+    /// Whether the manifest records signatures for the file.
+    pub signed: bool,
+}
+
+/// The single-file counterpart of [`verify_manifest`]: checks `filename`
+/// (an archive or SBOM in `dist`) against its manifest entry, verifies its
+/// signatures and reads what it contains. `None` when the manifest doesn't
+/// list it.
+pub fn inspect_artifact(
+    dist: &Path,
+    manifest: &Manifest,
+    filename: &str,
+    options: &VerifyOptions,
+    executor: &dyn Executor,
+) -> Result<Option<ArtifactInspection>> {
+    let found = manifest.packages.iter().find_map(|pkg| {
+        pkg.targets.iter().find_map(|target| {
+            target
+                .artifacts
+                .iter()
+                .chain(target.sboms())
+                .chain(&target.attestation)
+                .find(|a| a.filename == filename)
+                .map(|art| (pkg, target, art))
+        })
+    });
+    let Some((pkg, target, art)) = found else {
+        return Ok(None);
+    };
+    let path = dist.join(filename);
+    let mut report = VerifyReport::default();
+    report.push(filename, Check::Checksum, check_checksum(dist, art));
+    let mut contents = BTreeMap::new();
+    let mut provenance = BTreeMap::new();
+    if target.artifacts.iter().any(|a| a.filename == filename) && path.exists() {
+        let (outcome, failed) = check_contents(dist, art);
+        report.push_with(filename, Check::Contents, outcome, failed);
+        // an unreadable archive already failed the contents check
+        let _ = each_archive_file(&path, |name, bytes, reader| {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            if let Some(found) = Provenance::find(&data) {
+                provenance.insert(name.clone(), found);
+            }
+            contents.insert(name, bytes);
+            Ok(())
+        });
+    } else if let Some(found) = fs::read(&path).ok().and_then(|d| Provenance::find(&d)) {
+        provenance.insert(filename.to_string(), found);
+    }
+    let unverified = if options.strict {
+        CheckStatus::Fail
+    } else {
+        CheckStatus::Warn
+    };
+    let signatures: Vec<_> = target
+        .signatures
+        .iter()
+        .filter(|sig| sig.signed_file() == Some(filename))
+        .collect();
+    let mut signed_by = BTreeSet::new();
+    for sig in &signatures {
+        let bundle = sig.bundle.as_ref().map(|b| dist.join(b));
+        if let (Some(name), Some(bundle), false) = (&sig.bundle, &bundle, sig.key) {
+            let (outcome, failed) = check_bundle(bundle, Utc::now());
+            report.push_with(name, Check::Certificate, outcome, failed);
+            if let Some((outcome, failed)) = check_inclusion(bundle, sig) {
+                report.push_with(name, Check::Inclusion, outcome, failed);
+            }
+        }
+        let sig_path = dist.join(&sig.filename);
+        if !sig_path.exists() {
+            report.push(&sig.filename, Check::Signature, Err("missing".into()));
+            continue;
+        }
+        let outcome = signature_keys(sig, options).and_then(|keys| {
+            check_signature(
+                &sig_path,
+                &path,
+                sig,
+                bundle.as_deref().filter(|b| b.exists()),
+                &keys,
+                executor,
+            )
+        });
+        if let (Ok(()), Some(signer)) = (&outcome, &sig.signer) {
+            signed_by.insert(signer.as_str());
+        }
+        report.push_with(&sig.filename, Check::Signature, outcome, unverified);
+    }
+    if let Some(signers) = &options.signers {
+        if signed_artifacts(target).any(|a| a.filename == filename) {
+            let outcome = check_threshold(signers, Some(&signed_by));
+            report.push(filename, Check::Threshold, outcome);
+        }
+    }
+    Ok(Some(ArtifactInspection {
+        package: pkg.name.clone(),
+        target: target.target.clone(),
+        artifact: art.clone(),
+        contents,
+        provenance,
+        report,
+        signed: !signatures.is_empty(),
+    }))
+}
+
+/// Compares the files in an archive with the contents the manifest records.
+/// Manifests from before contents were recorded only get a warning.
+fn check_contents(dist: &Path, art: &ManifestArtifact) -> (Result<(), String>, CheckStatus) {
+    if art.contents.is_empty() {
+        return (
+            Err("the manifest does not record archive contents".into()),
+            CheckStatus::Warn,
+        );
+    }
+    let outcome = archive_contents(&dist.join(&art.filename))
+        .map_err(|e| format!("cannot read archive: {e}"))
+        .and_then(|mut found| {
+            let mut problems = Vec::new();
+            for entry in &art.contents {
+                match found.remove(&entry.path) {
+                    None => problems.push(format!("{} is missing", entry.path)),
+                    Some(bytes) if bytes != entry.bytes => problems.push(format!(
+                        "{} is {bytes} bytes, expected {}",
+                        entry.path, entry.bytes
+                    )),
+                    Some(_) => {}
+                }
+            }
+            problems.extend(found.keys().map(|path| format!("unexpected {path}")));
+            if problems.is_empty() {
+                Ok(())
+            } else {
+                Err(problems.join("; "))
+            }
+        });
+    (outcome, CheckStatus::Fail)
+}
+
+/// One [`Check::Rebuild`] per artifact of `original`, passing when `rebuilt`
+/// has an artifact of the same name and digest.
+pub fn compare_rebuild(original: &Manifest, rebuilt: &Manifest) -> VerifyReport {
+    let digests: BTreeMap<&str, &str> = rebuilt
+        .packages
+        .iter()
+        .flat_map(|p| &p.targets)
+        .flat_map(|t| &t.artifacts)
+        .map(|a| (a.filename.as_str(), a.sha256.as_str()))
+        .collect();
+    let mut report = VerifyReport::default();
+    for art in original
+        .packages
+        .iter()
+        .flat_map(|p| &p.targets)
+        .flat_map(|t| &t.artifacts)
+    {
+        let outcome = match digests.get(art.filename.as_str()) {
+            None => Err("not produced by the rebuild".to_string()),
+            Some(sha) if *sha != art.sha256 => Err(format!("rebuilt sha256 is {sha}")),
+            Some(_) => Ok(()),
+        };
+        report.push(&art.filename, Check::Rebuild, outcome);
+    }
+    report
+}
+
+/// Compares the config checksum and plan fingerprint recorded in `manifest`
+/// with `current`, the plan built from the working tree's config (named
+/// `config_name` in the report). Manifests from before they were recorded
+/// get warnings.
+pub fn compare_config(manifest: &Manifest, current: &Plan, config_name: &str) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    let rows = [
+        (
+            config_name,
+            &manifest.project.config_sha256,
+            current.config_sha256.clone(),
+            "modified since the release",
+        ),
+        (
+            "plan",
+            &manifest.project.plan_fingerprint,
+            Some(current.fingerprint()),
+            "the working tree's config resolves to a different plan",
+        ),
+    ];
+    for (artifact, recorded, current, changed) in rows {
+        match (recorded, current) {
+            (Some(recorded), Some(current)) => {
+                let outcome = if *recorded == current {
+                    Ok(())
+                } else {
+                    Err(changed.to_string())
+                };
+                report.push(artifact, Check::Config, outcome);
+            }
+            (None, _) => report.push_with(
+                artifact,
+                Check::Config,
+                Err("the manifest does not record it".into()),
+                CheckStatus::Warn,
+            ),
+            (Some(_), None) => report.push(
+                artifact,
+                Check::Config,
+                Err("no config to compare with".into()),
+            ),
+        }
+    }
+    report
+}
+
+/// Regular files in a `.tar.gz`, `.tar.zst` or `.zip` and their sizes.
+fn archive_contents(path: &Path) -> Result<BTreeMap<String, u64>> {
+    let mut files = BTreeMap::new();
+    each_archive_file(path, |name, bytes, _| {
+        files.insert(name, bytes);
+        Ok(())
+    })?;
+    Ok(files)
+}
+
+/// Writes the files of `archive` whose path `keep` accepts into `out`,
+/// under their base names, and returns where they went.
+pub fn extract_files(
+    archive: &Path,
+    out: &Path,
+    keep: impl Fn(&str) -> bool,
+) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    each_archive_file(archive, |name, _, data| {
+        let base = name.rsplit('/').next().unwrap_or_default();
+        if base.is_empty() || !keep(&name) {
+            return Ok(());
+        }
+        let path = out.join(base);
+        std::io::copy(data, &mut File::create(&path)?)?;
+        written.push(path);
+        Ok(())
+    })?;
+    Ok(written)
+}
+
+/// Calls `f` with the path, size and data of each regular file in a
+/// `.tar.gz`, `.tar.zst` or `.zip`.
+fn each_archive_file(
+    path: &Path,
+    mut f: impl FnMut(String, u64, &mut dyn Read) -> Result<()>,
+) -> Result<()> {
+    let name = path.to_string_lossy();
+    if name.ends_with(".zip") {
+        let mut zip = zip::ZipArchive::new(File::open(path)?)?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            if file.is_file() {
+                let (name, bytes) = (file.name().to_string(), file.size());
+                f(name, bytes, &mut file)?;
+            }
+        }
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tar.zst") {
+        let file = File::open(path)?;
+        let decoder: Box<dyn Read> = if name.ends_with(".tar.gz") {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(zstd::Decoder::new(file)?)
+        };
+        let mut tar = tar::Archive::new(decoder);
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            // sparse inputs are packed as GNU sparse entries
+            let kind = entry.header().entry_type();
+            if kind.is_file() || kind.is_gnu_sparse() {
+                let path = entry.path()?.to_string_lossy().replace('\\', "/");
+                let bytes = entry.size();
+                f(path, bytes, &mut entry)?;
+            }
+        }
+    } else {
+        return Err(PackageError::UnsupportedFormat(name.to_string()));
+    }
+    Ok(())
+}
+
+/// One [`Check::Sums`] per listed file and the manifest, plus a failure for
+/// every `SHA256SUMS` line the manifest doesn't account for.
+fn check_sums(
+    dist: &Path,
+    (manifest_name, manifest_sha): (&str, String),
+    listed: &BTreeMap<String, Option<String>>,
+    downloaded: bool,
+    report: &mut VerifyReport,
+) {
+    let data = match fs::read_to_string(dist.join(SUMS_FILE)) {
+        Ok(data) => data,
+        Err(e) => return report.push(SUMS_FILE, Check::Sums, Err(e.to_string())),
+    };
+    let mut sums = BTreeMap::new();
+    for (n, line) in data
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+    {
+        match line.split_once("  ") {
+            Some((sha, file)) => {
+                sums.insert(file.to_string(), sha.to_string());
+            }
+            None => report.push(
+                SUMS_FILE,
+                Check::Sums,
+                Err(format!("line {} is not `<sha256>  <file>`", n + 1)),
+            ),
+        }
+    }
+    let manifest_name = manifest_name.to_string();
+    let expected = std::iter::once((&manifest_name, Some(manifest_sha)))
+        .chain(listed.iter().map(|(f, s)| (f, s.clone())));
+    for (file, sha) in expected {
+        let path = dist.join(file);
+        let outcome = match (sums.remove(file.as_str()), sha) {
+            (None, _) => Err(format!("not listed in {SUMS_FILE}")),
+            (Some(listed), Some(sha)) if listed != sha => {
+                Err(format!("{SUMS_FILE} has {listed}, manifest has {sha}"))
+            }
+            (Some(_), Some(_)) => Ok(()),
+            (Some(_), None) if downloaded && !path.exists() => {
+                report.push_with(
+                    file,
+                    Check::Sums,
+                    Err("not downloaded".into()),
+                    CheckStatus::Warn,
+                );
+                continue;
+            }
+            // no digest in the manifest: compare with the file itself
+            (Some(listed), None) => match sha256_file(&path) {
+                Ok(actual) if actual == listed => Ok(()),
+                Ok(actual) => Err(format!("{SUMS_FILE} has {listed}, file is {actual}")),
+                Err(e) => Err(e.to_string()),
+            },
+        };
+        report.push(file, Check::Sums, outcome);
+    }
+    // VERIFYING.md has no digest in the manifest, and older dists don't list it
+    if let Some(listed) = sums.remove(VERIFYING_FILE) {
+        let path = dist.join(VERIFYING_FILE);
+        if downloaded && !path.exists() {
+            report.push_with(
+                VERIFYING_FILE,
+                Check::Sums,
+                Err("not downloaded".into()),
+                CheckStatus::Warn,
+            );
+        } else {
+            let outcome = match sha256_file(&path) {
+                Ok(actual) if actual == listed => Ok(()),
+                Ok(actual) => Err(format!("{SUMS_FILE} has {listed}, file is {actual}")),
+                Err(e) => Err(e.to_string()),
+            };
+            report.push(VERIFYING_FILE, Check::Sums, outcome);
+        }
+    }
+    for file in sums.keys() {
+        report.push(
+            file,
+            Check::Sums,
+            Err(format!("listed in {SUMS_FILE} but not in the manifest")),
+        );
+    }
+}
+
+fn check_provenance(dist: &Path, manifest: &Manifest) -> Result<(), String> {
+    let data = fs::read_to_string(dist.join(PROVENANCE_FILE)).map_err(|e| e.to_string())?;
+    let value: serde_json::Value =
+        serde_json::from_str(&data).map_err(|e| format!("invalid JSON: {e}"))?;
+    match value.get("version").and_then(|v| v.as_str()) {
+        Some(version) if version == manifest.project.version => {}
+        Some(version) => {
+            return Err(format!(
+                "version is {version}, manifest has {}",
+                manifest.project.version
+            ))
+        }
+        None => return Err("`version` is missing".into()),
+    }
+    value
+        .get("generated_at")
+        .and_then(|v| v.as_str())
+        .and_then(|v| v.parse::<DateTime<Utc>>().ok())
+        .ok_or("`generated_at` is missing or not a timestamp")?;
+    value
+        .get("ci")
+        .and_then(|v| v.as_bool())
+        .ok_or("`ci` is missing or not a boolean")?;
+    Ok(())
+}
+
+fn check_checksum(dist: &Path, art: &ManifestArtifact) -> Result<(), String> {
+    let path = dist.join(&art.filename);
+    if !path.exists() {
+        return Err("missing".into());
+    }
+    let sha = sha256_file(&path).map_err(|e| e.to_string())?;
+    if sha == art.sha256 {
+        Ok(())
+    } else {
+        Err(format!("sha256 is {sha}, manifest has {}", art.sha256))
+    }
+}
+
+/// Checks a cosign bundle's certificate validity window against its
+/// transparency-log entry; see [`BundleInfo::check`].
+fn check_bundle(path: &Path, now: DateTime<Utc>) -> (Result<(), String>, CheckStatus) {
+    let info = match fs::read_to_string(path) {
+        Ok(data) => BundleInfo::parse(&data),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err("missing".into()),
+        Err(e) => Err(e.to_string()),
+    };
+    match info.map(|info| info.check(now)) {
+        Ok(Ok(_)) => (Ok(()), CheckStatus::Fail),
+        Ok(Err((reason, true))) => (Err(reason), CheckStatus::Warn),
+        Ok(Err((reason, false))) | Err(reason) => (Err(reason), CheckStatus::Fail),
+    }
+}
+
+/// Checks that a bundle's rekor entry is the one `sig` recorded when it was
+/// signed and that its inclusion proof holds; see
+/// [`TlogEntry::check_inclusion`](crate::bundle::TlogEntry::check_inclusion).
+/// `None` when there is nothing to check: no entry was recorded and the
+/// bundle has no proof (bundles from before sigstore's protobuf format).
+fn check_inclusion(
+    path: &Path,
+    sig: &ManifestSignature,
+) -> Option<(Result<(), String>, CheckStatus)> {
+    let tlog = fs::read_to_string(path)
+        .ok()
+        .and_then(|data| BundleInfo::parse(&data).ok())
+        .and_then(|info| info.tlog);
+    let outcome = match (&sig.rekor, tlog) {
+        (None, None) => return None,
+        (None, Some(tlog)) if tlog.proof.is_none() => return None,
+        (Some(recorded), None) => Err(format!(
+            "the manifest records rekor entry {}, but the bundle has none",
+            recorded.log_index
+        )),
+        (recorded, Some(tlog)) => match recorded {
+            Some(recorded) if tlog.log_index != Some(recorded.log_index) => Err(format!(
+                "the bundle has rekor entry {}, the manifest {}",
+                tlog.log_index.map_or("none".into(), |i| i.to_string()),
+                recorded.log_index
+            )),
+            Some(recorded) if tlog.uuid.as_ref() != Some(&recorded.uuid) => Err(format!(
+                "the bundle's entry is not {}, which the manifest records",
+                recorded.uuid
+            )),
+            _ if tlog.proof.is_none() => {
+                return Some((
+                    Err("the bundle has no inclusion proof to check its entry with".into()),
+                    CheckStatus::Warn,
+                ))
+            }
+            _ => tlog.check_inclusion().map(|_| ()),
+        },
+    };
+    Some((outcome, CheckStatus::Fail))
+}
+
+/// Whether `signed_by`, the signers whose signatures of a file verified,
+/// are at least `[verify.signers] threshold`.
+fn check_threshold(
+    signers: &SignersConfig,
+    signed_by: Option<&BTreeSet<&str>>,
+) -> Result<(), String> {
+    let signed_by: Vec<&str> = signed_by.into_iter().flatten().copied().collect();
+    if signed_by.len() >= signers.threshold {
+        return Ok(());
+    }
+    let by = match signed_by.is_empty() {
+        true => String::new(),
+        false => format!(" (from {})", signed_by.join(", ")),
+    };
+    Err(format!(
+        "{} of the {} required signer signatures{by}",
+        signed_by.len(),
+        signers.threshold
+    ))
+}
+
+/// The keys `sig` is checked with: its signer's minisign key from
+/// `[verify.signers]` for a signature added with `shippo sign`, and
+/// `[verify.keys]` for the others.
+fn signature_keys(
+    sig: &ManifestSignature,
+    options: &VerifyOptions,
+) -> Result<VerifyKeysConfig, String> {
+    let Some(signer) = &sig.signer else {
+        return Ok(options.keys.clone());
+    };
+    options
+        .signers
+        .as_ref()
+        .and_then(|s| s.minisign.get(signer))
+        .map(|key| VerifyKeysConfig {
+            minisign: Some(key.clone()),
+            ..Default::default()
+        })
+        .ok_or_else(|| format!("signer {signer} is not in [verify.signers]"))
+}
+
+/// Verifies one detached signature with the tool that made it, using the
+/// configured public keys; `Err` explains why it could not be verified.
+/// Signatures of an in-toto envelope sign its statement's PAE.
+fn check_signature(
+    sig: &Path,
+    file: &Path,
+    signature: &ManifestSignature,
+    bundle: Option<&Path>,
+    keys: &VerifyKeysConfig,
+    executor: &dyn Executor,
+) -> Result<(), String> {
+    let pae;
+    let file = if file.to_string_lossy().ends_with(attest::INTOTO_SUFFIX) {
+        let data = fs::read_to_string(file).map_err(|e| e.to_string())?;
+        let statement = attest::envelope_pae(&data)
+            .ok_or("the statement file is not a DSSE envelope of an in-toto statement")?;
+        pae = tempfile::NamedTempFile::new().map_err(|e| e.to_string())?;
+        fs::write(pae.path(), statement).map_err(|e| e.to_string())?;
+        pae.path()
+    } else {
+        file
+    };
+    let sha = sha256_file(file).map_err(|e| e.to_string())?;
+    if fs::read_to_string(sig).is_ok_and(|contents| contents.trim() == sha) {
+        return Err("it is a checksum written because signing failed, not a signature".into());
+    }
+    let method = signature.method.as_str();
+    let missing_key = |key: &str| format!("no `{key}` in [verify.keys] to check it with");
+    let (sig_arg, file_arg) = (sig.to_string_lossy(), file.to_string_lossy());
+    let mut cmd = CommandSpec::new(if method == "ssh" {
+        "ssh-keygen"
+    } else {
+        method
+    });
+    match method {
+        "gpg" => {
+            cmd.arg("--batch");
+            if let Some(keyring) = &keys.gpg_keyring {
+                cmd.args(["--no-default-keyring", "--keyring", keyring]);
+            }
+            cmd.args(["--verify", &sig_arg, &file_arg])
+        }
+        "cosign" => {
+            cmd.args(["verify-blob", &file_arg]);
+            // a sigstore bundle holds the signature itself
+            if bundle != Some(sig) {
+                cmd.args(["--signature", &sig_arg]);
+            }
+            if let Some(key) = &keys.cosign {
+                cmd.args(["--key", key]);
+            } else {
+                // a keyless certificate proves only that someone signed;
+                // the identity and issuer say it was the release workflow
+                let identity = match (&keys.cosign_identity, &keys.cosign_identity_regexp) {
+                    (Some(identity), _) => ["--certificate-identity", identity],
+                    (None, Some(regexp)) => ["--certificate-identity-regexp", regexp],
+                    (None, None) => return Err(missing_key("cosign_identity")),
+                };
+                let issuer = keys
+                    .cosign_oidc_issuer
+                    .as_ref()
+                    .ok_or_else(|| missing_key("cosign_oidc_issuer"))?;
+                cmd.args(identity)
+                    .args(["--certificate-oidc-issuer", issuer]);
+            }
+            if let Some(bundle) = bundle {
+                // checked against the bundle's rekor entry, without a lookup
+                cmd.arg("--bundle").arg(bundle.to_string_lossy());
+                cmd.arg("--offline");
+                if bundle.to_string_lossy().ends_with(SIGSTORE_BUNDLE_SUFFIX) {
+                    cmd.arg("--new-bundle-format");
+                }
+            }
+            // signed without uploading to rekor, so there is no entry
+            if signature.offline {
+                cmd.arg("--insecure-ignore-tlog=true");
+            }
+            &mut cmd
+        }
+        "minisign" => {
+            let key = keys
+                .minisign
+                .as_ref()
+                .ok_or_else(|| missing_key("minisign"))?;
+            cmd.args(["-V", "-p", key, "-m", &file_arg, "-x", &sig_arg])
+        }
+        "ssh" => {
+            let signers = keys
+                .ssh_allowed_signers
+                .as_ref()
+                .ok_or_else(|| missing_key("ssh_allowed_signers"))?;
+            let principal = ssh_principal(sig, signers, executor)?;
+            let namespace = keys.ssh_namespace.as_deref().unwrap_or("file");
+            cmd.args(["-Y", "verify", "-f", signers, "-I", &principal])
+                .args(["-n", namespace, "-s", &sig_arg])
+                .stdin_file(file)
+        }
+        other => return Err(format!("signatures made with `{other}` cannot be verified")),
+    };
+    let output = run_verifier(&cmd, executor)?;
+    if output.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} rejected it ({}): {}",
+            cmd.program,
+            output.status(),
+            output.stderr.trim()
+        ))
+    }
+}
+
+/// The allowed signer an SSH signature belongs to.
+fn ssh_principal(sig: &Path, signers: &str, executor: &dyn Executor) -> Result<String, String> {
+    let mut cmd = CommandSpec::new("ssh-keygen");
+    cmd.args(["-Y", "find-principals", "-f", signers, "-s"])
+        .arg(sig.to_string_lossy());
+    let output = run_verifier(&cmd, executor)?;
+    output
+        .stdout
+        .lines()
+        .next()
+        .filter(|_| output.success())
+        .map(|p| p.trim().to_string())
+        .ok_or_else(|| "the signing key is not in ssh_allowed_signers".to_string())
+}
+
+fn run_verifier(cmd: &CommandSpec, executor: &dyn Executor) -> Result<CommandOutput, String> {
+    executor.output(cmd).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => format!("{} is not installed", cmd.program),
+        _ => format!("failed to run {}: {e}", cmd.program),
+    })
+}
@@ -0,0 +1,450 @@
+//! `VERIFYING.md`: copy-pasteable commands to check a release's downloads,
+//! with its own file names. Whom they trust comes from the manifest's
+//! [`SignerTrust`], never from the signatures being checked; the key IDs
+//! read from signatures are only shown.
+
+use std::fs;
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use shippo_core::{Manifest, ManifestSignature, SignerTrust};
+
+use crate::{attest, SUMS_FILE};
+
+/// The signatures of one kind and what the commands for them need.
+#[derive(Default)]
+struct Section {
+    commands: Vec<String>,
+    /// Key identities: GPG fingerprints, minisign key IDs or SSH public
+    /// keys, without duplicates.
+    keys: Vec<String>,
+    notes: Vec<String>,
+}
+
+impl Section {
+    fn key(&mut self, key: String) {
+        if !self.keys.contains(&key) {
+            self.keys.push(key);
+        }
+    }
+}
+
+/// The Markdown of `VERIFYING.md` for `manifest`'s files, reading key IDs
+/// from the signatures in `dist`. Checksums written in place of signatures
+/// that failed are left out, and so are signatures the manifest's signers
+/// don't say whom to check against.
+pub fn instructions(dist: &Path, manifest: &Manifest) -> String {
+    let mut out = format!(
+        "# Verifying {}\n\nEvery file of the release is listed with its SHA-256 in `{SUMS_FILE}`. Download it next to the files and check them:\n\n```sh\nsha256sum --ignore-missing -c {SUMS_FILE}\n# on macOS\nshasum -a 256 --ignore-missing -c {SUMS_FILE}\n```\n",
+        manifest.project.version
+    );
+    let mut keyless = Section::default();
+    let mut cosign = Section::default();
+    let mut gpg = Section::default();
+    let mut ssh = Section::default();
+    let mut minisign = Section::default();
+    let trust = manifest.project.trust.clone().unwrap_or_default();
+    for target in manifest.packages.iter().flat_map(|p| &p.targets) {
+        for sig in &target.signatures {
+            // an in-toto envelope's signature is for policy engines, which
+            // check it against the statement's PAE
+            let Some(file) = sig
+                .signed_file()
+                .filter(|f| !f.ends_with(attest::INTOTO_SUFFIX))
+            else {
+                continue;
+            };
+            let data = fs::read(dist.join(&sig.filename)).unwrap_or_default();
+            if is_placeholder(&data) {
+                continue;
+            }
+            let text = String::from_utf8_lossy(&data);
+            match (sig.method.as_str(), &sig.bundle) {
+                ("cosign", Some(bundle)) if sig.key => cosign.commands.push(format!(
+                    "cosign verify-blob {file} --key cosign.pub --bundle {bundle} --new-bundle-format{}",
+                    if sig.offline { " --insecure-ignore-tlog=true" } else { "" }
+                )),
+                ("cosign", Some(bundle)) => keyless_command(&mut keyless, &trust, file, bundle, sig),
+                ("cosign", None) => cosign.commands.push(format!(
+                    "cosign verify-blob {file} --key cosign.pub --signature {}",
+                    sig.filename
+                )),
+                ("gpg", _) => {
+                    if let Some(fingerprint) = gpg_issuer(&data) {
+                        gpg.key(fingerprint);
+                    }
+                    gpg.commands
+                        .push(format!("gpg --verify {} {file}", sig.filename));
+                }
+                ("ssh", _) => {
+                    // the principal the project lists the signing key for
+                    let signer = ssh_signer(&text).and_then(|(key, namespace)| {
+                        let line = trust.ssh_allowed_signers.iter().find(|line| {
+                            let fields: Vec<&str> = line.split_whitespace().collect();
+                            fields.windows(2).any(|w| key == format!("{} {}", w[0], w[1]))
+                        })?;
+                        let principal = line.split([' ', '\t', ',']).next()?.to_string();
+                        ssh.key(key);
+                        Some((principal, namespace))
+                    });
+                    match signer {
+                        Some((principal, namespace)) => ssh.commands.push(format!(
+                            "ssh-keygen -Y verify -f allowed_signers -I {principal} -n {namespace} -s {} < {file}",
+                            sig.filename
+                        )),
+                        None => ssh.notes.push(format!(
+                            "`{}` is not signed with a key the project lists, so it is left out.",
+                            sig.filename
+                        )),
+                    }
+                }
+                _ if sig.filename.ends_with(".minisig") => {
+                    if let Some(id) = minisign_key_id(&text) {
+                        minisign.key(id);
+                    }
+                    // each `shippo sign` signer has a key of their own
+                    let key = match &sig.signer {
+                        Some(signer) => format!("{signer}.pub"),
+                        None => "minisign.pub".to_string(),
+                    };
+                    let note = "Files are signed by several people: `<file>.<signer>.minisig` is checked with that signer's published key, saved as `<signer>.pub`.";
+                    if sig.signer.is_some() && minisign.notes.is_empty() {
+                        minisign.notes.push(note.to_string());
+                    }
+                    minisign.commands.push(format!(
+                        "minisign -V -p {key} -m {file} -x {}",
+                        sig.filename
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if !keyless.commands.is_empty() || !keyless.notes.is_empty() {
+        out.push_str("\n## Keyless cosign signatures\n\nEach file's sigstore bundle holds its signature, the short-lived certificate it was signed with and its transparency-log entry. `cosign` 2.4 or later checks all three, and that the certificate was issued to this release's signer:\n");
+        push_section(&mut out, &keyless);
+    }
+    if !cosign.commands.is_empty() {
+        out.push_str(
+            "\n## cosign signatures\n\nWith the project's public key saved as `cosign.pub`:\n",
+        );
+        push_section(&mut out, &cosign);
+    }
+    if !gpg.commands.is_empty() {
+        out.push_str("\n## GPG signatures\n\n");
+        if gpg.keys.is_empty() {
+            out.push_str("Import the project's public key, then:\n");
+        } else {
+            out.push_str(&format!(
+                "Signed with {}. Fetch the key, check that it is the project's, then verify:\n",
+                code_list(&gpg.keys)
+            ));
+            let fetch = format!("gpg --recv-keys {}", gpg.keys.join(" "));
+            gpg.commands.insert(0, fetch);
+        }
+        push_section(&mut out, &gpg);
+    }
+    if !ssh.commands.is_empty() || !ssh.notes.is_empty() {
+        out.push_str("\n## SSH signatures\n\n");
+        if ssh.commands.is_empty() {
+            out.push_str("None of the SSH signatures can be checked:\n");
+        } else {
+            out.push_str(&format!(
+                "Signed with {}. The first command saves the keys the project's `allowed_signers` lists; check that they are the ones it publishes:\n",
+                code_list(&ssh.keys),
+            ));
+            let signers = format!(
+                "cat > allowed_signers <<'EOF'\n{}\nEOF",
+                trust.ssh_allowed_signers.join("\n")
+            );
+            ssh.commands.insert(0, signers);
+        }
+        push_section(&mut out, &ssh);
+    }
+    if !minisign.commands.is_empty() {
+        out.push_str("\n## minisign signatures\n\n");
+        if minisign.keys.is_empty() {
+            out.push_str("With the project's public key saved as `minisign.pub`:\n");
+        } else {
+            out.push_str(&format!(
+                "Signed with key ID {}. With the project's public key saved as `minisign.pub`:\n",
+                code_list(&minisign.keys)
+            ));
+        }
+        push_section(&mut out, &minisign);
+    }
+    out
+}
+
+/// `cosign verify-blob` with the identity and issuer `trust` expects, plus
+/// a note of the file's rekor entry. Without them the file is left out:
+/// the bundle's own certificate would only say who signed, not who may.
+fn keyless_command(
+    section: &mut Section,
+    trust: &SignerTrust,
+    file: &str,
+    bundle: &str,
+    sig: &ManifestSignature,
+) {
+    let identity = match (&trust.cosign_identity, &trust.cosign_identity_regexp) {
+        (Some(identity), _) => Some(("--certificate-identity", identity)),
+        (None, Some(regexp)) => Some(("--certificate-identity-regexp", regexp)),
+        (None, None) => None,
+    };
+    let Some(((flag, identity), issuer)) = identity.zip(trust.cosign_oidc_issuer.as_ref()) else {
+        section.notes.push(format!(
+            "The project names no signer for keyless signatures, so `{bundle}` is left out."
+        ));
+        return;
+    };
+    let mut command = format!("cosign verify-blob {file} --bundle {bundle}");
+    if sig.in_bundle() {
+        command.push_str(" --new-bundle-format");
+    } else {
+        command.push_str(&format!(" --signature {}", sig.filename));
+    }
+    command.push_str(&format!(
+        " \\\n  {flag} '{identity}' \\\n  --certificate-oidc-issuer '{issuer}'"
+    ));
+    section.commands.push(command);
+    if let Some(rekor) = &sig.rekor {
+        section.notes.push(format!(
+            "`{file}` is rekor entry {} (`rekor-cli get --uuid {}`).",
+            rekor.log_index, rekor.uuid
+        ));
+    }
+}
+
+fn push_section(out: &mut String, section: &Section) {
+    if !section.commands.is_empty() {
+        out.push_str(&format!("\n```sh\n{}\n```\n", section.commands.join("\n")));
+    }
+    for note in &section.notes {
+        out.push_str(&format!("\n{note}\n"));
+    }
+}
+
+fn code_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("`{item}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The sha256 written in place of a signature when signing failed.
+fn is_placeholder(data: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(data);
+    let text = text.trim();
+    text.len() == 64 && text.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Big-endian unsigned integer.
+fn be(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |acc, b| (acc << 8) | *b as usize)
+}
+
+/// An OpenPGP packet or subpacket length (RFC 4880 4.2.2) and what
+/// follows it.
+fn openpgp_length(data: &[u8]) -> Option<(usize, &[u8])> {
+    let (&first, rest) = data.split_first()?;
+    match first {
+        0..=191 => Some((first as usize, rest)),
+        192..=223 => {
+            let (&second, rest) = rest.split_first()?;
+            Some((((first as usize - 192) << 8) + second as usize + 192, rest))
+        }
+        255 => Some((be(rest.get(..4)?), rest.get(4..)?)),
+        // partial body lengths
+        _ => None,
+    }
+}
+
+/// The issuer fingerprint of a binary OpenPGP v4 signature, or its key ID
+/// when it names no fingerprint.
+fn gpg_issuer(data: &[u8]) -> Option<String> {
+    let (&tag, rest) = data.split_first()?;
+    if tag & 0x80 == 0 {
+        return None;
+    }
+    let (packet, body) = if tag & 0x40 != 0 {
+        let (len, rest) = openpgp_length(rest)?;
+        (tag & 0x3f, rest.get(..len)?)
+    } else {
+        // old format; length type 3 is indeterminate
+        let size = *[1, 2, 4].get((tag & 3) as usize)?;
+        let len = be(rest.get(..size)?);
+        ((tag >> 2) & 0x0f, rest.get(size..)?.get(..len)?)
+    };
+    // version, signature type, key and hash algorithms, then the hashed
+    // and unhashed subpackets
+    if packet != 2 || body.first() != Some(&4) {
+        return None;
+    }
+    let hashed_len = be(body.get(4..6)?);
+    let hashed = body.get(6..6 + hashed_len)?;
+    let rest = body.get(6 + hashed_len..)?;
+    let unhashed = rest.get(2..2 + be(rest.get(..2)?))?;
+    let mut key_id = None;
+    for mut area in [hashed, unhashed] {
+        while !area.is_empty() {
+            let (len, rest) = openpgp_length(area)?;
+            let (&kind, value) = rest.get(..len)?.split_first()?;
+            area = rest.get(len..)?;
+            match kind & 0x7f {
+                // issuer fingerprint: key version, then the fingerprint
+                33 => return value.get(1..).map(hex::encode_upper),
+                16 => key_id = Some(hex::encode_upper(value)),
+                _ => {}
+            }
+        }
+    }
+    key_id
+}
+
+/// A length-prefixed SSH wire string and what follows it.
+fn ssh_string(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let len = be(data.get(..4)?);
+    Some((data.get(4..4 + len)?, data.get(4 + len..)?))
+}
+
+/// The public key (`ssh-ed25519 AAAA...`) and namespace of an armored
+/// `ssh-keygen -Y sign` signature.
+fn ssh_signer(text: &str) -> Option<(String, String)> {
+    let body: String = text
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let blob = STANDARD.decode(body.trim()).ok()?;
+    // magic, version, public key, namespace, ...
+    let rest = blob.strip_prefix(b"SSHSIG")?.get(4..)?;
+    let (key, rest) = ssh_string(rest)?;
+    let (namespace, _) = ssh_string(rest)?;
+    let (kind, _) = ssh_string(key)?;
+    Some((
+        format!(
+            "{} {}",
+            std::str::from_utf8(kind).ok()?,
+            STANDARD.encode(key)
+        ),
+        String::from_utf8(namespace.to_vec()).ok()?,
+    ))
+}
+
+/// The key ID of a minisign signature, as `minisign` prints it.
+fn minisign_key_id(text: &str) -> Option<String> {
+    let sig = STANDARD.decode(text.lines().nth(1)?.trim()).ok()?;
+    let id: [u8; 8] = sig.get(2..10)?.try_into().ok()?;
+    Some(format!("{:016X}", u64::from_le_bytes(id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `gpg --detach-sign` with an ed25519 key.
+    const GPG_SIG: &str = "iHUEABYIAB0WIQQd9M750ojmhGZRqT5Lx1W7mM2prAUCatFQFAAKCRBLx1W7mM2prNu4AP40flmY3ya5dZGafhf65+Da7xK174HTc7AmwZDlt4vk0AD/ZWF+feyv7FYYqHxkNDqQVsWAVZ99mquQjtIaPljc7A8=";
+
+    const SSH_SIG: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAggc3Nm+3GA1uoVKp8Q80zhSYT/O
+cbwSYVWSXfaoGrfNoAAAAEZmlsZQAAAAAAAAAGc2hhNTEyAAAAUwAAAAtzc2gtZWQyNTUx
+OQAAAEDaH5FEBD5ctbHd7vNlltbqTN50OGPWSuJJmAdPDXzHVNt4gP97jJKOCs4pxCf5t9
+MGaOlG+5JA4AQuyE6TaPAB
+-----END SSH SIGNATURE-----
+";
+
+    #[test]
+    fn test_signer_identities() {
+        let gpg = STANDARD.decode(GPG_SIG).unwrap();
+        assert_eq!(
+            gpg_issuer(&gpg).as_deref(),
+            Some("1DF4CEF9D288E6846651A93E4BC755BB98CDA9AC")
+        );
+        assert_eq!(gpg_issuer(b"not a signature"), None);
+
+        let (key, namespace) = ssh_signer(SSH_SIG).unwrap();
+        assert_eq!(
+            key,
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIIHNzZvtxgNbqFSqfEPNM4UmE/znG8EmFVkl32qBq3za"
+        );
+        assert_eq!(namespace, "file");
+
+        let mut minisig = vec![b'E', b'd'];
+        minisig.extend(0x0123_4567_89ab_cdefu64.to_le_bytes());
+        minisig.extend([0; 64]);
+        let minisig = format!(
+            "untrusted comment: signature from minisign secret key\n{}\n",
+            STANDARD.encode(minisig)
+        );
+        assert_eq!(
+            minisign_key_id(&minisig).as_deref(),
+            Some("0123456789ABCDEF")
+        );
+
+        assert!(is_placeholder(
+            b"2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824\n"
+        ));
+        assert!(!is_placeholder(SSH_SIG.as_bytes()));
+    }
+
+    #[test]
+    fn test_commands_trust_only_the_project_signers() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("app.tar.gz.sigstore.json"), "{}").unwrap();
+        fs::write(dir.path().join("app.tar.gz.sig"), SSH_SIG).unwrap();
+        let manifest = |trust: serde_json::Value| {
+            let manifest = serde_json::json!({
+                "manifest_version": shippo_core::MANIFEST_VERSION,
+                "shippo_version": "0.1.0",
+                "generated_at": "2026-01-01T00:00:00Z",
+                "project": { "repo_url": null, "commit": null, "version": "v1.0.0", "trust": trust },
+                "packages": [{
+                    "name": "app", "type": "rust", "path": ".",
+                    "targets": [{
+                        "target": "x86_64-unknown-linux-gnu",
+                        "artifacts": [], "sbom": null,
+                        "signatures": [
+                            { "filename": "app.tar.gz.sigstore.json", "method": "cosign", "bundle": "app.tar.gz.sigstore.json" },
+                            { "filename": "app.tar.gz.sig", "method": "ssh" },
+                        ],
+                    }],
+                }],
+                "tooling": { "rust": null, "go": null, "node": null, "python": null },
+                "build_env": { "os": "linux", "arch": "x86_64", "ci": false },
+            });
+            Manifest::from_json(&manifest.to_string()).unwrap()
+        };
+
+        // nothing says whom to trust: no command accepts any signer
+        let text = instructions(dir.path(), &manifest(serde_json::Value::Null));
+        assert!(!text.contains("verify-blob"), "{text}");
+        assert!(!text.contains("regexp"), "{text}");
+        assert!(!text.contains("ssh-keygen"), "{text}");
+        assert!(text.contains("`app.tar.gz.sig` is not signed with a key the project lists"));
+
+        let key =
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIIHNzZvtxgNbqFSqfEPNM4UmE/znG8EmFVkl32qBq3za";
+        let trust = serde_json::json!({
+            "cosign_identity_regexp": "^https://github\\.com/acme/app/\\.github/workflows/",
+            "cosign_oidc_issuer": shippo_core::GITHUB_ACTIONS_ISSUER,
+            "ssh_allowed_signers": [format!("maintainers@acme.dev,ci@acme.dev {key}")],
+        });
+        let text = instructions(dir.path(), &manifest(trust));
+        assert!(
+            text.contains(
+                "cosign verify-blob app.tar.gz --bundle app.tar.gz.sigstore.json --new-bundle-format \\\n  --certificate-identity-regexp '^https://github\\.com/acme/app/\\.github/workflows/' \\\n  --certificate-oidc-issuer 'https://token.actions.githubusercontent.com'"
+            ),
+            "{text}"
+        );
+        assert!(text.contains(&format!(
+            "cat > allowed_signers <<'EOF'\nmaintainers@acme.dev,ci@acme.dev {key}\nEOF\nssh-keygen -Y verify -f allowed_signers -I maintainers@acme.dev -n file -s app.tar.gz.sig < app.tar.gz"
+        )));
+
+        // a key the project doesn't list isn't trusted for being in the signature
+        let trust = serde_json::json!({ "ssh_allowed_signers": ["maintainers@acme.dev ssh-ed25519 AAAAother"] });
+        let text = instructions(dir.path(), &manifest(trust));
+        assert!(!text.contains("ssh-keygen"), "{text}");
+    }
+}
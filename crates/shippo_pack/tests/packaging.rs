@@ -1,51 +1,1833 @@
 use std::fs;
+use std::path::Path;
 
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use camino::Utf8PathBuf;
-use shippo_core::{PackageConfig, PackagePlan, Plan, ProjectType, SbomConfig, SignConfig};
-use shippo_pack::{package_outputs, verify_manifest, BuiltOutput};
+use shippo_core::exec::{CommandOutput, CommandSpec, Executor, MockExecutor, SystemExecutor};
+use shippo_core::provenance::Provenance;
+use shippo_core::{
+    AttestConfig, CosignKeyConfig, GpgKeyConfig, Manifest, ManifestSignature, PackageConfig,
+    PackagePlan, Plan, ProjectType, SbomConfig, SignConfig, SignersConfig, TransformConfig,
+    VerifyKeysConfig,
+};
+use shippo_pack::{
+    apply_transforms, compare_config, compare_rebuild, extract_files, inspect_artifact,
+    load_artifact_inputs, mark_partial, merge_partials, merge_signatures, package_outputs,
+    resolve_contents, sign_as, upgrade_manifest, verify_manifest, verify_manifest_data,
+    BuiltOutput, Check, CheckStatus, DistLock, PackageError, VerifyOptions,
+};
 use tempfile::tempdir;
 
-#[test]
-fn package_and_verify_manifest() {
-    let dir = tempdir().unwrap();
-    let artifact_path = dir.path().join("demo-bin");
-    fs::write(&artifact_path, "hello").unwrap();
-    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
-    let plan = Plan {
+fn thorough() -> VerifyOptions {
+    VerifyOptions {
+        strict: true,
+        deep: true,
+        ..Default::default()
+    }
+}
+
+fn demo_plan(targets: &[&str]) -> Plan {
+    Plan {
         version: "v1.0.0".into(),
         packages: vec![PackagePlan {
             name: "demo".into(),
             project_type: ProjectType::Rust,
             path: Utf8PathBuf::from("."),
-            targets: vec!["native".into()],
+            targets: targets.iter().map(|t| t.to_string()).collect(),
             package: PackageConfig {
                 formats: vec!["tar.gz".into(), "zip".into()],
                 name_template: "{name}-{version}-{target}".into(),
                 include: vec![],
                 exclude: vec![],
+                target_style: None,
+                keep_last: None,
+                contents: Default::default(),
+                transform: Vec::new(),
+                compression: Default::default(),
+                duplicates: None,
             },
             sbom: SbomConfig {
                 enabled: true,
                 format: "cyclonedx".into(),
                 mode: "auto".into(),
+                tool: None,
+                formats: Vec::new(),
             },
             sign: SignConfig {
                 enabled: false,
                 method: "cosign".into(),
                 cosign_mode: "keyless".into(),
+                ssh_key: None,
+                ssh_namespace: None,
+                cosign: None,
+                gpg: None,
             },
             node: None,
             python: None,
+            go: None,
+            builder: None,
+            docker: None,
         }],
+        plugins: Default::default(),
+        narrowed_from: None,
+        config_sha256: None,
+        submodules: Vec::new(),
+        attest: None,
+        source_date_epoch: None,
+        signers: Vec::new(),
+        trust: None,
+    }
+}
+
+#[test]
+fn package_and_verify_manifest() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let plan = demo_plan(&["native"]);
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![artifact],
+        contents: Vec::new(),
+    }];
+    let dist = dir.path().join("dist");
+    let manifest =
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap();
+    assert_eq!(manifest.packages.len(), 1);
+    let manifest_path = dist.join("manifest.json");
+    assert!(
+        verify_manifest(&manifest_path, &dist, &thorough(), &SystemExecutor)
+            .unwrap()
+            .ok()
+    );
+
+    // a dist from before manifest versioning still verifies and upgrades
+    let current = fs::read_to_string(&manifest_path).unwrap();
+    let legacy: String = current
+        .lines()
+        .filter(|l| !l.contains("\"manifest_version\""))
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::write(&manifest_path, legacy).unwrap();
+    let sha = shippo_core::sha256_file(&manifest_path).unwrap();
+    let sums: String = fs::read_to_string(dist.join("SHA256SUMS"))
+        .unwrap()
+        .lines()
+        .map(|l| match l.ends_with("  manifest.json") {
+            true => format!("{sha}  manifest.json\n"),
+            false => format!("{l}\n"),
+        })
+        .collect();
+    fs::write(dist.join("SHA256SUMS"), sums).unwrap();
+    assert!(
+        verify_manifest(&manifest_path, &dist, &thorough(), &SystemExecutor)
+            .unwrap()
+            .ok()
+    );
+    assert_eq!(upgrade_manifest(&dist).unwrap(), 1);
+    assert_eq!(upgrade_manifest(&dist).unwrap(), 2);
+    assert!(
+        verify_manifest(&manifest_path, &dist, &thorough(), &SystemExecutor)
+            .unwrap()
+            .ok()
+    );
+}
+
+#[test]
+fn verify_checks_signatures_against_configured_keys() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let mut plan = demo_plan(&["native"]);
+    plan.packages[0].package.formats = vec!["tar.gz".into()];
+    plan.packages[0].sign.enabled = true;
+    plan.packages[0].sign.method = "ssh".into();
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![artifact],
+        contents: Vec::new(),
+    }];
+    let dist = dir.path().join("dist");
+    let manifest =
+        package_outputs(&plan, &built, &dist, None, None, true, &SystemExecutor).unwrap();
+    for sig in &manifest.packages[0].targets[0].signatures {
+        fs::write(dist.join(&sig.filename), "-----BEGIN SSH SIGNATURE-----").unwrap();
+    }
+    let manifest_path = dist.join("manifest.json");
+    let executor = MockExecutor::new();
+    let report = verify_manifest(&manifest_path, &dist, &thorough(), &executor).unwrap();
+    let failure = report.failures().next().unwrap();
+    assert!(
+        failure.details.contains("ssh_allowed_signers"),
+        "{failure:?}"
+    );
+    assert!(executor.calls().is_empty());
+
+    let mut options = thorough();
+    options.keys.ssh_allowed_signers = Some("/keys/allowed_signers".into());
+    for _ in 0..2 {
+        executor.respond(
+            "ssh-keygen",
+            CommandOutput {
+                code: Some(0),
+                stdout: "release@acme.test\n".into(),
+                ..Default::default()
+            },
+        );
+        executor.respond(
+            "ssh-keygen",
+            CommandOutput {
+                code: Some(0),
+                ..Default::default()
+            },
+        );
+    }
+    let report = verify_manifest(&manifest_path, &dist, &options, &executor).unwrap();
+    // SHA256SUMS still lists the replaced signature files; only check those
+    assert!(report
+        .checks
+        .iter()
+        .filter(|c| c.check == Check::Signature)
+        .all(|c| c.status == CheckStatus::Pass));
+    let verify = &executor.calls()[1];
+    assert_eq!(
+        verify.args[..6],
+        [
+            "-Y",
+            "verify",
+            "-f",
+            "/keys/allowed_signers",
+            "-I",
+            "release@acme.test"
+        ]
+    );
+    let stdin = verify.stdin.as_ref().unwrap();
+    assert!(stdin.to_string_lossy().ends_with(".tar.gz"));
+}
+
+#[test]
+fn ssh_signing_uses_configured_key_and_namespace() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let mut plan = demo_plan(&["native"]);
+    plan.packages[0].package.formats = vec!["tar.gz".into()];
+    plan.packages[0].sign.enabled = true;
+    plan.packages[0].sign.method = "ssh".into();
+    plan.packages[0].sign.ssh_key = Some("keys/release_ed25519".into());
+    plan.packages[0].sign.ssh_namespace = Some("acme-release".into());
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![artifact],
+        contents: Vec::new(),
+    }];
+    let dist = dir.path().join("dist");
+    let executor = MockExecutor::new();
+    let manifest = package_outputs(&plan, &built, &dist, None, None, true, &executor).unwrap();
+    let calls: Vec<_> = executor
+        .calls()
+        .into_iter()
+        .filter(|c| c.program == "ssh-keygen")
+        .collect();
+    assert_eq!(calls.len(), 2);
+    let archive = &manifest.packages[0].targets[0].artifacts[0].filename;
+    assert_eq!(
+        calls[0].args,
+        [
+            "-Y",
+            "sign",
+            "-f",
+            "keys/release_ed25519",
+            "-n",
+            "acme-release",
+            dist.join(archive).to_str().unwrap(),
+        ]
+    );
+    // the mock writes no signature, so the checksum placeholder stays
+    let sig = &manifest.packages[0].targets[0].signatures[0];
+    assert_eq!(sig.method, "ssh");
+    assert_eq!(
+        fs::read_to_string(dist.join(&sig.filename)).unwrap(),
+        manifest.packages[0].targets[0].artifacts[0].sha256
+    );
+    // and VERIFYING.md doesn't offer to check it
+    let verifying = fs::read_to_string(dist.join("VERIFYING.md")).unwrap();
+    assert!(verifying.contains("sha256sum --ignore-missing -c SHA256SUMS"));
+    assert!(!verifying.contains("ssh-keygen"), "{verifying}");
+}
+
+#[test]
+fn verify_cross_checks_index_files() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![artifact],
+        contents: Vec::new(),
+    }];
+    let dist = dir.path().join("dist");
+    package_outputs(
+        &demo_plan(&["native"]),
+        &built,
+        &dist,
+        None,
+        None,
+        false,
+        &SystemExecutor,
+    )
+    .unwrap();
+    let manifest_path = dist.join("manifest.json");
+    fs::write(dist.join("extra.bin"), "smuggled").unwrap();
+    // the full release notes publishing writes for a cut release body
+    fs::write(dist.join("CHANGELOG.md"), "## Changes\n").unwrap();
+    // and what an earlier failed release got through
+    fs::write(dist.join("summary.json"), "{}").unwrap();
+    let sums = fs::read_to_string(dist.join("SHA256SUMS")).unwrap();
+    let tampered: String = sums
+        .lines()
+        .map(|l| match l.split_once("  ") {
+            Some((_, file)) if file.ends_with(".zip") => format!("{}  {file}\n", "0".repeat(64)),
+            _ => format!("{l}\n"),
+        })
+        .collect();
+    fs::write(dist.join("SHA256SUMS"), tampered + "abc  stray.txt\n").unwrap();
+    fs::write(dist.join("provenance.json"), r#"{"version":"v9.9.9"}"#).unwrap();
+    let tarball = fs::read_dir(&dist)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| p.to_string_lossy().ends_with(".tar.gz"))
+        .unwrap();
+    let bytes = fs::read(&tarball).unwrap();
+    fs::write(&tarball, &bytes[..bytes.len() / 2]).unwrap();
+
+    let report = verify_manifest(&manifest_path, &dist, &thorough(), &SystemExecutor).unwrap();
+    let failed: Vec<_> = report
+        .failures()
+        .map(|c| (c.check, c.artifact.as_str()))
+        .collect();
+    assert_eq!(failed.len(), 6, "{failed:?}");
+    assert!(failed
+        .iter()
+        .any(|(check, file)| *check == Check::Contents && file.ends_with(".tar.gz")));
+    assert!(failed.contains(&(Check::Unlisted, "extra.bin")));
+    assert!(failed.contains(&(Check::Sums, "stray.txt")));
+    assert!(failed.contains(&(Check::Provenance, "provenance.json")));
+    assert!(failed
+        .iter()
+        .any(|(check, file)| *check == Check::Sums && file.ends_with(".zip")));
+}
+
+#[test]
+fn verify_downloaded_release() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![artifact],
+        contents: Vec::new(),
+    }];
+    let dist = dir.path().join("dist");
+    package_outputs(
+        &demo_plan(&["native"]),
+        &built,
+        &dist,
+        None,
+        None,
+        false,
+        &SystemExecutor,
+    )
+    .unwrap();
+    let manifest = fs::read_to_string(dist.join("manifest.json")).unwrap();
+    let tarball = fs::read_dir(&dist)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .find(|name| name.ends_with(".tar.gz"))
+        .unwrap();
+    let downloads = dir.path().join("downloads");
+    fs::create_dir(&downloads).unwrap();
+    fs::copy(dist.join(&tarball), downloads.join(&tarball)).unwrap();
+    fs::copy(dist.join("SHA256SUMS"), downloads.join("SHA256SUMS")).unwrap();
+    fs::write(downloads.join("notes.txt"), "unrelated").unwrap();
+    let options = VerifyOptions {
+        downloaded: true,
+        ..thorough()
     };
+
+    let report = verify_manifest_data(
+        &manifest,
+        "manifest.json",
+        &downloads,
+        &options,
+        &SystemExecutor,
+    )
+    .unwrap();
+    assert!(report.ok(), "{report:?}");
+    assert!(report
+        .checks
+        .iter()
+        .any(|c| c.artifact.ends_with(".zip") && c.status == CheckStatus::Warn));
+
+    fs::write(downloads.join(&tarball), "tampered").unwrap();
+    let report = verify_manifest_data(
+        &manifest,
+        "manifest.json",
+        &downloads,
+        &options,
+        &SystemExecutor,
+    )
+    .unwrap();
+    let failed: Vec<_> = report.failures().map(|c| c.check).collect();
+    assert!(failed.contains(&Check::Checksum), "{failed:?}");
+
+    // the archive's signature is listed but was not downloaded with it
+    fs::copy(dist.join(&tarball), downloads.join(&tarball)).unwrap();
+    let mut signed = Manifest::from_json(&manifest).unwrap();
+    signed.packages[0].targets[0]
+        .signatures
+        .push(ManifestSignature {
+            filename: format!("{tarball}.minisig"),
+            method: "minisign".into(),
+            bundle: None,
+            rekor: None,
+            signer: None,
+            key: false,
+            offline: false,
+        });
+    let signed = signed.to_json().unwrap();
+    let signature = |options: &VerifyOptions| {
+        let report = verify_manifest_data(
+            &signed,
+            "manifest.json",
+            &downloads,
+            options,
+            &SystemExecutor,
+        )
+        .unwrap();
+        let check = report.checks.iter().find(|c| c.check == Check::Signature);
+        check.unwrap().clone()
+    };
+    let check = signature(&options);
+    assert_eq!(check.status, CheckStatus::Fail);
+    assert!(check.details.contains("unverified"), "{check:?}");
+    let lenient = VerifyOptions {
+        strict: false,
+        ..options.clone()
+    };
+    assert_eq!(signature(&lenient).status, CheckStatus::Warn);
+}
+
+#[test]
+fn keyless_signatures_are_checked_against_the_configured_identity() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let mut plan = demo_plan(&["native"]);
+    plan.packages[0].package.formats = vec!["tar.gz".into()];
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![artifact],
+        contents: Vec::new(),
+    }];
+    let dist = dir.path().join("dist");
+    let mut manifest =
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap();
+    let target = &mut manifest.packages[0].targets[0];
+    let bundle = format!("{}.sigstore.json", target.artifacts[0].filename);
+    fs::write(dist.join(&bundle), "{}").unwrap();
+    target.signatures.push(ManifestSignature {
+        filename: bundle.clone(),
+        method: "cosign".into(),
+        bundle: Some(bundle.clone()),
+        rekor: None,
+        signer: None,
+        key: false,
+        offline: false,
+    });
+    let data = manifest.to_json().unwrap();
+    let options = VerifyOptions {
+        downloaded: true,
+        ..thorough()
+    };
+    let signature = |options: &VerifyOptions, executor: &MockExecutor| {
+        let report =
+            verify_manifest_data(&data, "manifest.json", &dist, options, executor).unwrap();
+        let check = report.checks.iter().find(|c| c.check == Check::Signature);
+        check.unwrap().clone()
+    };
+
+    // anyone can make a keyless signature
+    let executor = MockExecutor::new();
+    let check = signature(&options, &executor);
+    assert_eq!(check.status, CheckStatus::Fail);
+    assert!(check.details.contains("cosign_identity"), "{check:?}");
+    assert!(executor.calls().is_empty());
+
+    let options = VerifyOptions {
+        keys: VerifyKeysConfig {
+            cosign_identity_regexp: Some("^https://github.com/acme/demo/".into()),
+            cosign_oidc_issuer: Some("https://token.actions.githubusercontent.com".into()),
+            ..Default::default()
+        },
+        ..options
+    };
+    assert_eq!(signature(&options, &executor).status, CheckStatus::Pass);
+    let verify = &executor.calls()[0];
+    assert_eq!(verify.program, "cosign");
+    let args = &verify.args[2..];
+    assert_eq!(
+        args,
+        [
+            "--certificate-identity-regexp",
+            "^https://github.com/acme/demo/",
+            "--certificate-oidc-issuer",
+            "https://token.actions.githubusercontent.com",
+            "--bundle",
+            dist.join(&bundle).to_str().unwrap(),
+            "--offline",
+            "--new-bundle-format",
+        ]
+    );
+    assert!(!verify.args.iter().any(|a| a == "--signature"));
+}
+
+/// Records commands like the mock and writes the bundle `cosign sign-blob
+/// --bundle` would.
+/// Writes a bundle whose "signature" is the signed file itself.
+struct Cosign(MockExecutor);
+
+impl Executor for Cosign {
+    fn run(&self, cmd: &CommandSpec) -> std::io::Result<CommandOutput> {
+        self.output(cmd)
+    }
+    fn output(&self, cmd: &CommandSpec) -> std::io::Result<CommandOutput> {
+        if let Some(i) = cmd.args.iter().position(|a| a == "--bundle") {
+            let signed = STANDARD.encode(fs::read(&cmd.args[1])?);
+            let bundle = serde_json::json!({ "messageSignature": { "signature": signed } });
+            fs::write(&cmd.args[i + 1], bundle.to_string())?;
+        }
+        self.0.output(cmd)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        self.0.var(name)
+    }
+}
+
+#[test]
+fn cosign_signatures_are_sigstore_bundles() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let mut plan = demo_plan(&["native"]);
+    plan.packages[0].package.formats = vec!["tar.gz".into()];
+    plan.packages[0].sign.enabled = true;
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![artifact],
+        contents: Vec::new(),
+    }];
+    let dist = dir.path().join("dist");
+    let sign = |plan: &Plan| {
+        let executor = Cosign(MockExecutor::new());
+        executor.0.set_var("COSIGN_KEY_PASSWORD", "hunter2");
+        let manifest = package_outputs(plan, &built, &dist, None, None, true, &executor).unwrap();
+        let calls: Vec<_> = executor
+            .0
+            .calls()
+            .into_iter()
+            .filter(|c| c.program == "cosign" && c.args[0] == "sign-blob")
+            .collect();
+        // the archive, then its SBOM
+        assert_eq!(calls.len(), 2);
+        (manifest, calls[0].clone())
+    };
+
+    let (manifest, call) = sign(&plan);
+    let archive = &manifest.packages[0].targets[0].artifacts[0].filename;
+    let bundle = format!("{archive}.sigstore.json");
+    assert_eq!(
+        call.args,
+        [
+            "sign-blob",
+            dist.join(archive).to_str().unwrap(),
+            "--yes",
+            "--new-bundle-format",
+            "--bundle",
+            dist.join(&bundle).to_str().unwrap(),
+        ]
+    );
+    let sig = &manifest.packages[0].targets[0].signatures[0];
+    assert_eq!(sig.filename, bundle);
+    assert!(sig.in_bundle() && !sig.key && !sig.offline);
+    assert!(!dist.join(format!("{archive}.sig")).exists());
+
+    plan.packages[0].sign.cosign_mode = "key".into();
+    plan.packages[0].sign.cosign = Some(CosignKeyConfig {
+        key: Some("cosign.key".into()),
+        password_env: Some("COSIGN_KEY_PASSWORD".into()),
+        tlog_upload: false,
+        ..Default::default()
+    });
+    let (manifest, call) = sign(&plan);
+    assert_eq!(
+        call.args[2..],
+        [
+            "--yes",
+            "--new-bundle-format",
+            "--bundle",
+            dist.join(&bundle).to_str().unwrap(),
+            "--key",
+            "cosign.key",
+            "--tlog-upload=false",
+        ]
+    );
+    assert_eq!(call.env["COSIGN_PASSWORD"], "hunter2");
+    let sig = &manifest.packages[0].targets[0].signatures[0];
+    assert_eq!(sig.filename, bundle);
+    // no rekor entry was made, so none is looked for
+    assert!(sig.in_bundle() && sig.key && sig.offline);
+    let verifying = fs::read_to_string(dist.join("VERIFYING.md")).unwrap();
+    assert!(
+        verifying.contains(&format!(
+            "cosign verify-blob {archive} --key cosign.pub --bundle {bundle} --new-bundle-format --insecure-ignore-tlog=true"
+        )),
+        "{verifying}"
+    );
+
+    // checked with the public key against the bundle, offline
+    let options = VerifyOptions {
+        keys: VerifyKeysConfig {
+            cosign: Some("cosign.pub".into()),
+            ..Default::default()
+        },
+        ..thorough()
+    };
+    let executor = MockExecutor::new();
+    let data = manifest.to_json().unwrap();
+    let report = verify_manifest_data(&data, "manifest.json", &dist, &options, &executor).unwrap();
+    assert!(report.ok(), "{report:?}");
+    let verify = executor
+        .calls()
+        .into_iter()
+        .find(|c| c.program == "cosign" && c.args[1] == dist.join(archive).to_str().unwrap())
+        .unwrap();
+    assert_eq!(
+        verify.args[2..],
+        [
+            "--key",
+            "cosign.pub",
+            "--bundle",
+            dist.join(&bundle).to_str().unwrap(),
+            "--offline",
+            "--new-bundle-format",
+            "--insecure-ignore-tlog=true",
+        ]
+    );
+}
+
+#[test]
+fn compare_config_detects_modified_configs() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let mut plan = demo_plan(&["native"]);
+    plan.config_sha256 = Some("a".repeat(64));
     let built = vec![BuiltOutput {
         package: "demo".into(),
         target: "native".into(),
         artifacts: vec![artifact],
+        contents: Vec::new(),
     }];
     let dist = dir.path().join("dist");
-    let manifest = package_outputs(&plan, &built, &dist, None, None, false).unwrap();
+    let manifest =
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap();
+    assert_eq!(manifest.project.config_sha256, plan.config_sha256);
+    assert!(compare_config(&manifest, &plan, ".shippo.toml").ok());
+
+    plan.config_sha256 = Some("b".repeat(64));
+    plan.packages[0].targets.push("linux-arm64".into());
+    let report = compare_config(&manifest, &plan, ".shippo.toml");
+    let failed: Vec<_> = report.failures().map(|c| c.artifact.as_str()).collect();
+    assert_eq!(failed, [".shippo.toml", "plan"]);
+}
+
+#[test]
+fn strict_verify_rejects_unverified_signatures() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let mut plan = demo_plan(&["native"]);
+    plan.packages[0].sign.enabled = true;
+    plan.packages[0].sign.method = "ssh".into();
+    plan.packages[0].sign.ssh_key = Some("keys/release_ed25519".into());
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![artifact],
+        contents: Vec::new(),
+    }];
+    let failed = CommandOutput {
+        code: Some(255),
+        stderr: "Could not verify signature.".into(),
+        ..Default::default()
+    };
+    // signing fails for every archive and the SBOM, leaving checksum
+    // placeholders
+    let signed = plan.packages[0].package.formats.len() + 1;
+    let executor = MockExecutor::new();
+    for _ in 0..signed {
+        executor.respond("ssh-keygen", failed.clone());
+    }
+    let dist = dir.path().join("dist");
+    let manifest = package_outputs(&plan, &built, &dist, None, None, true, &executor).unwrap();
+    let manifest_path = dist.join("manifest.json");
+    let lenient =
+        verify_manifest(&manifest_path, &dist, &VerifyOptions::default(), &executor).unwrap();
+    assert!(lenient.ok());
+    assert_eq!(
+        lenient
+            .checks
+            .iter()
+            .filter(|c| c.status == CheckStatus::Warn)
+            .count(),
+        signed
+    );
+
+    // every problem is reported, not just the first
+    fs::write(
+        dist.join(&manifest.packages[0].targets[0].artifacts[0].filename),
+        "x",
+    )
+    .unwrap();
+    let report = verify_manifest(&manifest_path, &dist, &thorough(), &executor).unwrap();
+    let failed_checks = |artifact: &str| -> Vec<Check> {
+        let failures = report.failures().filter(|c| c.artifact == artifact);
+        failures.map(|c| c.check).collect()
+    };
+    let archive = &manifest.packages[0].targets[0].artifacts[0].filename;
+    assert_eq!(failed_checks(archive), [Check::Checksum, Check::Contents]);
+    let sbom_sig = "demo-v1.0.0-native-sbom.cdx.json.sig";
+    assert_eq!(failed_checks(sbom_sig), [Check::Signature]);
+    assert!(report
+        .failures()
+        .any(|c| c.artifact == sbom_sig && c.details.contains("not a signature")));
+
+    let sigs = &manifest.packages[0].targets[0].signatures;
+    for sig in sigs {
+        fs::write(dist.join(&sig.filename), "-----BEGIN SSH SIGNATURE-----").unwrap();
+    }
+    let principal = CommandOutput {
+        code: Some(0),
+        stdout: "release\n".into(),
+        ..Default::default()
+    };
+    executor.respond("ssh-keygen", principal);
+    executor.respond("ssh-keygen", failed);
+    let options = VerifyOptions {
+        keys: VerifyKeysConfig {
+            ssh_allowed_signers: Some("allowed_signers".into()),
+            ..Default::default()
+        },
+        ..thorough()
+    };
+    let report = verify_manifest(&manifest_path, &dist, &options, &executor).unwrap();
+    let rejected = report
+        .failures()
+        .find(|c| c.check == Check::Signature)
+        .unwrap();
+    assert!(
+        rejected.details.contains("Could not verify signature"),
+        "{rejected:?}"
+    );
+    let err = report.ensure_ok(&dist).unwrap_err();
+    assert!(matches!(err, PackageError::VerifyFailed { .. }));
+}
+
+#[test]
+fn rebuild_comparison_flags_changed_artifacts() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path.clone()).unwrap();
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![artifact],
+        contents: Vec::new(),
+    }];
+    let plan = demo_plan(&["native"]);
+    let package = |name: &str| {
+        let dist = dir.path().join(name);
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap()
+    };
+    let original = package("dist");
+    assert!(compare_rebuild(&original, &package("again")).ok());
+
+    fs::write(&artifact_path, "hello, world").unwrap();
+    let report = compare_rebuild(&original, &package("changed"));
+    assert_eq!(report.failures().count(), 2);
+    assert!(report.checks.iter().all(|c| c.check == Check::Rebuild));
+}
+
+#[test]
+fn merge_partial_dists() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let mut partials = Vec::new();
+    let full = demo_plan(&["linux-amd64", "darwin-arm64"]);
+    for target in ["linux-amd64", "darwin-arm64"] {
+        let built = vec![BuiltOutput {
+            package: "demo".into(),
+            target: target.into(),
+            artifacts: vec![artifact.clone()],
+            contents: Vec::new(),
+        }];
+        let partial = dir.path().join(format!("dist-{target}"));
+        let plan = Plan {
+            narrowed_from: Some(full.fingerprint()),
+            ..demo_plan(&[target])
+        };
+        package_outputs(&plan, &built, &partial, None, None, false, &SystemExecutor).unwrap();
+        mark_partial(&partial).unwrap();
+        assert!(!partial.join("SHA256SUMS").exists());
+        partials.push(partial);
+    }
+    let out = dir.path().join("dist");
+    let manifest = merge_partials(&partials, &out).unwrap();
     assert_eq!(manifest.packages.len(), 1);
+    assert_eq!(manifest.packages[0].targets.len(), 2);
+    assert_eq!(manifest.project.plan_fingerprint, Some(full.fingerprint()));
+    assert!(verify_manifest(
+        &out.join("manifest.json"),
+        &out,
+        &thorough(),
+        &SystemExecutor
+    )
+    .unwrap()
+    .ok());
+    let sums = fs::read_to_string(out.join("SHA256SUMS")).unwrap();
+    assert_eq!(sums.lines().count(), 8);
+    assert!(sums.contains("  VERIFYING.md\n"));
+    let err = merge_partials(&[partials[0].clone(), partials[0].clone()], &out).unwrap_err();
+    assert!(matches!(err, PackageError::DuplicateTarget { .. }));
+}
+
+#[test]
+fn dist_lock_is_exclusive() {
+    let dir = tempdir().unwrap();
+    let dist = dir.path().join("dist");
+    let held = DistLock::acquire(&dist).unwrap();
+    assert!(matches!(
+        DistLock::acquire(&dist),
+        Err(PackageError::DistLocked { .. })
+    ));
+    drop(held);
+    DistLock::acquire(&dist).unwrap();
+}
+
+#[test]
+fn lfs_pointers_are_not_packaged() {
+    let dir = tempdir().unwrap();
+    let assets = dir.path().join("assets");
+    fs::create_dir_all(&assets).unwrap();
+    fs::write(
+        assets.join("model.bin"),
+        "version https://git-lfs.github.com/spec/v1\noid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\nsize 12345\n",
+    )
+    .unwrap();
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![Utf8PathBuf::from_path_buf(assets).unwrap()],
+        contents: Vec::new(),
+    }];
+    let mut plan = demo_plan(&["native"]);
+    plan.submodules = vec![shippo_core::SubmodulePin {
+        path: "vendor/lib".into(),
+        url: Some("https://example.com/lib.git".into()),
+        commit: "a".repeat(40),
+    }];
+    let dist = dir.path().join("dist");
+    let err =
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap_err();
+    assert!(matches!(err, PackageError::LfsPointer(path) if path.ends_with("model.bin")));
+
+    fs::write(dir.path().join("assets/model.bin"), [0u8; 64]).unwrap();
+    let manifest =
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap();
+    assert_eq!(manifest.project.submodules, plan.submodules);
+}
+
+#[test]
+fn inspect_single_artifact() {
+    let dir = tempdir().unwrap();
+    let provenance = Provenance {
+        package: "demo".into(),
+        target: "native".into(),
+        version: "v1.0.0".into(),
+        commit: Some("0123abc".into()),
+        builder: "rust".into(),
+        config_sha256: None,
+    };
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, format!("\x7fELF{}\0", provenance.blob())).unwrap();
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![Utf8PathBuf::from_path_buf(artifact_path).unwrap()],
+        contents: Vec::new(),
+    }];
+    let dist = dir.path().join("dist");
+    let manifest = package_outputs(
+        &demo_plan(&["native"]),
+        &built,
+        &dist,
+        None,
+        None,
+        false,
+        &SystemExecutor,
+    )
+    .unwrap();
+    let options = VerifyOptions::default();
+    let name = "demo-v1.0.0-native.zip";
+    let found = inspect_artifact(&dist, &manifest, name, &options, &SystemExecutor)
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.target, "native");
+    assert!(found.report.ok());
+    assert_eq!(
+        found
+            .report
+            .checks
+            .iter()
+            .map(|c| c.check)
+            .collect::<Vec<_>>(),
+        vec![Check::Checksum, Check::Contents]
+    );
+    assert!(!found.signed);
+    assert_eq!(found.contents.len(), 1);
+    assert_eq!(found.provenance["demo-bin"], provenance);
+
+    fs::write(dist.join(name), "tampered").unwrap();
+    let found = inspect_artifact(&dist, &manifest, name, &options, &SystemExecutor)
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.report.failures().count(), 2);
+    assert!(
+        inspect_artifact(&dist, &manifest, "other.zip", &options, &SystemExecutor)
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn wheels_extracted_from_archives() {
+    let dir = tempdir().unwrap();
+    let mut artifacts = Vec::new();
+    for name in [
+        "demo-1.0.0-py3-none-any.whl",
+        "demo-1.0.0.tar.gz",
+        "README.md",
+    ] {
+        let path = dir.path().join(name);
+        fs::write(&path, name).unwrap();
+        artifacts.push(Utf8PathBuf::from_path_buf(path).unwrap());
+    }
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts,
+        contents: Vec::new(),
+    }];
+    let dist = dir.path().join("dist");
+    package_outputs(
+        &demo_plan(&["native"]),
+        &built,
+        &dist,
+        None,
+        None,
+        false,
+        &SystemExecutor,
+    )
+    .unwrap();
+    let wheel = |name: &str| name.ends_with(".whl") || name.ends_with(".tar.gz");
+    for archive in ["demo-v1.0.0-native.tar.gz", "demo-v1.0.0-native.zip"] {
+        let out = dir.path().join(archive.replace('.', "_"));
+        fs::create_dir(&out).unwrap();
+        let mut files = extract_files(&dist.join(archive), &out, wheel).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                out.join("demo-1.0.0-py3-none-any.whl"),
+                out.join("demo-1.0.0.tar.gz")
+            ]
+        );
+        assert_eq!(fs::read_to_string(&files[1]).unwrap(), "demo-1.0.0.tar.gz");
+    }
+}
+
+#[test]
+fn source_files_packed_by_contents_mappings() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    for (path, content) in [
+        ("app/docs/index.md", "index"),
+        ("app/docs/guide/intro.md", "intro"),
+        ("app/examples/basic.toml", "basic"),
+        ("app/examples/secret.toml", "secret"),
+        ("app/README.md", "readme"),
+        ("app/target/app", "binary"),
+    ] {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    let mut plan = demo_plan(&["native"]);
+    let pkg = &mut plan.packages[0];
+    pkg.path = "app".into();
+    pkg.package.include = vec!["examples/*.toml".into()];
+    pkg.package.exclude = vec!["examples/secret.toml".into()];
+    pkg.package.contents = [
+        ("docs/**", "share/doc/{name}/"),
+        ("README.md", ""),
+        ("target/*", "bin/"),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect();
+    let mut built = BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![Utf8PathBuf::from_path_buf(root.join("app/target/app")).unwrap()],
+        contents: Vec::new(),
+    };
+    built.contents = resolve_contents(root, &plan.packages[0], &plan.version, &built).unwrap();
+    // the binary is an artifact already, so `target/*` adds nothing
+    assert_eq!(
+        built
+            .contents
+            .iter()
+            .map(|c| c.path.as_str())
+            .collect::<Vec<_>>(),
+        [
+            "README.md",
+            "examples/basic.toml",
+            "share/doc/demo/guide/intro.md",
+            "share/doc/demo/index.md"
+        ]
+    );
+
+    let dist = root.join("dist");
+    let manifest = package_outputs(
+        &plan,
+        std::slice::from_ref(&built),
+        &dist,
+        None,
+        None,
+        false,
+        &SystemExecutor,
+    )
+    .unwrap();
+    for artifact in &manifest.packages[0].targets[0].artifacts {
+        let paths: Vec<_> = artifact.contents.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths[0], "app");
+        assert!(
+            paths.contains(&"share/doc/demo/guide/intro.md"),
+            "{paths:?}"
+        );
+    }
+    assert!(verify_manifest(
+        &dist.join("manifest.json"),
+        &dist,
+        &thorough(),
+        &SystemExecutor
+    )
+    .unwrap()
+    .ok());
+
+    plan.packages[0]
+        .package
+        .contents
+        .insert("man/*.1".into(), "share/man/man1/".into());
+    let err = resolve_contents(root, &plan.packages[0], &plan.version, &built).unwrap_err();
+    assert!(matches!(err, PackageError::NoContents { .. }), "{err}");
+}
+
+#[test]
+fn target_style_normalizes_artifact_names() {
+    let dir = tempdir().unwrap();
+    let artifact = dir.path().join("demo");
+    fs::write(&artifact, "bin").unwrap();
+    let mut plan = demo_plan(&["x86_64-unknown-linux-gnu", "macos-arm64"]);
+    plan.packages[0].package.target_style = Some("goish".into());
+    let built: Vec<_> = plan.packages[0]
+        .targets
+        .iter()
+        .map(|target| BuiltOutput {
+            package: "demo".into(),
+            target: target.clone(),
+            artifacts: vec![Utf8PathBuf::from_path_buf(artifact.clone()).unwrap()],
+            contents: Vec::new(),
+        })
+        .collect();
+    let dist = dir.path().join("dist");
+    let manifest =
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap();
+    let targets = &manifest.packages[0].targets;
+    assert_eq!(targets[0].target, "x86_64-unknown-linux-gnu");
+    assert_eq!(
+        targets[0].artifacts[0].filename,
+        "demo-v1.0.0-linux-amd64.tar.gz"
+    );
+    assert_eq!(
+        targets[1].artifacts[1].filename,
+        "demo-v1.0.0-darwin-arm64.zip"
+    );
+    assert_eq!(
+        targets[1].sbom.as_ref().unwrap().filename,
+        "demo-v1.0.0-darwin-arm64-sbom.cdx.json"
+    );
+}
+
+#[test]
+fn colliding_asset_names_are_rejected() {
+    let dir = tempdir().unwrap();
+    let artifact = dir.path().join("demo");
+    fs::write(&artifact, "bin").unwrap();
+    let built = |package: &str, target: &str| BuiltOutput {
+        package: package.into(),
+        target: target.into(),
+        artifacts: vec![Utf8PathBuf::from_path_buf(artifact.clone()).unwrap()],
+        contents: Vec::new(),
+    };
+    let dist = dir.path().join("dist");
+
+    let mut plan = demo_plan(&["linux-amd64", "darwin-arm64"]);
+    plan.packages[0].package.name_template = "{name}-{version}".into();
+    let outputs = [built("demo", "linux-amd64"), built("demo", "darwin-arm64")];
+    let err =
+        package_outputs(&plan, &outputs, &dist, None, None, false, &SystemExecutor).unwrap_err();
+    match &err {
+        PackageError::AssetCollision {
+            filename,
+            first,
+            second,
+            ..
+        } => {
+            assert_eq!(filename, "demo-v1.0.0.tar.gz");
+            assert_eq!(first, "demo (linux-amd64)");
+            assert_eq!(second, "demo (darwin-arm64)");
+        }
+        other => panic!("unexpected error {other}"),
+    }
+    assert!(err.to_string().contains("add {target}"));
+    assert!(!dist.exists());
+
+    let mut plan = demo_plan(&["linux-amd64"]);
+    let mut web = plan.packages[0].clone();
+    web.name = "web".into();
+    for pkg in [&mut plan.packages[0], &mut web] {
+        pkg.package.name_template = "app-{version}-{target}".into();
+    }
+    plan.packages.push(web);
+    let outputs = [built("demo", "linux-amd64"), built("web", "linux-amd64")];
+    let err =
+        package_outputs(&plan, &outputs, &dist, None, None, false, &SystemExecutor).unwrap_err();
+    assert!(err.to_string().contains("add {name}"), "{err}");
+
+    // a signer's signature name is the longest one
+    let mut plan = demo_plan(&["linux-amd64"]);
+    plan.packages[0].package.name_template = format!("{}-{{target}}", "a".repeat(200));
+    plan.signers = vec!["s".repeat(30)];
+    let outputs = [built("demo", "linux-amd64")];
+    let err =
+        package_outputs(&plan, &outputs, &dist, None, None, false, &SystemExecutor).unwrap_err();
+    match &err {
+        PackageError::InvalidName { filename, .. } => {
+            assert!(filename.ends_with(&format!(".{}.minisig", "s".repeat(30))));
+        }
+        other => panic!("unexpected error {other}"),
+    }
+}
+
+#[test]
+fn prebuilt_artifacts_packaged_from_inputs_file() {
+    let dir = tempdir().unwrap();
+    let inputs = dir.path().join("prebuilt");
+    fs::create_dir_all(inputs.join("linux")).unwrap();
+    fs::write(inputs.join("linux/demo"), "bin").unwrap();
+    fs::write(inputs.join("demo.exe"), "exe").unwrap();
+    let plan = demo_plan(&["x86_64-unknown-linux-gnu", "windows-amd64"]);
+
+    fs::write(
+        inputs.join("artifacts.toml"),
+        "[[artifact]]\ntarget = 'linux-x64'\nfiles = ['linux/demo']\n\n\
+         [[artifact]]\npackage = 'demo'\ntarget = 'windows-amd64'\nfiles = ['demo.exe']\n",
+    )
+    .unwrap();
+    let built = load_artifact_inputs(&inputs, &plan).unwrap();
+    assert_eq!(built[0].target, "x86_64-unknown-linux-gnu");
+    assert!(built[0].artifacts[0].ends_with("linux/demo"));
+    let dist = dir.path().join("dist");
+    let manifest =
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap();
+    assert_eq!(manifest.packages[0].targets.len(), 2);
+    assert!(dist.join("demo-v1.0.0-windows-amd64.zip").exists());
+
+    fs::write(
+        inputs.join("artifacts.toml"),
+        "[[artifact]]\ntarget = 'darwin-arm64'\nfiles = ['demo.exe']\n",
+    )
+    .unwrap();
+    let err = load_artifact_inputs(&inputs, &plan).unwrap_err();
+    assert!(matches!(err, PackageError::ArtifactInputs { .. }), "{err}");
+
+    fs::write(
+        inputs.join("artifacts.toml"),
+        "[[artifact]]\ntarget = 'linux-amd64'\nfiles = ['missing']\n",
+    )
+    .unwrap();
+    let err = load_artifact_inputs(&inputs, &plan).unwrap_err();
+    assert!(matches!(
+        err,
+        PackageError::Missing {
+            kind: "artifact",
+            ..
+        }
+    ));
+}
+
+#[test]
+fn transforms_rename_and_wrap_built_files() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    fs::write(root.join("demo-x86_64-unknown-linux-gnu"), "bin").unwrap();
+    fs::write(root.join("README.md"), "readme").unwrap();
+    fs::write(
+        root.join("launcher.sh"),
+        "#!/bin/sh\nexec \"$(dirname \"$0\")/{binary}\" \"$@\"\n",
+    )
+    .unwrap();
+    let mut plan = demo_plan(&["linux-amd64"]);
+    let step = |pattern: &str| TransformConfig {
+        pattern: pattern.into(),
+        rename: None,
+        mode: None,
+        wrap: None,
+    };
+    plan.packages[0].path = Utf8PathBuf::from_path_buf(root.to_path_buf()).unwrap();
+    plan.packages[0].package.transform = vec![
+        TransformConfig {
+            rename: Some("{name}{ext}".into()),
+            mode: Some("0755".into()),
+            ..step("demo-*")
+        },
+        TransformConfig {
+            wrap: Some("launcher.sh".into()),
+            ..step("demo-*")
+        },
+    ];
+    let file = |name: &str| Utf8PathBuf::from_path_buf(root.join(name)).unwrap();
+    let mut built = BuiltOutput {
+        package: "demo".into(),
+        target: "linux-amd64".into(),
+        artifacts: vec![file("demo-x86_64-unknown-linux-gnu"), file("README.md")],
+        contents: Vec::new(),
+    };
+    let dist = root.join("dist");
+    let stage = dist.join(shippo_pack::TRANSFORM_DIR);
+    built.artifacts =
+        apply_transforms(root, &plan.packages[0], &plan.version, &built, &stage).unwrap();
+    let names: Vec<_> = built
+        .artifacts
+        .iter()
+        .map(|a| a.file_name().unwrap().to_string())
+        .collect();
+    assert_eq!(names, ["demo", "libexec", "README.md"]);
+    assert_eq!(
+        fs::read_to_string(&built.artifacts[0]).unwrap(),
+        "#!/bin/sh\nexec \"$(dirname \"$0\")/libexec/demo\" \"$@\"\n"
+    );
+    assert!(built.artifacts[1].join("demo").exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |p: &Utf8PathBuf| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&built.artifacts[0]), 0o755);
+        assert_eq!(mode(&built.artifacts[1].join("demo")), 0o755);
+    }
+    // the builder's output is left as it was
+    assert!(root.join("demo-x86_64-unknown-linux-gnu").exists());
+
+    let manifest =
+        package_outputs(&plan, &[built], &dist, None, None, false, &SystemExecutor).unwrap();
+    let paths: Vec<_> = manifest.packages[0].targets[0].artifacts[0]
+        .contents
+        .iter()
+        .map(|e| e.path.as_str())
+        .collect();
+    assert_eq!(paths, ["demo", "libexec/demo", "README.md"]);
+}
+
+#[test]
+fn syft_sbom_is_embedded() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let mut plan = demo_plan(&["native"]);
+    plan.packages[0].sbom.tool = Some("syft".into());
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![artifact.clone()],
+        contents: Vec::new(),
+    }];
+    let syft = r#"{"bomFormat": "CycloneDX", "specVersion": "1.5",
+        "metadata": {"component": {"type": "file", "name": "demo-bin"}},
+        "components": [{"type": "library", "name": "serde", "version": "1.0.200"}]}"#;
+    let executor = MockExecutor::new();
+    executor.respond(
+        "syft",
+        CommandOutput {
+            code: Some(0),
+            stdout: syft.into(),
+            ..Default::default()
+        },
+    );
+    let dist = dir.path().join("dist");
+    let manifest = package_outputs(&plan, &built, &dist, None, None, false, &executor).unwrap();
+    assert_eq!(
+        executor.calls()[0].args,
+        [
+            "scan",
+            format!("file:{artifact}").as_str(),
+            "-o",
+            "cyclonedx-json",
+            "-q"
+        ]
+    );
+    let sbom_file = &manifest.packages[0].targets[0]
+        .sbom
+        .as_ref()
+        .unwrap()
+        .filename;
+    let sbom: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dist.join(sbom_file)).unwrap()).unwrap();
+    assert_eq!(sbom["components"][0]["name"], "serde");
+    assert_eq!(sbom["metadata"]["component"]["name"], "demo");
+    assert_eq!(sbom["metadata"]["component"]["version"], "v1.0.0");
+    assert_eq!(
+        sbom["metadata"]["properties"],
+        serde_json::json!([{ "name": "shippo:target", "value": "native" }])
+    );
+
+    // a forced syft that fails doesn't quietly fall back
+    executor.respond(
+        "syft",
+        CommandOutput {
+            code: Some(1),
+            stderr: "unsupported source".into(),
+            ..Default::default()
+        },
+    );
+    let err = package_outputs(&plan, &built, &dist, None, None, false, &executor).unwrap_err();
+    assert!(matches!(err, PackageError::Sbom { .. }), "{err}");
+
+    // without a tool, an executor that finds no syft gets the builtin SBOM
+    struct NoSyft;
+    impl Executor for NoSyft {
+        fn run(&self, cmd: &CommandSpec) -> std::io::Result<CommandOutput> {
+            self.output(cmd)
+        }
+        fn output(&self, _: &CommandSpec) -> std::io::Result<CommandOutput> {
+            Err(std::io::ErrorKind::NotFound.into())
+        }
+    }
+    let err = package_outputs(&plan, &built, &dist, None, None, false, &NoSyft).unwrap_err();
+    assert!(err.to_string().contains("syft is not installed"), "{err}");
+    plan.packages[0].sbom.tool = None;
+    let manifest = package_outputs(&plan, &built, &dist, None, None, false, &NoSyft).unwrap();
+    let sbom_file = &manifest.packages[0].targets[0]
+        .sbom
+        .as_ref()
+        .unwrap()
+        .filename;
+    let sbom: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(dist.join(sbom_file)).unwrap()).unwrap();
+    assert_eq!(sbom["components"], serde_json::json!([]));
+    assert_eq!(sbom["metadata"]["properties"][0]["value"], "native");
+}
+
+#[test]
+fn spdx_and_cyclonedx_sboms_together() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let mut plan = demo_plan(&["native"]);
+    plan.packages[0].sbom.tool = Some("builtin".into());
+    plan.packages[0].sbom.formats = vec!["cyclonedx".into(), "spdx".into()];
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![Utf8PathBuf::from_path_buf(artifact_path).unwrap()],
+        contents: Vec::new(),
+    }];
+    let dist = dir.path().join("dist");
+    let manifest =
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap();
+    let target = &manifest.packages[0].targets[0];
+    let sboms: Vec<_> = target.sboms().map(|s| s.filename.as_str()).collect();
+    assert_eq!(
+        sboms,
+        [
+            "demo-v1.0.0-native-sbom.cdx.json",
+            "demo-v1.0.0-native-sbom.spdx.json"
+        ]
+    );
+    let verified = verify_manifest(
+        &dist.join("manifest.json"),
+        &dist,
+        &thorough(),
+        &SystemExecutor,
+    )
+    .unwrap();
+    assert!(verified.ok());
+
+    let spdx: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(dist.join("demo-v1.0.0-native-sbom.spdx.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(spdx["spdxVersion"], "SPDX-2.3");
+    assert_eq!(spdx["name"], "demo-v1.0.0-native");
+    assert!(spdx["documentNamespace"]
+        .as_str()
+        .unwrap()
+        .starts_with("https://spdx.org/spdxdocs/demo-v1.0.0-native-"));
+    assert!(spdx["creationInfo"]["creators"][0]
+        .as_str()
+        .unwrap()
+        .starts_with("Tool: shippo-"));
+    // the document describes the package, which each archive contains
+    let relationships: Vec<_> = spdx["relationships"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["spdxElementId"].as_str().unwrap(),
+                r["relationshipType"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        relationships,
+        [
+            ("SPDXRef-DOCUMENT", "DESCRIBES"),
+            ("SPDXRef-Archive-demo-v1.0.0-native.tar.gz", "CONTAINS"),
+            ("SPDXRef-Archive-demo-v1.0.0-native.zip", "CONTAINS"),
+        ]
+    );
+}
+
+#[test]
+fn in_toto_statement_per_target() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let mut plan = demo_plan(&["native"]);
+    plan.packages[0].sbom.tool = Some("builtin".into());
+    plan.attest = Some(AttestConfig {
+        enabled: true,
+        sign: true,
+    });
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![Utf8PathBuf::from_path_buf(artifact_path).unwrap()],
+        contents: Vec::new(),
+    }];
+    let dist = dir.path().join("dist");
+    let manifest = package_outputs(
+        &plan,
+        &built,
+        &dist,
+        Some("https://github.com/acme/demo".into()),
+        Some("abc123".into()),
+        false,
+        &SystemExecutor,
+    )
+    .unwrap();
+    let target = &manifest.packages[0].targets[0];
+    let attestation = target.attestation.as_ref().unwrap();
+    assert_eq!(attestation.filename, "demo-v1.0.0-native.intoto.jsonl");
+    assert!(target.files().contains(&"demo-v1.0.0-native.intoto.jsonl"));
+    let sums = fs::read_to_string(dist.join("SHA256SUMS")).unwrap();
+    assert!(sums.contains(&format!(
+        "{}  demo-v1.0.0-native.intoto.jsonl",
+        attestation.sha256
+    )));
+
+    let data = fs::read_to_string(dist.join(&attestation.filename)).unwrap();
+    assert_eq!(data.lines().count(), 1);
+    let envelope: serde_json::Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(envelope["payloadType"], "application/vnd.in-toto+json");
+    assert_eq!(envelope["signatures"], serde_json::json!([]));
+    let payload = STANDARD
+        .decode(envelope["payload"].as_str().unwrap())
+        .unwrap();
+    let statement: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+    assert_eq!(statement["_type"], "https://in-toto.io/Statement/v1");
+    assert_eq!(statement["predicateType"], "https://slsa.dev/provenance/v1");
+    let subject: Vec<_> = statement["subject"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| {
+            (
+                s["name"].as_str().unwrap(),
+                s["digest"]["sha256"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    let expected: Vec<_> = target
+        .artifacts
+        .iter()
+        .chain(target.sboms())
+        .map(|a| (a.filename.as_str(), a.sha256.as_str()))
+        .collect();
+    assert_eq!(subject, expected);
+    let definition = &statement["predicate"]["buildDefinition"];
+    assert_eq!(definition["externalParameters"]["version"], "v1.0.0");
+    assert_eq!(definition["externalParameters"]["target"], "native");
+    assert_eq!(
+        definition["resolvedDependencies"][0]["digest"]["gitCommit"],
+        "abc123"
+    );
+
+    let verified = verify_manifest(
+        &dist.join("manifest.json"),
+        &dist,
+        &thorough(),
+        &SystemExecutor,
+    )
+    .unwrap();
+    assert!(verified.ok());
+
+    // signed, the envelope holds the signature of the statement's PAE
+    plan.packages[0].sign.enabled = true;
+    plan.packages[0].sign.cosign_mode = "key".into();
+    let executor = Cosign(MockExecutor::new());
+    let dist = dir.path().join("dist-signed");
+    let manifest = package_outputs(&plan, &built, &dist, None, None, true, &executor).unwrap();
+    let target = &manifest.packages[0].targets[0];
+    let attestation = target.attestation.as_ref().unwrap();
+    let bundle = format!("{}.sigstore.json", attestation.filename);
+    assert!(target.signatures.iter().any(|s| s.filename == bundle));
+    let data = fs::read_to_string(dist.join(&attestation.filename)).unwrap();
+    let envelope: serde_json::Value = serde_json::from_str(&data).unwrap();
+    let payload = STANDARD
+        .decode(envelope["payload"].as_str().unwrap())
+        .unwrap();
+    let signed = STANDARD
+        .decode(envelope["signatures"][0]["sig"].as_str().unwrap())
+        .unwrap();
+    let pae = [
+        format!("DSSEv1 28 application/vnd.in-toto+json {} ", payload.len()).into_bytes(),
+        payload,
+    ]
+    .concat();
+    assert_eq!(signed, pae);
+
+    // and `shippo verify` checks the signature against the PAE
+    let options = VerifyOptions {
+        keys: VerifyKeysConfig {
+            cosign: Some("cosign.pub".into()),
+            ..Default::default()
+        },
+        ..thorough()
+    };
+    let report = verify_manifest(&dist.join("manifest.json"), &dist, &options, &executor).unwrap();
+    assert!(report.ok(), "{:?}", report.failures().collect::<Vec<_>>());
+    let checked = executor
+        .0
+        .calls()
+        .into_iter()
+        .filter(|c| c.program == "cosign" && c.args[0] == "verify-blob")
+        .find(|c| {
+            c.args
+                .contains(&dist.join(&bundle).to_string_lossy().to_string())
+        })
+        .unwrap();
+    assert_ne!(
+        checked.args[1],
+        dist.join(&attestation.filename).to_str().unwrap()
+    );
+}
+
+#[test]
+fn identical_archives_are_aliased_or_skipped() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo.py");
+    fs::write(&artifact_path, "print('hello')").unwrap();
+    let artifact = Utf8PathBuf::from_path_buf(artifact_path).unwrap();
+    let targets = ["x86_64-unknown-linux-gnu", "aarch64-unknown-linux-gnu"];
+    let built: Vec<_> = targets
+        .iter()
+        .map(|target| BuiltOutput {
+            package: "demo".into(),
+            target: target.to_string(),
+            artifacts: vec![artifact.clone()],
+            contents: Vec::new(),
+        })
+        .collect();
+    let mut plan = demo_plan(&targets);
+    plan.packages[0].package.formats = vec!["tar.gz".into()];
+    plan.packages[0].sbom.tool = Some("builtin".into());
+    let first = "demo-v1.0.0-x86_64-unknown-linux-gnu.tar.gz";
+    let second = "demo-v1.0.0-aarch64-unknown-linux-gnu.tar.gz";
+
+    plan.packages[0].package.duplicates = Some("alias".into());
+    let dist = dir.path().join("dist-alias");
+    let manifest =
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap();
+    let aliased = &manifest.packages[0].targets[1].artifacts[0];
+    assert_eq!(aliased.filename, first);
+    assert_eq!(aliased.alias.as_deref(), Some(second));
+    assert!(dist.join(first).exists());
+    assert!(!dist.join(second).exists());
+    let sums = fs::read_to_string(dist.join("SHA256SUMS")).unwrap();
+    assert_eq!(sums.matches(first).count(), 1);
+    assert!(!sums.contains(second));
+    let verified = verify_manifest(
+        &dist.join("manifest.json"),
+        &dist,
+        &thorough(),
+        &SystemExecutor,
+    )
+    .unwrap();
+    assert!(verified.ok());
+
+    plan.packages[0].package.duplicates = Some("skip".into());
+    let dist = dir.path().join("dist-skip");
+    let manifest =
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap();
+    assert_eq!(manifest.packages[0].targets[0].artifacts.len(), 1);
+    assert!(manifest.packages[0].targets[1].artifacts.is_empty());
+    assert!(!dist.join(second).exists());
+
+    plan.packages[0].package.duplicates = None;
+    let dist = dir.path().join("dist-keep");
+    package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap();
+    assert!(dist.join(second).exists());
+}
+
+#[test]
+fn gpg_signing_uses_configured_key_and_fails_loudly() {
+    let dir = tempdir().unwrap();
+    let artifact_path = dir.path().join("demo-bin");
+    fs::write(&artifact_path, "hello").unwrap();
+    let mut plan = demo_plan(&["native"]);
+    plan.packages[0].package.formats = vec!["tar.gz".into()];
+    plan.packages[0].sign.enabled = true;
+    plan.packages[0].sign.method = "gpg".into();
+    plan.packages[0].sign.gpg = Some(GpgKeyConfig {
+        key_id: Some("0xABCDEF0123456789".into()),
+        homedir: Some("keys/gnupg".into()),
+        passphrase_env: Some("SHIPPO_TEST_GPG_PASSPHRASE".into()),
+    });
+    let built = vec![BuiltOutput {
+        package: "demo".into(),
+        target: "native".into(),
+        artifacts: vec![Utf8PathBuf::from_path_buf(artifact_path).unwrap()],
+        contents: Vec::new(),
+    }];
+    let executor = MockExecutor::new();
+    executor.set_var("SHIPPO_TEST_GPG_PASSPHRASE", "hunter2");
+    executor.respond(
+        "gpg",
+        CommandOutput {
+            code: Some(2),
+            stderr: "gpg: signing failed: Bad passphrase".into(),
+            ..Default::default()
+        },
+    );
+    let dist = dir.path().join("dist");
+    let err = package_outputs(&plan, &built, &dist, None, None, true, &executor).unwrap_err();
+    assert!(matches!(err, PackageError::Sign { .. }));
+    assert!(err.to_string().contains("Bad passphrase"), "{err}");
+    let archive = "demo-v1.0.0-native.tar.gz";
+    assert!(!dist.join(format!("{archive}.sig")).exists());
+
+    let calls = executor.calls();
+    let args = &calls.iter().find(|c| c.program == "gpg").unwrap().args;
+    assert_eq!(
+        args[..8],
+        [
+            "--batch",
+            "--yes",
+            "--homedir",
+            "keys/gnupg",
+            "--local-user",
+            "0xABCDEF0123456789",
+            "--pinentry-mode",
+            "loopback",
+        ]
+    );
+    assert_eq!(args[8], "--passphrase-file");
+    // the passphrase file is gone, and the passphrase was never an argument
+    assert!(!std::path::Path::new(&args[9]).exists());
+    assert!(!args.iter().any(|a| a.contains("hunter2")));
+    assert_eq!(args[args.len() - 1], dist.join(archive).to_str().unwrap());
+
+    // gpg runs but writes nothing
+    let executor = MockExecutor::new();
+    executor.set_var("SHIPPO_TEST_GPG_PASSPHRASE", "hunter2");
+    let err = package_outputs(&plan, &built, &dist, None, None, true, &executor).unwrap_err();
+    assert!(err.to_string().contains("gpg wrote no signature"), "{err}");
+
+    // nor without the passphrase
+    let err =
+        package_outputs(&plan, &built, &dist, None, None, true, &MockExecutor::new()).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("SHIPPO_TEST_GPG_PASSPHRASE is not set"),
+        "{err}"
+    );
+}
+
+#[test]
+fn signer_threshold_is_met_by_merged_signatures() {
+    let dir = tempdir().unwrap();
+    let package = |content: &str, name: &str| {
+        let artifact_path = dir.path().join(format!("{name}-bin"));
+        fs::write(&artifact_path, content).unwrap();
+        let mut plan = demo_plan(&["native"]);
+        plan.packages[0].package.formats = vec!["tar.gz".into()];
+        let built = vec![BuiltOutput {
+            package: "demo".into(),
+            target: "native".into(),
+            artifacts: vec![Utf8PathBuf::from_path_buf(artifact_path).unwrap()],
+            contents: Vec::new(),
+        }];
+        let dist = dir.path().join(name);
+        package_outputs(&plan, &built, &dist, None, None, false, &SystemExecutor).unwrap();
+        dist
+    };
+    let dist = package("hello", "dist");
+    let archive = "demo-v1.0.0-native.tar.gz";
+    let sbom = "demo-v1.0.0-native-sbom.cdx.json";
+    // each signer signs a copy of the dist on their own machine
+    let sign = |signer: &str| {
+        let copy = dir.path().join(signer);
+        fs::create_dir(&copy).unwrap();
+        for entry in fs::read_dir(&dist).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_file() {
+                fs::copy(entry.path(), copy.join(entry.file_name())).unwrap();
+            }
+        }
+        let executor = MockExecutor::new();
+        // what minisign would write
+        for file in [archive, sbom] {
+            fs::write(
+                copy.join(format!("{file}.{signer}.minisig")),
+                format!("untrusted comment: signature from {signer}'s key\nRWQ="),
+            )
+            .unwrap();
+        }
+        let key = format!("/keys/{signer}.key");
+        let signed = sign_as(&copy, signer, Path::new(&key), &executor).unwrap();
+        assert_eq!(signed.len(), 2, "{signed:?}");
+        let args = &executor.calls()[0].args;
+        assert_eq!(args[..3], ["-S", "-s", key.as_str()]);
+        assert!(args[args.len() - 1].ends_with(&format!("{archive}.{signer}.minisig")));
+        copy
+    };
+    let alice = sign("alice");
+    let bob = sign("bob");
+    assert!(sign_as(&dist, "../eve", Path::new("eve.key"), &MockExecutor::new()).is_err());
+
+    let options = VerifyOptions {
+        signers: Some(SignersConfig {
+            threshold: 2,
+            minisign: [("alice", "/keys/alice.pub"), ("bob", "/keys/bob.pub")]
+                .into_iter()
+                .map(|(n, k)| (n.to_string(), k.to_string()))
+                .collect(),
+        }),
+        ..thorough()
+    };
     let manifest_path = dist.join("manifest.json");
-    verify_manifest(&manifest_path, &dist).unwrap();
+    let report = verify_manifest(&manifest_path, &dist, &options, &MockExecutor::new()).unwrap();
+    let short = report.failures().next().unwrap();
+    assert_eq!(short.check, Check::Threshold);
+    assert_eq!(short.details, "0 of the 2 required signer signatures");
+
+    assert_eq!(
+        merge_signatures(&dist, std::slice::from_ref(&alice))
+            .unwrap()
+            .len(),
+        2
+    );
+    let report = verify_manifest(&manifest_path, &dist, &options, &MockExecutor::new()).unwrap();
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 2, "{failures:?}");
+    assert!(failures.iter().all(|f| f.check == Check::Threshold
+        && f.details == "1 of the 2 required signer signatures (from alice)"));
+
+    assert_eq!(merge_signatures(&dist, &[alice, bob]).unwrap().len(), 2);
+    let executor = MockExecutor::new();
+    let report = verify_manifest(&manifest_path, &dist, &options, &executor).unwrap();
+    assert!(report.ok(), "{:?}", report.failures().collect::<Vec<_>>());
+    assert!(executor
+        .calls()
+        .iter()
+        .any(|c| c.program == "minisign" && c.args.contains(&"/keys/bob.pub".to_string())));
+    let sums = fs::read_to_string(dist.join("SHA256SUMS")).unwrap();
+    assert!(sums.contains(&format!("  {archive}.bob.minisig\n")));
+    let verifying = fs::read_to_string(dist.join("VERIFYING.md")).unwrap();
+    assert!(verifying.contains(&format!("minisign -V -p alice.pub -m {archive}")));
+
+    // signatures of a different build are refused
+    let other = package("bye", "other");
+    fs::write(
+        other.join(format!("{archive}.carol.minisig")),
+        "untrusted comment: carol",
+    )
+    .unwrap();
+    fs::write(other.join(format!("{sbom}.carol.minisig")), "x").unwrap();
+    sign_as(
+        &other,
+        "carol",
+        Path::new("carol.key"),
+        &MockExecutor::new(),
+    )
+    .unwrap();
+    let err = merge_signatures(&dist, &[other]).unwrap_err();
+    assert!(
+        matches!(err, PackageError::SignatureMismatch { .. }),
+        "{err}"
+    );
 }
@@ -0,0 +1,601 @@
+//! `[release.approval]`: the release is held before anything is uploaded
+//! until someone other than whoever runs it approves. `github` opens an
+//! issue in the release repository and waits for an `/approve` (or
+//! `/reject`) comment; `webhook` POSTs the summary and polls the status
+//! URL it answers with.
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use reqwest::{Method, StatusCode};
+use shippo_core::exec::Executor;
+use shippo_core::{ApprovalConfig, Manifest};
+
+use crate::http::{HttpClient, HttpRequest, ReqwestClient, Retry};
+use crate::{GitHubApi, PublishError, Result, GITHUB_API};
+
+/// Comments read per page of the approval issue, GitHub's most.
+const PER_PAGE: usize = 100;
+
+/// What the release is and who asks for it.
+pub struct ApprovalRequest<'a> {
+    pub owner: &'a str,
+    pub repo: &'a str,
+    pub tag: &'a str,
+    /// Markdown shown to approvers; see [`summary`].
+    pub summary: &'a str,
+    /// Who runs the release as CI names them, e.g. `GITHUB_ACTOR`, and so
+    /// may not approve it. With `github`, the token's own user can't
+    /// either; this is who is excluded when the token can't tell, such as
+    /// the `GITHUB_TOKEN` of GitHub Actions.
+    pub requester: Option<&'a str>,
+}
+
+/// The approver and where they approved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Approval {
+    pub by: String,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Approved { by: String },
+    Rejected { by: String },
+}
+
+/// The release's files and digests, for approvers to check against what
+/// they expect.
+pub fn summary(manifest: &Manifest, tag: &str, requester: Option<&str>) -> String {
+    let mut out = format!("Release **{tag}** is waiting for approval");
+    if let Some(requester) = requester {
+        out.push_str(&format!(", requested by @{requester}"));
+    }
+    out.push_str(".\n\n");
+    if let Some(commit) = &manifest.project.commit {
+        out.push_str(&format!("Commit: `{commit}`\n\n"));
+    }
+    out.push_str("| Package | File | SHA-256 |\n| --- | --- | --- |\n");
+    for pkg in &manifest.packages {
+        for target in &pkg.targets {
+            for art in &target.artifacts {
+                out.push_str(&format!(
+                    "| {} | {} | `{}` |\n",
+                    pkg.name, art.filename, art.sha256
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// The first `/approve` or `/reject` in `comments` (login and text, oldest
+/// first) from someone `may_approve` allows, other than the `excluded`.
+pub fn decide(
+    comments: &[(String, String)],
+    excluded: &[&str],
+    mut may_approve: impl FnMut(&str) -> Result<bool>,
+) -> Result<Option<Decision>> {
+    for (login, body) in comments {
+        let command = body.lines().next().unwrap_or_default().trim();
+        if !matches!(command, "/approve" | "/reject") {
+            continue;
+        }
+        if excluded.iter().any(|e| e.eq_ignore_ascii_case(login)) || !may_approve(login)? {
+            continue;
+        }
+        let by = login.clone();
+        return Ok(Some(match command {
+            "/approve" => Decision::Approved { by },
+            _ => Decision::Rejected { by },
+        }));
+    }
+    Ok(None)
+}
+
+/// Holds the release until `config`'s provider approves it, failing when
+/// it is rejected or `timeout_minutes` pass first. `waiting` gets where
+/// the request went, once it is sent. The webhook's token is read from
+/// `token_env` through `executor`.
+pub fn await_approval(
+    config: &ApprovalConfig,
+    request: &ApprovalRequest,
+    token: &str,
+    executor: &dyn Executor,
+    waiting: &dyn Fn(&str),
+) -> Result<Approval> {
+    let client = ReqwestClient::new();
+    let github = GitHubApi {
+        client: &client,
+        base: GITHUB_API,
+        token,
+        retry: Retry::default(),
+    };
+    await_approval_with(&github, config, request, executor, waiting)
+}
+
+/// [`await_approval`] through `github`, whose client also reaches the
+/// webhook, e.g. a mock.
+pub fn await_approval_with(
+    github: &GitHubApi,
+    config: &ApprovalConfig,
+    request: &ApprovalRequest,
+    executor: &dyn Executor,
+    waiting: &dyn Fn(&str),
+) -> Result<Approval> {
+    let error = |message: String| PublishError::Approval {
+        tag: request.tag.to_string(),
+        message,
+    };
+    let deadline = Instant::now() + Duration::from_secs(config.timeout_minutes * 60);
+    let poll = Duration::from_secs(config.poll_seconds);
+    let (decision, url) = match config.provider.as_str() {
+        "webhook" => {
+            let url = config.url.as_deref().unwrap_or_default();
+            let token = match &config.token_env {
+                Some(name) => Some(
+                    executor
+                        .var(name)
+                        .ok_or_else(|| error(format!("{name} is not set")))?,
+                ),
+                None => None,
+            };
+            waiting(url);
+            let decision = webhook(
+                github.client,
+                url,
+                token.as_deref(),
+                request,
+                deadline,
+                poll,
+            )
+            .map_err(error)?;
+            (decision, None)
+        }
+        _ => self::github(github, config, request, (deadline, poll), waiting)?,
+    };
+    match decision {
+        Some(Decision::Approved { by }) => Ok(Approval { by, url }),
+        Some(Decision::Rejected { by }) => Err(error(format!("rejected by {by}"))),
+        None => Err(error(format!(
+            "not approved within {} minutes",
+            config.timeout_minutes
+        ))),
+    }
+}
+
+/// Opens the approval issue and polls its comments; closes it once decided
+/// or timed out. Returns the decision and the issue's URL.
+fn github(
+    github: &GitHubApi,
+    config: &ApprovalConfig,
+    request: &ApprovalRequest,
+    (deadline, poll): (Instant, Duration),
+    waiting: &dyn Fn(&str),
+) -> Result<(Option<Decision>, Option<String>)> {
+    let error = |message: String| PublishError::Approval {
+        tag: request.tag.to_string(),
+        message,
+    };
+    let api = format!("/repos/{}/{}", request.owner, request.repo);
+    let send = |request: &HttpRequest| -> Result<serde_json::Value> {
+        let res = github.send(request)?;
+        if !res.is_success() {
+            return Err(error(format!(
+                "GitHub answered {} {}",
+                res.status,
+                res.text()
+            )));
+        }
+        res.json()
+    };
+    // whoever the token belongs to runs the release; installation tokens
+    // such as Actions' GITHUB_TOKEN can't read /user, and the CI's actor
+    // is who runs it then
+    let res = github.send(&github.request(Method::GET, "/user"))?;
+    let login = match res.status {
+        StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED if request.requester.is_some() => None,
+        _ if !res.is_success() => {
+            return Err(error(format!(
+                "can't tell who the token belongs to, so who may not approve: GitHub answered {} for /user",
+                res.status
+            )))
+        }
+        _ => res.json::<serde_json::Value>()?["login"]
+            .as_str()
+            .map(String::from),
+    };
+    let excluded: Vec<&str> = login
+        .as_deref()
+        .into_iter()
+        .chain(request.requester)
+        .collect();
+
+    let body = format!(
+        "{}\nComment `/approve` to publish it, or `/reject` to stop it.",
+        request.summary
+    );
+    let issue = send(github.request(Method::POST, &format!("{api}/issues")).json(
+        &serde_json::json!({
+            "title": format!("Approve release {}", request.tag),
+            "body": body,
+        }),
+    ))?;
+    let number = issue["number"]
+        .as_u64()
+        .ok_or_else(|| error("GitHub's approval issue has no number".into()))?;
+    let html_url = issue["html_url"].as_str().map(String::from);
+    waiting(html_url.as_deref().unwrap_or_default());
+
+    let may_approve = |login: &str| -> Result<bool> {
+        if !config.approvers.is_empty() {
+            return Ok(config
+                .approvers
+                .iter()
+                .any(|a| a.eq_ignore_ascii_case(login)));
+        }
+        let found = send(&github.request(
+            Method::GET,
+            &format!("{api}/collaborators/{login}/permission"),
+        ))?;
+        Ok(matches!(
+            found["permission"].as_str(),
+            Some("admin" | "maintain" | "write")
+        ))
+    };
+    let decision = loop {
+        let mut comments = Vec::new();
+        for page in 1.. {
+            let found = send(&github.request(
+                Method::GET,
+                &format!("{api}/issues/{number}/comments?per_page={PER_PAGE}&page={page}"),
+            ))?;
+            let found = found.as_array().cloned().unwrap_or_default();
+            comments.extend(found.iter().map(|c| {
+                (
+                    c["user"]["login"].as_str().unwrap_or_default().to_string(),
+                    c["body"].as_str().unwrap_or_default().to_string(),
+                )
+            }));
+            if found.len() < PER_PAGE {
+                break;
+            }
+        }
+        if let Some(decision) = decide(&comments, &excluded, &may_approve)? {
+            break Some(decision);
+        }
+        if Instant::now() + poll > deadline {
+            break None;
+        }
+        sleep(poll);
+    };
+    let outcome = match &decision {
+        Some(Decision::Approved { by }) => format!("Approved by @{by}; publishing."),
+        Some(Decision::Rejected { by }) => format!("Rejected by @{by}; not published."),
+        None => "Timed out; not published.".to_string(),
+    };
+    send(
+        github
+            .request(Method::POST, &format!("{api}/issues/{number}/comments"))
+            .json(&serde_json::json!({ "body": outcome })),
+    )?;
+    send(
+        github
+            .request(Method::PATCH, &format!("{api}/issues/{number}"))
+            .json(&serde_json::json!({ "state": "closed" })),
+    )?;
+    Ok((decision, html_url))
+}
+
+/// POSTs the request to `url`, which answers with the decision right away
+/// (`{"status": "approved", "by": "..."}`) or a `status_url` to poll for it.
+/// An approval must name who gave it, and not the requester.
+fn webhook(
+    client: &dyn HttpClient,
+    url: &str,
+    token: Option<&str>,
+    request: &ApprovalRequest,
+    deadline: Instant,
+    poll: Duration,
+) -> std::result::Result<Option<Decision>, String> {
+    let call = |method: Method, url: &str| {
+        let mut call = HttpRequest::new(method, url);
+        call.header("User-Agent", "shippo/1.0");
+        if let Some(token) = token {
+            call.header("Authorization", format!("Bearer {token}"));
+        }
+        call
+    };
+    let send = |request: &HttpRequest| -> std::result::Result<serde_json::Value, String> {
+        let res = client.send(request).map_err(|e| e.to_string())?;
+        if !res.is_success() {
+            return Err(format!("{url} answered {}", res.status));
+        }
+        res.json().map_err(|e| e.to_string())
+    };
+    let decision = |answer: &serde_json::Value| {
+        let by = answer["by"].as_str();
+        match answer["status"].as_str() {
+            Some("approved") => match by {
+                None => Err(format!("{url} approved the release without saying by whom")),
+                Some(by)
+                    if request
+                        .requester
+                        .is_some_and(|r| r.eq_ignore_ascii_case(by)) =>
+                {
+                    Err(format!(
+                        "{url} answered approved by {by}, who requested the release"
+                    ))
+                }
+                Some(by) => Ok(Some(Decision::Approved { by: by.to_string() })),
+            },
+            Some("rejected") => Ok(Some(Decision::Rejected {
+                by: by.unwrap_or("webhook").to_string(),
+            })),
+            _ => Ok(None),
+        }
+    };
+    let mut answer = send(call(Method::POST, url).json(&serde_json::json!({
+        "owner": request.owner,
+        "repo": request.repo,
+        "tag": request.tag,
+        "summary": request.summary,
+        "requested_by": request.requester,
+    })))?;
+    let status_url = answer["status_url"].as_str().map(String::from);
+    loop {
+        if let Some(decision) = decision(&answer)? {
+            return Ok(Some(decision));
+        }
+        let Some(status_url) = &status_url else {
+            return Err(format!("{url} answered neither a status nor a status_url"));
+        };
+        if Instant::now() + poll > deadline {
+            return Ok(None);
+        }
+        sleep(poll);
+        answer = send(&call(Method::GET, status_url))?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{HttpResponse, MockHttpClient};
+    use serde_json::json;
+    use shippo_core::exec::MockExecutor;
+
+    const ISSUES: &str = "/repos/acme/tool/issues";
+    const COMMENTS: &str = "/repos/acme/tool/issues/7/comments";
+
+    fn github_api(client: &MockHttpClient) -> GitHubApi<'_> {
+        GitHubApi {
+            client,
+            base: "https://api.test",
+            token: "t",
+            retry: Retry {
+                attempts: 1,
+                backoff: Duration::ZERO,
+            },
+        }
+    }
+
+    fn config(provider: &str) -> ApprovalConfig {
+        ApprovalConfig {
+            provider: provider.into(),
+            approvers: Vec::new(),
+            url: Some("https://hooks.test/approve".into()),
+            token_env: Some("SHIPPO_TEST_APPROVAL_TOKEN".into()),
+            timeout_minutes: 0,
+            poll_seconds: 0,
+        }
+    }
+
+    fn request(requester: Option<&str>) -> ApprovalRequest<'_> {
+        ApprovalRequest {
+            owner: "acme",
+            repo: "tool",
+            tag: "v1.0.0",
+            summary: "Release **v1.0.0**",
+            requester,
+        }
+    }
+
+    fn comment(login: &str, body: &str) -> serde_json::Value {
+        json!({ "user": { "login": login }, "body": body })
+    }
+
+    /// Queues the issue, and the outcome comment and closing after it.
+    fn open_issue(client: &MockHttpClient, issue: serde_json::Value) {
+        client
+            .respond(Method::POST, ISSUES, HttpResponse::json_body(201, &issue))
+            .respond(
+                Method::POST,
+                COMMENTS,
+                HttpResponse::json_body(201, &json!({})),
+            )
+            .respond(
+                Method::PATCH,
+                &format!("{ISSUES}/7"),
+                HttpResponse::json_body(200, &json!({})),
+            );
+    }
+
+    #[test]
+    fn test_github_approval_reads_every_page_and_excludes_the_token_user() {
+        let client = MockHttpClient::new();
+        let url = "https://github.com/acme/tool/issues/7";
+        // the token user's own /approve is on the first page, bob's on the second
+        let mut first: Vec<_> = (0..99)
+            .map(|i| comment("carol", &format!("note {i}")))
+            .collect();
+        first.push(comment("releaser", "/approve"));
+        client.respond(
+            Method::GET,
+            "/user",
+            HttpResponse::json_body(200, &json!({ "login": "Releaser" })),
+        );
+        open_issue(&client, json!({ "number": 7, "html_url": url }));
+        client
+            .respond(
+                Method::GET,
+                COMMENTS,
+                HttpResponse::json_body(200, &json!(first)),
+            )
+            .respond(
+                Method::GET,
+                COMMENTS,
+                HttpResponse::json_body(200, &json!([comment("bob", "/approve")])),
+            )
+            .respond(
+                Method::GET,
+                "/repos/acme/tool/collaborators/bob/permission",
+                HttpResponse::json_body(200, &json!({ "permission": "write" })),
+            );
+        let approval = await_approval_with(
+            &github_api(&client),
+            &config("github"),
+            &request(Some("ci")),
+            &MockExecutor::new(),
+            &|waiting| assert_eq!(waiting, url),
+        )
+        .unwrap();
+        assert_eq!(
+            approval,
+            Approval {
+                by: "bob".into(),
+                url: Some(url.into())
+            }
+        );
+        let requests = client.requests();
+        assert!(requests
+            .iter()
+            .any(|r| r.url.ends_with("/comments?per_page=100&page=2")));
+        let closed = requests.last().unwrap();
+        assert_eq!(closed.method, Method::PATCH);
+        let body: serde_json::Value = serde_json::from_slice(&closed.body).unwrap();
+        assert_eq!(body, json!({ "state": "closed" }));
+    }
+
+    #[test]
+    fn test_github_approval_failures() {
+        let approve = |client: &MockHttpClient, requester| {
+            await_approval_with(
+                &github_api(client),
+                &config("github"),
+                &request(requester),
+                &MockExecutor::new(),
+                &|_| {},
+            )
+            .unwrap_err()
+            .to_string()
+        };
+
+        // an installation token can't read /user; the CI actor is excluded
+        let client = MockHttpClient::new();
+        client.respond(Method::GET, "/user", HttpResponse::new(403, ""));
+        open_issue(&client, json!({ "number": 7 }));
+        client.respond(
+            Method::GET,
+            COMMENTS,
+            HttpResponse::json_body(200, &json!([comment("alice", "/approve")])),
+        );
+        let err = approve(&client, Some("alice"));
+        assert!(err.contains("not approved within 0 minutes"), "{err}");
+
+        // and without one nobody can tell who may not approve
+        let client = MockHttpClient::new();
+        client.respond(Method::GET, "/user", HttpResponse::new(403, ""));
+        assert!(approve(&client, None).contains("can't tell who the token belongs to"));
+        assert_eq!(client.requests().len(), 1);
+
+        let client = MockHttpClient::new();
+        client.respond(
+            Method::GET,
+            "/user",
+            HttpResponse::json_body(200, &json!({ "login": "releaser" })),
+        );
+        open_issue(
+            &client,
+            json!({ "html_url": "https://github.com/acme/tool/issues/7" }),
+        );
+        assert!(approve(&client, None).contains("has no number"));
+    }
+
+    #[test]
+    fn test_webhook_approval() {
+        let client = MockHttpClient::new();
+        let executor = MockExecutor::new();
+        let approve = || {
+            await_approval_with(
+                &github_api(&client),
+                &config("webhook"),
+                &request(Some("alice")),
+                &executor,
+                &|_| {},
+            )
+        };
+        assert!(approve().unwrap_err().to_string().contains("is not set"));
+        executor.set_var("SHIPPO_TEST_APPROVAL_TOKEN", "hook-secret");
+        client.respond(
+            Method::POST,
+            "/approve",
+            HttpResponse::json_body(200, &json!({ "status": "rejected", "by": "dana" })),
+        );
+        let err = approve().unwrap_err();
+        assert!(err.to_string().contains("rejected by dana"), "{err}");
+        let sent = &client.requests()[0];
+        assert!(sent
+            .headers
+            .contains(&("Authorization".into(), "Bearer hook-secret".into())));
+        let body: serde_json::Value = serde_json::from_slice(&sent.body).unwrap();
+        assert_eq!(body["requested_by"], "alice");
+
+        // the requester can't approve their own release, nor can nobody
+        for (answer, reason) in [
+            (
+                json!({ "status": "approved", "by": "Alice" }),
+                "who requested the release",
+            ),
+            (json!({ "status": "approved" }), "without saying by whom"),
+        ] {
+            client.respond(
+                Method::POST,
+                "/approve",
+                HttpResponse::json_body(200, &answer),
+            );
+            let err = approve().unwrap_err().to_string();
+            assert!(err.contains(reason), "{err}");
+        }
+        client.respond(
+            Method::POST,
+            "/approve",
+            HttpResponse::json_body(200, &json!({ "status": "approved", "by": "dana" })),
+        );
+        assert_eq!(approve().unwrap().by, "dana");
+    }
+
+    #[test]
+    fn test_decide_skips_requester_and_non_approvers() {
+        let comments: Vec<(String, String)> = [
+            ("alice", "/approve"),
+            ("mallory", "/approve"),
+            ("bob", "looks good\n/approve"),
+            ("carol", "/approve\nchecked the digests"),
+            ("bob", "/reject"),
+        ]
+        .iter()
+        .map(|(l, b)| (l.to_string(), b.to_string()))
+        .collect();
+        let approvers = |login: &str| Ok(login != "mallory");
+        assert_eq!(
+            decide(&comments, &["Alice"], approvers).unwrap(),
+            Some(Decision::Approved { by: "carol".into() })
+        );
+        assert_eq!(
+            decide(&comments[4..], &["alice"], approvers).unwrap(),
+            Some(Decision::Rejected { by: "bob".into() })
+        );
+        assert_eq!(decide(&comments[..3], &["alice"], approvers).unwrap(), None);
+    }
+}
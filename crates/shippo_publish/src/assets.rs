@@ -0,0 +1,295 @@
+//! `[release.assets]`: labels such as "Linux x86_64 (tar.gz)" for the
+//! manifest's files, and MIME types by extension, for uploads.
+
+use std::collections::BTreeMap;
+
+use shippo_core::{targets, ManifestPackage, ReleaseAssetsConfig};
+
+/// MIME types of the files shippo writes and common installers, by
+/// extension; the longest matching extension wins.
+const CONTENT_TYPES: [(&str, &str); 18] = [
+    ("tar.gz", "application/gzip"),
+    ("tgz", "application/gzip"),
+    ("gz", "application/gzip"),
+    ("tar.zst", "application/zstd"),
+    ("zst", "application/zstd"),
+    ("zip", "application/zip"),
+    ("whl", "application/zip"),
+    ("json", "application/json"),
+    ("intoto.jsonl", "application/vnd.in-toto+json"),
+    ("bundle", "application/json"),
+    ("asc", "application/pgp-signature"),
+    ("md", "text/markdown"),
+    ("txt", "text/plain"),
+    ("deb", "application/vnd.debian.binary-package"),
+    ("rpm", "application/x-rpm"),
+    ("dmg", "application/x-apple-diskimage"),
+    ("msi", "application/x-msi"),
+    ("exe", "application/vnd.microsoft.portable-executable"),
+];
+
+/// Text files without an extension.
+const TEXT_FILES: [&str; 1] = ["SHA256SUMS"];
+
+/// The MIME type to upload `name` with: `overrides` by extension, then
+/// the built-in ones, then `application/octet-stream`.
+pub fn content_type(name: &str, overrides: &BTreeMap<String, String>) -> String {
+    let extension_of = |ext: &str| name.len() > ext.len() + 1 && name.ends_with(&format!(".{ext}"));
+    let found = overrides
+        .iter()
+        .map(|(ext, mime)| (ext.trim_start_matches('.'), mime.as_str()))
+        .filter(|(ext, _)| extension_of(ext))
+        .max_by_key(|(ext, _)| ext.len())
+        .or_else(|| {
+            CONTENT_TYPES
+                .iter()
+                .copied()
+                .filter(|(ext, _)| extension_of(ext))
+                .max_by_key(|(ext, _)| ext.len())
+        });
+    match found {
+        Some((_, mime)) => mime.to_string(),
+        None if TEXT_FILES.contains(&name) => "text/plain".to_string(),
+        None => "application/octet-stream".to_string(),
+    }
+}
+
+/// How `target` reads in a label: `("Linux", "x86_64", "")`, with the
+/// libc for musl targets so they differ from the glibc ones, or the target
+/// itself for ones shippo doesn't know, such as a plugin's.
+fn platform(target: &str) -> (String, String, String) {
+    let info = match target {
+        "native" => targets::host(),
+        name => targets::resolve(name),
+    };
+    let Some(((os, arch), triple)) = info.and_then(|i| Some((i.go?, i.triple))) else {
+        return (target.to_string(), String::new(), String::new());
+    };
+    let libc = match triple.contains("-musl") {
+        true => "musl",
+        false => "",
+    };
+    let os = match os {
+        "linux" => "Linux",
+        "darwin" => "macOS",
+        "windows" => "Windows",
+        "freebsd" => "FreeBSD",
+        "netbsd" => "NetBSD",
+        "openbsd" => "OpenBSD",
+        "android" => "Android",
+        "illumos" => "illumos",
+        other => other,
+    };
+    let arch = match arch {
+        "amd64" => "x86_64",
+        "386" => "x86",
+        "arm" => "ARMv7",
+        other => other,
+    };
+    (os.to_string(), arch.to_string(), libc.to_string())
+}
+
+/// The archive format in `name`, e.g. `tar.gz`.
+fn format(name: &str) -> &str {
+    ["tar.gz", "tar.zst", "zip"]
+        .into_iter()
+        .find(|f| name.ends_with(&format!(".{f}")))
+        .unwrap_or_else(|| name.rsplit_once('.').map_or("", |(_, ext)| ext))
+}
+
+/// The label of each file of `packages` that gets one, by file name:
+/// archives per the label template, SBOMs, attestations and signatures
+/// after theirs, the checksums and manifest by their role; `config.names`
+/// over all.
+pub fn asset_labels(
+    packages: &[ManifestPackage],
+    config: &ReleaseAssetsConfig,
+) -> BTreeMap<String, String> {
+    let mut labels = BTreeMap::new();
+    if config.labels {
+        let default_template = match packages.len() {
+            1 => "{os} {arch} {libc} ({format})",
+            _ => "{package} {os} {arch} {libc} ({format})",
+        };
+        let template = config.label_template.as_deref().unwrap_or(default_template);
+        for pkg in packages {
+            for target in &pkg.targets {
+                let (os, arch, libc) = platform(&target.target);
+                let render = |format: &str| {
+                    let label = template
+                        .replace("{package}", &pkg.name)
+                        .replace("{os}", &os)
+                        .replace("{arch}", &arch)
+                        .replace("{libc}", &libc)
+                        .replace("{target}", &target.target)
+                        .replace("{format}", format);
+                    // an unknown target has no arch, and only musl has a libc
+                    label.split_whitespace().collect::<Vec<_>>().join(" ")
+                };
+                // an alias's file is labelled for the target it was packed for
+                for art in target.artifacts.iter().filter(|a| a.alias.is_none()) {
+                    labels.insert(art.filename.clone(), render(format(&art.filename)));
+                }
+                for sbom in target.sboms() {
+                    let kind = if sbom.filename.ends_with(".spdx.json") {
+                        "SPDX"
+                    } else {
+                        "CycloneDX"
+                    };
+                    labels.insert(sbom.filename.clone(), render(&format!("SBOM, {kind}")));
+                }
+                if let Some(attestation) = &target.attestation {
+                    labels.insert(attestation.filename.clone(), render("in-toto attestation"));
+                }
+                for sig in &target.signatures {
+                    let signed = sig.signed_file().unwrap_or(&sig.filename);
+                    let Some(label) = labels.get(signed).cloned() else {
+                        continue;
+                    };
+                    if sig.in_bundle() {
+                        labels.insert(sig.filename.clone(), format!("{label} sigstore bundle"));
+                        continue;
+                    }
+                    // `<file>.<signer>.minisig`, one per `shippo sign` signer
+                    let signature = match &sig.signer {
+                        Some(signer) => format!("{label} signature by {signer}"),
+                        None => format!("{label} signature"),
+                    };
+                    labels.insert(sig.filename.clone(), signature);
+                    if let Some(bundle) = &sig.bundle {
+                        labels.insert(bundle.clone(), format!("{label} cosign bundle"));
+                    }
+                }
+            }
+        }
+        labels.insert("SHA256SUMS".to_string(), "Checksums (SHA-256)".to_string());
+        labels.insert("manifest.json".to_string(), "Release manifest".to_string());
+        labels.insert(
+            "VERIFYING.md".to_string(),
+            "How to verify the downloads".to_string(),
+        );
+    }
+    labels.extend(config.names.clone());
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shippo_core::{ManifestArtifact, ManifestSignature, ManifestTarget, ProjectType};
+
+    #[test]
+    fn test_labels_and_content_types() {
+        let artifact = |filename: &str| ManifestArtifact {
+            filename: filename.into(),
+            bytes: 1,
+            sha256: "ab".repeat(32),
+            contents: Vec::new(),
+            alias: None,
+        };
+        let target = |target: &str| ManifestTarget {
+            target: target.into(),
+            artifacts: Vec::new(),
+            sbom: None,
+            extra_sboms: Vec::new(),
+            attestation: None,
+            signatures: Vec::new(),
+            origin: None,
+        };
+        let packages = [ManifestPackage {
+            name: "cli".into(),
+            project_type: ProjectType::Rust,
+            path: ".".into(),
+            targets: vec![
+                ManifestTarget {
+                    artifacts: vec![artifact("cli-linux.tar.gz"), artifact("cli-linux.zip")],
+                    sbom: Some(artifact("cli-linux-sbom.spdx.json")),
+                    signatures: vec![
+                        ManifestSignature {
+                            filename: "cli-linux.tar.gz.sig".into(),
+                            method: "cosign".into(),
+                            bundle: Some("cli-linux.tar.gz.bundle".into()),
+                            rekor: None,
+                            signer: None,
+                            key: false,
+                            offline: false,
+                        },
+                        ManifestSignature {
+                            filename: "cli-linux.zip.sigstore.json".into(),
+                            method: "cosign".into(),
+                            bundle: Some("cli-linux.zip.sigstore.json".into()),
+                            rekor: None,
+                            signer: None,
+                            key: false,
+                            offline: false,
+                        },
+                        ManifestSignature {
+                            filename: "cli-linux.tar.gz.alice.minisig".into(),
+                            method: "minisign".into(),
+                            bundle: None,
+                            rekor: None,
+                            signer: Some("alice".into()),
+                            key: false,
+                            offline: false,
+                        },
+                    ],
+                    ..target("x86_64-unknown-linux-gnu")
+                },
+                ManifestTarget {
+                    artifacts: vec![artifact("cli-linux-musl.tar.gz")],
+                    ..target("x86_64-unknown-linux-musl")
+                },
+                ManifestTarget {
+                    artifacts: vec![artifact("cli.wasm")],
+                    ..target("wasm32-custom")
+                },
+            ],
+            tests: None,
+            scan: None,
+            metadata: None,
+            image: None,
+            npm: None,
+        }];
+        let mut config = ReleaseAssetsConfig::default();
+        config.names.insert("SHA256SUMS".into(), "Checksums".into());
+        let labels = asset_labels(&packages, &config);
+        assert_eq!(labels["cli-linux.tar.gz"], "Linux x86_64 (tar.gz)");
+        assert_eq!(labels["cli-linux.zip"], "Linux x86_64 (zip)");
+        assert_eq!(
+            labels["cli-linux-musl.tar.gz"],
+            "Linux x86_64 musl (tar.gz)"
+        );
+        assert_eq!(
+            labels["cli-linux-sbom.spdx.json"],
+            "Linux x86_64 (SBOM, SPDX)"
+        );
+        assert_eq!(
+            labels["cli-linux.tar.gz.sig"],
+            "Linux x86_64 (tar.gz) signature"
+        );
+        assert_eq!(
+            labels["cli-linux.tar.gz.alice.minisig"],
+            "Linux x86_64 (tar.gz) signature by alice"
+        );
+        assert_eq!(
+            labels["cli-linux.tar.gz.bundle"],
+            "Linux x86_64 (tar.gz) cosign bundle"
+        );
+        assert_eq!(
+            labels["cli-linux.zip.sigstore.json"],
+            "Linux x86_64 (zip) sigstore bundle"
+        );
+        assert_eq!(labels["cli.wasm"], "wasm32-custom (wasm)");
+        assert_eq!(labels["SHA256SUMS"], "Checksums");
+        assert_eq!(labels["manifest.json"], "Release manifest");
+
+        config.labels = false;
+        assert_eq!(asset_labels(&packages, &config).len(), 1);
+
+        let overrides = BTreeMap::from([("zst".to_string(), "application/x-zstd".to_string())]);
+        assert_eq!(content_type("a.tar.gz", &overrides), "application/gzip");
+        assert_eq!(content_type("a.tar.zst", &overrides), "application/x-zstd");
+        assert_eq!(content_type("SHA256SUMS", &overrides), "text/plain");
+        assert_eq!(content_type("cli", &overrides), "application/octet-stream");
+    }
+}
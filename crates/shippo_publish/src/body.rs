@@ -0,0 +1,209 @@
+//! The `[release.body]` sections around the changelog: a hand-written
+//! header, a table of the release's files and how to verify them.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use shippo_core::{human_bytes, ManifestPackage, ManifestTarget};
+
+/// `file`'s text, trimmed; `file` is relative to `root`.
+pub fn header(root: &Path, file: &str) -> io::Result<String> {
+    let path = root.join(file);
+    fs::read_to_string(&path)
+        .map(|text| text.trim().to_string())
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("release.body.header_file {}: {e}", path.display()),
+            )
+        })
+}
+
+/// The targets of the `released` ones of the manifest's `packages`.
+fn release_targets<'a>(
+    packages: &'a [ManifestPackage],
+    released: &'a BTreeMap<String, String>,
+) -> impl Iterator<Item = (&'a str, &'a ManifestTarget)> {
+    packages
+        .iter()
+        .filter(|p| released.contains_key(&p.name))
+        .flat_map(|p| p.targets.iter().map(move |t| (p.name.as_str(), t)))
+}
+
+/// The archives and SBOMs of the `released` packages, linked to
+/// `download`, the release's download URL.
+pub fn artifacts_table(
+    packages: &[ManifestPackage],
+    released: &BTreeMap<String, String>,
+    download: &str,
+) -> String {
+    let mut out = String::from("### Downloads\n\n| Package | Target | File | Size | SHA256 |\n");
+    out.push_str("|---|---|---|---:|---|\n");
+    for (package, target) in release_targets(packages, released) {
+        for art in target.artifacts.iter().chain(target.sboms()) {
+            out.push_str(&format!(
+                "| {package} | {} | [{}]({download}/{}) | {} | `{}` |\n",
+                target.target,
+                art.filename,
+                art.filename,
+                human_bytes(art.bytes),
+                art.sha256
+            ));
+        }
+    }
+    out.trim_end().to_string()
+}
+
+/// Commands to check a download against `SHA256SUMS` and, for each
+/// signing method the `released` packages use, its signature, with one of
+/// the release's own files as the example.
+pub fn verification(
+    packages: &[ManifestPackage],
+    released: &BTreeMap<String, String>,
+    owner: &str,
+    repo: &str,
+) -> String {
+    let mut out = String::from(
+        "### Verifying downloads\n\nDownload `SHA256SUMS` next to the files and check them:\n\n```sh\nsha256sum --ignore-missing -c SHA256SUMS\n```\n",
+    );
+    // `shippo sign` signers' signatures are checked with their own keys
+    let mut methods: Vec<(&str, bool)> = Vec::new();
+    for (_, target) in release_targets(packages, released) {
+        for sig in &target.signatures {
+            let method = (sig.method.as_str(), sig.signer.is_some());
+            if methods.contains(&method) {
+                continue;
+            }
+            methods.push(method);
+            let file = sig.signed_file().unwrap_or(&sig.filename);
+            let (what, command) = match (sig.method.as_str(), &sig.bundle) {
+                ("gpg", _) => (
+                    "GPG signatures, with the signing key imported",
+                    format!("gpg --verify {} {file}", sig.filename),
+                ),
+                ("cosign", Some(bundle)) if sig.key => (
+                    "cosign signatures, with the project's public key",
+                    format!(
+                        "cosign verify-blob {file} --bundle {bundle} --new-bundle-format --key cosign.pub{}",
+                        if sig.offline { " --insecure-ignore-tlog=true" } else { "" }
+                    ),
+                ),
+                ("cosign", Some(bundle)) => (
+                    "Keyless cosign signatures, made in this repository's CI",
+                    format!(
+                        "cosign verify-blob {file} --bundle {bundle}{} \\\n  --certificate-identity-regexp '^https://github.com/{owner}/{repo}/' \\\n  --certificate-oidc-issuer https://token.actions.githubusercontent.com",
+                        if sig.in_bundle() { " --new-bundle-format" } else { "" }
+                    ),
+                ),
+                ("cosign", None) => (
+                    "cosign signatures, with the project's public key",
+                    format!(
+                        "cosign verify-blob {file} --signature {} --key cosign.pub",
+                        sig.filename
+                    ),
+                ),
+                ("minisign", _) if sig.signer.is_some() => (
+                    "Signers' minisign signatures, with each signer's public key saved as `<signer>.pub`",
+                    format!(
+                        "minisign -V -p {}.pub -m {file} -x {}",
+                        sig.signer.as_deref().unwrap_or_default(),
+                        sig.filename
+                    ),
+                ),
+                ("minisign", _) => (
+                    "minisign signatures, with the project's public key",
+                    format!("minisign -V -p minisign.pub -m {file} -x {}", sig.filename),
+                ),
+                ("ssh", _) => (
+                    "SSH signatures, with the project's allowed signers",
+                    format!(
+                        "ssh-keygen -Y verify -f allowed_signers -I <signer> -n file -s {} < {file}",
+                        sig.filename
+                    ),
+                ),
+                _ => continue,
+            };
+            out.push_str(&format!("\n{what}:\n\n```sh\n{command}\n```\n"));
+        }
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shippo_core::{ManifestArtifact, ManifestSignature, ProjectType};
+
+    #[test]
+    fn test_artifacts_and_verification_sections() {
+        let package = |name: &str, method: &str, bundle: Option<&str>| ManifestPackage {
+            name: name.into(),
+            project_type: ProjectType::Rust,
+            path: ".".into(),
+            targets: vec![ManifestTarget {
+                target: "x86_64-unknown-linux-gnu".into(),
+                artifacts: vec![ManifestArtifact {
+                    filename: format!("{name}-linux.tar.gz"),
+                    bytes: 2048,
+                    sha256: "ab".repeat(32),
+                    contents: Vec::new(),
+                    alias: None,
+                }],
+                sbom: None,
+                extra_sboms: Vec::new(),
+                attestation: None,
+                signatures: vec![ManifestSignature {
+                    filename: format!("{name}-linux.tar.gz.sig"),
+                    method: method.into(),
+                    bundle: bundle.map(String::from),
+                    rekor: None,
+                    signer: None,
+                    key: false,
+                    offline: false,
+                }],
+                origin: None,
+            }],
+            tests: None,
+            scan: None,
+            metadata: None,
+            image: None,
+            npm: None,
+        };
+        let mut cli = package("cli", "cosign", Some("cli-linux.tar.gz.bundle"));
+        let signer = |name: &str| ManifestSignature {
+            filename: format!("cli-linux.tar.gz.{name}.minisig"),
+            method: "minisign".into(),
+            bundle: None,
+            rekor: None,
+            signer: Some(name.into()),
+            key: false,
+            offline: false,
+        };
+        cli.targets[0]
+            .signatures
+            .extend([signer("alice"), signer("bob")]);
+        let packages = [cli, package("agent", "gpg", None)];
+        let released = BTreeMap::from([("cli".to_string(), ".".to_string())]);
+        let download = "https://github.com/acme/tool/releases/download/v1.0.0";
+
+        let table = artifacts_table(&packages, &released, download);
+        assert!(table.contains(&format!(
+            "| cli | x86_64-unknown-linux-gnu | [cli-linux.tar.gz]({download}/cli-linux.tar.gz) | 2.0 KiB |"
+        )));
+        assert!(!table.contains("agent"));
+
+        let steps = verification(&packages, &released, "acme", "tool");
+        assert!(steps.contains("sha256sum --ignore-missing -c SHA256SUMS"));
+        assert!(
+            steps.contains("cosign verify-blob cli-linux.tar.gz --bundle cli-linux.tar.gz.bundle")
+        );
+        assert!(steps.contains("'^https://github.com/acme/tool/'"));
+        assert!(!steps.contains("gpg --verify"));
+        assert!(steps.contains(
+            "minisign -V -p alice.pub -m cli-linux.tar.gz -x cli-linux.tar.gz.alice.minisig"
+        ));
+        assert!(!steps.contains("minisign.pub"));
+    }
+}
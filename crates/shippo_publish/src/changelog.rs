@@ -0,0 +1,288 @@
+//! Renders release notes from a structured git log.
+
+use shippo_core::ChangelogConfig;
+use shippo_git::Commit;
+
+/// Where an [`Entry`] goes in a `conventional` changelog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Section {
+    Breaking,
+    Features,
+    Fixes,
+    Other,
+}
+
+impl Section {
+    pub fn title(self) -> &'static str {
+        match self {
+            Section::Breaking => "Breaking changes",
+            Section::Features => "Features",
+            Section::Fixes => "Fixes",
+            Section::Other => "Other changes",
+        }
+    }
+}
+
+/// A commit as it appears in the changelog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry<'a> {
+    pub section: Section,
+    pub scope: Option<String>,
+    /// The conventional-commit description, or the subject.
+    pub description: String,
+    /// Trailers such as `Reviewed-by` or `Ticket` are on the commit.
+    pub commit: &'a Commit,
+}
+
+impl<'a> Entry<'a> {
+    /// Reads `commit` as a conventional commit in `conventional` mode, and
+    /// as a plain subject otherwise.
+    pub fn new(commit: &'a Commit, conventional: bool) -> Self {
+        let parsed = conventional.then(|| commit.conventional()).flatten();
+        let Some(cc) = parsed else {
+            return Self {
+                section: Section::Other,
+                scope: None,
+                description: commit.subject.clone(),
+                commit,
+            };
+        };
+        let section = match cc.kind.as_str() {
+            _ if cc.breaking => Section::Breaking,
+            "feat" => Section::Features,
+            "fix" => Section::Fixes,
+            _ => Section::Other,
+        };
+        Self {
+            section,
+            scope: cc.scope,
+            description: cc.description,
+            commit,
+        }
+    }
+
+    /// The entry's line: `[changelog] template` with placeholders filled
+    /// in, or `- description (id)` followed by the configured trailers.
+    fn line(&self, config: &ChangelogConfig) -> String {
+        if let Some(template) = &config.template {
+            return expand(template, |key| self.placeholder(key, config));
+        }
+        let description = match &self.scope {
+            Some(scope) => format!("**{scope}:** {}", self.description),
+            None => self.description.clone(),
+        };
+        let mut line = format!("- {description} ({})", self.commit.short_id());
+        let trailers = self.trailers(config);
+        if !trailers.is_empty() {
+            line.push_str(&format!(" — {trailers}"));
+        }
+        line
+    }
+
+    fn placeholder(&self, key: &str, config: &ChangelogConfig) -> Option<String> {
+        let commit = self.commit;
+        Some(match key {
+            "description" => self.description.clone(),
+            "subject" => commit.subject.clone(),
+            "scope" => self.scope.clone().unwrap_or_default(),
+            "id" => commit.short_id().to_string(),
+            "sha" => commit.id.clone(),
+            "author" => commit.author.clone(),
+            "email" => commit.email.clone(),
+            "trailers" => self.trailers(config),
+            _ => {
+                let name = key.strip_prefix("trailer:")?;
+                commit.trailer(name).collect::<Vec<_>>().join(", ")
+            }
+        })
+    }
+
+    /// `[changelog] trailers` present on the commit, e.g.
+    /// `Reviewed-by: Ana; Ticket: OPS-12`.
+    fn trailers(&self, config: &ChangelogConfig) -> String {
+        config
+            .trailers
+            .iter()
+            .filter_map(|key| {
+                let values: Vec<_> = self.commit.trailer(key).collect();
+                (!values.is_empty()).then(|| format!("{key}: {}", values.join(", ")))
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// GitHub rejects release bodies longer than this many characters.
+pub const GITHUB_BODY_LIMIT: usize = 125_000;
+
+/// Cuts `body` to at most `max` characters, at a line boundary where there
+/// is one, and ends it with `note`. `None` when `body` fits.
+pub fn truncate(body: &str, max: usize, note: &str) -> Option<String> {
+    if body.chars().count() <= max {
+        return None;
+    }
+    let budget = max.saturating_sub(note.chars().count() + 2);
+    let end = body
+        .char_indices()
+        .nth(budget)
+        .map_or(body.len(), |(i, _)| i);
+    let kept = &body[..end];
+    let kept = kept.rfind('\n').map_or(kept, |line| &kept[..line]);
+    Some(format!("{}\n\n{note}", kept.trim_end()))
+}
+
+/// Replaces `{key}` with `value(key)`; unknown keys are kept as written.
+fn expand(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let placeholder = &rest[start..=start + len];
+        match value(&placeholder[1..len]) {
+            Some(v) => out.push_str(&v),
+            None => out.push_str(placeholder),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The changelog entries for `commits` under `[changelog] mode`.
+pub fn entries<'a>(commits: &'a [Commit], mode: &str) -> Vec<Entry<'a>> {
+    commits
+        .iter()
+        .map(|c| Entry::new(c, mode == "conventional"))
+        .collect()
+}
+
+/// Renders `commits` (newest first) per `[changelog]`: `conventional`
+/// mode groups conventional commits into breaking changes, features, fixes
+/// and everything else; any other mode lists every commit.
+pub fn render(commits: &[Commit], config: &ChangelogConfig) -> String {
+    let entries = entries(commits, &config.mode);
+    if config.mode != "conventional" {
+        return entries
+            .iter()
+            .map(|e| e.line(config))
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    let mut sections: Vec<(Section, Vec<String>)> = Vec::new();
+    for section in [
+        Section::Breaking,
+        Section::Features,
+        Section::Fixes,
+        Section::Other,
+    ] {
+        let lines: Vec<_> = entries
+            .iter()
+            .filter(|e| e.section == section)
+            .map(|e| e.line(config))
+            .collect();
+        if !lines.is_empty() {
+            sections.push((section, lines));
+        }
+    }
+    sections
+        .iter()
+        .map(|(section, lines)| format!("### {}\n\n{}", section.title(), lines.join("\n")))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(id: &str, subject: &str, trailers: &[(&str, &str)]) -> Commit {
+        Commit {
+            id: id.repeat(40),
+            author: "Shippo".into(),
+            email: "shippo@example.com".into(),
+            time: Default::default(),
+            subject: subject.into(),
+            body: String::new(),
+            trailers: trailers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    fn config(mode: &str) -> ChangelogConfig {
+        ChangelogConfig {
+            mode: mode.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_conventional_sections() {
+        let commits = [
+            commit("a", "feat(cli): add --json", &[]),
+            commit("b", "fix: crash on empty dist", &[]),
+            commit(
+                "c",
+                "refactor: drop v1 manifests",
+                &[("BREAKING CHANGE", "x")],
+            ),
+            commit("d", "update readme", &[]),
+        ];
+        let notes = render(&commits, &config("conventional"));
+        assert_eq!(
+            notes,
+            "### Breaking changes\n\n- drop v1 manifests (ccccccc)\n\n\
+             ### Features\n\n- **cli:** add --json (aaaaaaa)\n\n\
+             ### Fixes\n\n- crash on empty dist (bbbbbbb)\n\n\
+             ### Other changes\n\n- update readme (ddddddd)"
+        );
+        assert_eq!(
+            render(&commits[..1], &config("auto")),
+            "- feat(cli): add --json (aaaaaaa)"
+        );
+    }
+
+    #[test]
+    fn test_truncate_at_line_boundary() {
+        let body = "- first change\n- second change\n- third change";
+        assert_eq!(truncate(body, 100, "cut"), None);
+        let cut = truncate(body, 40, "*cut*").unwrap();
+        assert_eq!(cut, "- first change\n- second change\n\n*cut*");
+        assert!(cut.chars().count() <= 40);
+        // multi-byte characters count once and are never split
+        let wide = "é".repeat(50);
+        assert_eq!(truncate(&wide, 20, "…").unwrap().chars().count(), 20);
+    }
+
+    #[test]
+    fn test_trailers_in_lines_and_templates() {
+        let commits = [commit(
+            "a",
+            "fix(api): reject empty tokens",
+            &[
+                ("Reviewed-by", "Ana <ana@example.com>"),
+                ("Ticket", "SEC-7"),
+                ("reviewed-by", "Bo"),
+            ],
+        )];
+        let mut cfg = config("conventional");
+        cfg.trailers = vec![
+            "Reviewed-by".into(),
+            "Ticket".into(),
+            "Co-authored-by".into(),
+        ];
+        assert_eq!(
+            render(&commits, &cfg),
+            "### Fixes\n\n- **api:** reject empty tokens (aaaaaaa) — Reviewed-by: Ana <ana@example.com>, Bo; Ticket: SEC-7"
+        );
+        cfg.template = Some("* [{trailer:Ticket}] {description} by {author} {unknown}".into());
+        assert_eq!(
+            render(&commits, &cfg),
+            "### Fixes\n\n* [SEC-7] reject empty tokens by Shippo {unknown}"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! `shippo crates`: crate owners and yanked versions on crates.io, or
+//! another registry with its web API, for `[release.crates]`.
+
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::http::{HttpClient, HttpRequest, HttpResponse, Retry};
+use crate::{PublishError, Result};
+
+const USER_AGENT: &str = concat!(
+    "shippo/",
+    env!("CARGO_PKG_VERSION"),
+    " (https://github.com/polsala/Shippo)"
+);
+
+/// The registry API on behalf of the token's user.
+pub struct CratesApi<'a> {
+    pub client: &'a dyn HttpClient,
+    pub retry: Retry,
+    /// The web API root, e.g. `https://crates.io`.
+    pub api: &'a str,
+    pub token: &'a str,
+}
+
+/// What [`CratesApi::sync_owners`] changed, or would change.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OwnersChange {
+    /// Invited: crates.io adds users once they accept, teams right away.
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Owners {
+    users: Vec<Owner>,
+}
+
+#[derive(Deserialize)]
+struct Owner {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct Me {
+    user: Owner,
+}
+
+impl CratesApi<'_> {
+    /// `path` under the API root, e.g. `crates/serde/owners`.
+    fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<HttpResponse> {
+        let url = format!("{}/api/v1/{path}", self.api.trim_end_matches('/'));
+        let mut request = HttpRequest::new(method, url);
+        request
+            .header("Authorization", self.token)
+            .header("User-Agent", USER_AGENT)
+            .header("Accept", "application/json");
+        if let Some(body) = &body {
+            request.json(body);
+        }
+        self.retry.send(self.client, &request)
+    }
+
+    /// `res` when it succeeded, else the registry's error details.
+    fn check(krate: &str, res: HttpResponse) -> Result<HttpResponse> {
+        if res.is_success() {
+            return Ok(res);
+        }
+        let details: Vec<String> = res
+            .json::<serde_json::Value>()
+            .ok()
+            .and_then(|v| {
+                let errors = v["errors"].as_array()?.iter();
+                Some(
+                    errors
+                        .filter_map(|e| e["detail"].as_str())
+                        .map(str::to_string)
+                        .collect(),
+                )
+            })
+            .unwrap_or_default();
+        let message = match details.is_empty() {
+            true => res.text().trim().to_string(),
+            false => details.join("; "),
+        };
+        Err(PublishError::Crates {
+            krate: krate.to_string(),
+            message: format!("{}: {message}", res.status),
+        })
+    }
+
+    /// The logins of `krate`'s owners, users and teams.
+    pub fn owners(&self, krate: &str) -> Result<Vec<String>> {
+        let res = self.send(Method::GET, &format!("crates/{krate}/owners"), None)?;
+        let owners: Owners = Self::check(krate, res)?.json()?;
+        Ok(owners.users.into_iter().map(|o| o.login).collect())
+    }
+
+    /// The login of the token's user; `krate` is what errors name.
+    fn me(&self, krate: &str) -> Result<String> {
+        let res = self.send(Method::GET, "me", None)?;
+        let me: Me = Self::check(krate, res)?.json()?;
+        Ok(me.user.login)
+    }
+
+    /// Invites the `wanted` owners `krate` lacks and, with
+    /// `remove_unlisted`, removes the others, except the token's own user,
+    /// who would lock themselves out. Logins compare ignoring case, as
+    /// crates.io does. `dry_run` only works out the change.
+    pub fn sync_owners(
+        &self,
+        krate: &str,
+        wanted: &[String],
+        remove_unlisted: bool,
+        dry_run: bool,
+    ) -> Result<OwnersChange> {
+        let current = self.owners(krate)?;
+        let has = |list: &[String], login: &str| list.iter().any(|o| o.eq_ignore_ascii_case(login));
+        let mut change = OwnersChange {
+            added: wanted
+                .iter()
+                .filter(|w| !has(&current, w))
+                .cloned()
+                .collect(),
+            removed: current
+                .iter()
+                .filter(|o| remove_unlisted && !has(wanted, o))
+                .cloned()
+                .collect(),
+        };
+        if !change.removed.is_empty() {
+            let me = self.me(krate)?;
+            change.removed.retain(|o| !o.eq_ignore_ascii_case(&me));
+        }
+        if dry_run {
+            return Ok(change);
+        }
+        let path = format!("crates/{krate}/owners");
+        if !change.added.is_empty() {
+            let res = self.send(Method::PUT, &path, Some(json!({ "users": change.added })))?;
+            Self::check(krate, res)?;
+        }
+        if !change.removed.is_empty() {
+            let res = self.send(
+                Method::DELETE,
+                &path,
+                Some(json!({ "users": change.removed })),
+            )?;
+            Self::check(krate, res)?;
+        }
+        Ok(change)
+    }
+
+    /// Yanks `version` of `krate`, or with `undo` unyanks it.
+    pub fn yank(&self, krate: &str, version: &str, undo: bool) -> Result<()> {
+        let (method, action) = match undo {
+            true => (Method::PUT, "unyank"),
+            false => (Method::DELETE, "yank"),
+        };
+        let res = self.send(method, &format!("crates/{krate}/{version}/{action}"), None)?;
+        Self::check(krate, res).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::MockHttpClient;
+    use std::time::Duration;
+
+    fn api(client: &MockHttpClient) -> CratesApi<'_> {
+        CratesApi {
+            client,
+            retry: Retry {
+                attempts: 1,
+                backoff: Duration::ZERO,
+            },
+            api: "https://crates.test/",
+            token: "cio_secret",
+        }
+    }
+
+    #[test]
+    fn test_sync_owners() {
+        let client = MockHttpClient::new();
+        let owners = json!({ "users": [
+            { "id": 1, "login": "Alice", "kind": "user" },
+            { "id": 2, "login": "mallory", "kind": "user" },
+            { "id": 3, "login": "release-bot", "kind": "user" },
+        ]});
+        let path = "/api/v1/crates/acme-cli/owners";
+        client
+            .respond(Method::GET, path, HttpResponse::json_body(200, &owners))
+            .respond(Method::GET, path, HttpResponse::json_body(200, &owners))
+            .respond(
+                Method::GET,
+                "/api/v1/me",
+                HttpResponse::json_body(
+                    200,
+                    &json!({ "user": { "id": 3, "login": "Release-Bot" } }),
+                ),
+            )
+            .respond(
+                Method::PUT,
+                path,
+                HttpResponse::json_body(200, &json!({ "ok": true })),
+            )
+            .respond(
+                Method::DELETE,
+                path,
+                HttpResponse::json_body(200, &json!({ "ok": true })),
+            );
+        let wanted = vec!["alice".to_string(), "github:acme:release".to_string()];
+
+        let planned = api(&client)
+            .sync_owners("acme-cli", &wanted, false, true)
+            .unwrap();
+        assert_eq!(planned.added, ["github:acme:release"]);
+        assert!(planned.removed.is_empty());
+        assert_eq!(client.requests().len(), 1);
+
+        let change = api(&client)
+            .sync_owners("acme-cli", &wanted, true, false)
+            .unwrap();
+        assert_eq!(change.added, ["github:acme:release"]);
+        // the token's own user stays an owner
+        assert_eq!(change.removed, ["mallory"]);
+        let requests = client.requests();
+        assert_eq!(requests.len(), 5);
+        assert_eq!(requests[2].url, "https://crates.test/api/v1/me");
+        assert_eq!(requests[3].method, Method::PUT);
+        let body: serde_json::Value = serde_json::from_slice(&requests[3].body).unwrap();
+        assert_eq!(body, json!({ "users": ["github:acme:release"] }));
+        let body: serde_json::Value = serde_json::from_slice(&requests[4].body).unwrap();
+        assert_eq!(body, json!({ "users": ["mallory"] }));
+        assert!(requests[4]
+            .headers
+            .contains(&("Authorization".into(), "cio_secret".into())));
+        assert!(requests[4]
+            .headers
+            .iter()
+            .any(|(name, value)| name == "User-Agent" && value.starts_with("shippo/")));
+    }
+
+    #[test]
+    fn test_yank() {
+        let client = MockHttpClient::new();
+        client
+            .respond(
+                Method::DELETE,
+                "/api/v1/crates/acme-cli/1.4.0/yank",
+                HttpResponse::json_body(200, &json!({ "ok": true })),
+            )
+            .respond(
+                Method::PUT,
+                "/api/v1/crates/acme-cli/1.4.0/unyank",
+                HttpResponse::json_body(
+                    403,
+                    &json!({ "errors": [{ "detail": "must already be an owner to unyank" }] }),
+                ),
+            );
+        api(&client).yank("acme-cli", "1.4.0", false).unwrap();
+        let err = api(&client).yank("acme-cli", "1.4.0", true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "crate acme-cli: 403 Forbidden: must already be an owner to unyank"
+        );
+        // unknown versions are the registry's 404
+        assert!(api(&client).yank("acme-cli", "9.9.9", false).is_err());
+    }
+}
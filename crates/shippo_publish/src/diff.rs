@@ -0,0 +1,254 @@
+//! Compares a packaged dist with the release already on GitHub, for
+//! `shippo release --dry-run-remote`. Only reads from the provider.
+
+use std::path::Path;
+
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
+use reqwest::StatusCode;
+use serde::Serialize;
+use serde_json::Value;
+use shippo_core::sha256_file;
+
+use crate::{dist_assets, PublishError, Result};
+
+/// What publishing would do to one asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetChange {
+    /// Not on the release yet.
+    Create,
+    /// On the release with different content.
+    Replace,
+    /// On the release with the same digest (or size, when GitHub reports
+    /// no digest).
+    Keep,
+    /// Only on the release; publishing leaves it alone.
+    RemoteOnly,
+}
+
+impl AssetChange {
+    pub fn label(self) -> &'static str {
+        match self {
+            AssetChange::Create => "create",
+            AssetChange::Replace => "replace",
+            AssetChange::Keep => "unchanged",
+            AssetChange::RemoteOnly => "remote only",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AssetDiff {
+    pub filename: String,
+    pub change: AssetChange,
+    /// Local size, or the remote one for [`AssetChange::RemoteOnly`].
+    pub bytes: u64,
+}
+
+/// An asset as GitHub lists it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteAsset {
+    pub name: String,
+    pub size: u64,
+    /// `sha256:<hex>`, on assets uploaded since GitHub started recording it.
+    pub digest: Option<String>,
+}
+
+/// A dist file to upload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalAsset {
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RemoteDiff {
+    pub tag: String,
+    pub tag_exists: bool,
+    /// Whether the release exists, and if so whether it is a draft.
+    pub release: Option<RemoteRelease>,
+    pub assets: Vec<AssetDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RemoteRelease {
+    pub draft: bool,
+    pub prerelease: bool,
+    pub url: Option<String>,
+}
+
+impl RemoteDiff {
+    pub fn count(&self, change: AssetChange) -> usize {
+        self.assets.iter().filter(|a| a.change == change).count()
+    }
+}
+
+/// Matches `local` files against the `remote` assets by name, sorted by
+/// change and then name.
+pub fn diff_assets(local: &[LocalAsset], remote: &[RemoteAsset]) -> Vec<AssetDiff> {
+    let mut diff: Vec<AssetDiff> = local
+        .iter()
+        .map(|file| {
+            let change = match remote.iter().find(|r| r.name == file.name) {
+                None => AssetChange::Create,
+                Some(r) => {
+                    let same = match &r.digest {
+                        Some(digest) => {
+                            digest.strip_prefix("sha256:") == Some(file.sha256.as_str())
+                        }
+                        None => r.size == file.size,
+                    };
+                    if same {
+                        AssetChange::Keep
+                    } else {
+                        AssetChange::Replace
+                    }
+                }
+            };
+            AssetDiff {
+                filename: file.name.clone(),
+                change,
+                bytes: file.size,
+            }
+        })
+        .collect();
+    diff.extend(
+        remote
+            .iter()
+            .filter(|r| !local.iter().any(|l| l.name == r.name))
+            .map(|r| AssetDiff {
+                filename: r.name.clone(),
+                change: AssetChange::RemoteOnly,
+                bytes: r.size,
+            }),
+    );
+    diff.sort_by(|a, b| (a.change, &a.filename).cmp(&(b.change, &b.filename)));
+    diff
+}
+
+/// Looks up `tag` and its release in `owner/repo` and diffs the release's
+/// assets with the files in `dist`, leaving out `skip`. Without a token only public
+/// repositories can be read, and drafts are not visible.
+pub fn diff_github(
+    token: Option<&str>,
+    owner: &str,
+    repo: &str,
+    tag: &str,
+    dist: &Path,
+    skip: &[String],
+) -> Result<RemoteDiff> {
+    let client = Client::new();
+    let api = format!("https://api.github.com/repos/{owner}/{repo}");
+    let get = |url: String| -> RequestBuilder {
+        let req = client
+            .get(url)
+            .header(USER_AGENT, "shippo/1.0")
+            .header(ACCEPT, "application/vnd.github+json");
+        match token {
+            Some(token) => req.header(AUTHORIZATION, format!("Bearer {token}")),
+            None => req,
+        }
+    };
+    let found = |res: reqwest::blocking::Response| -> Result<Option<Value>> {
+        match res.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(res.json()?)),
+            status => Err(PublishError::LookupFailed { status }),
+        }
+    };
+
+    let tag_exists = found(get(format!("{api}/git/ref/tags/{tag}")).send()?)?.is_some();
+    // the by-tag endpoint skips drafts, which only show up in the list
+    let release = match found(get(format!("{api}/releases/tags/{tag}")).send()?)? {
+        Some(release) => Some(release),
+        None => found(get(format!("{api}/releases?per_page=100")).send()?)?.and_then(|list| {
+            list.as_array()?
+                .iter()
+                .find(|r| r["tag_name"].as_str() == Some(tag))
+                .cloned()
+        }),
+    };
+    let remote: Vec<RemoteAsset> = release
+        .as_ref()
+        .and_then(|r| r["assets"].as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|a| {
+            Some(RemoteAsset {
+                name: a["name"].as_str()?.to_string(),
+                size: a["size"].as_u64().unwrap_or_default(),
+                digest: a["digest"].as_str().map(str::to_string),
+            })
+        })
+        .collect();
+    let mut local = Vec::new();
+    for (name, path) in dist_assets(dist)? {
+        if skip.contains(&name) {
+            continue;
+        }
+        local.push(LocalAsset {
+            size: std::fs::metadata(&path)?.len(),
+            sha256: sha256_file(&path)?,
+            name,
+        });
+    }
+    Ok(RemoteDiff {
+        tag: tag.to_string(),
+        tag_exists,
+        release: release.map(|r| RemoteRelease {
+            draft: r["draft"].as_bool().unwrap_or_default(),
+            prerelease: r["prerelease"].as_bool().unwrap_or_default(),
+            url: r["html_url"].as_str().map(str::to_string),
+        }),
+        assets: diff_assets(&local, &remote),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assets_diffed_by_digest_then_size() {
+        let local = |name: &str, size: u64, sha256: &str| LocalAsset {
+            name: name.into(),
+            size,
+            sha256: sha256.into(),
+        };
+        let remote = |name: &str, size: u64, digest: Option<&str>| RemoteAsset {
+            name: name.into(),
+            size,
+            digest: digest.map(str::to_string),
+        };
+        let diff = diff_assets(
+            &[
+                local("app.tar.gz", 10, "aa"),
+                local("app.zip", 12, "bb"),
+                local("SHA256SUMS", 80, "cc"),
+                local("manifest.json", 500, "dd"),
+            ],
+            &[
+                remote("app.tar.gz", 10, Some("sha256:aa")),
+                remote("app.zip", 12, Some("sha256:00")),
+                remote("SHA256SUMS", 80, None),
+                remote("notes.txt", 3, None),
+            ],
+        );
+        let summary: Vec<_> = diff
+            .iter()
+            .map(|a| (a.filename.as_str(), a.change))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("manifest.json", AssetChange::Create),
+                ("app.zip", AssetChange::Replace),
+                ("SHA256SUMS", AssetChange::Keep),
+                ("app.tar.gz", AssetChange::Keep),
+                ("notes.txt", AssetChange::RemoteOnly),
+            ]
+        );
+    }
+}
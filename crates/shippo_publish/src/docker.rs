@@ -0,0 +1,107 @@
+//! `docker push` of the images built for `[docker]`. The registry's
+//! digest of the pushed image goes into the manifest, so a release pins
+//! exactly what was pushed.
+
+use shippo_core::exec::{CommandSpec, Executor};
+use shippo_core::ManifestImage;
+
+use crate::{PublishError, Result};
+
+/// The digest in `docker push` output, from its last line:
+/// `1.2.0: digest: sha256:… size: 1234`.
+fn pushed_digest(output: &str) -> Option<&str> {
+    let (_, rest) = output.rsplit_once("digest: ")?;
+    rest.split_whitespace()
+        .next()
+        .filter(|d| d.starts_with("sha256:"))
+}
+
+/// Pushes every tag of `image`, after adding the `extra` ones (such as
+/// `latest`) to it, and returns the registry's digest. The registry has
+/// to be logged in to already, e.g. with `docker login`.
+pub fn push_image(
+    image: &ManifestImage,
+    extra: &[&str],
+    executor: &dyn Executor,
+) -> Result<String> {
+    let repository = &image.repository;
+    let error = |message: String| PublishError::Docker {
+        image: repository.clone(),
+        message,
+    };
+    let docker = |args: &[&str]| -> Result<String> {
+        let mut cmd = CommandSpec::new("docker");
+        cmd.args(args);
+        let output = executor.output(&cmd).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => error("docker is not installed".into()),
+            _ => PublishError::Io(e),
+        })?;
+        if !output.success() {
+            return Err(error(format!(
+                "docker {} failed ({}): {}",
+                args[0],
+                output.status(),
+                output.stderr.trim()
+            )));
+        }
+        Ok(output.stdout)
+    };
+    let Some(first) = image.tags.first() else {
+        return Err(error("the image has no tags".into()));
+    };
+    let source = format!("{repository}:{first}");
+    for tag in extra {
+        docker(&["tag", &source, &format!("{repository}:{tag}")])?;
+    }
+    let mut digest = None;
+    for tag in image
+        .tags
+        .iter()
+        .map(String::as_str)
+        .chain(extra.iter().copied())
+    {
+        let stdout = docker(&["push", &format!("{repository}:{tag}")])?;
+        digest = pushed_digest(&stdout).map(String::from).or(digest);
+    }
+    digest.ok_or_else(|| error("docker push printed no digest".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shippo_core::exec::{CommandOutput, MockExecutor};
+
+    #[test]
+    fn test_push_tags_and_read_digest() {
+        let image = ManifestImage {
+            repository: "ghcr.io/acme/cli".into(),
+            tags: vec!["1.2.0".into(), "1.2".into()],
+            id: "sha256:config".into(),
+            digest: None,
+        };
+        let executor = MockExecutor::new();
+        for _ in 0..4 {
+            executor.respond(
+                "docker",
+                CommandOutput {
+                    code: Some(0),
+                    stdout: "The push refers to repository [ghcr.io/acme/cli]\n5f70bf18a086: Pushed\n1.2.0: digest: sha256:feed size: 528\n".into(),
+                    ..Default::default()
+                },
+            );
+        }
+        let digest = push_image(&image, &["latest"], &executor).unwrap();
+        assert_eq!(digest, "sha256:feed");
+        let calls: Vec<_> = executor.calls().iter().map(|c| c.args.join(" ")).collect();
+        assert_eq!(
+            calls,
+            [
+                "tag ghcr.io/acme/cli:1.2.0 ghcr.io/acme/cli:latest",
+                "push ghcr.io/acme/cli:1.2.0",
+                "push ghcr.io/acme/cli:1.2",
+                "push ghcr.io/acme/cli:latest",
+            ]
+        );
+        assert_eq!(pushed_digest("no digest here"), None);
+    }
+}
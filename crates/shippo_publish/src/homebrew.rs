@@ -0,0 +1,323 @@
+//! Homebrew formulae for `[release.homebrew]`: rendered from the manifest's
+//! archives, or from a Tera template, and committed to a tap repository,
+//! through a pull request by default.
+
+use std::path::Path;
+
+use serde::Serialize;
+use shippo_core::{targets, HomebrewConfig, ManifestPackage};
+
+use crate::tap::{commit_file, render_template, TapCommit};
+use crate::{PublishError, Result};
+
+/// One archive a formula can install, for a Homebrew platform.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FormulaArchive {
+    /// `macos` or `linux`.
+    pub os: &'static str,
+    /// `arm` or `intel`.
+    pub arch: &'static str,
+    pub url: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Formula {
+    pub name: String,
+    /// Without a leading `v`.
+    pub version: String,
+    pub description: String,
+    pub homepage: String,
+    pub license: Option<String>,
+    pub bins: Vec<String>,
+    pub archives: Vec<FormulaArchive>,
+}
+
+/// The archives of `package` that Homebrew can install, downloaded from
+/// the GitHub release `tag`. tar.gz archives are preferred over zips;
+/// targets other than macOS and Linux on arm64 or x86_64 are left out.
+pub fn formula_archives(
+    package: &ManifestPackage,
+    owner: &str,
+    repo: &str,
+    tag: &str,
+) -> Vec<FormulaArchive> {
+    let mut archives: Vec<FormulaArchive> = Vec::new();
+    for target in &package.targets {
+        let info = match target.target.as_str() {
+            "native" => targets::host(),
+            name => targets::resolve(name),
+        };
+        let (os, arch) = match info.and_then(|i| i.go) {
+            Some(("darwin", arch)) => ("macos", arch),
+            Some(("linux", arch)) => ("linux", arch),
+            _ => continue,
+        };
+        let arch = match arch {
+            "arm64" => "arm",
+            "amd64" => "intel",
+            _ => continue,
+        };
+        let archive = target
+            .artifacts
+            .iter()
+            .find(|a| a.filename.ends_with(".tar.gz"))
+            .or_else(|| {
+                target
+                    .artifacts
+                    .iter()
+                    .find(|a| a.filename.ends_with(".zip"))
+            });
+        let Some(archive) = archive else {
+            continue;
+        };
+        if archives.iter().any(|a| a.os == os && a.arch == arch) {
+            continue;
+        }
+        archives.push(FormulaArchive {
+            os,
+            arch,
+            url: format!(
+                "https://github.com/{owner}/{repo}/releases/download/{tag}/{}",
+                archive.filename
+            ),
+            sha256: archive.sha256.clone(),
+        });
+    }
+    archives
+}
+
+/// The Ruby class Homebrew expects for `name`, e.g. `MyTool` for `my-tool`.
+pub fn class_name(name: &str) -> String {
+    name.replace('@', "AT")
+        .split(['-', '_', '.'])
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// A double-quoted Ruby string literal; `#` is escaped so nothing is
+/// interpolated.
+fn quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('#', "\\#")
+        .replace('\n', " ");
+    format!("\"{escaped}\"")
+}
+
+pub fn render_formula(formula: &Formula) -> String {
+    let mut out = format!("class {} < Formula\n", class_name(&formula.name));
+    out.push_str(&format!("  desc {}\n", quote(&formula.description)));
+    out.push_str(&format!("  homepage {}\n", quote(&formula.homepage)));
+    out.push_str(&format!("  version {}\n", quote(&formula.version)));
+    if let Some(license) = &formula.license {
+        out.push_str(&format!("  license {}\n", quote(license)));
+    }
+    for os in ["macos", "linux"] {
+        let archives: Vec<_> = formula.archives.iter().filter(|a| a.os == os).collect();
+        if archives.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n  on_{os} do\n"));
+        for archive in archives {
+            out.push_str(&format!("    on_{} do\n", archive.arch));
+            out.push_str(&format!("      url {}\n", quote(&archive.url)));
+            out.push_str(&format!("      sha256 {}\n", quote(&archive.sha256)));
+            out.push_str("    end\n");
+        }
+        out.push_str("  end\n");
+    }
+    out.push_str("\n  def install\n");
+    for bin in &formula.bins {
+        out.push_str(&format!("    bin.install {}\n", quote(bin)));
+    }
+    out.push_str("  end\n");
+    if let Some(bin) = formula.bins.first() {
+        out.push_str("\n  test do\n");
+        out.push_str(&format!("    system \"#{{bin}}/{bin}\", \"--version\"\n"));
+        out.push_str("  end\n");
+    }
+    out.push_str("end\n");
+    out
+}
+
+/// Renders `[release.homebrew] template`, `file` relative to `root`, with
+/// `formula`'s fields and its `class_name` as variables.
+pub fn render_formula_template(root: &Path, file: &str, formula: &Formula) -> Result<String> {
+    #[derive(Serialize)]
+    struct Variables<'a> {
+        #[serde(flatten)]
+        formula: &'a Formula,
+        class_name: String,
+    }
+    render_template(
+        root,
+        file,
+        &Variables {
+            formula,
+            class_name: class_name(&formula.name),
+        },
+    )
+}
+
+/// Commits `text` as the formula to the tap, on a new branch with a pull
+/// request unless `config.pull_request` is off. Returns the pull request's
+/// URL, or the formula's when committed directly.
+pub fn publish_homebrew(
+    token: &str,
+    config: &HomebrewConfig,
+    formula: &Formula,
+    text: &str,
+) -> Result<String> {
+    let branch = format!("shippo/{}-{}", formula.name, formula.version);
+    let commit = TapCommit {
+        repo: &config.tap,
+        path: &format!("{}/{}.rb", config.directory.trim_matches('/'), formula.name),
+        content: text,
+        message: &format!("{} {}", formula.name, formula.version),
+        branch: config.pull_request.then_some(branch.as_str()),
+        pull_body: &format!("Updates {} to {}.", formula.name, formula.version),
+    };
+    commit_file(token, &commit, |message| PublishError::Homebrew {
+        tap: config.tap.clone(),
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shippo_core::{ManifestArtifact, ManifestTarget, ProjectType};
+
+    fn target(target: &str, files: &[&str]) -> ManifestTarget {
+        ManifestTarget {
+            target: target.into(),
+            artifacts: files
+                .iter()
+                .map(|f| ManifestArtifact {
+                    filename: f.to_string(),
+                    bytes: 1,
+                    sha256: format!("sha-of-{f}"),
+                    contents: Vec::new(),
+                    alias: None,
+                })
+                .collect(),
+            sbom: None,
+            extra_sboms: Vec::new(),
+            attestation: None,
+            signatures: Vec::new(),
+            origin: None,
+        }
+    }
+
+    #[test]
+    fn test_formula_from_manifest() {
+        let package = ManifestPackage {
+            name: "my-tool".into(),
+            project_type: ProjectType::Rust,
+            path: ".".into(),
+            targets: vec![
+                target(
+                    "aarch64-apple-darwin",
+                    &["a-darwin-arm64.zip", "a-darwin-arm64.tar.gz"],
+                ),
+                target("x86_64-unknown-linux-gnu", &["a-linux-amd64.tar.gz"]),
+                target("x86_64-pc-windows-msvc", &["a-windows-amd64.zip"]),
+            ],
+            tests: None,
+            scan: None,
+            metadata: None,
+            image: None,
+            npm: None,
+        };
+        let archives = formula_archives(&package, "acme", "tool", "v1.2.0");
+        assert_eq!(archives.len(), 2);
+        assert_eq!((archives[0].os, archives[0].arch), ("macos", "arm"));
+        assert_eq!(
+            archives[0].url,
+            "https://github.com/acme/tool/releases/download/v1.2.0/a-darwin-arm64.tar.gz"
+        );
+        assert_eq!((archives[1].os, archives[1].arch), ("linux", "intel"));
+
+        let formula = render_formula(&Formula {
+            name: "my-tool".into(),
+            version: "1.2.0".into(),
+            description: "A \"tool\"".into(),
+            homepage: "https://github.com/acme/tool".into(),
+            license: Some("MIT".into()),
+            bins: vec!["my-tool".into()],
+            archives,
+        });
+        assert!(formula.starts_with("class MyTool < Formula\n  desc \"A \\\"tool\\\"\"\n"));
+        assert!(formula.contains(
+            "  on_macos do\n    on_arm do\n      url \"https://github.com/acme/tool/releases/download/v1.2.0/a-darwin-arm64.tar.gz\"\n      sha256 \"sha-of-a-darwin-arm64.tar.gz\"\n    end\n  end\n"
+        ));
+        assert!(formula.contains("  on_linux do\n    on_intel do\n"));
+        assert!(formula.contains("    bin.install \"my-tool\"\n"));
+        assert!(formula.contains("    system \"#{bin}/my-tool\", \"--version\"\n"));
+        assert_eq!(class_name("node@18"), "NodeAT18");
+    }
+
+    #[test]
+    fn test_formula_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = r#"class {{ class_name }} < Formula
+  desc {{ description | json_encode }}
+  version "{{ version }}"
+{% for archive in archives %}
+  on_{{ archive.os }} do
+    on_{{ archive.arch }} do
+      url "{{ archive.url }}"
+      sha256 "{{ archive.sha256 }}"
+    end
+  end
+{% endfor %}
+  def install
+    bin.install "{{ bins.0 }}"
+    generate_completions_from_executable(bin/"{{ bins.0 }}", "completions")
+  end
+
+  def caveats
+    "Run `{{ bins.0 }} init` once."
+  end
+end
+"#;
+        std::fs::write(dir.path().join("formula.rb.tera"), template).unwrap();
+        let formula = Formula {
+            name: "my-tool".into(),
+            version: "1.2.0".into(),
+            description: "A \"tool\"".into(),
+            homepage: "https://github.com/acme/tool".into(),
+            license: None,
+            bins: vec!["my-tool".into()],
+            archives: vec![FormulaArchive {
+                os: "linux",
+                arch: "intel",
+                url: "https://example.test/a-linux-amd64.tar.gz".into(),
+                sha256: "abc".into(),
+            }],
+        };
+        let text = render_formula_template(dir.path(), "formula.rb.tera", &formula).unwrap();
+        assert!(text.starts_with("class MyTool < Formula\n  desc \"A \\\"tool\\\"\"\n"));
+        assert!(text.contains(
+            "  on_linux do\n    on_intel do\n      url \"https://example.test/a-linux-amd64.tar.gz\"\n      sha256 \"abc\"\n"
+        ));
+        assert!(text.contains("    \"Run `my-tool init` once.\"\n"));
+
+        std::fs::write(dir.path().join("broken.rb.tera"), "{{ tap }}").unwrap();
+        let err = render_formula_template(dir.path(), "broken.rb.tera", &formula)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("broken.rb.tera") && err.contains("tap"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! The HTTP layer of the GitHub publisher and the crates.io helpers, behind
+//! [`HttpClient`] so it can be tested without either: [`ReqwestClient`] sends requests, and
+//! [`MockHttpClient`] answers them with queued responses and records them.
+//! [`Retry`] retries rate-limited and failed requests on either.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{PublishError, Result};
+
+/// Longest a `Retry-After` is waited for.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn header(&mut self, name: &str, value: impl Into<String>) -> &mut Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// Sends `value` as the JSON body.
+    pub fn json<T: Serialize + ?Sized>(&mut self, value: &T) -> &mut Self {
+        self.body = serde_json::to_vec(value).unwrap_or_default();
+        self.header("Content-Type", "application/json")
+    }
+
+    pub fn body(&mut self, body: Vec<u8>) -> &mut Self {
+        self.body = body;
+        self
+    }
+
+    /// The URL's path, without the scheme, host and query.
+    pub fn path(&self) -> &str {
+        let rest = self
+            .url
+            .split_once("://")
+            .map_or(self.url.as_str(), |(_, rest)| rest);
+        let path = rest.find('/').map_or("/", |i| &rest[i..]);
+        path.split('?').next().unwrap_or(path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// A response with `value` as its JSON body.
+    pub fn json_body(status: u16, value: &serde_json::Value) -> Self {
+        Self::new(status, value.to_string())
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+
+    /// The first header called `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+pub trait HttpClient: Send + Sync {
+    /// Sends `request`; `Err` only when no response came back.
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse>;
+}
+
+/// Sends requests with `reqwest`.
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestClient {
+    client: Client,
+}
+
+impl ReqwestClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl HttpClient for ReqwestClient {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let mut builder = self.client.request(request.method.clone(), &request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if !request.body.is_empty() {
+            builder = builder.body(request.body.clone());
+        }
+        let res = builder.send()?;
+        let status = res.status();
+        let headers = res
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = value.to_str().unwrap_or_default();
+                (name.to_string(), value.to_string())
+            })
+            .collect();
+        Ok(HttpResponse {
+            status,
+            headers,
+            body: res.bytes()?.to_vec(),
+        })
+    }
+}
+
+/// Test double: records requests and replays the responses queued for
+/// their method and path, in order. Requests without one get a 404.
+#[derive(Debug, Default)]
+pub struct MockHttpClient {
+    responses: Mutex<BTreeMap<String, VecDeque<Result<HttpResponse, String>>>>,
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl MockHttpClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the response to the next `method` request for `path`, such
+    /// as `/repos/acme/tool/releases`.
+    pub fn respond(&self, method: Method, path: &str, response: HttpResponse) -> &Self {
+        self.queue(method, path, Ok(response))
+    }
+
+    /// Makes the next `method` request for `path` fail without a response,
+    /// like a dropped connection.
+    pub fn fail(&self, method: Method, path: &str, message: &str) -> &Self {
+        self.queue(method, path, Err(message.to_string()))
+    }
+
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn queue(&self, method: Method, path: &str, next: Result<HttpResponse, String>) -> &Self {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(format!("{method} {path}"))
+            .or_default()
+            .push_back(next);
+        self
+    }
+}
+
+impl HttpClient for MockHttpClient {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        self.requests.lock().unwrap().push(request.clone());
+        let queued = self
+            .responses
+            .lock()
+            .unwrap()
+            .get_mut(&format!("{} {}", request.method, request.path()))
+            .and_then(|q| q.pop_front());
+        match queued {
+            Some(Ok(response)) => Ok(response),
+            Some(Err(message)) => Err(PublishError::Io(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                message,
+            ))),
+            None => Ok(HttpResponse::new(404, "")),
+        }
+    }
+}
+
+/// How often, and how far apart, failed requests are sent again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retry {
+    /// Tries in all, the first included.
+    pub attempts: u32,
+    /// Wait before the first retry, doubled for each one after.
+    pub backoff: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Retry {
+    /// Sends `request` until it gets an answer worth keeping. Rate-limited
+    /// requests (429, or a 403 with no requests left) are retried after
+    /// `Retry-After`; server errors (502, 503, 504) and failed connections
+    /// only for requests other than `POST`, which may have gone through.
+    pub fn send(&self, client: &dyn HttpClient, request: &HttpRequest) -> Result<HttpResponse> {
+        let idempotent = request.method != Method::POST;
+        let mut wait = self.backoff;
+        for attempt in 1.. {
+            let last = attempt >= self.attempts;
+            let pause = match client.send(request) {
+                Err(e) if last || !idempotent => return Err(e),
+                Err(_) => wait,
+                Ok(res) if last => return Ok(res),
+                Ok(res) if rate_limited(&res) => res
+                    .header("retry-after")
+                    .and_then(|s| s.trim().parse().ok())
+                    .map_or(wait, Duration::from_secs)
+                    .min(MAX_RETRY_AFTER),
+                Ok(res)
+                    if idempotent
+                        && matches!(
+                            res.status,
+                            StatusCode::BAD_GATEWAY
+                                | StatusCode::SERVICE_UNAVAILABLE
+                                | StatusCode::GATEWAY_TIMEOUT
+                        ) =>
+                {
+                    wait
+                }
+                Ok(res) => return Ok(res),
+            };
+            sleep(pause);
+            wait *= 2;
+        }
+        unreachable!("the last attempt returns")
+    }
+}
+
+fn rate_limited(res: &HttpResponse) -> bool {
+    res.status == StatusCode::TOO_MANY_REQUESTS
+        || (res.status == StatusCode::FORBIDDEN && res.header("x-ratelimit-remaining") == Some("0"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retries_rate_limits_and_idempotent_failures() {
+        let retry = Retry {
+            attempts: 3,
+            backoff: Duration::ZERO,
+        };
+        let client = MockHttpClient::new();
+        let path = "/repos/acme/tool/releases/tags/v1.0.0";
+        client
+            .respond(
+                Method::GET,
+                path,
+                HttpResponse::new(429, "").with_header("Retry-After", "0"),
+            )
+            .fail(Method::GET, path, "connection reset")
+            .respond(Method::GET, path, HttpResponse::new(200, "{}"));
+        let request = HttpRequest::new(Method::GET, format!("https://api.test{path}?x=1"));
+        assert_eq!(request.path(), path);
+        assert!(retry.send(&client, &request).unwrap().is_success());
+        assert_eq!(client.requests().len(), 3);
+
+        // a POST may have created something; only rate limits are retried
+        let client = MockHttpClient::new();
+        client
+            .respond(Method::POST, "/r", HttpResponse::new(502, ""))
+            .respond(Method::POST, "/r", HttpResponse::new(201, ""));
+        let post = HttpRequest::new(Method::POST, "https://api.test/r");
+        assert_eq!(
+            retry.send(&client, &post).unwrap().status,
+            StatusCode::BAD_GATEWAY
+        );
+        let client = MockHttpClient::new();
+        client.fail(Method::POST, "/r", "timed out");
+        assert!(retry.send(&client, &post).is_err());
+        assert_eq!(client.requests().len(), 1);
+
+        // gives up after the last attempt with what it got
+        let client = MockHttpClient::new();
+        for _ in 0..3 {
+            client.respond(Method::GET, "/r", HttpResponse::new(503, ""));
+        }
+        let get = HttpRequest::new(Method::GET, "https://api.test/r");
+        assert_eq!(
+            retry.send(&client, &get).unwrap().status,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(client.requests().len(), 3);
+    }
+}
@@ -1,13 +1,104 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, Context, Result};
 use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
-use reqwest::blocking::Client;
-use reqwest::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
+use reqwest::{Method, StatusCode};
 use serde::Serialize;
-use shippo_core::Manifest;
-use shippo_git::{changelog_between, latest_tag};
+use shippo_core::events::{Event, EventBus};
+use shippo_core::exec::COMMANDS_FILE;
+use shippo_core::plugin::{Plugin, PluginError, PublishParams};
+use shippo_core::{
+    ChangelogConfig, Manifest, ReleaseAssetsConfig, ReleaseBodyConfig, CHANGELOG_FILE, SUMMARY_FILE,
+};
+use shippo_git::{commits_touching, is_shallow, previous_tag};
+
+use changelog::GITHUB_BODY_LIMIT;
+use http::{HttpClient, HttpRequest, HttpResponse, ReqwestClient, Retry};
+
+pub mod approval;
+pub mod assets;
+pub mod body;
+pub mod changelog;
+pub mod crates_io;
+pub mod diff;
+pub mod docker;
+pub mod homebrew;
+pub mod http;
+pub mod npm;
+pub mod pypi;
+pub mod s3;
+pub mod scoop;
+pub mod symbols;
+pub mod tag;
+mod tap;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PublishError {
+    #[error("github request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("github sent invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("github release creation failed: {status}")]
+    ReleaseRejected { status: StatusCode },
+    #[error("failed to upload {filename}: {status} {body}")]
+    UploadRejected {
+        filename: String,
+        status: StatusCode,
+        body: String,
+    },
+    #[error("github lookup failed: {status}")]
+    LookupFailed { status: StatusCode },
+    #[error("moving the latest tag failed: {status} {body}")]
+    LatestTag { status: StatusCode, body: String },
+    #[error("missing upload_url")]
+    MissingUploadUrl,
+    #[error("tag {tag} points at {remote} on GitHub, but the artifacts were built from {built}; refusing to attach them")]
+    TagMismatch {
+        tag: String,
+        remote: String,
+        built: String,
+    },
+    #[error("{tag} already has a release{}, so its assets are left alone: {url}", .immutable.then_some(" marked immutable").unwrap_or_default())]
+    ReleaseExists {
+        tag: String,
+        url: String,
+        immutable: bool,
+    },
+    #[error("{} is a shallow clone, so the changelog would miss commits; fetch the full history and tags (`fetch-depth: 0` on actions/checkout) or set `[changelog] unshallow = true`", .0.display())]
+    ShallowCheckout(PathBuf),
+    #[error("release tag {tag} failed signature verification: {reason}")]
+    TagSignature { tag: String, reason: String },
+    #[error("release {tag} was not approved: {message}")]
+    Approval { tag: String, message: String },
+    #[error("homebrew tap {tap}: {message}")]
+    Homebrew { tap: String, message: String },
+    #[error("scoop bucket {bucket}: {message}")]
+    Scoop { bucket: String, message: String },
+    #[error("template {path}: {message}")]
+    Template { path: String, message: String },
+    #[error("docker image {image}: {message}")]
+    Docker { image: String, message: String },
+    #[error("{bucket}: {message}")]
+    S3 { bucket: String, message: String },
+    #[error("npm package {package}: {message}")]
+    Npm { package: String, message: String },
+    #[error("pypi upload of {package}: {message}")]
+    Pypi { package: String, message: String },
+    #[error("crate {krate}: {message}")]
+    Crates { krate: String, message: String },
+    #[error("symbol upload failed: {0}")]
+    Symbols(String),
+    #[error(transparent)]
+    Plugin(#[from] PluginError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T, E = PublishError> = std::result::Result<T, E>;
+
+/// Tag moved to each full release with `[release] update_latest`.
+pub const LATEST_TAG: &str = "latest";
 
 #[derive(Debug, Clone)]
 pub struct ReleaseInput<'a> {
@@ -17,9 +108,26 @@ pub struct ReleaseInput<'a> {
     pub name: &'a str,
     pub draft: bool,
     pub prerelease: bool,
-    pub changelog_mode: &'a str,
+    /// Make this GitHub's latest release and move the [`LATEST_TAG`] to it,
+    /// unless it is a draft or prerelease.
+    pub update_latest: bool,
+    pub changelog: &'a ChangelogConfig,
+    /// The body's sections.
+    pub body: &'a ReleaseBodyConfig,
+    /// `[version] tag_pattern`, to find the previous release.
+    pub tag_pattern: Option<&'a str>,
+    /// Package name to path; with several, the changelog has a section each.
+    pub packages: &'a BTreeMap<String, String>,
+    /// Repository the changelog is read from.
+    pub workspace_root: &'a Path,
     pub dist: &'a Path,
+    /// Dist files not uploaded to this release, such as other packages'
+    /// archives when each package gets a release of its own.
+    pub skip_assets: &'a [String],
+    /// Labels and MIME types of the uploaded files.
+    pub assets: &'a ReleaseAssetsConfig,
     pub manifest: &'a Manifest,
+    pub events: Option<&'a EventBus>,
 }
 
 #[derive(Serialize)]
@@ -29,76 +137,537 @@ struct CreateRelease<'a> {
     body: &'a str,
     draft: bool,
     prerelease: bool,
+    /// `"true"` or `"false"`; GitHub decides by date and version without it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    make_latest: Option<&'a str>,
+    /// Commit GitHub creates a missing tag at, instead of the default
+    /// branch's head.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_commitish: Option<&'a str>,
+}
+
+/// GitHub's REST API.
+pub const GITHUB_API: &str = "https://api.github.com";
+
+/// The GitHub REST API at `base` (normally [`GITHUB_API`]), as `token`.
+pub struct GitHubApi<'a> {
+    pub client: &'a dyn HttpClient,
+    pub base: &'a str,
+    pub token: &'a str,
+    pub retry: Retry,
+}
+
+impl GitHubApi<'_> {
+    /// A request for `url`, or for a path such as `/repos/acme/tool` under
+    /// `base`.
+    fn request(&self, method: Method, url: &str) -> HttpRequest {
+        let url = if url.starts_with('/') {
+            format!("{}{url}", self.base.trim_end_matches('/'))
+        } else {
+            url.to_string()
+        };
+        let mut request = HttpRequest::new(method, url);
+        request
+            .header("User-Agent", "shippo/1.0")
+            .header("Accept", "application/vnd.github+json")
+            .header("Authorization", format!("Bearer {}", self.token));
+        request
+    }
+
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        self.retry.send(self.client, request)
+    }
+
+    /// GETs `path`; `None` when it isn't there.
+    fn get(&self, path: &str) -> Result<Option<serde_json::Value>> {
+        let res = self.send(&self.request(Method::GET, path))?;
+        if res.status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !res.is_success() {
+            return Err(PublishError::LookupFailed { status: res.status });
+        }
+        Ok(Some(res.json()?))
+    }
 }
 
+/// Creates `input`'s release on github.com and uploads its assets.
 pub fn publish_github(token: &str, input: &ReleaseInput) -> Result<()> {
-    let client = Client::new();
-    let body = changelog_body(input.changelog_mode, input.tag)?;
-    let url = format!(
-        "https://api.github.com/repos/{}/{}/releases",
-        input.owner, input.repo
+    let client = ReqwestClient::new();
+    let github = GitHubApi {
+        client: &client,
+        base: GITHUB_API,
+        token,
+        retry: Retry::default(),
+    };
+    publish_github_with(&github, input)
+}
+
+/// Runs [`publish_github`]'s checks of `input` without creating anything:
+/// renders the release body, so a shallow checkout or a missing header
+/// file fails, and checks the tag. A release GitHub would refuse fails so
+/// before images or packages are pushed elsewhere.
+pub fn check_github(token: &str, input: &ReleaseInput) -> Result<()> {
+    release_sections(input)?;
+    let client = ReqwestClient::new();
+    let github = GitHubApi {
+        client: &client,
+        base: GITHUB_API,
+        token,
+        retry: Retry::default(),
+    };
+    check_release_target(&github, input).map(|_| ())
+}
+
+/// The non-empty sections of `input`'s release body, by name, as
+/// `[release.body]` lists them.
+fn release_sections<'a>(input: &'a ReleaseInput) -> Result<Vec<(&'a str, String)>> {
+    let download = release_download(input);
+    let mut sections = Vec::new();
+    for section in &input.body.sections {
+        let text = match section.as_str() {
+            "header" => match &input.body.header_file {
+                Some(file) => body::header(input.workspace_root, file)?,
+                None => String::new(),
+            },
+            "changelog" => changelog_body(
+                input.workspace_root,
+                input.changelog,
+                input.tag,
+                input.tag_pattern,
+                input.packages,
+            )?,
+            "artifacts" => {
+                body::artifacts_table(&input.manifest.packages, input.packages, &download)
+            }
+            "verification" => body::verification(
+                &input.manifest.packages,
+                input.packages,
+                input.owner,
+                input.repo,
+            ),
+            _ => String::new(),
+        };
+        if !text.is_empty() {
+            sections.push((section.as_str(), text));
+        }
+    }
+    Ok(sections)
+}
+
+/// Where `input`'s release assets are downloaded from.
+fn release_download(input: &ReleaseInput) -> String {
+    format!(
+        "https://github.com/{}/{}/releases/download/{}",
+        input.owner, input.repo, input.tag
+    )
+}
+
+/// [`publish_github`] through `github`, e.g. a mock or a test server.
+pub fn publish_github_with(github: &GitHubApi, input: &ReleaseInput) -> Result<()> {
+    let target_commitish = check_release_target(github, input)?;
+    let download = release_download(input);
+    let mut sections = release_sections(input)?;
+    let footer = match previous_tag(input.workspace_root, input.tag, input.tag_pattern) {
+        Ok(Some(prev)) => format!(
+            "\n\n**Full changelog**: https://github.com/{}/{}/compare/{prev}...{}",
+            input.owner, input.repo, input.tag
+        ),
+        _ => String::new(),
+    };
+    // only the changelog is cut; the other sections are short
+    let others: usize = sections
+        .iter()
+        .filter(|(name, _)| *name != "changelog")
+        .map(|(_, text)| text.chars().count() + 2)
+        .sum();
+    let max = input
+        .changelog
+        .max_length
+        .unwrap_or(GITHUB_BODY_LIMIT)
+        .min(GITHUB_BODY_LIMIT)
+        .saturating_sub(footer.chars().count() + others);
+    let note = format!(
+        "*Release notes truncated; see [{CHANGELOG_FILE}]({download}/{CHANGELOG_FILE}) for all of them.*"
     );
+    let full_notes = input.dist.join(CHANGELOG_FILE);
+    let mut truncated = false;
+    for (name, text) in &mut sections {
+        if *name != "changelog" {
+            continue;
+        }
+        if let Some(cut) = changelog::truncate(text, max, &note) {
+            // uploaded with the rest of the dist
+            fs::write(&full_notes, &text)?;
+            *text = cut;
+            truncated = true;
+        }
+    }
+    if !truncated && full_notes.exists() {
+        // left by an earlier release of this dist, e.g. another package's
+        fs::remove_file(&full_notes)?;
+    }
+    let mut body = sections
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    if body.is_empty() {
+        body = format!("Release {}", input.tag);
+    }
+    body.push_str(&footer);
+    let url = format!("/repos/{}/{}/releases", input.owner, input.repo);
     let payload = CreateRelease {
         tag_name: input.tag,
         name: input.name,
         body: &body,
         draft: input.draft,
         prerelease: input.prerelease,
+        make_latest: input.update_latest.then_some(if input.moves_latest() {
+            "true"
+        } else {
+            "false"
+        }),
+        target_commitish,
     };
-    let res = client
-        .post(&url)
-        .header(USER_AGENT, "shippo/1.0")
-        .header(ACCEPT, "application/vnd.github+json")
-        .header(AUTHORIZATION, format!("Bearer {}", token))
-        .json(&payload)
-        .send()
-        .context("failed to create release")?;
-    if !res.status().is_success() {
-        return Err(anyhow!("github release creation failed: {}", res.status()));
-    }
-    let release: serde_json::Value = res.json().context("release json parse")?;
+    let res = github.send(github.request(Method::POST, &url).json(&payload))?;
+    if !res.is_success() {
+        return Err(PublishError::ReleaseRejected { status: res.status });
+    }
+    let release: serde_json::Value = res.json()?;
     let upload_url = release
         .get("upload_url")
         .and_then(|v| v.as_str())
-        .ok_or_else(|| anyhow!("missing upload_url"))?
+        .ok_or(PublishError::MissingUploadUrl)?
         .replace("{?name,label}", "");
-    upload_artifacts(token, &upload_url, input)?;
+    upload_artifacts(github, &upload_url, input)?;
+    if input.moves_latest() {
+        move_latest_tag(github, input)?;
+    }
+    Ok(())
+}
+
+impl ReleaseInput<'_> {
+    fn moves_latest(&self) -> bool {
+        self.update_latest && !self.draft && !self.prerelease
+    }
+}
+
+/// The commit `input.tag` points at on GitHub, or `None` when GitHub
+/// doesn't have the tag.
+fn remote_tag_commit(github: &GitHubApi, input: &ReleaseInput) -> Result<Option<String>> {
+    let api = format!("/repos/{}/{}/git", input.owner, input.repo);
+    let Some(found) = github.get(&format!("{api}/ref/tags/{}", input.tag))? else {
+        return Ok(None);
+    };
+    let mut object = found["object"].clone();
+    // annotated tags point at a tag object first
+    while object["type"] == "tag" {
+        let sha = object["sha"].as_str().unwrap_or_default();
+        object = github
+            .get(&format!("{api}/tags/{sha}"))?
+            .map(|tag| tag["object"].clone())
+            .unwrap_or_default();
+    }
+    Ok(object["sha"].as_str().map(String::from))
+}
+
+/// Makes sure the release goes where the manifest's commit is: refuses a
+/// tag that already has a release, since published assets are never
+/// replaced, and one GitHub has at another commit. Returns the commit to
+/// create the tag at when GitHub doesn't have it yet.
+fn check_release_target<'a>(
+    github: &GitHubApi,
+    input: &ReleaseInput<'a>,
+) -> Result<Option<&'a str>> {
+    let releases = format!(
+        "/repos/{}/{}/releases/tags/{}",
+        input.owner, input.repo, input.tag
+    );
+    if let Some(existing) = github.get(&releases)? {
+        return Err(PublishError::ReleaseExists {
+            tag: input.tag.to_string(),
+            url: existing["html_url"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            immutable: existing["immutable"].as_bool().unwrap_or(false),
+        });
+    }
+    let remote = remote_tag_commit(github, input)?;
+    tag_target(
+        input.tag,
+        remote.as_deref(),
+        input.manifest.project.commit.as_deref(),
+    )
+}
+
+/// What to create the release at, given the commit GitHub has `tag` at
+/// (if any) and the one the artifacts were `built` from (if known).
+fn tag_target<'a>(
+    tag: &str,
+    remote: Option<&str>,
+    built: Option<&'a str>,
+) -> Result<Option<&'a str>> {
+    match (remote, built) {
+        (Some(remote), Some(built)) if remote != built => Err(PublishError::TagMismatch {
+            tag: tag.to_string(),
+            remote: remote.to_string(),
+            built: built.to_string(),
+        }),
+        (Some(_), _) => Ok(None),
+        (None, built) => Ok(built),
+    }
+}
+
+/// Points [`LATEST_TAG`] at the commit of the release tag, which GitHub
+/// has created by now if it didn't exist.
+fn move_latest_tag(github: &GitHubApi, input: &ReleaseInput) -> Result<()> {
+    let api = format!("/repos/{}/{}/git", input.owner, input.repo);
+    let sha = remote_tag_commit(github, input)?.ok_or(PublishError::LookupFailed {
+        status: StatusCode::NOT_FOUND,
+    })?;
+    set_latest_tag(github, &api, &sha)
+}
+
+/// Force-moves [`LATEST_TAG`] under the git data API `api` to `sha`,
+/// creating it only when GitHub reports it doesn't exist.
+fn set_latest_tag(github: &GitHubApi, api: &str, sha: &str) -> Result<()> {
+    let res = github.send(
+        github
+            .request(Method::PATCH, &format!("{api}/refs/tags/{LATEST_TAG}"))
+            .json(&serde_json::json!({ "sha": sha, "force": true })),
+    )?;
+    let missing = res.status == StatusCode::NOT_FOUND
+        // GitHub answers a PATCH of a missing ref with this 422
+        || (res.status == StatusCode::UNPROCESSABLE_ENTITY
+            && res.text().contains("Reference does not exist"));
+    let res =
+        if missing {
+            github.send(github.request(Method::POST, &format!("{api}/refs")).json(
+                &serde_json::json!({ "ref": format!("refs/tags/{LATEST_TAG}"), "sha": sha }),
+            ))?
+        } else {
+            res
+        };
+    if !res.is_success() {
+        return Err(PublishError::LatestTag {
+            status: res.status,
+            body: res.text(),
+        });
+    }
     Ok(())
 }
 
-fn upload_artifacts(token: &str, upload_url: &str, input: &ReleaseInput) -> Result<()> {
-    let client = Client::new();
-    for entry in std::fs::read_dir(input.dist)? {
+/// The files of `dist` uploaded as release assets, by name.
+fn dist_assets(dist: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut assets = Vec::new();
+    for entry in std::fs::read_dir(dist)? {
         let entry = entry?;
         if !entry.file_type()?.is_file() {
             continue;
         }
-        let path = entry.path();
         let name = entry.file_name().to_string_lossy().to_string();
+        // dotfiles are local state such as the dist lock, and the command
+        // log and a failed run's summary are the build's own record
+        if name.starts_with('.') || name == COMMANDS_FILE || name == SUMMARY_FILE {
+            continue;
+        }
+        assets.push((name, entry.path()));
+    }
+    assets.sort();
+    Ok(assets)
+}
+
+fn upload_artifacts(github: &GitHubApi, upload_url: &str, input: &ReleaseInput) -> Result<()> {
+    let labels = assets::asset_labels(&input.manifest.packages, input.assets);
+    for (name, path) in dist_assets(input.dist)? {
+        if input.skip_assets.contains(&name) {
+            continue;
+        }
         let encoded = utf8_percent_encode(&name, NON_ALPHANUMERIC).to_string();
-        let url = format!("{}?name={}", upload_url, encoded);
+        let mut url = format!("{}?name={}", upload_url, encoded);
+        if let Some(label) = labels.get(&name) {
+            url.push_str("&label=");
+            url.extend(utf8_percent_encode(label, NON_ALPHANUMERIC));
+        }
         let data = fs::read(&path)?;
-        let res = client
-            .post(&url)
-            .header(USER_AGENT, "shippo/1.0")
-            .header(ACCEPT, "application/vnd.github+json")
-            .header("Content-Type", "application/octet-stream")
-            .header(AUTHORIZATION, format!("Bearer {}", token))
-            .body(data)
-            .send()?;
-        if !res.status().is_success() {
-            let status = res.status();
-            let body = res.text().unwrap_or_default();
-            return Err(anyhow!("failed to upload {}: {} {}", name, status, body));
+        let bytes = data.len() as u64;
+        let res = github.send(
+            github
+                .request(Method::POST, &url)
+                .header(
+                    "Content-Type",
+                    assets::content_type(&name, &input.assets.content_types),
+                )
+                .body(data),
+        )?;
+        if !res.is_success() {
+            return Err(PublishError::UploadRejected {
+                filename: name,
+                status: res.status,
+                body: res.text(),
+            });
+        }
+        if let Some(events) = input.events {
+            events.emit(Event::AssetUploaded {
+                filename: name,
+                bytes,
+            });
         }
     }
     Ok(())
 }
 
-fn changelog_body(mode: &str, tag: &str) -> Result<String> {
-    let prev = latest_tag().unwrap_or_default();
-    if prev.is_empty() {
+/// Hands the packaged dist to a publisher plugin.
+pub fn publish_plugin(plugin: &Plugin, input: &ReleaseInput) -> Result<()> {
+    let params = PublishParams {
+        tag: input.tag.to_string(),
+        dist: input.dist.to_string_lossy().to_string(),
+        manifest: input
+            .dist
+            .join("manifest.json")
+            .to_string_lossy()
+            .to_string(),
+    };
+    let _: serde_json::Value = plugin.call("publish", &params)?;
+    Ok(())
+}
+
+/// Release notes for `tag`: the commits since the previous release tag
+/// matching `pattern`, rendered per [`changelog::render`]. With several
+/// `packages` (name to path), each gets a section of the commits touching
+/// its path. The tag itself usually doesn't exist yet, so the range ends at
+/// `HEAD` then. Fails on shallow clones rather than returning a truncated
+/// changelog.
+pub fn changelog_body(
+    repo: &Path,
+    config: &ChangelogConfig,
+    tag: &str,
+    pattern: Option<&str>,
+    packages: &BTreeMap<String, String>,
+) -> Result<String> {
+    if is_shallow(repo) {
+        return Err(PublishError::ShallowCheckout(repo.to_path_buf()));
+    }
+    let Ok(Some(prev)) = previous_tag(repo, tag, pattern) else {
+        return Ok(format!("Release {}", tag));
+    };
+    let log = |path: &str| {
+        commits_touching(repo, path, &prev, tag)
+            .or_else(|_| commits_touching(repo, path, &prev, "HEAD"))
+            .unwrap_or_default()
+    };
+    let body = if packages.len() <= 1 {
+        let path = packages.values().next().map_or(".", String::as_str);
+        changelog::render(&log(path), config)
+    } else {
+        packages
+            .iter()
+            .filter_map(|(name, path)| {
+                let commits = log(path);
+                (!commits.is_empty())
+                    .then(|| format!("## {name}\n\n{}", changelog::render(&commits, config)))
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+    if body.is_empty() {
         return Ok(format!("Release {}", tag));
     }
-    Ok(changelog_between(&prev, tag, mode).unwrap_or_else(|_| format!("Release {}", tag)))
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_target_guards_the_built_commit() {
+        assert_eq!(
+            tag_target("v1.0.0", None, Some("abc")).unwrap(),
+            Some("abc")
+        );
+        assert_eq!(
+            tag_target("v1.0.0", Some("abc"), Some("abc")).unwrap(),
+            None
+        );
+        assert_eq!(tag_target("v1.0.0", Some("abc"), None).unwrap(), None);
+        let err = tag_target("v1.0.0", Some("def"), Some("abc")).unwrap_err();
+        assert!(matches!(err, PublishError::TagMismatch { .. }));
+        assert!(err.to_string().contains("built from abc"));
+    }
+
+    #[test]
+    fn test_latest_tag_created_only_when_missing() {
+        let client = http::MockHttpClient::new();
+        let github = GitHubApi {
+            client: &client,
+            base: GITHUB_API,
+            token: "t",
+            retry: Retry {
+                attempts: 1,
+                backoff: std::time::Duration::ZERO,
+            },
+        };
+        let api = "/repos/acme/tool/git";
+        let patch = "/repos/acme/tool/git/refs/tags/latest";
+        client.respond(
+            Method::PATCH,
+            patch,
+            HttpResponse::json_body(200, &serde_json::json!({})),
+        );
+        set_latest_tag(&github, api, "abc").unwrap();
+        assert_eq!(client.requests().len(), 1);
+
+        // a refused update is not a reason to create the tag
+        client.respond(
+            Method::PATCH,
+            patch,
+            HttpResponse::json_body(403, &serde_json::json!({ "message": "forbidden" })),
+        );
+        let err = set_latest_tag(&github, api, "abc").unwrap_err();
+        assert!(matches!(err, PublishError::LatestTag { .. }), "{err}");
+        assert_eq!(client.requests().len(), 2);
+
+        client
+            .respond(
+                Method::PATCH,
+                patch,
+                HttpResponse::json_body(
+                    422,
+                    &serde_json::json!({ "message": "Reference does not exist" }),
+                ),
+            )
+            .respond(
+                Method::POST,
+                "/repos/acme/tool/git/refs",
+                HttpResponse::json_body(201, &serde_json::json!({})),
+            );
+        set_latest_tag(&github, api, "abc").unwrap();
+        let requests = client.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[3].method, Method::POST);
+    }
+
+    #[test]
+    fn test_dist_assets_leave_out_local_state() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "app.tar.gz",
+            "SHA256SUMS",
+            ".dist.lock",
+            COMMANDS_FILE,
+            SUMMARY_FILE,
+        ] {
+            fs::write(dir.path().join(name), "x").unwrap();
+        }
+        fs::create_dir(dir.path().join("build")).unwrap();
+        let names: Vec<_> = dist_assets(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["SHA256SUMS", "app.tar.gz"]);
+    }
 }
@@ -0,0 +1,336 @@
+//! `npm publish` for `[release.npm]`: each Node package's `package.json`
+//! must carry the release version, and versions already on the registry
+//! are left alone so a re-run release doesn't fail.
+
+use std::fs;
+use std::path::Path;
+
+use shippo_core::exec::{CommandSpec, Executor};
+use shippo_core::{ManifestNpm, NpmConfig};
+
+use crate::{PublishError, Result};
+
+/// The variable the generated `.npmrc` reads the token from.
+const TOKEN_VAR: &str = "NODE_AUTH_TOKEN";
+
+/// `name` and `version` from `dir/package.json`.
+pub fn package_json(dir: &Path) -> Result<(String, String)> {
+    let path = dir.join("package.json");
+    let error = |message: String| PublishError::Npm {
+        package: path.display().to_string(),
+        message,
+    };
+    let text = fs::read_to_string(&path).map_err(|e| error(e.to_string()))?;
+    let json: serde_json::Value = serde_json::from_str(&text).map_err(|e| error(e.to_string()))?;
+    if json["private"].as_bool() == Some(true) {
+        return Err(error(
+            "is private; remove it from release.npm.packages".into(),
+        ));
+    }
+    match (json["name"].as_str(), json["version"].as_str()) {
+        (Some(name), Some(version)) => Ok((name.to_string(), version.to_string())),
+        _ => Err(error("needs a name and a version".into())),
+    }
+}
+
+/// `config.tag`, or `next` for prereleases and `latest` otherwise.
+pub fn dist_tag(config: &NpmConfig, prerelease: bool) -> &str {
+    match &config.tag {
+        Some(tag) => tag,
+        None if prerelease => "next",
+        None => "latest",
+    }
+}
+
+/// Why npm can't sign provenance here, if it can't: npm needs the CI
+/// run's OIDC token, which GitHub Actions only offers to jobs with
+/// `id-token: write` and GitLab CI in a `SIGSTORE_ID_TOKEN` id token.
+fn missing_oidc(env: impl Fn(&str) -> Option<String>) -> Option<&'static str> {
+    let github = [
+        "ACTIONS_ID_TOKEN_REQUEST_URL",
+        "ACTIONS_ID_TOKEN_REQUEST_TOKEN",
+    ];
+    if github.iter().all(|v| env(v).is_some()) || env("SIGSTORE_ID_TOKEN").is_some() {
+        return None;
+    }
+    if env("GITHUB_ACTIONS").is_some() {
+        return Some("grant the job `permissions: id-token: write` for npm provenance");
+    }
+    Some("npm provenance needs an OIDC token: run in GitHub Actions with `id-token: write`, or set a `SIGSTORE_ID_TOKEN` id token in GitLab CI")
+}
+
+/// The transparency-log link npm prints after publishing with
+/// `--provenance`.
+fn provenance_link(output: &str) -> Option<String> {
+    let (_, rest) = output.split_once("transparency log: ")?;
+    rest.split_whitespace().next().map(str::to_string)
+}
+
+/// The `.npmrc` line giving `registry` the token in [`TOKEN_VAR`], which
+/// npm expands itself so the token is never written to disk.
+fn auth_line(registry: &str) -> String {
+    let host = registry
+        .split_once("://")
+        .map_or(registry, |(_, rest)| rest)
+        .trim_end_matches('/');
+    format!("//{host}/:_authToken=${{{TOKEN_VAR}}}\n")
+}
+
+/// What [`publish_npm`] did, with what to record in the manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NpmPublished {
+    New(ManifestNpm),
+    /// The registry already had the version, e.g. from an earlier run of
+    /// the release; its provenance link isn't known.
+    Existing(ManifestNpm),
+}
+
+impl NpmPublished {
+    pub fn record(&self) -> &ManifestNpm {
+        match self {
+            Self::New(record) | Self::Existing(record) => record,
+        }
+    }
+}
+
+/// Checks, without publishing, that the package in `dir` is at `version`,
+/// the token is set and, for provenance, the CI run offers an OIDC token;
+/// run before anything of the release is published. Returns the token.
+pub fn check_npm(
+    config: &NpmConfig,
+    dir: &Path,
+    version: &str,
+    executor: &dyn Executor,
+) -> Result<String> {
+    let (name, found) = package_json(dir)?;
+    let error = |message: String| PublishError::Npm {
+        package: name.clone(),
+        message,
+    };
+    if found != version {
+        return Err(error(format!(
+            "package.json has version {found}, but the release is {version}"
+        )));
+    }
+    let token = executor
+        .var(&config.token_env)
+        .ok_or_else(|| error(format!("{} is not set", config.token_env)))?;
+    if config.provenance {
+        if let Some(problem) = missing_oidc(|v| executor.var(v).filter(|s| !s.is_empty())) {
+            return Err(error(problem.into()));
+        }
+    }
+    Ok(token)
+}
+
+/// Publishes the package in `dir`, which must be at `version` (without a
+/// leading `v`), unless the registry already has that version.
+pub fn publish_npm(
+    config: &NpmConfig,
+    dir: &Path,
+    version: &str,
+    prerelease: bool,
+    executor: &dyn Executor,
+) -> Result<NpmPublished> {
+    let token = check_npm(config, dir, version, executor)?;
+    let (name, _) = package_json(dir)?;
+    let error = |message: String| PublishError::Npm {
+        package: name.clone(),
+        message,
+    };
+    let userconfig = tempfile::Builder::new().suffix(".npmrc").tempfile()?;
+    fs::write(userconfig.path(), auth_line(&config.registry))?;
+    let npm = |args: &[&str]| {
+        let mut cmd = CommandSpec::new("npm");
+        cmd.args(args)
+            .args(["--registry", &config.registry])
+            .current_dir(dir)
+            .env("NPM_CONFIG_USERCONFIG", userconfig.path().to_string_lossy())
+            .env(TOKEN_VAR, &token);
+        executor.output(&cmd).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => error("npm is not installed".into()),
+            _ => PublishError::Io(e),
+        })
+    };
+
+    let spec = format!("{name}@{version}");
+    // a 404 for an unpublished package fails; an unknown version prints nothing
+    let existing = npm(&["view", &spec, "version"])?;
+    if existing.success() && existing.stdout.trim() == version {
+        return Ok(NpmPublished::Existing(ManifestNpm {
+            package: spec,
+            registry: config.registry.clone(),
+            provenance: None,
+        }));
+    }
+    let mut publish = vec!["publish", "--tag", dist_tag(config, prerelease)];
+    if let Some(access) = &config.access {
+        publish.extend(["--access", access.as_str()]);
+    }
+    if config.provenance {
+        publish.push("--provenance");
+    }
+    let output = npm(&publish)?;
+    if !output.success() {
+        return Err(error(format!(
+            "npm publish failed ({}): {}",
+            output.status(),
+            output.stderr.trim()
+        )));
+    }
+    let provenance = provenance_link(&output.stderr).or_else(|| provenance_link(&output.stdout));
+    if config.provenance && provenance.is_none() {
+        return Err(error(
+            "npm publish printed no provenance transparency-log entry".into(),
+        ));
+    }
+    Ok(NpmPublished::New(ManifestNpm {
+        package: spec,
+        registry: config.registry.clone(),
+        provenance,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shippo_core::exec::{CommandOutput, MockExecutor};
+
+    #[test]
+    fn test_publish_skips_published_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "@acme/cli", "version": "1.2.0-rc.1"}"#,
+        )
+        .unwrap();
+        let config = NpmConfig {
+            registry: "https://npm.example.com/api/".into(),
+            tag: None,
+            access: Some("public".into()),
+            packages: Vec::new(),
+            token_env: "SHIPPO_TEST_NPM_TOKEN".into(),
+            provenance: false,
+        };
+        assert_eq!(
+            auth_line(&config.registry),
+            "//npm.example.com/api/:_authToken=${NODE_AUTH_TOKEN}\n"
+        );
+        let executor = MockExecutor::new();
+        executor.set_var("SHIPPO_TEST_NPM_TOKEN", "npm_secret");
+        executor.respond(
+            "npm",
+            CommandOutput {
+                code: Some(1),
+                stderr: "npm error code E404".into(),
+                ..Default::default()
+            },
+        );
+        let published = publish_npm(&config, dir.path(), "1.2.0-rc.1", true, &executor).unwrap();
+        let NpmPublished::New(published) = published else {
+            panic!("not published: {published:?}");
+        };
+        assert_eq!(published.package, "@acme/cli@1.2.0-rc.1");
+        assert_eq!(published.provenance, None);
+        let calls = executor.calls();
+        assert_eq!(
+            calls[1].args,
+            [
+                "publish",
+                "--tag",
+                "next",
+                "--access",
+                "public",
+                "--registry",
+                "https://npm.example.com/api/"
+            ]
+        );
+        assert_eq!(calls[1].env[TOKEN_VAR], "npm_secret");
+        assert_eq!(calls[1].cwd.as_deref(), Some(dir.path()));
+
+        let executor = MockExecutor::new();
+        executor.set_var("SHIPPO_TEST_NPM_TOKEN", "npm_secret");
+        executor.respond(
+            "npm",
+            CommandOutput {
+                code: Some(0),
+                stdout: "1.2.0-rc.1\n".into(),
+                ..Default::default()
+            },
+        );
+        // a re-run records the version it finds on the registry
+        let published = publish_npm(&config, dir.path(), "1.2.0-rc.1", true, &executor).unwrap();
+        assert_eq!(
+            published,
+            NpmPublished::Existing(ManifestNpm {
+                package: "@acme/cli@1.2.0-rc.1".into(),
+                registry: "https://npm.example.com/api/".into(),
+                provenance: None,
+            })
+        );
+        assert_eq!(executor.calls().len(), 1);
+
+        let err = publish_npm(&config, dir.path(), "1.2.0", false, &executor);
+        assert!(err.unwrap_err().to_string().contains("version 1.2.0-rc.1"));
+        let err = publish_npm(
+            &config,
+            dir.path(),
+            "1.2.0-rc.1",
+            true,
+            &MockExecutor::new(),
+        );
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("SHIPPO_TEST_NPM_TOKEN is not set"));
+    }
+
+    #[test]
+    fn test_provenance() {
+        let env = |vars: &'static [&'static str]| {
+            move |name: &str| vars.contains(&name).then(|| "x".to_string())
+        };
+        assert_eq!(
+            missing_oidc(env(&[
+                "ACTIONS_ID_TOKEN_REQUEST_URL",
+                "ACTIONS_ID_TOKEN_REQUEST_TOKEN"
+            ])),
+            None
+        );
+        assert_eq!(missing_oidc(env(&["SIGSTORE_ID_TOKEN"])), None);
+        assert!(missing_oidc(env(&["GITHUB_ACTIONS"]))
+            .unwrap()
+            .contains("id-token: write"));
+        assert!(missing_oidc(env(&[])).is_some());
+
+        // checked before anything is published
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "@acme/cli", "version": "1.2.0"}"#,
+        )
+        .unwrap();
+        let config = NpmConfig {
+            registry: "https://registry.npmjs.org/".into(),
+            tag: None,
+            access: None,
+            packages: Vec::new(),
+            token_env: "NPM_TOKEN".into(),
+            provenance: true,
+        };
+        let executor = MockExecutor::new();
+        executor.set_var(&config.token_env, "npm_secret");
+        let err = check_npm(&config, dir.path(), "1.2.0", &executor).unwrap_err();
+        assert!(err.to_string().contains("OIDC token"), "{err}");
+        executor.set_var("SIGSTORE_ID_TOKEN", "eyJ...");
+        assert!(check_npm(&config, dir.path(), "1.2.0", &executor).is_ok());
+        assert!(executor.calls().is_empty());
+
+        let stderr = "npm notice Publishing to https://registry.npmjs.org/ with tag latest\nnpm notice publish Signed provenance statement with source and build information from GitHub Actions\nnpm notice publish Provenance statement published to transparency log: https://search.sigstore.dev/?logIndex=123456\n+ @acme/cli@1.2.0\n";
+        assert_eq!(
+            provenance_link(stderr).as_deref(),
+            Some("https://search.sigstore.dev/?logIndex=123456")
+        );
+        assert_eq!(provenance_link("+ @acme/cli@1.2.0"), None);
+    }
+}
@@ -0,0 +1,270 @@
+//! `twine upload` for `[release.pypi]`: the wheels and sdists a Python
+//! package built for the release, to PyPI or a private index.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use shippo_core::exec::{CommandSpec, Executor};
+use shippo_core::PypiConfig;
+
+use crate::{PublishError, Result};
+
+/// `version` in PEP 440's normal form, the way wheel and sdist names spell
+/// it: `v1.2.0-beta.1` is `1.2.0b1` and `1.0-1` is `1.0.post1`. `None`
+/// when it isn't a PEP 440 version.
+fn normalized(version: &str) -> Option<String> {
+    let lower = version.trim().to_ascii_lowercase();
+    let version = lower.strip_prefix('v').unwrap_or(&lower);
+    let (mut rest, local) = match version.split_once('+') {
+        Some((public, local)) => (public, Some(local)),
+        None => (version, None),
+    };
+    let mut out = String::new();
+    if let Some((epoch, after)) = rest.split_once('!') {
+        let (epoch, left) = number(epoch)?;
+        if !left.is_empty() {
+            return None;
+        }
+        if epoch != 0 {
+            out.push_str(&format!("{epoch}!"));
+        }
+        rest = after;
+    }
+    let mut release = Vec::new();
+    loop {
+        let (n, after) = number(rest)?;
+        release.push(n.to_string());
+        rest = after;
+        match rest.strip_prefix('.') {
+            Some(after) if after.starts_with(|c: char| c.is_ascii_digit()) => rest = after,
+            _ => break,
+        }
+    }
+    out.push_str(&release.join("."));
+    let pre = [
+        ("alpha", "a"),
+        ("a", "a"),
+        ("beta", "b"),
+        ("b", "b"),
+        ("preview", "rc"),
+        ("pre", "rc"),
+        ("rc", "rc"),
+        ("c", "rc"),
+    ];
+    if let Some((label, n, after)) = labelled(rest, &pre) {
+        out.push_str(&format!("{label}{n}"));
+        rest = after;
+    }
+    let post = [("post", "post"), ("rev", "post"), ("r", "post")];
+    if let Some((n, after)) = rest.strip_prefix('-').and_then(number) {
+        out.push_str(&format!(".post{n}"));
+        rest = after;
+    } else if let Some((_, n, after)) = labelled(rest, &post) {
+        out.push_str(&format!(".post{n}"));
+        rest = after;
+    }
+    if let Some((_, n, after)) = labelled(rest, &[("dev", "dev")]) {
+        out.push_str(&format!(".dev{n}"));
+        rest = after;
+    }
+    if !rest.is_empty() {
+        return None;
+    }
+    if let Some(local) = local {
+        let mut segments = Vec::new();
+        for segment in local.split(['.', '-', '_']) {
+            if segment.is_empty() || !segment.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return None;
+            }
+            segments.push(match number(segment) {
+                Some((n, "")) => n.to_string(),
+                _ => segment.to_string(),
+            });
+        }
+        out.push('+');
+        out.push_str(&segments.join("."));
+    }
+    Some(out)
+}
+
+/// The number `s` starts with, without leading zeros, and the rest.
+fn number(s: &str) -> Option<(u64, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    Some((s[..end].parse().ok()?, &s[end..]))
+}
+
+/// A `[.-_]label[.-_]N` part of a version starting `s`, with one of
+/// `labels` (spelling, normal form): the normal form, `N` (0 when left
+/// out) and the rest.
+fn labelled<'a>(
+    s: &'a str,
+    labels: &[(&str, &'static str)],
+) -> Option<(&'static str, u64, &'a str)> {
+    let s = s.strip_prefix(['.', '-', '_']).unwrap_or(s);
+    let (normal, s) = labels
+        .iter()
+        .find_map(|(spelling, normal)| Some((*normal, s.strip_prefix(spelling)?)))?;
+    let digits = s.strip_prefix(['.', '-', '_']).unwrap_or(s);
+    Some(match number(digits) {
+        Some((n, rest)) => (normal, n, rest),
+        None => (normal, 0, s),
+    })
+}
+
+/// The wheels and sdists in `dir` for `version`; files of other versions
+/// are left out.
+pub fn dist_files(dir: &Path, version: &str) -> io::Result<Vec<PathBuf>> {
+    let wanted = normalized(version);
+    let mut files = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(files),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        // `{name}-{version}-{tags}.whl` and `{name}-{version}.tar.gz`, with
+        // `-` in the name escaped as `_`
+        let found = if let Some(stem) = name.strip_suffix(".whl") {
+            stem.split('-').nth(1)
+        } else if let Some(stem) = name.strip_suffix(".tar.gz") {
+            stem.rsplit_once('-').map(|(_, v)| v)
+        } else {
+            None
+        };
+        if wanted.is_some() && found.and_then(normalized) == wanted {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Uploads `files` of `package` with twine, authenticated as `__token__`
+/// with the token in `config.token_env`.
+pub fn publish_pypi(
+    config: &PypiConfig,
+    package: &str,
+    files: &[PathBuf],
+    executor: &dyn Executor,
+) -> Result<()> {
+    let error = |message: String| PublishError::Pypi {
+        package: package.to_string(),
+        message,
+    };
+    let token = executor
+        .var(&config.token_env)
+        .ok_or_else(|| error(format!("{} is not set", config.token_env)))?;
+    let mut cmd = CommandSpec::new("twine");
+    cmd.args([
+        "upload",
+        "--non-interactive",
+        "--repository-url",
+        &config.repository_url,
+    ]);
+    if config.skip_existing {
+        cmd.arg("--skip-existing");
+    }
+    cmd.args(files.iter().map(|f| f.to_string_lossy()))
+        .env("TWINE_USERNAME", "__token__")
+        .env("TWINE_PASSWORD", token);
+    let output = executor.output(&cmd).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => error("twine is not installed; pip install twine".into()),
+        _ => PublishError::Io(e),
+    })?;
+    if !output.success() {
+        return Err(error(format!(
+            "twine upload failed ({}): {}",
+            output.status(),
+            output.stderr.trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shippo_core::exec::MockExecutor;
+
+    #[test]
+    fn test_normalized() {
+        for (version, normal) in [
+            ("v1.2.0-beta.1", "1.2.0b1"),
+            ("1.2.0-rc.1", "1.2.0rc1"),
+            ("1.0.0-alpha", "1.0.0a0"),
+            ("1.0.0-preview.2", "1.0.0rc2"),
+            ("01.002.0", "1.2.0"),
+            ("0!1.0", "1.0"),
+            ("2!1.0.post1.dev0", "2!1.0.post1.dev0"),
+            ("1.0-1", "1.0.post1"),
+            ("1.0rev2", "1.0.post2"),
+            ("1.0.0+Build_05", "1.0.0+build.5"),
+        ] {
+            assert_eq!(normalized(version).as_deref(), Some(normal), "{version}");
+        }
+        assert_ne!(normalized("1.10.0"), normalized("11.0.0"));
+        assert_ne!(normalized("1.2.0b1"), normalized("1.2.0"));
+        for bad in ["", "nightly", "1.0.0-", "1.0..0", "1.0.0+", "1.0.0-beta.x"] {
+            assert_eq!(normalized(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_dist_files_for_version_uploaded_with_twine() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "my_tool-1.2.0b1-py3-none-any.whl",
+            "my_tool-1.2.0b1.tar.gz",
+            "my_tool-1.1.0-py3-none-any.whl",
+            "my_tool-1.1.0.tar.gz",
+            "my_tool-12.0b1-py3-none-any.whl",
+            ".gitignore",
+        ] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        let files = dist_files(dir.path(), "v1.2.0-beta.1").unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            ["my_tool-1.2.0b1-py3-none-any.whl", "my_tool-1.2.0b1.tar.gz"]
+        );
+        assert!(dist_files(&dir.path().join("missing"), "1.0.0")
+            .unwrap()
+            .is_empty());
+
+        let config = PypiConfig {
+            repository_url: "https://pypi.example.com/legacy/".into(),
+            packages: Vec::new(),
+            token_env: "SHIPPO_TEST_PYPI_TOKEN".into(),
+            skip_existing: true,
+        };
+        let executor = MockExecutor::new();
+        assert!(publish_pypi(&config, "my-tool", &files, &executor).is_err());
+        executor.set_var("SHIPPO_TEST_PYPI_TOKEN", "pypi-secret");
+        publish_pypi(&config, "my-tool", &files, &executor).unwrap();
+        let calls = executor.calls();
+        assert_eq!(calls[0].program, "twine");
+        assert_eq!(
+            calls[0].args[..5],
+            [
+                "upload",
+                "--non-interactive",
+                "--repository-url",
+                "https://pypi.example.com/legacy/",
+                "--skip-existing"
+            ]
+        );
+        assert_eq!(calls[0].args.len(), 7);
+        assert_eq!(calls[0].env["TWINE_USERNAME"], "__token__");
+        assert_eq!(calls[0].env["TWINE_PASSWORD"], "pypi-secret");
+    }
+}
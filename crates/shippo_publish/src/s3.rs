@@ -0,0 +1,276 @@
+//! `provider = "s3"`: the dist uploaded to a bucket with `aws s3 cp`, for
+//! AWS S3 or an S3-compatible store. Credentials come from the AWS CLI's
+//! usual sources: the environment, a profile or an instance role.
+
+use shippo_core::events::Event;
+use shippo_core::exec::{CommandOutput, CommandSpec, Executor};
+use shippo_core::S3Config;
+
+use crate::{assets, dist_assets, PublishError, ReleaseInput, Result};
+
+/// Written last, so a reader that finds them finds the whole release.
+const LAST: [&str; 2] = ["SHA256SUMS", "manifest.json"];
+
+/// The options every `aws` call gets from `config`.
+fn common_args(config: &S3Config) -> Vec<String> {
+    let mut args = Vec::new();
+    for (flag, value) in [
+        ("--region", &config.region),
+        ("--endpoint-url", &config.endpoint),
+        ("--profile", &config.profile),
+    ] {
+        if let Some(value) = value {
+            args.extend([flag.to_string(), value.clone()]);
+        }
+    }
+    args
+}
+
+fn error(config: &S3Config, message: String) -> PublishError {
+    PublishError::S3 {
+        bucket: format!("s3://{}", config.bucket),
+        message,
+    }
+}
+
+/// Runs `aws <command> <args>` with `config`'s options.
+fn aws(
+    config: &S3Config,
+    executor: &dyn Executor,
+    command: &str,
+    args: &[&str],
+) -> Result<CommandOutput> {
+    let mut cmd = CommandSpec::new("aws");
+    cmd.arg(command).args(args).args(common_args(config));
+    let output = executor.output(&cmd).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => error(config, "the AWS CLI (aws) is not installed".into()),
+        _ => PublishError::Io(e),
+    })?;
+    if !output.success() {
+        return Err(error(
+            config,
+            format!(
+                "aws {command} {} failed ({}): {}",
+                args[0],
+                output.status(),
+                output.stderr.trim()
+            ),
+        ));
+    }
+    Ok(output)
+}
+
+/// Refuses a release whose key prefix already has objects, since a
+/// published release's files are never replaced, as on GitHub.
+pub fn check_s3(
+    config: &S3Config,
+    input: &ReleaseInput,
+    version: &str,
+    executor: &dyn Executor,
+) -> Result<()> {
+    let key = config.key_prefix(input.tag, version);
+    let output = aws(
+        config,
+        executor,
+        "s3api",
+        &[
+            "list-objects-v2",
+            "--bucket",
+            &config.bucket,
+            "--prefix",
+            &format!("{key}/"),
+            "--max-items",
+            "1",
+            "--query",
+            "Contents[].Key",
+            "--output",
+            "text",
+        ],
+    )?;
+    match output.stdout.trim() {
+        "" | "None" => Ok(()),
+        found => Err(error(
+            config,
+            format!(
+                "{} already has a release ({found}), so its files are left alone",
+                input.tag
+            ),
+        )),
+    }
+}
+
+/// Uploads the dist files of `input`'s release under its key prefix and
+/// returns the prefix's `s3://` URL, after [`check_s3`]. With
+/// `update_latest`, a full release is then copied over the prefix for
+/// `latest` (files the release doesn't have are removed there); drafts
+/// and prereleases leave it alone.
+pub fn publish_s3(
+    config: &S3Config,
+    input: &ReleaseInput,
+    version: &str,
+    executor: &dyn Executor,
+) -> Result<String> {
+    check_s3(config, input, version, executor)?;
+    let bucket = format!("s3://{}", config.bucket);
+    let s3 = |args: &[&str]| {
+        let mut args = args.to_vec();
+        args.push("--only-show-errors");
+        aws(config, executor, "s3", &args).map(|_| ())
+    };
+
+    let prefix = format!("{bucket}/{}", config.key_prefix(input.tag, version));
+    let mut files = dist_assets(input.dist)?;
+    files.retain(|(name, _)| !input.skip_assets.contains(name));
+    files.sort_by_key(|(name, _)| LAST.contains(&name.as_str()));
+    for (name, path) in files {
+        let bytes = path.metadata()?.len();
+        let content_type = assets::content_type(&name, &input.assets.content_types);
+        let mut args = vec![
+            "cp".to_string(),
+            path.to_string_lossy().into_owned(),
+            format!("{prefix}/{name}"),
+            "--content-type".to_string(),
+            content_type,
+        ];
+        if let Some(acl) = &config.acl {
+            args.extend(["--acl".to_string(), acl.clone()]);
+        }
+        s3(&args.iter().map(String::as_str).collect::<Vec<_>>())?;
+        if let Some(events) = input.events {
+            events.emit(Event::AssetUploaded {
+                filename: name,
+                bytes,
+            });
+        }
+    }
+
+    let latest_tag = input.tag.replace(version, "latest");
+    let latest = format!("{bucket}/{}", config.key_prefix(&latest_tag, "latest"));
+    if input.moves_latest() && latest != prefix {
+        let mut args = vec!["sync", &prefix, &latest, "--delete"];
+        if let Some(acl) = &config.acl {
+            args.extend(["--acl", acl.as_str()]);
+        }
+        s3(&args)?;
+    }
+    Ok(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    use shippo_core::exec::MockExecutor;
+    use shippo_core::{ChangelogConfig, Manifest, ReleaseAssetsConfig, ReleaseBodyConfig};
+
+    #[test]
+    fn test_upload_dist_and_sync_latest() {
+        let dist = tempfile::tempdir().unwrap();
+        for name in [
+            "manifest.json",
+            "SHA256SUMS",
+            "cli-linux.tar.gz",
+            "agent-linux.tar.gz",
+            ".shippo-dist.lock",
+        ] {
+            fs::write(dist.path().join(name), "x").unwrap();
+        }
+        let manifest = Manifest::from_json(
+            r#"{
+                "shippo_version": "0.1.0",
+                "generated_at": "2026-01-01T00:00:00Z",
+                "project": {"repo_url": null, "commit": null, "version": "v1.2.0"},
+                "packages": [],
+                "tooling": {"rust": null, "go": null, "node": null, "python": null},
+                "build_env": {"os": "linux", "arch": "x86_64", "ci": false}
+            }"#,
+        )
+        .unwrap();
+        let config = S3Config {
+            bucket: "acme-releases".into(),
+            prefix: "/tools/{tag}/".into(),
+            region: Some("eu-west-1".into()),
+            endpoint: None,
+            profile: None,
+            acl: None,
+        };
+        let (changelog, body, assets) = (
+            ChangelogConfig::default(),
+            ReleaseBodyConfig::default(),
+            ReleaseAssetsConfig::default(),
+        );
+        let packages = BTreeMap::new();
+        let skip = ["agent-linux.tar.gz".to_string()];
+        let input = ReleaseInput {
+            owner: "",
+            repo: "",
+            tag: "cli-v1.2.0",
+            name: "cli-v1.2.0",
+            draft: false,
+            prerelease: false,
+            update_latest: true,
+            changelog: &changelog,
+            body: &body,
+            tag_pattern: None,
+            packages: &packages,
+            workspace_root: dist.path(),
+            dist: dist.path(),
+            skip_assets: &skip,
+            assets: &assets,
+            manifest: &manifest,
+            events: None,
+        };
+        let executor = MockExecutor::new();
+        let url = publish_s3(&config, &input, "v1.2.0", &executor).unwrap();
+        assert_eq!(url, "s3://acme-releases/tools/cli-v1.2.0");
+        let listed = &executor.calls()[0];
+        assert_eq!(
+            listed.args[..6],
+            [
+                "s3api",
+                "list-objects-v2",
+                "--bucket",
+                "acme-releases",
+                "--prefix",
+                "tools/cli-v1.2.0/"
+            ]
+        );
+        let calls: Vec<_> = executor.calls()[1..]
+            .iter()
+            .map(|c| c.args[1..4].join(" "))
+            .collect();
+        let path = |name: &str| dist.path().join(name).to_string_lossy().into_owned();
+        assert_eq!(
+            calls,
+            [
+                format!("cp {} {url}/cli-linux.tar.gz", path("cli-linux.tar.gz")),
+                format!("cp {} {url}/SHA256SUMS", path("SHA256SUMS")),
+                format!("cp {} {url}/manifest.json", path("manifest.json")),
+                format!("sync {url} s3://acme-releases/tools/cli-latest"),
+            ]
+        );
+        assert_eq!(executor.calls()[4].args[4], "--delete");
+        let first = &executor.calls()[1];
+        assert_eq!(first.args[4..6], ["--content-type", "application/gzip"]);
+        assert!(first
+            .args
+            .ends_with(&["--region".into(), "eu-west-1".into()]));
+        assert!(first.args.contains(&"--only-show-errors".into()));
+
+        // a release already in the bucket is left alone
+        let executor = MockExecutor::new();
+        executor.respond(
+            "aws",
+            CommandOutput {
+                code: Some(0),
+                stdout: "tools/cli-v1.2.0/SHA256SUMS\n".into(),
+                stderr: String::new(),
+            },
+        );
+        let err = publish_s3(&config, &input, "v1.2.0", &executor).unwrap_err();
+        assert!(err.to_string().contains("already has a release"), "{err}");
+        assert_eq!(executor.calls().len(), 1);
+    }
+}
@@ -0,0 +1,232 @@
+//! Scoop manifests for `[release.scoop]`: rendered from the manifest's
+//! Windows archives, or from a Tera template, and committed to a bucket
+//! repository, through a pull request by default.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+use shippo_core::{targets, ManifestPackage, ScoopConfig};
+
+use crate::tap::{commit_file, render_template, TapCommit};
+use crate::{PublishError, Result};
+
+/// A Scoop app manifest, `<name>.json` in a bucket.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoopManifest {
+    /// Without a leading `v`.
+    pub version: String,
+    pub description: String,
+    pub homepage: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// By Scoop architecture: `64bit`, `32bit` or `arm64`.
+    pub architecture: BTreeMap<&'static str, ScoopArchive>,
+    /// Executables in the archive to put on `PATH`.
+    pub bin: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScoopArchive {
+    pub url: String,
+    /// The archive's sha256.
+    pub hash: String,
+}
+
+/// The Windows archives of `package` by Scoop architecture, downloaded
+/// from the GitHub release `tag`. Zips are preferred over tar.gz archives.
+pub fn scoop_archives(
+    package: &ManifestPackage,
+    owner: &str,
+    repo: &str,
+    tag: &str,
+) -> BTreeMap<&'static str, ScoopArchive> {
+    let mut archives = BTreeMap::new();
+    for target in &package.targets {
+        let info = match target.target.as_str() {
+            "native" => targets::host(),
+            name => targets::resolve(name),
+        };
+        let arch = match info.and_then(|i| i.go) {
+            Some(("windows", "amd64")) => "64bit",
+            Some(("windows", "386")) => "32bit",
+            Some(("windows", "arm64")) => "arm64",
+            _ => continue,
+        };
+        let archive = target
+            .artifacts
+            .iter()
+            .find(|a| a.filename.ends_with(".zip"))
+            .or_else(|| {
+                target
+                    .artifacts
+                    .iter()
+                    .find(|a| a.filename.ends_with(".tar.gz"))
+            });
+        let Some(archive) = archive else {
+            continue;
+        };
+        archives.entry(arch).or_insert_with(|| ScoopArchive {
+            url: format!(
+                "https://github.com/{owner}/{repo}/releases/download/{tag}/{}",
+                archive.filename
+            ),
+            hash: archive.sha256.clone(),
+        });
+    }
+    archives
+}
+
+pub fn render_manifest(manifest: &ScoopManifest) -> String {
+    let mut json = serde_json::to_string_pretty(manifest).unwrap_or_default();
+    json.push('\n');
+    json
+}
+
+/// Renders `[release.scoop] template`, `file` relative to `root`, with
+/// `manifest`'s fields and the app's `name` as variables.
+pub fn render_manifest_template(
+    root: &Path,
+    file: &str,
+    name: &str,
+    manifest: &ScoopManifest,
+) -> Result<String> {
+    #[derive(Serialize)]
+    struct Variables<'a> {
+        #[serde(flatten)]
+        manifest: &'a ScoopManifest,
+        name: &'a str,
+    }
+    render_template(root, file, &Variables { manifest, name })
+}
+
+/// Commits `text` as `<name>.json` to the bucket, on a new branch with a
+/// pull request unless `config.pull_request` is off. Returns the pull
+/// request's URL, or the manifest's when committed directly.
+pub fn publish_scoop(
+    token: &str,
+    config: &ScoopConfig,
+    name: &str,
+    version: &str,
+    text: &str,
+) -> Result<String> {
+    let directory = config.directory.trim_matches('/');
+    let path = if directory.is_empty() {
+        format!("{name}.json")
+    } else {
+        format!("{directory}/{name}.json")
+    };
+    let branch = format!("shippo/{name}-{version}");
+    let commit = TapCommit {
+        repo: &config.bucket,
+        path: &path,
+        content: text,
+        message: &format!("{name}: Update to version {version}"),
+        branch: config.pull_request.then_some(branch.as_str()),
+        pull_body: &format!("Updates {name} to {version}."),
+    };
+    commit_file(token, &commit, |message| PublishError::Scoop {
+        bucket: config.bucket.clone(),
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shippo_core::{ManifestArtifact, ManifestTarget, ProjectType};
+
+    fn target(target: &str, files: &[&str]) -> ManifestTarget {
+        ManifestTarget {
+            target: target.into(),
+            artifacts: files
+                .iter()
+                .map(|f| ManifestArtifact {
+                    filename: f.to_string(),
+                    bytes: 1,
+                    sha256: format!("sha-of-{f}"),
+                    contents: Vec::new(),
+                    alias: None,
+                })
+                .collect(),
+            sbom: None,
+            extra_sboms: Vec::new(),
+            attestation: None,
+            signatures: Vec::new(),
+            origin: None,
+        }
+    }
+
+    #[test]
+    fn test_scoop_manifest_from_windows_archives() {
+        let package = ManifestPackage {
+            name: "my-tool".into(),
+            project_type: ProjectType::Rust,
+            path: ".".into(),
+            targets: vec![
+                target(
+                    "x86_64-pc-windows-msvc",
+                    &["a-windows-amd64.tar.gz", "a-windows-amd64.zip"],
+                ),
+                target("aarch64-pc-windows-msvc", &["a-windows-arm64.zip"]),
+                target("x86_64-unknown-linux-gnu", &["a-linux-amd64.tar.gz"]),
+            ],
+            tests: None,
+            scan: None,
+            metadata: None,
+            image: None,
+            npm: None,
+        };
+        let architecture = scoop_archives(&package, "acme", "tool", "v1.2.0");
+        assert_eq!(
+            architecture.keys().copied().collect::<Vec<_>>(),
+            ["64bit", "arm64"]
+        );
+        assert_eq!(
+            architecture["64bit"],
+            ScoopArchive {
+                url: "https://github.com/acme/tool/releases/download/v1.2.0/a-windows-amd64.zip"
+                    .into(),
+                hash: "sha-of-a-windows-amd64.zip".into(),
+            }
+        );
+
+        let manifest = ScoopManifest {
+            version: "1.2.0".into(),
+            description: "A tool".into(),
+            homepage: "https://github.com/acme/tool".into(),
+            license: None,
+            architecture,
+            bin: vec!["my-tool.exe".into()],
+        };
+        let text = render_manifest(&manifest);
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["version"], "1.2.0");
+        assert_eq!(
+            json["architecture"]["arm64"]["hash"],
+            "sha-of-a-windows-arm64.zip"
+        );
+        assert_eq!(json["bin"], serde_json::json!(["my-tool.exe"]));
+        assert!(json.get("license").is_none());
+
+        // a template adds what the generated manifest can't, such as notes
+        let dir = tempfile::tempdir().unwrap();
+        let template = r#"{
+  "version": "{{ version }}",
+  "description": {{ description | json_encode }},
+  "url": "{{ architecture["64bit"].url }}",
+  "hash": "{{ architecture["64bit"].hash }}",
+  "bin": {{ bin | json_encode }},
+  "persist": "{{ name }}.toml",
+  "notes": "Run {{ name }} init once."
+}
+"#;
+        std::fs::write(dir.path().join("scoop.json.tera"), template).unwrap();
+        let text =
+            render_manifest_template(dir.path(), "scoop.json.tera", "my-tool", &manifest).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["hash"], "sha-of-a-windows-amd64.zip");
+        assert_eq!(json["bin"], serde_json::json!(["my-tool.exe"]));
+        assert_eq!(json["persist"], "my-tool.toml");
+    }
+}
@@ -0,0 +1,219 @@
+//! Uploads split debug info (PDBs, dSYMs, `.debug` and `.dwp` files) to
+//! Sentry or a symbol server, so crash reports from released binaries are
+//! symbolicated.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use reqwest::blocking::Client;
+use reqwest::header::{AUTHORIZATION, USER_AGENT};
+use shippo_core::exec::{CommandSpec, Executor};
+use shippo_core::SymbolsConfig;
+
+use crate::{PublishError, Result};
+
+/// Extensions of debug files, whether built as artifacts or written next
+/// to a binary: `app.pdb` for `app.exe`, `app.dSYM`, `app.debug`, `app.dwp`.
+const DEBUG_EXTENSIONS: [&str; 5] = ["pdb", "dSYM", "debug", "dwp", "sym"];
+
+/// Debug files among `artifacts` or next to them, without duplicates.
+pub fn debug_files(artifacts: &[PathBuf]) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    for artifact in artifacts {
+        let is_debug = artifact
+            .extension()
+            .is_some_and(|ext| DEBUG_EXTENSIONS.iter().any(|d| ext == *d));
+        if is_debug {
+            found.push(artifact.clone());
+            continue;
+        }
+        let Some(name) = artifact.file_name() else {
+            continue;
+        };
+        for ext in DEBUG_EXTENSIONS {
+            // rustc and MSVC replace `.exe`; split-debuginfo tools append
+            let candidates = [
+                artifact.with_extension(ext),
+                artifact.with_file_name(format!("{}.{ext}", name.to_string_lossy())),
+            ];
+            for candidate in candidates {
+                if candidate.exists() && !artifacts.contains(&candidate) {
+                    found.push(candidate);
+                }
+            }
+        }
+    }
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Uploads `files` for `package` at `version`. Sentry finds them by debug
+/// ID; symbol servers get `<url>/<package>/<version>/<file>`.
+pub fn upload_symbols(
+    config: &SymbolsConfig,
+    package: &str,
+    version: &str,
+    files: &[PathBuf],
+    executor: &dyn Executor,
+) -> Result<()> {
+    let token_env = config.token_env.as_deref();
+    match config.provider.as_str() {
+        "sentry" => {
+            let (Some(org), Some(project)) = (&config.org, &config.project) else {
+                return Err(PublishError::Symbols(
+                    "[symbols] provider \"sentry\" needs org and project".into(),
+                ));
+            };
+            let mut cmd = CommandSpec::new("sentry-cli");
+            if let Some(url) = &config.url {
+                cmd.args(["--url", url]);
+            }
+            cmd.args(["debug-files", "upload", "--org", org, "--project", project])
+                .args(files.iter().map(|f| f.to_string_lossy()));
+            // sentry-cli reads SENTRY_AUTH_TOKEN itself
+            if let Some(name) = token_env.filter(|n| *n != "SENTRY_AUTH_TOKEN") {
+                let token = std::env::var(name)
+                    .map_err(|_| PublishError::Symbols(format!("{name} is not set")))?;
+                cmd.env("SENTRY_AUTH_TOKEN", token);
+            }
+            let output = executor.output(&cmd).map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    PublishError::Symbols("sentry-cli is not installed".into())
+                }
+                _ => PublishError::Io(e),
+            })?;
+            if !output.success() {
+                return Err(PublishError::Symbols(format!(
+                    "sentry-cli failed ({}): {}",
+                    output.status(),
+                    output.stderr.trim()
+                )));
+            }
+            Ok(())
+        }
+        "http" => {
+            let base = config.url.as_deref().ok_or_else(|| {
+                PublishError::Symbols("[symbols] provider \"http\" needs a url".into())
+            })?;
+            let token = std::env::var(token_env.unwrap_or("SYMBOL_SERVER_TOKEN")).ok();
+            let base = base.trim_end_matches('/');
+            let client = Client::new();
+            for file in files {
+                let root = file.parent().unwrap_or(Path::new(""));
+                for path in files_under(file)? {
+                    let name = path
+                        .strip_prefix(root)
+                        .unwrap_or(&path)
+                        .to_string_lossy()
+                        .replace('\\', "/");
+                    let url = format!("{base}/{package}/{version}/{name}");
+                    let mut req = client
+                        .put(&url)
+                        .header(USER_AGENT, "shippo/1.0")
+                        .body(fs::read(&path)?);
+                    if let Some(token) = &token {
+                        req = req.header(AUTHORIZATION, format!("Bearer {token}"));
+                    }
+                    let res = req.send()?;
+                    if !res.status().is_success() {
+                        return Err(PublishError::UploadRejected {
+                            filename: name,
+                            status: res.status(),
+                            body: res.text().unwrap_or_default(),
+                        });
+                    }
+                }
+            }
+            Ok(())
+        }
+        other => Err(PublishError::Symbols(format!(
+            "unknown [symbols] provider {other:?}; expected \"sentry\" or \"http\""
+        ))),
+    }
+}
+
+/// `path` itself, or the files in it for bundles such as `app.dSYM`.
+fn files_under(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        files.extend(files_under(&entry?.path())?);
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shippo_core::exec::MockExecutor;
+
+    #[test]
+    fn test_debug_files_found_and_sent_to_sentry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+        for name in [
+            "app.exe",
+            "app.pdb",
+            "tool",
+            "tool.debug",
+            "lib.dwp",
+            "plain",
+        ] {
+            fs::write(path(name), b"x").unwrap();
+        }
+        fs::create_dir_all(path("tool.dSYM/Contents")).unwrap();
+        let artifacts = vec![
+            path("app.exe"),
+            path("tool"),
+            path("lib.dwp"),
+            path("plain"),
+        ];
+        let files = debug_files(&artifacts);
+        assert_eq!(
+            files,
+            vec![
+                path("app.pdb"),
+                path("lib.dwp"),
+                path("tool.dSYM"),
+                path("tool.debug")
+            ]
+        );
+
+        let config = SymbolsConfig {
+            provider: "sentry".into(),
+            url: Some("https://sentry.example.com".into()),
+            org: Some("acme".into()),
+            project: Some("cli".into()),
+            token_env: None,
+        };
+        let executor = MockExecutor::new();
+        upload_symbols(&config, "cli", "1.2.0", &files[..1], &executor).unwrap();
+        let calls = executor.calls();
+        assert_eq!(calls[0].program, "sentry-cli");
+        assert_eq!(
+            calls[0].args[..8],
+            [
+                "--url",
+                "https://sentry.example.com",
+                "debug-files",
+                "upload",
+                "--org",
+                "acme",
+                "--project",
+                "cli"
+            ]
+        );
+
+        let config = SymbolsConfig {
+            provider: "http".into(),
+            url: None,
+            ..config
+        };
+        let err = upload_symbols(&config, "cli", "1.2.0", &files, &executor).unwrap_err();
+        assert!(err.to_string().contains("needs a url"), "{err}");
+    }
+}
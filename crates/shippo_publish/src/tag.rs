@@ -0,0 +1,182 @@
+//! Checks that a release tag is signed by an allowed key before publishing,
+//! so only holders of release keys can cut releases.
+
+use std::fs;
+use std::path::Path;
+
+use shippo_core::exec::{CommandOutput, CommandSpec, Executor};
+use shippo_core::SignedTagConfig;
+use shippo_git::{tag_signature, SignatureFormat};
+
+use crate::{PublishError, Result};
+
+/// Verifies the signature of `tag` with gpg or ssh-keygen, like
+/// `git verify-tag` would, and returns who signed it.
+pub fn verify_tag_signature(
+    repo: &Path,
+    tag: &str,
+    config: &SignedTagConfig,
+    executor: &dyn Executor,
+) -> Result<String> {
+    let rejected = |reason: String| PublishError::TagSignature {
+        tag: tag.to_string(),
+        reason,
+    };
+    let signed = tag_signature(repo, tag)
+        .map_err(|e| rejected(e.to_string()))?
+        .ok_or_else(|| rejected("it is not a signed annotated tag".into()))?;
+    let dir = tempfile::tempdir()?;
+    let (payload, sig) = (dir.path().join("payload"), dir.path().join("payload.sig"));
+    fs::write(&payload, &signed.payload)?;
+    fs::write(&sig, &signed.signature)?;
+    let sig_arg = sig.to_string_lossy();
+    match signed.format {
+        SignatureFormat::Gpg => {
+            if config.gpg_fingerprints.is_empty() && config.gpg_keyring.is_none() {
+                return Err(rejected(
+                    "it is GPG-signed, but [release.signed_tag] allows no GPG keys".into(),
+                ));
+            }
+            let mut cmd = CommandSpec::new("gpg");
+            cmd.args(["--batch", "--status-fd", "1"]);
+            if let Some(keyring) = &config.gpg_keyring {
+                cmd.args(["--no-default-keyring", "--keyring", keyring]);
+            }
+            cmd.args(["--verify", &sig_arg, &payload.to_string_lossy()]);
+            let output = run(&cmd, executor).map_err(rejected)?;
+            // [GNUPG:] VALIDSIG <fingerprint> ... <primary key fingerprint>
+            let fingerprints: Vec<&str> = output
+                .stdout
+                .lines()
+                .find_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+                .map(|fields| {
+                    let fields: Vec<&str> = fields.split_whitespace().collect();
+                    [fields.first(), fields.get(9)]
+                        .into_iter()
+                        .flatten()
+                        .copied()
+                        .collect()
+                })
+                .unwrap_or_default();
+            let Some(signer) = fingerprints.first() else {
+                return Err(rejected("gpg reported no valid signature".into()));
+            };
+            let normalize = |fpr: &str| fpr.replace(' ', "").to_ascii_uppercase();
+            let allowed = config.gpg_fingerprints.is_empty()
+                || config.gpg_fingerprints.iter().any(|allowed| {
+                    fingerprints
+                        .iter()
+                        .any(|fpr| normalize(fpr) == normalize(allowed))
+                });
+            if !allowed {
+                return Err(rejected(format!(
+                    "GPG key {signer} is not in gpg_fingerprints"
+                )));
+            }
+            Ok(format!("GPG key {signer}"))
+        }
+        SignatureFormat::Ssh => {
+            let signers = config.ssh_allowed_signers.as_deref().ok_or_else(|| {
+                rejected(
+                    "it is SSH-signed, but [release.signed_tag] has no ssh_allowed_signers".into(),
+                )
+            })?;
+            let mut find = CommandSpec::new("ssh-keygen");
+            find.args(["-Y", "find-principals", "-f", signers, "-s", &sig_arg]);
+            let principal = run(&find, executor)
+                .ok()
+                .and_then(|output| output.stdout.lines().next().map(|p| p.trim().to_string()))
+                .ok_or_else(|| rejected("the signing key is not in ssh_allowed_signers".into()))?;
+            // git signs tags in the `git` namespace
+            let mut verify = CommandSpec::new("ssh-keygen");
+            verify
+                .args(["-Y", "verify", "-f", signers, "-I", &principal])
+                .args(["-n", "git", "-s", &sig_arg])
+                .stdin_file(&payload);
+            run(&verify, executor).map_err(rejected)?;
+            Ok(format!("SSH principal {principal}"))
+        }
+    }
+}
+
+/// Runs a verifier, failing unless it exits successfully.
+fn run(cmd: &CommandSpec, executor: &dyn Executor) -> Result<CommandOutput, String> {
+    let output = executor.output(cmd).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => format!("{} is not installed", cmd.program),
+        _ => format!("failed to run {}: {e}", cmd.program),
+    })?;
+    if !output.success() {
+        return Err(format!(
+            "{} rejected it ({}): {}",
+            cmd.program,
+            output.status(),
+            output.stderr.trim()
+        ));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shippo_core::exec::MockExecutor;
+
+    const SSH_ARMOR: &str =
+        "-----BEGIN SSH SIGNATURE-----\nU1NIU0lH\n-----END SSH SIGNATURE-----\n";
+    const GPG_ARMOR: &str = "-----BEGIN PGP SIGNATURE-----\niQEz\n-----END PGP SIGNATURE-----\n";
+
+    fn stdout(stdout: &str) -> CommandOutput {
+        CommandOutput {
+            code: Some(0),
+            stdout: stdout.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_signed_tags_checked_against_allowed_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let sig = git2::Signature::now("Shippo", "shippo@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let head = repo
+            .commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+        let object = repo.find_object(head, None).unwrap();
+        for (tag, armor) in [("v1.0.0", SSH_ARMOR), ("v1.1.0", GPG_ARMOR), ("v1.2.0", "")] {
+            repo.tag(tag, &object, &sig, &format!("Release\n{armor}"), false)
+                .unwrap();
+        }
+        let config = SignedTagConfig {
+            gpg_fingerprints: vec!["AAAA BBBB".into()],
+            ssh_allowed_signers: Some(".github/allowed_signers".into()),
+            ..Default::default()
+        };
+
+        let executor = MockExecutor::new();
+        executor.respond("ssh-keygen", stdout("release@example.com\n"));
+        let signer = verify_tag_signature(dir.path(), "v1.0.0", &config, &executor).unwrap();
+        assert_eq!(signer, "SSH principal release@example.com");
+        let calls = executor.calls();
+        assert_eq!(calls.len(), 2);
+        assert!(calls[1].args.windows(2).any(|w| w == ["-n", "git"]));
+
+        let validsig = |fpr: &str| {
+            format!("[GNUPG:] VALIDSIG {fpr} 2024-01-01 1704067200 0 4 0 1 10 00 {fpr}\n")
+        };
+        executor.respond("gpg", stdout(&validsig("AAAABBBB")));
+        let signer = verify_tag_signature(dir.path(), "v1.1.0", &config, &executor).unwrap();
+        assert_eq!(signer, "GPG key AAAABBBB");
+        executor.respond("gpg", stdout(&validsig("CCCCDDDD")));
+        let err = verify_tag_signature(dir.path(), "v1.1.0", &config, &executor).unwrap_err();
+        assert!(err.to_string().contains("not in gpg_fingerprints"), "{err}");
+
+        let err = verify_tag_signature(dir.path(), "v1.2.0", &config, &executor).unwrap_err();
+        assert!(
+            err.to_string().contains("not a signed annotated tag"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Committing one generated file, such as a Homebrew formula or Scoop
+//! manifest, to a GitHub repository, through a pull request by default,
+//! and rendering such files from user templates.
+
+use std::error::Error as _;
+use std::fs;
+use std::path::Path;
+
+use base64::Engine;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use tera::{Context, Tera};
+
+use crate::{PublishError, Result};
+
+/// Renders the Tera template `file`, relative to `root`, with the fields of
+/// `context` as its variables.
+pub(crate) fn render_template(root: &Path, file: &str, context: &impl Serialize) -> Result<String> {
+    let path = root.join(file);
+    let error = |message: String| PublishError::Template {
+        path: path.display().to_string(),
+        message,
+    };
+    let text = fs::read_to_string(&path).map_err(|e| error(e.to_string()))?;
+    let context = Context::from_serialize(context).map_err(|e| error(e.to_string()))?;
+    Tera::one_off(&text, &context, false).map_err(|e| {
+        // the top error only names the template; the causes say what failed
+        let mut message = e.to_string();
+        let mut source = e.source();
+        while let Some(cause) = source {
+            message.push_str(&format!(": {cause}"));
+            source = cause.source();
+        }
+        error(message)
+    })
+}
+
+pub(crate) struct TapCommit<'a> {
+    /// `owner/repo`.
+    pub repo: &'a str,
+    /// Path of the file in the repository.
+    pub path: &'a str,
+    pub content: &'a str,
+    /// Commit message, and the pull request's title.
+    pub message: &'a str,
+    /// Branch to open a pull request from; `None` commits to the default
+    /// branch.
+    pub branch: Option<&'a str>,
+    pub pull_body: &'a str,
+}
+
+/// Commits `commit.content` and returns the pull request's URL, or the
+/// file's when committed directly. Failures are turned into errors by
+/// `error`.
+pub(crate) fn commit_file(
+    token: &str,
+    commit: &TapCommit,
+    error: impl Fn(String) -> PublishError,
+) -> Result<String> {
+    let client = Client::new();
+    let api = format!("https://api.github.com/repos/{}", commit.repo);
+    let request = |method: Method, url: String| -> RequestBuilder {
+        client
+            .request(method, url)
+            .header(USER_AGENT, "shippo/1.0")
+            .header(ACCEPT, "application/vnd.github+json")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+    };
+    let rejected = |what: &str, res: reqwest::blocking::Response| {
+        error(format!(
+            "{what} failed: {} {}",
+            res.status(),
+            res.text().unwrap_or_default()
+        ))
+    };
+    let res = request(Method::GET, api.clone()).send()?;
+    if !res.status().is_success() {
+        return Err(rejected("reading the repository", res));
+    }
+    let repo: serde_json::Value = res.json()?;
+    let base = repo["default_branch"]
+        .as_str()
+        .unwrap_or("main")
+        .to_string();
+    let branch = match commit.branch {
+        Some(branch) => {
+            let res = request(Method::GET, format!("{api}/git/ref/heads/{base}")).send()?;
+            if !res.status().is_success() {
+                return Err(rejected("reading the default branch", res));
+            }
+            let head: serde_json::Value = res.json()?;
+            let res = request(Method::POST, format!("{api}/git/refs"))
+                .json(&serde_json::json!({
+                    "ref": format!("refs/heads/{branch}"),
+                    "sha": head["object"]["sha"],
+                }))
+                .send()?;
+            if !res.status().is_success() {
+                let status = res.status();
+                let text = res.text().unwrap_or_default();
+                // the branch is left from an earlier attempt: commit to it
+                if status != StatusCode::UNPROCESSABLE_ENTITY
+                    || !text.contains("Reference already exists")
+                {
+                    return Err(error(format!("creating a branch failed: {status} {text}")));
+                }
+            }
+            branch.to_string()
+        }
+        None => base.clone(),
+    };
+    let path = commit.path;
+    let res = request(Method::GET, format!("{api}/contents/{path}?ref={branch}")).send()?;
+    let existing = if res.status().is_success() {
+        res.json::<serde_json::Value>()?["sha"]
+            .as_str()
+            .map(String::from)
+    } else if res.status() == StatusCode::NOT_FOUND {
+        None
+    } else {
+        return Err(rejected(&format!("reading {path}"), res));
+    };
+    let mut body = serde_json::json!({
+        "message": commit.message,
+        "content": base64::engine::general_purpose::STANDARD.encode(commit.content),
+        "branch": branch,
+    });
+    if let Some(sha) = existing {
+        body["sha"] = sha.into();
+    }
+    let res = request(Method::PUT, format!("{api}/contents/{path}"))
+        .json(&body)
+        .send()?;
+    if !res.status().is_success() {
+        return Err(rejected(&format!("committing {path}"), res));
+    }
+    if commit.branch.is_none() {
+        return Ok(format!(
+            "https://github.com/{}/blob/{branch}/{path}",
+            commit.repo
+        ));
+    }
+    let res = request(Method::POST, format!("{api}/pulls"))
+        .json(&serde_json::json!({
+            "title": commit.message,
+            "head": branch,
+            "base": base,
+            "body": commit.pull_body,
+        }))
+        .send()?;
+    if res.status() == StatusCode::UNPROCESSABLE_ENTITY {
+        // an earlier attempt opened it; the commit above updated it
+        let owner = commit.repo.split('/').next().unwrap_or_default();
+        let res = request(
+            Method::GET,
+            format!("{api}/pulls?head={owner}:{branch}&base={base}&state=open"),
+        )
+        .send()?;
+        if !res.status().is_success() {
+            return Err(rejected("finding the open pull request", res));
+        }
+        let pulls: serde_json::Value = res.json()?;
+        return match pulls[0]["html_url"].as_str() {
+            Some(url) => Ok(url.to_string()),
+            None => Err(error(format!(
+                "opening a pull request failed: GitHub refused one from {branch} but has none open"
+            ))),
+        };
+    }
+    if !res.status().is_success() {
+        return Err(rejected("opening a pull request", res));
+    }
+    let pull: serde_json::Value = res.json()?;
+    Ok(pull["html_url"].as_str().unwrap_or_default().to_string())
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde_json::json;
+use shippo_core::{ChangelogConfig, Manifest, ReleaseAssetsConfig, ReleaseBodyConfig};
+use shippo_publish::http::{ReqwestClient, Retry};
+use shippo_publish::{publish_github_with, GitHubApi, PublishError, ReleaseInput};
+use tokio::runtime::Runtime;
+use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const RELEASE_TAG: &str = "/repos/acme/tool/releases/tags/v1.0.0";
+const GIT_TAG: &str = "/repos/acme/tool/git/ref/tags/v1.0.0";
+
+fn manifest() -> Manifest {
+    Manifest::from_json(
+        r#"{
+            "shippo_version": "0.1.0",
+            "generated_at": "2026-01-01T00:00:00Z",
+            "project": {"repo_url": null, "commit": "abc123", "version": "v1.0.0"},
+            "packages": [],
+            "tooling": {"rust": null, "go": null, "node": null, "python": null},
+            "build_env": {"os": "linux", "arch": "x86_64", "ci": false}
+        }"#,
+    )
+    .unwrap()
+}
+
+/// Publishes `v1.0.0` of acme/tool from `dist` to the API at `base`.
+fn publish(base: &str, dist: &Path) -> Result<(), PublishError> {
+    let (changelog, assets) = (ChangelogConfig::default(), ReleaseAssetsConfig::default());
+    let body = ReleaseBodyConfig {
+        sections: vec!["artifacts".into()],
+        header_file: None,
+    };
+    let (packages, manifest) = (BTreeMap::new(), manifest());
+    let input = ReleaseInput {
+        owner: "acme",
+        repo: "tool",
+        tag: "v1.0.0",
+        name: "v1.0.0",
+        draft: true,
+        prerelease: false,
+        update_latest: false,
+        changelog: &changelog,
+        body: &body,
+        tag_pattern: None,
+        packages: &packages,
+        workspace_root: dist,
+        dist,
+        skip_assets: &[],
+        assets: &assets,
+        manifest: &manifest,
+        events: None,
+    };
+    let client = ReqwestClient::new();
+    let github = GitHubApi {
+        client: &client,
+        base,
+        token: "t0ken",
+        retry: Retry {
+            attempts: 3,
+            backoff: Duration::ZERO,
+        },
+    };
+    publish_github_with(&github, &input)
+}
+
+fn not_found(route: &str) -> Mock {
+    Mock::given(method("GET"))
+        .and(path(route))
+        .respond_with(ResponseTemplate::new(404))
+}
+
+#[test]
+fn creates_release_at_built_commit_and_uploads_dist() {
+    let rt = Runtime::new().unwrap();
+    let server = rt.block_on(MockServer::start());
+    let dist = tempfile::tempdir().unwrap();
+    fs::write(dist.path().join("tool-linux.tar.gz"), "archive").unwrap();
+    fs::write(dist.path().join("SHA256SUMS"), "sums").unwrap();
+    fs::write(dist.path().join(".shippo-dist.lock"), "").unwrap();
+    rt.block_on(async {
+        not_found(RELEASE_TAG).mount(&server).await;
+        not_found(GIT_TAG).mount(&server).await;
+        Mock::given(method("POST"))
+            .and(path("/repos/acme/tool/releases"))
+            .and(header("authorization", "Bearer t0ken"))
+            .and(body_partial_json(json!({
+                "tag_name": "v1.0.0",
+                "target_commitish": "abc123",
+                "draft": true,
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "upload_url": format!("{}/uploads/releases/1/assets{{?name,label}}", server.uri()),
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        for (name, content_type) in [
+            ("tool-linux.tar.gz", "application/gzip"),
+            ("SHA256SUMS", "text/plain"),
+        ] {
+            Mock::given(method("POST"))
+                .and(path("/uploads/releases/1/assets"))
+                .and(query_param("name", name))
+                .and(header("content-type", content_type))
+                .respond_with(ResponseTemplate::new(201))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+    });
+    publish(&server.uri(), dist.path()).unwrap();
+}
+
+#[test]
+fn retries_server_errors_but_not_rejected_releases() {
+    let rt = Runtime::new().unwrap();
+    let server = rt.block_on(MockServer::start());
+    let dist = tempfile::tempdir().unwrap();
+    rt.block_on(async {
+        Mock::given(method("GET"))
+            .and(path(RELEASE_TAG))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        not_found(RELEASE_TAG).mount(&server).await;
+        not_found(GIT_TAG).mount(&server).await;
+        Mock::given(method("POST"))
+            .and(path("/repos/acme/tool/releases"))
+            .respond_with(ResponseTemplate::new(422))
+            .expect(1)
+            .mount(&server)
+            .await;
+    });
+    let err = publish(&server.uri(), dist.path()).unwrap_err();
+    assert!(
+        matches!(err, PublishError::ReleaseRejected { status } if status == StatusCode::UNPROCESSABLE_ENTITY),
+        "{err}"
+    );
+}
+
+#[test]
+fn refuses_a_tag_that_already_has_a_release() {
+    let rt = Runtime::new().unwrap();
+    let server = rt.block_on(MockServer::start());
+    let dist = tempfile::tempdir().unwrap();
+    rt.block_on(
+        Mock::given(method("GET"))
+            .and(path(RELEASE_TAG))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "html_url": "https://github.com/acme/tool/releases/tag/v1.0.0",
+                "immutable": true,
+            })))
+            .mount(&server),
+    );
+    let err = publish(&server.uri(), dist.path()).unwrap_err();
+    assert!(matches!(
+        err,
+        PublishError::ReleaseExists {
+            immutable: true,
+            ..
+        }
+    ));
+    let requests = rt.block_on(server.received_requests()).unwrap();
+    assert_eq!(requests.len(), 1);
+}
@@ -0,0 +1,261 @@
+//! Sandboxed WebAssembly plugins for Shippo.
+//!
+//! Plugins are WebAssembly components implementing one of the worlds in
+//! `wit/plugin.wit`. Unlike exec plugins they run in-process with no WASI:
+//! they cannot read files or environment variables (and so never see signing
+//! keys), and can only reach the network through `http-post` to the hosts
+//! listed in their config. Every call runs with a fuel and memory budget.
+
+use std::path::PathBuf;
+
+use shippo_core::WasmPluginConfig;
+use wasmtime::component::{Component, HasSelf, Linker};
+use wasmtime::{Config, Engine, Store, StoreLimits, StoreLimitsBuilder};
+
+mod transformer {
+    wasmtime::component::bindgen!({
+        world: "transformer",
+        path: "wit",
+    });
+}
+
+mod notifier {
+    wasmtime::component::bindgen!({
+        world: "notifier",
+        path: "wit",
+        with: {
+            "shippo:plugin/types": crate::transformer::shippo::plugin::types,
+            "shippo:plugin/host": crate::transformer::shippo::plugin::host,
+        },
+    });
+}
+
+pub use transformer::shippo::plugin::types::{Artifact, Release};
+
+#[derive(thiserror::Error, Debug)]
+pub enum WasmPluginError {
+    #[error("failed to read wasm plugin {}: {source}", .path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to load wasm plugin {name}: {source:#}")]
+    Load {
+        name: String,
+        source: wasmtime::Error,
+    },
+    #[error("wasm plugin {name} trapped: {source:#}")]
+    Trap {
+        name: String,
+        source: wasmtime::Error,
+    },
+    #[error("wasm plugin {name} failed: {message}")]
+    Plugin { name: String, message: String },
+    #[error("wasm plugin {name} is a {kind}, not a {expected}")]
+    WrongKind {
+        name: String,
+        kind: String,
+        expected: &'static str,
+    },
+}
+
+pub type Result<T, E = WasmPluginError> = std::result::Result<T, E>;
+
+struct HostState {
+    plugin: String,
+    allowed_hosts: Vec<String>,
+    limits: StoreLimits,
+}
+
+impl transformer::shippo::plugin::host::Host for HostState {
+    fn log(&mut self, message: String) {
+        tracing::info!("[{}] {message}", self.plugin);
+    }
+
+    fn http_post(
+        &mut self,
+        url: String,
+        content_type: String,
+        body: Vec<u8>,
+    ) -> Result<u16, String> {
+        let parsed = url::Url::parse(&url).map_err(|e| format!("invalid url {url}: {e}"))?;
+        let host = parsed.host_str().unwrap_or_default();
+        if !self.allowed_hosts.iter().any(|h| h == host) {
+            return Err(format!("host {host} is not in allowed_hosts"));
+        }
+        reqwest::blocking::Client::new()
+            .post(parsed)
+            .header("Content-Type", content_type)
+            .body(body)
+            .send()
+            .map(|res| res.status().as_u16())
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl transformer::shippo::plugin::types::Host for HostState {}
+
+/// A compiled wasm plugin, ready to be called.
+pub struct WasmPlugin {
+    name: String,
+    config: WasmPluginConfig,
+    engine: Engine,
+    component: Component,
+}
+
+impl WasmPlugin {
+    pub fn load(name: &str, config: &WasmPluginConfig) -> Result<Self> {
+        let path = PathBuf::from(&config.path);
+        let bytes = std::fs::read(&path).map_err(|source| WasmPluginError::Read {
+            path: path.clone(),
+            source,
+        })?;
+        Self::from_bytes(name, config, &bytes)
+    }
+
+    pub fn from_bytes(name: &str, config: &WasmPluginConfig, bytes: &[u8]) -> Result<Self> {
+        let load_err = |source| WasmPluginError::Load {
+            name: name.to_string(),
+            source,
+        };
+        let mut engine_cfg = Config::new();
+        engine_cfg.consume_fuel(true);
+        let engine = Engine::new(&engine_cfg).map_err(load_err)?;
+        let component = Component::new(&engine, bytes).map_err(load_err)?;
+        Ok(Self {
+            name: name.to_string(),
+            config: config.clone(),
+            engine,
+            component,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.config.kind
+    }
+
+    pub fn transform(&self, input: &Artifact) -> Result<Artifact> {
+        self.expect_kind("transformer")?;
+        let mut store = self.store()?;
+        let mut linker = Linker::new(&self.engine);
+        transformer::Transformer::add_to_linker::<_, HasSelf<_>>(&mut linker, |s| s)
+            .map_err(|e| self.load_err(e))?;
+        let instance = transformer::Transformer::instantiate(&mut store, &self.component, &linker)
+            .map_err(|e| self.load_err(e))?;
+        instance
+            .call_transform(&mut store, input)
+            .map_err(|e| self.trap(e))?
+            .map_err(|message| self.failed(message))
+    }
+
+    pub fn notify(&self, release: &Release) -> Result<()> {
+        self.expect_kind("notifier")?;
+        let mut store = self.store()?;
+        let mut linker = Linker::new(&self.engine);
+        notifier::Notifier::add_to_linker::<_, HasSelf<_>>(&mut linker, |s| s)
+            .map_err(|e| self.load_err(e))?;
+        let instance = notifier::Notifier::instantiate(&mut store, &self.component, &linker)
+            .map_err(|e| self.load_err(e))?;
+        instance
+            .call_notify(&mut store, release)
+            .map_err(|e| self.trap(e))?
+            .map_err(|message| self.failed(message))
+    }
+
+    fn store(&self) -> Result<Store<HostState>> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size((self.config.memory_mb * 1024 * 1024) as usize)
+            .build();
+        let mut store = Store::new(
+            &self.engine,
+            HostState {
+                plugin: self.name.clone(),
+                allowed_hosts: self.config.allowed_hosts.clone(),
+                limits,
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store
+            .set_fuel(self.config.fuel)
+            .map_err(|e| self.load_err(e))?;
+        Ok(store)
+    }
+
+    fn expect_kind(&self, expected: &'static str) -> Result<()> {
+        if self.config.kind == expected {
+            Ok(())
+        } else {
+            Err(WasmPluginError::WrongKind {
+                name: self.name.clone(),
+                kind: self.config.kind.clone(),
+                expected,
+            })
+        }
+    }
+
+    fn load_err(&self, source: wasmtime::Error) -> WasmPluginError {
+        WasmPluginError::Load {
+            name: self.name.clone(),
+            source,
+        }
+    }
+
+    fn trap(&self, source: wasmtime::Error) -> WasmPluginError {
+        WasmPluginError::Trap {
+            name: self.name.clone(),
+            source,
+        }
+    }
+
+    fn failed(&self, message: String) -> WasmPluginError {
+        WasmPluginError::Plugin {
+            name: self.name.clone(),
+            message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_kind_checks() {
+        let config = WasmPluginConfig {
+            path: "x.wasm".into(),
+            kind: "transformer".into(),
+            allowed_hosts: Vec::new(),
+            fuel: 1_000,
+            memory_mb: 1,
+        };
+        let err = WasmPlugin::from_bytes("bad", &config, b"not wasm")
+            .err()
+            .expect("invalid bytes must not load");
+        assert!(matches!(err, WasmPluginError::Load { .. }));
+
+        // an empty component: loads, but exports nothing
+        let empty = WasmPlugin::from_bytes("empty", &config, b"\0asm\x0d\0\x01\0").unwrap();
+        let release = Release {
+            project: "demo".into(),
+            version: "v1.0.0".into(),
+            artifacts: Vec::new(),
+        };
+        assert!(matches!(
+            empty.notify(&release),
+            Err(WasmPluginError::WrongKind { .. })
+        ));
+        let artifact = Artifact {
+            name: "demo".into(),
+            target: "native".into(),
+            contents: Vec::new(),
+        };
+        assert!(matches!(
+            empty.transform(&artifact),
+            Err(WasmPluginError::Load { .. })
+        ));
+    }
+}